@@ -9,6 +9,7 @@ use apollo_compiler::ExecutableDocument;
 use apollo_federation::error::FederationError;
 use apollo_federation::error::SingleFederationError;
 use apollo_federation::query_graph;
+use apollo_federation::query_plan::query_planner::QueryPlanOptions;
 use apollo_federation::query_plan::query_planner::QueryPlanner;
 use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
 use apollo_federation::subgraph;
@@ -79,6 +80,14 @@ enum Command {
         #[command(flatten)]
         planner: QueryPlannerArgs,
     },
+    /// Outputs how the query plan for the given query and schema was chosen, as JSON
+    Explain {
+        query: PathBuf,
+        /// Path(s) to one supergraph schema file, `-` for stdin or multiple subgraph schemas.
+        schemas: Vec<PathBuf>,
+        #[command(flatten)]
+        planner: QueryPlannerArgs,
+    },
     /// Validate one supergraph schema file or multiple subgraph schemas
     Validate {
         /// Path(s) to one supergraph schema file, `-` for stdin or multiple subgraph schemas.
@@ -143,6 +152,11 @@ fn main() -> ExitCode {
             schemas,
             planner,
         } => cmd_plan(&query, &schemas, planner),
+        Command::Explain {
+            query,
+            schemas,
+            planner,
+        } => cmd_explain(&query, &schemas, planner),
         Command::Validate { schemas } => cmd_validate(&schemas),
         Command::Compose { schemas } => cmd_compose(&schemas),
         Command::Extract {
@@ -261,6 +275,34 @@ fn cmd_plan(
     Ok(())
 }
 
+fn cmd_explain(
+    query_path: &Path,
+    schema_paths: &[PathBuf],
+    planner: QueryPlannerArgs,
+) -> Result<(), FederationError> {
+    let query = read_input(query_path);
+    let supergraph = load_supergraph(schema_paths)?;
+
+    let config = QueryPlannerConfig::from(planner);
+    let planner = QueryPlanner::new(&supergraph, config)?;
+
+    let query_doc =
+        ExecutableDocument::parse_and_validate(planner.api_schema().schema(), query, query_path)?;
+    let plan = planner.build_query_plan(
+        &query_doc,
+        None,
+        QueryPlanOptions {
+            explain: true,
+            ..Default::default()
+        },
+    )?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&plan.explanation).unwrap()
+    );
+    Ok(())
+}
+
 fn cmd_validate(file_paths: &[PathBuf]) -> Result<(), FederationError> {
     load_supergraph(file_paths)?;
     println!("[SUCCESS]");
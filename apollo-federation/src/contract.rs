@@ -0,0 +1,384 @@
+//! Implements contract API schema generation.
+//!
+//! A contract is a variant of the API schema filtered by `@tag`. Elements tagged with an excluded
+//! tag are removed, and when included tags are set, object and interface fields are only kept if
+//! they or their parent type have an included tag. Removals are then propagated so the contract
+//! stays a valid schema: fields returning a removed type are removed, types with no remaining
+//! fields are removed, and so on.
+use apollo_compiler::ast::Directive;
+use apollo_compiler::collections::IndexMap;
+use apollo_compiler::collections::IndexSet;
+use apollo_compiler::name;
+use apollo_compiler::schema::Component;
+use apollo_compiler::schema::ComponentName;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::schema::FieldDefinition;
+use apollo_compiler::Name;
+use apollo_compiler::Node;
+use apollo_compiler::Schema;
+
+use crate::api_schema;
+use crate::api_schema::ApiSchemaOptions;
+use crate::error::FederationError;
+use crate::error::SingleFederationError;
+use crate::link::spec::Identity;
+use crate::schema::ValidFederationSchema;
+
+const TAG_DIRECTIVE_NAME_IN_SPEC: Name = name!("tag");
+const TAG_NAME_ARGUMENT: Name = name!("name");
+
+/// The `@tag` filters defining a contract.
+#[derive(Debug, Default, Clone)]
+pub struct ContractFilter {
+    /// When not empty, object and interface fields are only kept if they or their parent type
+    /// have one of these tags.
+    pub include: Vec<String>,
+    /// Elements with one of these tags are removed.
+    pub exclude: Vec<String>,
+}
+
+impl ContractFilter {
+    fn excludes<'a>(&self, mut tags: impl Iterator<Item = &'a str>) -> bool {
+        tags.any(|tag| self.exclude.iter().any(|excluded| excluded == tag))
+    }
+
+    fn includes<'a>(&self, mut tags: impl Iterator<Item = &'a str>) -> bool {
+        tags.any(|tag| self.include.iter().any(|included| included == tag))
+    }
+}
+
+/// The elements removed from the supergraph schema to make the contract.
+#[derive(Debug, Default)]
+struct Removed {
+    types: IndexSet<Name>,
+    /// Fields, input fields and enum values, by parent type name
+    members: IndexSet<(Name, Name)>,
+    /// Field arguments, by parent type and field names
+    arguments: IndexSet<(Name, Name, Name)>,
+    /// Interfaces that an object or interface type no longer implements
+    implementations: IndexSet<(Name, Name)>,
+}
+
+pub fn to_contract_api_schema(
+    schema: ValidFederationSchema,
+    filter: &ContractFilter,
+    options: ApiSchemaOptions,
+) -> Result<ValidFederationSchema, FederationError> {
+    let tag_directive_name = schema
+        .metadata()
+        .and_then(|metadata| metadata.for_identity(&Identity::tag_identity()))
+        .map(|link| link.directive_name_in_schema(&TAG_DIRECTIVE_NAME_IN_SPEC))
+        .unwrap_or(TAG_DIRECTIVE_NAME_IN_SPEC);
+
+    let mut removed = Removed::default();
+    removed.apply_filter(schema.schema(), &tag_directive_name, filter);
+    while removed.propagate(schema.schema()) {}
+
+    if let Some(query) = &schema.schema().schema_definition.query {
+        if removed.types.contains(&query.name) {
+            return Err(SingleFederationError::QueryRootTypeInaccessible {
+                message: format!(
+                    "The contract removes every field of the query root type `{}`",
+                    query.name
+                ),
+            }
+            .into());
+        }
+    }
+
+    let mut contract = schema.schema().clone().into_inner();
+    removed.remove_from(&mut contract);
+    let contract = ValidFederationSchema::new(contract.validate()?)?;
+    api_schema::to_api_schema(contract, options)
+}
+
+fn tags<'a>(
+    directives: impl Iterator<Item = &'a Node<Directive>> + 'a,
+) -> impl Iterator<Item = &'a str> + 'a {
+    directives.filter_map(|directive| {
+        directive
+            .specified_argument_by_name(&TAG_NAME_ARGUMENT)?
+            .as_str()
+    })
+}
+
+impl Removed {
+    fn apply_filter(&mut self, schema: &Schema, tag: &Name, filter: &ContractFilter) {
+        for (type_name, ty) in &schema.types {
+            if ty.is_built_in() {
+                continue;
+            }
+            if filter.excludes(tags(ty.directives().get_all(tag).map(|d| &d.node))) {
+                self.types.insert(type_name.clone());
+                continue;
+            }
+            let type_included = filter.include.is_empty()
+                || filter.includes(tags(ty.directives().get_all(tag).map(|d| &d.node)));
+
+            let fields = match ty {
+                ExtendedType::Object(object) => &object.fields,
+                ExtendedType::Interface(interface) => &interface.fields,
+                ExtendedType::Enum(enum_) => {
+                    for (value_name, value) in &enum_.values {
+                        if filter.excludes(tags(value.directives.get_all(tag))) {
+                            self.members.insert((type_name.clone(), value_name.clone()));
+                        }
+                    }
+                    continue;
+                }
+                ExtendedType::InputObject(input_object) => {
+                    for (field_name, field) in &input_object.fields {
+                        if filter.excludes(tags(field.directives.get_all(tag))) {
+                            self.members.insert((type_name.clone(), field_name.clone()));
+                        }
+                    }
+                    continue;
+                }
+                ExtendedType::Scalar(_) | ExtendedType::Union(_) => continue,
+            };
+            for (field_name, field) in fields {
+                let field_tags = || tags(field.directives.get_all(tag));
+                if filter.excludes(field_tags())
+                    || !(type_included || filter.includes(field_tags()))
+                {
+                    self.members.insert((type_name.clone(), field_name.clone()));
+                    continue;
+                }
+                for argument in &field.arguments {
+                    if filter.excludes(tags(argument.directives.get_all(tag))) {
+                        self.arguments.insert((
+                            type_name.clone(),
+                            field_name.clone(),
+                            argument.name.clone(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the elements that reference removed elements. Returns whether anything changed.
+    fn propagate(&mut self, schema: &Schema) -> bool {
+        let mut changed = false;
+        for (type_name, ty) in &schema.types {
+            if ty.is_built_in() || self.types.contains(type_name) {
+                continue;
+            }
+            let remove_type = match ty {
+                ExtendedType::Object(object) => {
+                    self.propagate_to_fields(type_name, &object.fields, &mut changed);
+                    self.propagate_to_implementations(
+                        schema,
+                        type_name,
+                        &object.implements_interfaces,
+                        &mut changed,
+                    );
+                    self.all_members_removed(type_name, object.fields.keys())
+                }
+                ExtendedType::Interface(interface) => {
+                    self.propagate_to_fields(type_name, &interface.fields, &mut changed);
+                    self.propagate_to_implementations(
+                        schema,
+                        type_name,
+                        &interface.implements_interfaces,
+                        &mut changed,
+                    );
+                    self.all_members_removed(type_name, interface.fields.keys())
+                }
+                ExtendedType::Union(union_) => union_
+                    .members
+                    .iter()
+                    .all(|member| self.types.contains(&member.name)),
+                ExtendedType::Enum(enum_) => {
+                    self.all_members_removed(type_name, enum_.values.keys())
+                }
+                ExtendedType::InputObject(input_object) => {
+                    let mut remove_type = false;
+                    for (field_name, field) in &input_object.fields {
+                        let key = (type_name.clone(), field_name.clone());
+                        if self.members.contains(&key)
+                            || !self.types.contains(field.ty.inner_named_type())
+                        {
+                            continue;
+                        }
+                        // a required input field can't be removed without its parent type
+                        if field.is_required() {
+                            remove_type = true;
+                        } else {
+                            self.members.insert(key);
+                            changed = true;
+                        }
+                    }
+                    remove_type || self.all_members_removed(type_name, input_object.fields.keys())
+                }
+                ExtendedType::Scalar(_) => false,
+            };
+            if remove_type {
+                self.types.insert(type_name.clone());
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn propagate_to_fields(
+        &mut self,
+        type_name: &Name,
+        fields: &IndexMap<Name, Component<FieldDefinition>>,
+        changed: &mut bool,
+    ) {
+        for (field_name, field) in fields {
+            let key = (type_name.clone(), field_name.clone());
+            if self.members.contains(&key) {
+                continue;
+            }
+            if self.types.contains(field.ty.inner_named_type()) {
+                self.members.insert(key);
+                *changed = true;
+                continue;
+            }
+            for argument in &field.arguments {
+                let key = (type_name.clone(), field_name.clone(), argument.name.clone());
+                if self.arguments.contains(&key)
+                    || !self.types.contains(argument.ty.inner_named_type())
+                {
+                    continue;
+                }
+                *changed = true;
+                // a required argument can't be removed without its field
+                if argument.is_required() {
+                    self.members.insert((type_name.clone(), field_name.clone()));
+                    break;
+                }
+                self.arguments.insert(key);
+            }
+        }
+    }
+
+    /// An implementation must keep every field and argument kept by its interfaces, otherwise it
+    /// no longer implements them in the contract.
+    fn propagate_to_implementations(
+        &mut self,
+        schema: &Schema,
+        type_name: &Name,
+        implements_interfaces: &IndexSet<ComponentName>,
+        changed: &mut bool,
+    ) {
+        for interface_name in implements_interfaces {
+            let key = (type_name.clone(), interface_name.name.clone());
+            if self.implementations.contains(&key) {
+                continue;
+            }
+            let Some(interface) = schema.get_interface(interface_name) else {
+                continue;
+            };
+            let broken = self.types.contains(&interface_name.name)
+                || interface.fields.iter().any(|(field_name, field)| {
+                    if self
+                        .members
+                        .contains(&(interface_name.name.clone(), field_name.clone()))
+                    {
+                        return false;
+                    }
+                    self.members
+                        .contains(&(type_name.clone(), field_name.clone()))
+                        || field.arguments.iter().any(|argument| {
+                            !self.arguments.contains(&(
+                                interface_name.name.clone(),
+                                field_name.clone(),
+                                argument.name.clone(),
+                            )) && self.arguments.contains(&(
+                                type_name.clone(),
+                                field_name.clone(),
+                                argument.name.clone(),
+                            ))
+                        })
+                });
+            if broken {
+                self.implementations.insert(key);
+                *changed = true;
+            }
+        }
+    }
+
+    fn all_members_removed<'a>(
+        &self,
+        type_name: &Name,
+        mut members: impl Iterator<Item = &'a Name>,
+    ) -> bool {
+        members.all(|member| self.members.contains(&(type_name.clone(), member.clone())))
+    }
+
+    fn remove_from(&self, schema: &mut Schema) {
+        schema
+            .types
+            .retain(|type_name, _| !self.types.contains(type_name));
+        for (type_name, ty) in schema.types.iter_mut() {
+            let keep_member =
+                |member: &Name| !self.members.contains(&(type_name.clone(), member.clone()));
+            let keep_interface = |interface: &ComponentName| {
+                !self.types.contains(&interface.name)
+                    && !self
+                        .implementations
+                        .contains(&(type_name.clone(), interface.name.clone()))
+            };
+            match ty {
+                ExtendedType::Object(object) => {
+                    let object = object.make_mut();
+                    object.fields.retain(|name, _| keep_member(name));
+                    for (field_name, field) in object.fields.iter_mut() {
+                        self.remove_arguments(type_name, field_name, field.make_mut());
+                    }
+                    object.implements_interfaces.retain(keep_interface);
+                }
+                ExtendedType::Interface(interface) => {
+                    let interface = interface.make_mut();
+                    interface.fields.retain(|name, _| keep_member(name));
+                    for (field_name, field) in interface.fields.iter_mut() {
+                        self.remove_arguments(type_name, field_name, field.make_mut());
+                    }
+                    interface.implements_interfaces.retain(keep_interface);
+                }
+                ExtendedType::Union(union_) => {
+                    union_
+                        .make_mut()
+                        .members
+                        .retain(|member| !self.types.contains(&member.name));
+                }
+                ExtendedType::Enum(enum_) => {
+                    enum_.make_mut().values.retain(|name, _| keep_member(name));
+                }
+                ExtendedType::InputObject(input_object) => {
+                    input_object
+                        .make_mut()
+                        .fields
+                        .retain(|name, _| keep_member(name));
+                }
+                ExtendedType::Scalar(_) => {}
+            }
+        }
+
+        let schema_definition = schema.schema_definition.make_mut();
+        for root in [
+            &mut schema_definition.mutation,
+            &mut schema_definition.subscription,
+        ] {
+            if root
+                .as_ref()
+                .is_some_and(|root| self.types.contains(&root.name))
+            {
+                *root = None;
+            }
+        }
+    }
+
+    fn remove_arguments(&self, type_name: &Name, field_name: &Name, field: &mut FieldDefinition) {
+        field.arguments.retain(|argument| {
+            !self.arguments.contains(&(
+                type_name.clone(),
+                field_name.clone(),
+                argument.name.clone(),
+            ))
+        });
+    }
+}
@@ -217,7 +217,7 @@ pub enum SingleFederationError {
     InterfaceKeyNotOnImplementation { message: String },
     #[error("{message}")]
     InterfaceKeyMissingImplementationType { message: String },
-    #[error("@defer is not supported on subscriptions")]
+    #[error("@defer is not supported on the root fields of subscriptions")]
     DeferredSubscriptionUnsupported,
 }
 
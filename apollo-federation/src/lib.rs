@@ -20,6 +20,7 @@
 
 mod api_schema;
 mod compat;
+mod contract;
 mod display_helpers;
 pub mod error;
 pub mod link;
@@ -39,6 +40,7 @@ use link::join_spec_definition::JOIN_VERSIONS;
 use schema::FederationSchema;
 
 pub use crate::api_schema::ApiSchemaOptions;
+pub use crate::contract::ContractFilter;
 use crate::error::FederationError;
 use crate::error::SingleFederationError;
 use crate::link::join_spec_definition::JoinSpecDefinition;
@@ -128,6 +130,16 @@ impl Supergraph {
         api_schema::to_api_schema(self.schema.clone(), options)
     }
 
+    /// Generates the API Schema of a contract of this supergraph schema: the API schema filtered
+    /// by `@tag`.
+    pub fn to_contract_api_schema(
+        &self,
+        filter: &ContractFilter,
+        options: ApiSchemaOptions,
+    ) -> Result<ValidFederationSchema, FederationError> {
+        contract::to_contract_api_schema(self.schema.clone(), filter, options)
+    }
+
     pub fn extract_subgraphs(&self) -> Result<ValidFederationSubgraphs, FederationError> {
         supergraph::extract_subgraphs_from_supergraph(&self.schema, None)
     }
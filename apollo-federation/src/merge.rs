@@ -48,10 +48,14 @@ use crate::link::inaccessible_spec_definition::InaccessibleSpecDefinition;
 use crate::link::inaccessible_spec_definition::INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC;
 use crate::link::join_spec_definition::JOIN_OVERRIDE_LABEL_ARGUMENT_NAME;
 use crate::link::spec::Identity;
+use crate::link::spec::Url;
 use crate::link::spec::Version;
 use crate::link::spec_definition::SpecDefinition;
+use crate::link::Link;
 use crate::link::LinksMetadata;
 use crate::schema::ValidFederationSchema;
+use crate::subgraph::spec::COMPOSE_DIRECTIVE_NAME;
+use crate::subgraph::spec::TAG_DIRECTIVE_NAME;
 use crate::subgraph::ValidSubgraph;
 use crate::ValidFederationSubgraph;
 use crate::ValidFederationSubgraphs;
@@ -155,13 +159,15 @@ impl Merger {
         }
 
         let mut supergraph = Schema::new();
-        // TODO handle @compose
 
         // add core features
         // TODO verify federation versions across subgraphs
         add_core_feature_link(&mut supergraph);
         add_core_feature_join(&mut supergraph, &subgraphs_and_enum_values);
 
+        let composed_directives = self.compose_directives(&supergraph, &subgraphs);
+        add_composed_directives(&mut supergraph, &composed_directives);
+
         // create stubs
         for (subgraph, subgraph_name) in &subgraphs_and_enum_values {
             let sources = Arc::make_mut(&mut supergraph.sources);
@@ -170,10 +176,14 @@ impl Merger {
             }
 
             self.merge_schema(&mut supergraph, subgraph);
-            // TODO merge directives
 
             let metadata = subgraph.schema.metadata();
-            let relevant_directives = DirectiveNames::for_metadata(&metadata);
+            let mut relevant_directives = DirectiveNames::for_metadata(&metadata);
+            relevant_directives.composed = composed_directives
+                .iter()
+                .filter(|(_, directive)| directive.subgraphs.contains(&subgraph.name))
+                .map(|(name, _)| name.clone())
+                .collect();
 
             for (type_name, ty) in &subgraph.schema.schema().types {
                 if ty.is_built_in() || !is_mergeable_type(type_name) {
@@ -230,15 +240,11 @@ impl Merger {
                     }
                 }
             }
-
-            // merge executable directives
-            for (_, directive) in subgraph.schema.schema().directive_definitions.iter() {
-                if is_executable_directive(directive) {
-                    merge_directive(&mut supergraph.directive_definitions, directive);
-                }
-            }
         }
 
+        self.merge_composed_directive_applications(&mut supergraph, &composed_directives);
+        self.merge_executable_directives(&mut supergraph, &subgraphs);
+
         if self.needs_inaccessible {
             add_core_feature_inaccessible(&mut supergraph);
         }
@@ -311,7 +317,7 @@ impl Merger {
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             e.make_mut().directives.extend(join_type_directives);
 
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 metadata,
                 &mut e.make_mut().directives,
                 &enum_type.directives,
@@ -333,7 +339,7 @@ impl Merger {
                     }));
                 self.merge_descriptions(&mut ev.make_mut().description, &enum_value.description);
 
-                self.add_inaccessible(
+                self.merge_applied_directives(
                     metadata,
                     &mut ev.make_mut().directives,
                     &enum_value.directives,
@@ -372,7 +378,7 @@ impl Merger {
             let mutable_object = obj.make_mut();
             mutable_object.directives.extend(join_type_directives);
 
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut mutable_object.directives,
                 &input_object.directives,
@@ -386,7 +392,7 @@ impl Merger {
                         // TODO warning - mismatch on input fields
                     }
                     Occupied(mut i) => {
-                        self.add_inaccessible(
+                        self.merge_applied_directives(
                             directive_names,
                             &mut i.get_mut().make_mut().directives,
                             &field.directives,
@@ -395,7 +401,6 @@ impl Merger {
                         // TODO check description
                         // TODO check type
                         // TODO check default value
-                        // TODO process directives
                     }
                 }
             }
@@ -423,7 +428,7 @@ impl Merger {
             let mutable_intf = intf.make_mut();
             mutable_intf.directives.extend(join_type_directives);
 
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut mutable_intf.directives,
                 &interface.directives,
@@ -442,17 +447,21 @@ impl Merger {
                             directives: Default::default(),
                         }));
 
-                        self.add_inaccessible(
+                        self.merge_applied_directives(
                             directive_names,
                             &mut f.make_mut().directives,
                             &field.directives,
                         );
                     }
-                    Occupied(_i) => {
+                    Occupied(mut i) => {
+                        self.merge_applied_directives(
+                            directive_names,
+                            &mut i.get_mut().make_mut().directives,
+                            &field.directives,
+                        );
                         // TODO check description
                         // TODO check type
                         // TODO check default value
-                        // TODO process directives
                     }
                 }
             }
@@ -485,7 +494,7 @@ impl Merger {
             let mutable_object = obj.make_mut();
             mutable_object.directives.extend(join_type_directives);
             self.merge_descriptions(&mut mutable_object.description, &object.description);
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut mutable_object.directives,
                 &object.directives,
@@ -527,7 +536,7 @@ impl Merger {
                     &field.description,
                 );
 
-                self.add_inaccessible(
+                self.merge_applied_directives(
                     directive_names,
                     &mut supergraph_field.make_mut().directives,
                     &field.directives,
@@ -540,7 +549,7 @@ impl Merger {
                         .find_map(|a| (a.name == arg.name).then(|| a.make_mut()));
 
                     if let Some(argument) = argument_to_merge {
-                        self.add_inaccessible(
+                        self.merge_applied_directives(
                             directive_names,
                             &mut argument.directives,
                             &arg.directives,
@@ -554,7 +563,7 @@ impl Merger {
                             default_value: arg.default_value.clone(),
                         };
 
-                        self.add_inaccessible(
+                        self.merge_applied_directives(
                             directive_names,
                             &mut argument.directives,
                             &arg.directives,
@@ -636,7 +645,7 @@ impl Merger {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             u.make_mut().directives.extend(join_type_directives);
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut u.make_mut().directives,
                 &union.directives,
@@ -678,7 +687,7 @@ impl Merger {
             let join_type_directives =
                 join_type_applied_directive(subgraph_name.clone(), iter::empty(), false);
             s.make_mut().directives.extend(join_type_directives);
-            self.add_inaccessible(
+            self.merge_applied_directives(
                 directive_names,
                 &mut s.make_mut().directives,
                 &ty.directives,
@@ -715,6 +724,584 @@ impl Merger {
             );
         }
     }
+
+    fn merge_applied_directives<I>(
+        &mut self,
+        directive_names: &DirectiveNames,
+        new_directives: &mut Vec<I>,
+        original_directives: &[I],
+    ) where
+        I: AsRef<Directive> + From<Directive> + Clone,
+    {
+        self.add_inaccessible(directive_names, new_directives, original_directives);
+
+        // Applications of composed directives are all copied over, and deduplicated once every
+        // subgraph has been merged (see `merge_composed_directive_applications`).
+        new_directives.extend(
+            original_directives
+                .iter()
+                .filter(|d| directive_names.composed.contains(&d.as_ref().name))
+                .cloned(),
+        );
+    }
+
+    /// Collects the directives that subgraphs request to be part of the supergraph through
+    /// `@composeDirective`, and validates that they can be composed.
+    fn compose_directives(
+        &mut self,
+        supergraph: &Schema,
+        subgraphs: &[ValidFederationSubgraph],
+    ) -> IndexMap<Name, ComposedDirective> {
+        // The composed directives of each feature, by name in the feature's specification
+        let mut features: IndexMap<Identity, IndexMap<Name, Vec<ComposeDirectiveRequest>>> =
+            IndexMap::default();
+        for subgraph in subgraphs {
+            let Some(metadata) = subgraph.schema.metadata() else {
+                continue;
+            };
+            let Some(federation_link) = metadata.for_identity(&Identity::federation_identity())
+            else {
+                continue;
+            };
+            let compose_directive_name =
+                federation_link.directive_name_in_schema(&COMPOSE_DIRECTIVE_NAME);
+            let schema = subgraph.schema.schema();
+            for application in schema
+                .schema_definition
+                .directives
+                .get_all(&compose_directive_name)
+            {
+                let Some(argument) = directive_string_arg_value(application, &name!("name")) else {
+                    continue;
+                };
+                let Some(directive_name) = argument.strip_prefix('@') else {
+                    self.errors.push(format!(
+                        r#"Argument to @composeDirective "{argument}" in subgraph "{}" must have a leading "@""#,
+                        subgraph.name
+                    ));
+                    continue;
+                };
+                let Some(definition) = Name::new(directive_name)
+                    .ok()
+                    .and_then(|name| schema.directive_definitions.get(&name))
+                else {
+                    self.errors.push(format!(
+                        r#"Could not find matching directive definition for argument to @composeDirective "{argument}" in subgraph "{}""#,
+                        subgraph.name
+                    ));
+                    continue;
+                };
+                let Some(linked) = metadata.source_link_of_directive(&definition.name) else {
+                    self.errors.push(format!(
+                        r#"Directive "{argument}" in subgraph "{}" cannot be composed because it is not a member of a core feature"#,
+                        subgraph.name
+                    ));
+                    continue;
+                };
+
+                let identity = &linked.link.url.identity;
+                let name_in_spec = match &linked.import {
+                    Some(import) => import.element.clone(),
+                    None => definition
+                        .name
+                        .strip_prefix(&format!("{}__", linked.link.spec_name_in_schema()))
+                        .and_then(|name| Name::new(name).ok())
+                        .unwrap_or_else(|| identity.name.clone()),
+                };
+                if *identity == Identity::federation_identity() {
+                    if name_in_spec == TAG_DIRECTIVE_NAME
+                        || name_in_spec == INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC
+                    {
+                        self.composition_hints.push(format!(
+                            r#"Directive "{argument}" in subgraph "{}" should not be explicitly composed since it is a federation directive composed by default"#,
+                            subgraph.name
+                        ));
+                    } else {
+                        self.errors.push(format!(
+                            r#"Composing federation directive "{argument}" in subgraph "{}" is not supported"#,
+                            subgraph.name
+                        ));
+                    }
+                    continue;
+                }
+                if [
+                    Identity::core_identity(),
+                    Identity::link_identity(),
+                    Identity::join_identity(),
+                ]
+                .contains(identity)
+                {
+                    self.errors.push(format!(
+                        r#"Directive "{argument}" in subgraph "{}" cannot be composed because it belongs to the "{identity}" specification"#,
+                        subgraph.name
+                    ));
+                    continue;
+                }
+
+                features
+                    .entry(identity.clone())
+                    .or_default()
+                    .entry(name_in_spec)
+                    .or_default()
+                    .push(ComposeDirectiveRequest {
+                        subgraph: subgraph.name.clone(),
+                        link: linked.link.clone(),
+                        imported: linked.import.is_some(),
+                        definition: definition.clone(),
+                    });
+            }
+        }
+
+        let mut composed: IndexMap<Name, ComposedDirective> = IndexMap::default();
+        for (identity, directives) in features {
+            let requests = || directives.values().flatten();
+            if requests()
+                .map(|request| request.link.url.version.major)
+                .all_equal_value()
+                .is_err()
+            {
+                self.errors.push(format!(
+                    r#"Core feature "{identity}" requested to be merged has major version mismatch across subgraphs"#
+                ));
+                continue;
+            }
+            // All the directives of a feature are linked from the latest version used by subgraphs
+            let Some(url) = requests()
+                .map(|request| &request.link.url)
+                .max_by_key(|url| &url.version)
+                .cloned()
+            else {
+                continue;
+            };
+
+            for (name_in_spec, requests) in &directives {
+                let mut names = requests
+                    .iter()
+                    .unique_by(|request| &request.definition.name);
+                if let (Some(first), Some(second)) = (names.next(), names.next()) {
+                    self.errors.push(format!(
+                        r#"Composed directive "@{name_in_spec}" of feature "{identity}" is not named consistently in all subgraphs: it is named "@{}" in subgraph "{}" but "@{}" in subgraph "{}""#,
+                        first.definition.name,
+                        first.subgraph,
+                        second.definition.name,
+                        second.subgraph,
+                    ));
+                    continue;
+                }
+
+                // The definition of the latest version of the feature is used in the supergraph.
+                // Other subgraphs must use a compatible definition: the same one if they link the
+                // same version, or a subset of it for older versions.
+                let Some(latest) = requests
+                    .iter()
+                    .rev()
+                    .max_by_key(|request| &request.link.url.version)
+                else {
+                    continue;
+                };
+                let name = latest.definition.name.clone();
+                let mut consistent = true;
+                for request in requests {
+                    let compatible =
+                        is_compatible_directive_definition(&latest.definition, &request.definition)
+                            && (request.link.url.version != latest.link.url.version
+                                || is_compatible_directive_definition(
+                                    &request.definition,
+                                    &latest.definition,
+                                ));
+                    if !compatible {
+                        self.errors.push(format!(
+                            r#"Composed directive "@{name}" has incompatible definitions in subgraphs "{}" and "{}""#,
+                            latest.subgraph, request.subgraph
+                        ));
+                        consistent = false;
+                    }
+                }
+                if !consistent {
+                    continue;
+                }
+
+                if supergraph.directive_definitions.contains_key(&name)
+                    || name == INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC
+                {
+                    self.errors.push(format!(
+                        r#"Directive "@{name}" in subgraph "{}" cannot be composed because its name conflicts with a supergraph directive"#,
+                        latest.subgraph
+                    ));
+                    continue;
+                }
+                if let Some(existing) = composed.get(&name) {
+                    self.errors.push(format!(
+                        r#"Composed directive "@{name}" is defined by different features: "{}" and "{identity}""#,
+                        existing.url.identity
+                    ));
+                    continue;
+                }
+
+                composed.insert(
+                    name,
+                    ComposedDirective {
+                        url: url.clone(),
+                        name_in_spec: name_in_spec.clone(),
+                        spec_alias: latest.link.spec_alias.clone().filter(|_| !latest.imported),
+                        imported: latest.imported,
+                        definition: latest.definition.clone(),
+                        subgraphs: requests
+                            .iter()
+                            .map(|request| request.subgraph.clone())
+                            .collect(),
+                    },
+                );
+            }
+        }
+        composed
+    }
+
+    /// Deduplicates the applications of composed directives coming from different subgraphs.
+    /// Repeatable directives keep every distinct application, while non-repeatable directives keep
+    /// the most common one.
+    fn merge_composed_directive_applications(
+        &mut self,
+        supergraph: &mut Schema,
+        composed_directives: &IndexMap<Name, ComposedDirective>,
+    ) {
+        if composed_directives.is_empty() {
+            return;
+        }
+        for (type_name, ty) in supergraph.types.iter_mut() {
+            match ty {
+                ExtendedType::Object(object) => {
+                    let object = object.make_mut();
+                    self.dedupe_composed_directives(
+                        composed_directives,
+                        type_name,
+                        &mut object.directives,
+                    );
+                    for (field_name, field) in object.fields.iter_mut() {
+                        self.dedupe_field_composed_directives(
+                            composed_directives,
+                            &format!("{type_name}.{field_name}"),
+                            field.make_mut(),
+                        );
+                    }
+                }
+                ExtendedType::Interface(interface) => {
+                    let interface = interface.make_mut();
+                    self.dedupe_composed_directives(
+                        composed_directives,
+                        type_name,
+                        &mut interface.directives,
+                    );
+                    for (field_name, field) in interface.fields.iter_mut() {
+                        self.dedupe_field_composed_directives(
+                            composed_directives,
+                            &format!("{type_name}.{field_name}"),
+                            field.make_mut(),
+                        );
+                    }
+                }
+                ExtendedType::InputObject(input_object) => {
+                    let input_object = input_object.make_mut();
+                    self.dedupe_composed_directives(
+                        composed_directives,
+                        type_name,
+                        &mut input_object.directives,
+                    );
+                    for (field_name, field) in input_object.fields.iter_mut() {
+                        self.dedupe_composed_directives(
+                            composed_directives,
+                            &format!("{type_name}.{field_name}"),
+                            &mut field.make_mut().directives,
+                        );
+                    }
+                }
+                ExtendedType::Enum(enum_type) => {
+                    let enum_type = enum_type.make_mut();
+                    self.dedupe_composed_directives(
+                        composed_directives,
+                        type_name,
+                        &mut enum_type.directives,
+                    );
+                    for (value_name, value) in enum_type.values.iter_mut() {
+                        self.dedupe_composed_directives(
+                            composed_directives,
+                            &format!("{type_name}.{value_name}"),
+                            &mut value.make_mut().directives,
+                        );
+                    }
+                }
+                ExtendedType::Union(union) => self.dedupe_composed_directives(
+                    composed_directives,
+                    type_name,
+                    &mut union.make_mut().directives,
+                ),
+                ExtendedType::Scalar(scalar) => self.dedupe_composed_directives(
+                    composed_directives,
+                    type_name,
+                    &mut scalar.make_mut().directives,
+                ),
+            }
+        }
+    }
+
+    fn dedupe_field_composed_directives(
+        &mut self,
+        composed_directives: &IndexMap<Name, ComposedDirective>,
+        coordinate: &str,
+        field: &mut FieldDefinition,
+    ) {
+        self.dedupe_composed_directives(composed_directives, coordinate, &mut field.directives);
+        for argument in field.arguments.iter_mut() {
+            let argument = argument.make_mut();
+            self.dedupe_composed_directives(
+                composed_directives,
+                &format!("{coordinate}({}:)", argument.name),
+                &mut argument.directives,
+            );
+        }
+    }
+
+    fn dedupe_composed_directives<I>(
+        &mut self,
+        composed_directives: &IndexMap<Name, ComposedDirective>,
+        coordinate: &str,
+        directives: &mut Vec<I>,
+    ) where
+        I: AsRef<Directive>,
+    {
+        for (name, composed) in composed_directives {
+            // The distinct applications of the directive, with their number of occurrences
+            let mut applications: Vec<(&Directive, usize)> = Vec::new();
+            for directive in directives.iter().map(AsRef::as_ref) {
+                if directive.name != *name {
+                    continue;
+                }
+                match applications.iter_mut().find(|(d, _)| *d == directive) {
+                    Some((_, count)) => *count += 1,
+                    None => applications.push((directive, 1)),
+                }
+            }
+
+            let kept: Vec<Directive> = if composed.definition.repeatable {
+                applications.iter().map(|(d, _)| (*d).clone()).collect()
+            } else {
+                // On ties, the first application wins
+                let most_common = applications
+                    .iter()
+                    .rev()
+                    .max_by_key(|(_, count)| *count)
+                    .map(|(d, _)| (*d).clone());
+                if applications.len() > 1 {
+                    if let Some(most_common) = &most_common {
+                        self.composition_hints.push(format!(
+                            r#"Non-repeatable directive "@{name}" is applied to "{coordinate}" with different arguments across subgraphs. The supergraph will use the most common application, {most_common}"#
+                        ));
+                    }
+                }
+                most_common.into_iter().collect()
+            };
+
+            // Keep the first occurrence of each kept application
+            let mut kept = kept;
+            directives.retain(|directive| {
+                let directive = directive.as_ref();
+                if directive.name != *name {
+                    return true;
+                }
+                match kept.iter().position(|kept| kept == directive) {
+                    Some(index) => {
+                        kept.remove(index);
+                        true
+                    }
+                    None => false,
+                }
+            });
+        }
+    }
+
+    /// Executable directives are only part of the supergraph if all subgraphs define them. The
+    /// supergraph then uses the intersection of their locations and arguments.
+    fn merge_executable_directives(
+        &mut self,
+        supergraph: &mut Schema,
+        subgraphs: &[ValidFederationSubgraph],
+    ) {
+        let names: IndexSet<&Name> = subgraphs
+            .iter()
+            .flat_map(|subgraph| subgraph.schema.schema().directive_definitions.values())
+            .filter(|definition| is_executable_directive(definition))
+            .map(|definition| &definition.name)
+            .filter(|name| !supergraph.directive_definitions.contains_key(*name))
+            .collect();
+
+        for name in names {
+            let (definitions, missing): (Vec<_>, Vec<_>) = subgraphs
+                .iter()
+                .map(|subgraph| {
+                    (
+                        subgraph.name.as_str(),
+                        subgraph.schema.schema().directive_definitions.get(name),
+                    )
+                })
+                .partition(|(_, definition)| definition.is_some());
+            let definitions = definitions
+                .into_iter()
+                .filter_map(|(subgraph, definition)| Some((subgraph, definition?)))
+                .collect_vec();
+            if !missing.is_empty() {
+                self.composition_hints.push(format!(
+                    r#"Executable directive "@{name}" will not be part of the supergraph as it does not appear in all subgraphs: it is defined in {} but not in {}"#,
+                    quoted_subgraph_names(definitions.iter().map(|(subgraph, _)| *subgraph)),
+                    quoted_subgraph_names(missing.iter().map(|(subgraph, _)| *subgraph)),
+                ));
+                continue;
+            }
+            let Some((_, first)) = definitions.first() else {
+                continue;
+            };
+
+            let locations = first
+                .locations
+                .iter()
+                .filter(|location| {
+                    EXECUTABLE_DIRECTIVE_LOCATIONS.contains(location)
+                        && definitions
+                            .iter()
+                            .all(|(_, definition)| definition.locations.contains(location))
+                })
+                .cloned()
+                .collect_vec();
+            if locations.is_empty() {
+                self.composition_hints.push(format!(
+                    r#"Executable directive "@{name}" will not be part of the supergraph as it has no location common to all subgraphs"#
+                ));
+                continue;
+            }
+            let all_locations_shared = definitions.iter().all(|(_, definition)| {
+                definition.locations.iter().all(|location| {
+                    !EXECUTABLE_DIRECTIVE_LOCATIONS.contains(location)
+                        || locations.contains(location)
+                })
+            });
+            if !all_locations_shared {
+                self.composition_hints.push(format!(
+                    r#"Executable directive "@{name}" has inconsistent locations across subgraphs and will use {} (intersection of all subgraphs) in the supergraph"#,
+                    locations.iter().join(" | ")
+                ));
+            }
+
+            let repeatable = definitions
+                .iter()
+                .all(|(_, definition)| definition.repeatable);
+            if !repeatable
+                && definitions
+                    .iter()
+                    .any(|(_, definition)| definition.repeatable)
+            {
+                self.composition_hints.push(format!(
+                    r#"Executable directive "@{name}" will not be repeatable in the supergraph as it is not repeatable in all subgraphs"#
+                ));
+            }
+
+            let argument_names: IndexSet<&Name> = definitions
+                .iter()
+                .flat_map(|(_, definition)| definition.arguments.iter())
+                .map(|argument| &argument.name)
+                .collect();
+            let mut arguments = Vec::new();
+            for argument_name in argument_names {
+                let (present, absent): (Vec<_>, Vec<_>) = definitions
+                    .iter()
+                    .map(|(subgraph, definition)| {
+                        (*subgraph, definition.argument_by_name(argument_name))
+                    })
+                    .partition(|(_, argument)| argument.is_some());
+                let present = present
+                    .into_iter()
+                    .filter_map(|(subgraph, argument)| Some((subgraph, argument?)))
+                    .collect_vec();
+                let coordinate = format!("@{name}({argument_name}:)");
+                if !absent.is_empty() {
+                    let required = present
+                        .iter()
+                        .filter(|(_, argument)| argument.is_required())
+                        .map(|(subgraph, _)| *subgraph)
+                        .collect_vec();
+                    if required.is_empty() {
+                        self.composition_hints.push(format!(
+                            r#"Argument "{coordinate}" will not be part of the supergraph as it does not appear in all subgraphs: it is defined in {} but not in {}"#,
+                            quoted_subgraph_names(present.iter().map(|(subgraph, _)| *subgraph)),
+                            quoted_subgraph_names(absent.iter().map(|(subgraph, _)| *subgraph)),
+                        ));
+                    } else {
+                        self.errors.push(format!(
+                            r#"Argument "{coordinate}" is required in some subgraphs but does not appear in all subgraphs: it is required in {} but does not appear in {}"#,
+                            quoted_subgraph_names(required.into_iter()),
+                            quoted_subgraph_names(absent.iter().map(|(subgraph, _)| *subgraph)),
+                        ));
+                    }
+                    continue;
+                }
+                let Some((_, argument)) = present.first() else {
+                    continue;
+                };
+                if present.iter().any(|(_, other)| other.ty != argument.ty) {
+                    self.errors.push(format!(
+                        r#"Argument "{coordinate}" has incompatible types across subgraphs"#
+                    ));
+                    continue;
+                }
+                if present
+                    .iter()
+                    .any(|(_, other)| other.default_value != argument.default_value)
+                {
+                    self.errors.push(format!(
+                        r#"Argument "{coordinate}" has incompatible default values across subgraphs"#
+                    ));
+                    continue;
+                }
+                arguments.push(Node::new(InputValueDefinition {
+                    name: argument.name.clone(),
+                    description: argument.description.clone(),
+                    directives: Default::default(),
+                    ty: argument.ty.clone(),
+                    default_value: argument.default_value.clone(),
+                }));
+            }
+
+            supergraph.directive_definitions.insert(
+                name.clone(),
+                Node::new(DirectiveDefinition {
+                    name: name.clone(),
+                    description: first.description.clone(),
+                    arguments,
+                    locations,
+                    repeatable,
+                }),
+            );
+        }
+    }
+}
+
+/// A subgraph's request to compose a directive.
+struct ComposeDirectiveRequest {
+    subgraph: String,
+    link: Arc<Link>,
+    imported: bool,
+    definition: Node<DirectiveDefinition>,
+}
+
+/// A directive composed through `@composeDirective`, as it is added to the supergraph.
+struct ComposedDirective {
+    /// The url of the feature defining the directive, at the latest version used by subgraphs.
+    url: Url,
+    name_in_spec: Name,
+    /// The prefix of the directive name in the supergraph, when the directive is not imported.
+    spec_alias: Option<Name>,
+    imported: bool,
+    definition: Node<DirectiveDefinition>,
+    /// The subgraphs requesting the directive to be composed.
+    subgraphs: Vec<String>,
 }
 
 struct DirectiveNames {
@@ -725,6 +1312,8 @@ struct DirectiveNames {
     interface_object: Name,
     r#override: Name,
     inaccessible: Name,
+    /// The directives the subgraph composes through `@composeDirective`.
+    composed: IndexSet<Name>,
 }
 
 impl DirectiveNames {
@@ -770,6 +1359,7 @@ impl DirectiveNames {
             interface_object,
             r#override,
             inaccessible,
+            composed: IndexSet::default(),
         }
     }
 }
@@ -1533,6 +2123,79 @@ fn join_graph_enum_type(
     (join_graph_enum_name, join_graph_enum_type)
 }
 
+/// Links the features of composed directives and adds their definitions to the supergraph.
+fn add_composed_directives(
+    supergraph: &mut Schema,
+    composed_directives: &IndexMap<Name, ComposedDirective>,
+) {
+    // All the composed directives of a feature share the same url
+    let mut features: IndexMap<&Identity, Vec<&ComposedDirective>> = IndexMap::default();
+    for directive in composed_directives.values() {
+        features
+            .entry(&directive.url.identity)
+            .or_default()
+            .push(directive);
+    }
+    for directives in features.into_values() {
+        let Some(url) = directives.first().map(|directive| &directive.url) else {
+            continue;
+        };
+        // @link(url: "https://custom.dev/feature/v1.0", import: ["@custom"])
+        let mut arguments = vec![Node::new(Argument {
+            name: name!("url"),
+            value: url.to_string().into(),
+        })];
+        if let Some(spec_alias) = directives
+            .iter()
+            .find_map(|directive| directive.spec_alias.as_ref())
+        {
+            arguments.push(Node::new(Argument {
+                name: name!("as"),
+                value: spec_alias.as_str().into(),
+            }));
+        }
+        let imports = directives
+            .iter()
+            .filter(|directive| directive.imported)
+            .map(|directive| {
+                let name_in_spec = format!("@{}", directive.name_in_spec);
+                if directive.definition.name == directive.name_in_spec {
+                    Node::new(name_in_spec.into())
+                } else {
+                    Node::new(Value::Object(vec![
+                        (name!("name"), Node::new(name_in_spec.into())),
+                        (
+                            name!("as"),
+                            Node::new(format!("@{}", directive.definition.name).into()),
+                        ),
+                    ]))
+                }
+            })
+            .collect_vec();
+        if !imports.is_empty() {
+            arguments.push(Node::new(Argument {
+                name: name!("import"),
+                value: Node::new(Value::List(imports)),
+            }));
+        }
+        supergraph
+            .schema_definition
+            .make_mut()
+            .directives
+            .push(Component::new(Directive {
+                name: name!("link"),
+                arguments,
+            }));
+
+        for directive in directives {
+            supergraph.directive_definitions.insert(
+                directive.definition.name.clone(),
+                directive.definition.clone(),
+            );
+        }
+    }
+}
+
 fn add_core_feature_inaccessible(supergraph: &mut Schema) {
     // @link(url: "https://specs.apollo.dev/inaccessible/v0.2")
     let spec = InaccessibleSpecDefinition::new(Version { major: 0, minor: 2 }, None);
@@ -1592,13 +2255,31 @@ fn parse_keys<'a>(
     )
 }
 
-fn merge_directive(
-    supergraph_directives: &mut IndexMap<Name, Node<DirectiveDefinition>>,
-    directive: &Node<DirectiveDefinition>,
-) {
-    if !supergraph_directives.contains_key(&directive.name.clone()) {
-        supergraph_directives.insert(directive.name.clone(), directive.clone());
-    }
+/// Whether `definition` accepts every usage of `other`: it has the locations and arguments of
+/// `other`, with the same types.
+fn is_compatible_directive_definition(
+    definition: &DirectiveDefinition,
+    other: &DirectiveDefinition,
+) -> bool {
+    definition.repeatable == other.repeatable
+        && other
+            .locations
+            .iter()
+            .all(|location| definition.locations.contains(location))
+        && other.arguments.iter().all(|other_argument| {
+            definition
+                .argument_by_name(&other_argument.name)
+                .is_some_and(|argument| argument.ty == other_argument.ty)
+        })
+        && definition
+            .arguments
+            .iter()
+            .filter(|argument| argument.is_required())
+            .all(|argument| other.argument_by_name(&argument.name).is_some())
+}
+
+fn quoted_subgraph_names<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names.map(|name| format!(r#""{name}""#)).join(", ")
 }
 
 #[cfg(test)]
@@ -1743,4 +2424,95 @@ mod tests {
 
         assert_snapshot!(schema.serialize());
     }
+
+    #[test]
+    fn test_compose_directive() {
+        let one_sdl = include_str!("./sources/connect/expand/merge/compose_directive_1.graphql");
+        let two_sdl = include_str!("./sources/connect/expand/merge/compose_directive_2.graphql");
+
+        let mut subgraphs = ValidFederationSubgraphs::new();
+        subgraphs
+            .add(ValidFederationSubgraph {
+                name: "compose_directive_1".to_string(),
+                url: "".to_string(),
+                schema: ValidFederationSchema::new(
+                    Schema::parse_and_validate(one_sdl, "./compose_directive_1.graphql").unwrap(),
+                )
+                .unwrap(),
+            })
+            .unwrap();
+        subgraphs
+            .add(ValidFederationSubgraph {
+                name: "compose_directive_2".to_string(),
+                url: "".to_string(),
+                schema: ValidFederationSchema::new(
+                    Schema::parse_and_validate(two_sdl, "./compose_directive_2.graphql").unwrap(),
+                )
+                .unwrap(),
+            })
+            .unwrap();
+
+        let result = merge_federation_subgraphs(subgraphs).unwrap();
+
+        assert_eq!(
+            result.composition_hints,
+            [
+                r#"Non-repeatable directive "@custom" is applied to "A" with different arguments across subgraphs. The supergraph will use the most common application, @custom(level: 1)"#,
+                r#"Executable directive "@exec" has inconsistent locations across subgraphs and will use FIELD (intersection of all subgraphs) in the supergraph"#,
+                r#"Executable directive "@exec" will not be repeatable in the supergraph as it is not repeatable in all subgraphs"#,
+                r#"Argument "@exec(b:)" will not be part of the supergraph as it does not appear in all subgraphs: it is defined in "compose_directive_1" but not in "compose_directive_2""#,
+                r#"Executable directive "@local" will not be part of the supergraph as it does not appear in all subgraphs: it is defined in "compose_directive_1" but not in "compose_directive_2""#,
+            ]
+        );
+
+        let schema = result.schema.into_inner();
+        let validation = schema.clone().validate();
+        assert!(validation.is_ok(), "{:?}", validation);
+
+        assert_snapshot!(schema.serialize());
+    }
+
+    #[test]
+    fn test_compose_directive_conflicts() {
+        let one_sdl = include_str!("./sources/connect/expand/merge/compose_directive_1.graphql");
+        let two_sdl =
+            include_str!("./sources/connect/expand/merge/compose_directive_conflict.graphql");
+
+        let mut subgraphs = ValidFederationSubgraphs::new();
+        subgraphs
+            .add(ValidFederationSubgraph {
+                name: "compose_directive_1".to_string(),
+                url: "".to_string(),
+                schema: ValidFederationSchema::new(
+                    Schema::parse_and_validate(one_sdl, "./compose_directive_1.graphql").unwrap(),
+                )
+                .unwrap(),
+            })
+            .unwrap();
+        subgraphs
+            .add(ValidFederationSubgraph {
+                name: "compose_directive_conflict".to_string(),
+                url: "".to_string(),
+                schema: ValidFederationSchema::new(
+                    Schema::parse_and_validate(two_sdl, "./compose_directive_conflict.graphql")
+                        .unwrap(),
+                )
+                .unwrap(),
+            })
+            .unwrap();
+
+        let Err(result) = merge_federation_subgraphs(subgraphs) else {
+            panic!("composition should fail");
+        };
+
+        assert_eq!(
+            result.errors,
+            [
+                r#"Composing federation directive "@federation__shareable" in subgraph "compose_directive_conflict" is not supported"#,
+                r#"Argument to @composeDirective "unknown" in subgraph "compose_directive_conflict" must have a leading "@""#,
+                r#"Core feature "https://custom.dev/custom" requested to be merged has major version mismatch across subgraphs"#,
+                r#"Composed directive "@label" of feature "https://custom.dev/label" is not named consistently in all subgraphs: it is named "@tagged" in subgraph "compose_directive_1" but "@label" in subgraph "compose_directive_conflict""#,
+            ]
+        );
+    }
 }
@@ -11,6 +11,7 @@ impl QueryPlan {
         let Self {
             node,
             statistics: _,
+            explanation: _,
         } = self;
         state.write("QueryPlan {")?;
         if let Some(node) = node {
@@ -0,0 +1,225 @@
+//! Explanations of the decisions taken by the query planner.
+//!
+//! When requested through [`QueryPlanOptions::explain`], the query planner records, for every
+//! top-level planning traversal, the options it had for each branch of the operation, which of
+//! those options were pruned before generating plans, the cost of every complete plan it
+//! generated, and the subgraph jumps (`@key` and root type resolutions) and `@requires`
+//! conditions used by the plan it picked.
+//!
+//! [`QueryPlanOptions::explain`]: crate::query_plan::query_planner::QueryPlanOptions::explain
+
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::error::FederationError;
+use crate::query_graph::graph_path::ClosedBranch;
+use crate::query_graph::graph_path::ClosedPath;
+use crate::query_graph::path_tree::OpPathTree;
+use crate::query_graph::QueryGraphEdgeTransition;
+use crate::query_plan::QueryPlanCost;
+
+/// Explains how a query plan was chosen.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct QueryPlanExplanation {
+    /// The `debug.max_evaluated_plans` option the plan was computed with.
+    pub max_evaluated_plans: u32,
+    /// The `debug.paths_limit` option the plan was computed with. Planning fails outright when
+    /// a selection has more options than this limit, so it never prunes options silently.
+    pub paths_limit: Option<u32>,
+    /// One entry per top-level planning traversal. Most operations only need one, but mutations
+    /// are planned one root field at a time, and `@defer` conditions plan each variant separately.
+    pub traversals: Vec<TraversalExplanation>,
+}
+
+/// The decisions taken by a single planning traversal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TraversalExplanation {
+    /// The branches of the operation that could be planned in more than one way. Branches with a
+    /// single option are left out since there was no decision to take.
+    pub branches: Vec<BranchExplanation>,
+    /// Every complete plan that was generated, in the order they were generated. Partial plans
+    /// whose cost already exceeded the best complete plan are abandoned and not listed.
+    pub evaluated_plans: Vec<PlanCandidate>,
+    /// The cost of the chosen plan, if any plan was found.
+    pub chosen_cost: Option<QueryPlanCost>,
+    /// The subgraph jumps and `@requires` conditions of the chosen plan.
+    pub edges: Vec<EdgeExplanation>,
+}
+
+/// The options for one branch of the operation, sorted from fewest to most subgraph jumps.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BranchExplanation {
+    pub options: Vec<OptionExplanation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OptionExplanation {
+    /// The paths of the option, as they are displayed in query planner traces.
+    pub path: String,
+    /// The number of subgraph jumps of the option.
+    pub subgraph_jumps: u32,
+    /// Whether the option was dropped without being evaluated, because the number of possible
+    /// plans exceeded `debug.max_evaluated_plans`.
+    pub pruned: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlanCandidate {
+    /// The cost of the plan.
+    pub cost: QueryPlanCost,
+    /// Whether the plan was the best one generated so far.
+    pub best_so_far: bool,
+    /// The path tree of the plan.
+    pub path_tree: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// An entity fetched from another subgraph through one of its `@key`s.
+    Key,
+    /// A root type field queried from another subgraph.
+    RootType,
+    /// A field whose `@requires` conditions had to be fetched first.
+    Requires,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EdgeExplanation {
+    pub kind: EdgeKind,
+    pub from_subgraph: String,
+    pub to_subgraph: String,
+    /// The type at the head of the edge, or the required field's coordinate for `@requires`.
+    pub target: String,
+    /// The key fields or required fields.
+    pub conditions: Option<String>,
+}
+
+impl QueryPlanExplanation {
+    pub(crate) fn new(max_evaluated_plans: u32, paths_limit: Option<u32>) -> Self {
+        Self {
+            max_evaluated_plans,
+            paths_limit,
+            traversals: Vec::new(),
+        }
+    }
+
+    pub(crate) fn start_traversal(&mut self) {
+        self.traversals.push(TraversalExplanation::default());
+    }
+
+    fn current_traversal(&mut self) -> &mut TraversalExplanation {
+        if self.traversals.is_empty() {
+            self.start_traversal();
+        }
+        self.traversals.last_mut().unwrap()
+    }
+
+    /// Records a branch with its options before and after pruning.
+    pub(crate) fn record_branch(
+        &mut self,
+        options: &[Arc<ClosedPath>],
+        remaining: &[ClosedBranch],
+    ) -> Result<(), FederationError> {
+        let options = options
+            .iter()
+            .map(|option| {
+                let subgraph_jumps = option.paths.0.iter().try_fold(0, |max_so_far, path| {
+                    Ok::<_, FederationError>(max_so_far.max(path.subgraph_jumps()?))
+                })?;
+                let pruned = !remaining
+                    .iter()
+                    .flat_map(|branch| &branch.0)
+                    .any(|kept| Arc::ptr_eq(kept, option));
+                Ok(OptionExplanation {
+                    path: option.to_string(),
+                    subgraph_jumps,
+                    pruned,
+                })
+            })
+            .collect::<Result<_, FederationError>>()?;
+        self.current_traversal()
+            .branches
+            .push(BranchExplanation { options });
+        Ok(())
+    }
+
+    pub(crate) fn record_plan(
+        &mut self,
+        path_tree: &OpPathTree,
+        cost: QueryPlanCost,
+        prev_cost: Option<QueryPlanCost>,
+    ) {
+        self.current_traversal()
+            .evaluated_plans
+            .push(PlanCandidate {
+                cost,
+                best_so_far: prev_cost.map_or(true, |prev_cost| cost < prev_cost),
+                path_tree: path_tree.to_string(),
+            });
+    }
+
+    /// Records the chosen plan of the current traversal.
+    pub(crate) fn record_chosen_plan(
+        &mut self,
+        path_tree: &OpPathTree,
+        cost: QueryPlanCost,
+    ) -> Result<(), FederationError> {
+        let mut edges = Vec::new();
+        collect_edges(path_tree, &mut edges)?;
+        let traversal = self.current_traversal();
+        traversal.chosen_cost = Some(cost);
+        traversal.edges = edges;
+        Ok(())
+    }
+}
+
+fn collect_edges(
+    tree: &OpPathTree,
+    edges: &mut Vec<EdgeExplanation>,
+) -> Result<(), FederationError> {
+    for child in &tree.childs {
+        if let Some(edge) = child.edge {
+            let (head, tail) = tree.graph.edge_endpoints(edge)?;
+            let head = tree.graph.node_weight(head)?;
+            let tail = tree.graph.node_weight(tail)?;
+            let edge_weight = tree.graph.edge_weight(edge)?;
+            let explanation = match &edge_weight.transition {
+                QueryGraphEdgeTransition::KeyResolution => {
+                    Some((EdgeKind::Key, tail.type_.to_string()))
+                }
+                QueryGraphEdgeTransition::RootTypeResolution { .. } => {
+                    Some((EdgeKind::RootType, tail.type_.to_string()))
+                }
+                QueryGraphEdgeTransition::FieldCollection {
+                    field_definition_position,
+                    ..
+                } if edge_weight.conditions.is_some() => {
+                    Some((EdgeKind::Requires, field_definition_position.to_string()))
+                }
+                _ => None,
+            };
+            if let Some((kind, target)) = explanation {
+                let explanation = EdgeExplanation {
+                    kind,
+                    from_subgraph: head.source.to_string(),
+                    to_subgraph: tail.source.to_string(),
+                    target,
+                    conditions: edge_weight
+                        .conditions
+                        .as_ref()
+                        .map(|conditions| conditions.to_string()),
+                };
+                if !edges.contains(&explanation) {
+                    edges.push(explanation);
+                }
+            }
+        }
+        if let Some(conditions) = &child.conditions {
+            collect_edges(conditions, edges)?;
+        }
+        collect_edges(&child.tree, edges)?;
+    }
+    Ok(())
+}
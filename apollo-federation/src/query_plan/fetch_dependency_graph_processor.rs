@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use apollo_compiler::collections::IndexMap;
 use apollo_compiler::collections::IndexSet;
 use apollo_compiler::executable::VariableDefinition;
 use apollo_compiler::Name;
@@ -78,8 +79,14 @@ pub(crate) struct FetchDependencyGraphToQueryPlanProcessor {
 ///    it assumes that the networking and other query processing costs are much higher than
 ///    the cost of resolving a single field. Or to put it more concretely, it assumes that
 ///    a fetch of 5 fields is probably not too different from than of 2 fields.
-#[derive(Clone, Copy)]
-pub(crate) struct FetchDependencyGraphToCostProcessor;
+///
+/// The one exception is the fetch cost itself, which can be weighted per subgraph (see
+/// `QueryPlannerConfig::subgraph_latency_weights`) when subgraphs are known to be faster or slower
+/// than others.
+#[derive(Clone, Default)]
+pub(crate) struct FetchDependencyGraphToCostProcessor {
+    subgraph_latency_weights: Arc<IndexMap<Arc<str>, f64>>,
+}
 
 /// Generic interface for "processing" a (reduced) dependency graph of fetch dependency nodes
 /// (a `FetchDependencyGraph`).
@@ -159,13 +166,20 @@ impl FetchDependencyGraphProcessor<QueryPlanCost, QueryPlanCost>
     /// (but see `selectionCost` for more details)
     /// plus some constant "premium" to account for the fact than doing each fetch is costly
     /// (and that fetch cost often dwarfted the actual cost of fields resolution).
+    ///
+    /// That premium is weighted by the latency weight of the fetched subgraph, if any.
     fn on_node(
         &mut self,
         _query_graph: &QueryGraph,
         node: &mut FetchDependencyGraphNode,
         _handled_conditions: &Conditions,
     ) -> Result<QueryPlanCost, FederationError> {
-        Ok(FETCH_COST + node.cost()?)
+        let weight = self
+            .subgraph_latency_weights
+            .get(&node.subgraph_name)
+            .copied()
+            .unwrap_or(1.0);
+        Ok(FETCH_COST * weight + node.cost()?)
     }
 
     /// We don't take conditions into account in costing for now
@@ -232,6 +246,14 @@ impl FetchDependencyGraphProcessor<QueryPlanCost, QueryPlanCost>
     }
 }
 
+impl FetchDependencyGraphToCostProcessor {
+    pub(crate) fn new(subgraph_latency_weights: Arc<IndexMap<Arc<str>, f64>>) -> Self {
+        Self {
+            subgraph_latency_weights,
+        }
+    }
+}
+
 fn parallel_cost(values: impl IntoIterator<Item = QueryPlanCost>) -> QueryPlanCost {
     values.into_iter().sum()
 }
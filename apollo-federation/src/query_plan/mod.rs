@@ -10,6 +10,7 @@ use crate::query_plan::query_planner::QueryPlanningStatistics;
 
 pub(crate) mod conditions;
 pub(crate) mod display;
+pub mod explain;
 pub(crate) mod fetch_dependency_graph;
 pub(crate) mod fetch_dependency_graph_processor;
 pub mod generate;
@@ -22,6 +23,11 @@ pub type QueryPlanCost = f64;
 pub struct QueryPlan {
     pub node: Option<TopLevelPlanNode>,
     pub statistics: QueryPlanningStatistics,
+    /// How the plan was chosen, when requested with [`QueryPlanOptions::explain`].
+    ///
+    /// [`QueryPlanOptions::explain`]: query_planner::QueryPlanOptions::explain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<explain::QueryPlanExplanation>,
 }
 
 #[derive(Debug, PartialEq, derive_more::From, Serialize)]
@@ -257,6 +263,7 @@ impl QueryPlan {
         Self {
             node: Some(node.into()),
             statistics,
+            explanation: None,
         }
     }
 }
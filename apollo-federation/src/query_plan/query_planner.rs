@@ -1,7 +1,9 @@
 use std::cell::Cell;
+use std::cell::RefCell;
 use std::num::NonZeroU32;
 use std::ops::Deref;
 use std::sync::Arc;
+use std::sync::RwLock;
 
 use apollo_compiler::collections::HashSet;
 use apollo_compiler::collections::IndexMap;
@@ -26,6 +28,7 @@ use crate::query_graph::build_federated_query_graph;
 use crate::query_graph::path_tree::OpPathTree;
 use crate::query_graph::QueryGraph;
 use crate::query_graph::QueryGraphNodeType;
+use crate::query_plan::explain::QueryPlanExplanation;
 use crate::query_plan::fetch_dependency_graph::compute_nodes_for_tree;
 use crate::query_plan::fetch_dependency_graph::FetchDependencyGraph;
 use crate::query_plan::fetch_dependency_graph::FetchDependencyGraphNodePath;
@@ -35,10 +38,15 @@ use crate::query_plan::fetch_dependency_graph_processor::FetchDependencyGraphToQ
 use crate::query_plan::query_planning_traversal::BestQueryPlanInfo;
 use crate::query_plan::query_planning_traversal::QueryPlanningParameters;
 use crate::query_plan::query_planning_traversal::QueryPlanningTraversal;
+use crate::query_plan::DeferNode;
 use crate::query_plan::FetchNode;
+use crate::query_plan::FlattenNode;
+use crate::query_plan::ParallelNode;
 use crate::query_plan::PlanNode;
+use crate::query_plan::PrimaryDeferBlock;
 use crate::query_plan::QueryPlan;
 use crate::query_plan::SequenceNode;
+use crate::query_plan::SubscriptionNode;
 use crate::query_plan::TopLevelPlanNode;
 use crate::schema::position::AbstractTypeDefinitionPosition;
 use crate::schema::position::CompositeTypeDefinitionPosition;
@@ -107,6 +115,13 @@ pub struct QueryPlannerConfig {
     ///
     /// If you aren't aware of this flag, you probably don't need it.
     pub type_conditioned_fetching: bool,
+
+    /// Per-subgraph weights applied to the cost of fetches when comparing alternative plans.
+    ///
+    /// This is a shared handle: weights set after the query planner is created are used for all
+    /// the plans computed afterwards. Subgraphs without a weight use the default of 1, so with no
+    /// weights set, plans are chosen solely on their structure.
+    pub subgraph_latency_weights: SubgraphLatencyWeights,
 }
 
 impl Default for QueryPlannerConfig {
@@ -118,6 +133,7 @@ impl Default for QueryPlannerConfig {
             incremental_delivery: Default::default(),
             debug: Default::default(),
             type_conditioned_fetching: Default::default(),
+            subgraph_latency_weights: Default::default(),
         }
     }
 }
@@ -184,6 +200,60 @@ impl Default for QueryPlannerDebugConfig {
     }
 }
 
+/// Rounded per-subgraph weights, as returned by [`SubgraphLatencyWeights::snapshot`].
+pub type LatencyWeights = Arc<IndexMap<Arc<str>, f64>>;
+
+/// Lower and upper bounds of subgraph latency weights.
+const MIN_LATENCY_WEIGHT: f64 = 0.125;
+const MAX_LATENCY_WEIGHT: f64 = 8.0;
+
+/// A shared, updatable set of per-subgraph weights for the cost of fetches.
+///
+/// A weight of 2 means a fetch to that subgraph is considered twice as costly as the default, so
+/// that the query planner favors resolving fields from faster subgraphs when it has the choice.
+/// Weights are rounded to the nearest power of `sqrt(2)` and clamped between 1/8 and 8, so that
+/// small latency variations do not change the selected plans, and the [`Hash`] implementation
+/// hashes the current rounded weights so it can be used in plan cache keys.
+#[derive(Debug, Clone, Default)]
+pub struct SubgraphLatencyWeights(Arc<RwLock<LatencyWeights>>);
+
+impl SubgraphLatencyWeights {
+    /// Replaces all the weights. Weights that round to 1 are not stored.
+    pub fn set(&self, weights: impl IntoIterator<Item = (Arc<str>, f64)>) {
+        let mut weights: IndexMap<Arc<str>, f64> = weights
+            .into_iter()
+            .filter(|(_, weight)| weight.is_finite() && *weight > 0.0)
+            .map(|(subgraph, weight)| (subgraph, Self::bucket(weight)))
+            .filter(|(_, weight)| *weight != 1.0)
+            .collect();
+        weights.sort_keys();
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(weights);
+    }
+
+    /// The current rounded weights.
+    pub fn snapshot(&self) -> LatencyWeights {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshot().is_empty()
+    }
+
+    fn bucket(weight: f64) -> f64 {
+        let weight = weight.clamp(MIN_LATENCY_WEIGHT, MAX_LATENCY_WEIGHT);
+        2f64.powf((weight.log2() * 2.0).round() / 2.0)
+    }
+}
+
+impl std::hash::Hash for SubgraphLatencyWeights {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        for (subgraph, weight) in self.snapshot().iter() {
+            subgraph.hash(state);
+            weight.to_bits().hash(state);
+        }
+    }
+}
+
 // PORT_NOTE: renamed from PlanningStatistics in the JS codebase.
 #[derive(Debug, PartialEq, Default, Serialize)]
 pub struct QueryPlanningStatistics {
@@ -208,6 +278,12 @@ pub struct QueryPlanOptions {
     /// progressive @override feature.
     // PORT_NOTE: In JS implementation this was a Map
     pub override_conditions: Vec<String>,
+    /// Whether to record how the query plan was chosen in [`QueryPlan::explanation`].
+    pub explain: bool,
+    /// Latency weights to plan with instead of the current
+    /// [`QueryPlannerConfig::subgraph_latency_weights`], so that callers caching plans by weights
+    /// plan with the exact weights they used in their cache key.
+    pub subgraph_latency_weights: Option<LatencyWeights>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -418,9 +494,6 @@ impl QueryPlanner {
                     defer_conditions,
                     has_defers,
                 } = normalized_operation.with_normalized_defer()?;
-                if has_defers && is_subscription {
-                    return Err(SingleFederationError::DeferredSubscriptionUnsupported.into());
-                }
                 (
                     operation,
                     Some(assigned_defer_labels),
@@ -481,6 +554,17 @@ impl QueryPlanner {
             operation.name.clone(),
             assigned_defer_labels,
         );
+        let explanation = options.explain.then(|| {
+            RefCell::new(QueryPlanExplanation::new(
+                self.config.debug.max_evaluated_plans.into(),
+                self.config.debug.paths_limit,
+            ))
+        });
+        let mut config = self.config.clone();
+        if let Some(weights) = options.subgraph_latency_weights {
+            config.subgraph_latency_weights =
+                SubgraphLatencyWeights(Arc::new(RwLock::new(weights)));
+        }
         let mut parameters = QueryPlanningParameters {
             supergraph_schema: self.supergraph_schema.clone(),
             federated_query_graph: self.federated_query_graph.clone(),
@@ -494,11 +578,12 @@ impl QueryPlanner {
                 .abstract_types_with_inconsistent_runtime_types
                 .clone()
                 .into(),
-            config: self.config.clone(),
+            config,
             override_conditions: EnabledOverrideConditions(HashSet::from_iter(
                 options.override_conditions,
             )),
             fetch_id_generator: Arc::new(FetchIdGenerator::new()),
+            explanation: explanation.as_ref(),
         };
 
         let root_node = match defer_conditions {
@@ -514,35 +599,13 @@ impl QueryPlanner {
 
         let root_node = match root_node {
             // If this is a subscription, we want to make sure that we return a SubscriptionNode rather than a PlanNode
-            // We potentially will need to separate "primary" from "rest"
-            // Note that if it is a subscription, we are guaranteed that nothing is deferred.
-            Some(PlanNode::Fetch(root_node)) if is_subscription => Some(
-                TopLevelPlanNode::Subscription(crate::query_plan::SubscriptionNode {
-                    primary: root_node,
-                    rest: None,
-                }),
-            ),
-            Some(PlanNode::Sequence(root_node)) if is_subscription => {
-                let Some((primary, rest)) = root_node.nodes.split_first() else {
-                    unreachable!("Sequence must have at least one node");
-                };
-                let PlanNode::Fetch(primary) = primary.clone() else {
-                    unreachable!("Primary node of a subscription is not a Fetch");
-                };
-                let rest = PlanNode::Sequence(SequenceNode {
-                    nodes: rest.to_vec(),
-                });
-                Some(TopLevelPlanNode::Subscription(
-                    crate::query_plan::SubscriptionNode {
-                        primary,
-                        rest: Some(Box::new(rest)),
-                    },
-                ))
-            }
+            // We need to separate the subscription fetch ("primary") from what is executed for each event ("rest").
             Some(node) if is_subscription => {
-                unreachable!(
-                    "Unexpected top level PlanNode: '{node:?}' when processing subscription"
-                )
+                let (primary, rest) = split_subscription_node(node)?;
+                Some(TopLevelPlanNode::Subscription(SubscriptionNode {
+                    primary,
+                    rest: rest.map(Box::new),
+                }))
             }
             Some(PlanNode::Fetch(inner)) => Some(TopLevelPlanNode::Fetch(inner)),
             Some(PlanNode::Sequence(inner)) => Some(TopLevelPlanNode::Sequence(inner)),
@@ -556,6 +619,7 @@ impl QueryPlanner {
         let plan = QueryPlan {
             node: root_node,
             statistics,
+            explanation: explanation.map(RefCell::into_inner),
         };
 
         snapshot!(plan, "query plan");
@@ -768,14 +832,20 @@ fn compute_root_parallel_best_plan(
         selection,
         has_defers,
         parameters.operation.root_kind,
-        FetchDependencyGraphToCostProcessor,
+        FetchDependencyGraphToCostProcessor::new(
+            parameters.config.subgraph_latency_weights.snapshot(),
+        ),
     )?;
 
     // Getting no plan means the query is essentially unsatisfiable (it's a valid query, but we can prove it will never return a result),
     // so we just return an empty plan.
-    Ok(planning_traversal
-        .find_best_plan()?
-        .unwrap_or_else(|| BestQueryPlanInfo::empty(parameters)))
+    let best_plan = planning_traversal.find_best_plan()?;
+    if let (Some(explanation), Some(best_plan)) = (parameters.explanation, &best_plan) {
+        explanation
+            .borrow_mut()
+            .record_chosen_plan(&best_plan.path_tree, best_plan.cost)?;
+    }
+    Ok(best_plan.unwrap_or_else(|| BestQueryPlanInfo::empty(parameters)))
 }
 
 fn compute_plan_internal(
@@ -874,6 +944,141 @@ fn generate_condition_nodes<'a>(
     }
 }
 
+/// Splits the plan of a subscription into the subscription fetch and the plan to execute for each
+/// subscription event.
+///
+/// When the subscription has deferred fragments, the subscription fetch is the first node of the
+/// primary block of a `DeferNode` (in each branch of the defer conditions, if any). That fetch is
+/// hoisted out and the `DeferNode` becomes the plan of each event, so every event gets its own
+/// initial payload followed by deferred payloads.
+fn split_subscription_node(
+    node: PlanNode,
+) -> Result<(Box<FetchNode>, Option<PlanNode>), FederationError> {
+    match node {
+        PlanNode::Fetch(primary) => Ok((primary, None)),
+        PlanNode::Sequence(SequenceNode { mut nodes }) => {
+            if nodes.is_empty() {
+                return Err(FederationError::internal(
+                    "Sequence must have at least one node",
+                ));
+            }
+            let PlanNode::Fetch(primary) = nodes.remove(0) else {
+                return Err(FederationError::internal(
+                    "Primary node of a subscription is not a Fetch",
+                ));
+            };
+            Ok((primary, Some(PlanNode::Sequence(SequenceNode { nodes }))))
+        }
+        PlanNode::Defer(DeferNode { primary, deferred }) => {
+            // Without a primary node, the subscription field itself is deferred.
+            let Some(node) = primary.node else {
+                return Err(SingleFederationError::DeferredSubscriptionUnsupported.into());
+            };
+            let (subscription, node) = split_subscription_node(*node)?;
+            let mut rest = PlanNode::Defer(DeferNode {
+                primary: PrimaryDeferBlock {
+                    sub_selection: primary.sub_selection,
+                    node: node.map(Box::new),
+                },
+                deferred,
+            });
+            // The event data is in hand by the time the rest of the plan runs, so deferred
+            // blocks must not wait on the subscription fetch: it is never executed for an event.
+            if let Some(id) = subscription.id {
+                remove_deferred_dependency(&mut rest, &id.to_string());
+            }
+            Ok((subscription, Some(rest)))
+        }
+        PlanNode::Condition(condition) => {
+            let ConditionNode {
+                condition_variable,
+                if_clause,
+                else_clause,
+            } = *condition;
+            let (if_subscription, if_clause) = match if_clause {
+                Some(node) => {
+                    let (subscription, rest) = split_subscription_node(*node)?;
+                    (Some(subscription), rest)
+                }
+                None => (None, None),
+            };
+            let (else_subscription, else_clause) = match else_clause {
+                Some(node) => {
+                    let (subscription, rest) = split_subscription_node(*node)?;
+                    (Some(subscription), rest)
+                }
+                None => (None, None),
+            };
+            let subscription = match (if_subscription, else_subscription) {
+                (Some(if_subscription), Some(else_subscription)) => {
+                    // Fetch ids are only there to track defer dependencies, which are gone by now.
+                    let same_fetch = FetchNode {
+                        id: None,
+                        ..(*if_subscription).clone()
+                    } == FetchNode {
+                        id: None,
+                        ..*else_subscription
+                    };
+                    if !same_fetch {
+                        return Err(FederationError::internal(
+                            "The subscription fetch must not depend on defer conditions",
+                        ));
+                    }
+                    if_subscription
+                }
+                (Some(subscription), None) | (None, Some(subscription)) => subscription,
+                (None, None) => {
+                    return Err(FederationError::internal(
+                        "Condition node of a subscription has no branches",
+                    ))
+                }
+            };
+            let rest = (if_clause.is_some() || else_clause.is_some()).then(|| {
+                PlanNode::Condition(Box::new(ConditionNode {
+                    condition_variable,
+                    if_clause: if_clause.map(Box::new),
+                    else_clause: else_clause.map(Box::new),
+                }))
+            });
+            Ok((subscription, rest))
+        }
+        node => Err(FederationError::internal(format!(
+            "Unexpected top level PlanNode: '{node:?}' when processing subscription"
+        ))),
+    }
+}
+
+fn remove_deferred_dependency(node: &mut PlanNode, id: &str) {
+    match node {
+        PlanNode::Fetch(_) => {}
+        PlanNode::Sequence(SequenceNode { nodes }) | PlanNode::Parallel(ParallelNode { nodes }) => {
+            for node in nodes {
+                remove_deferred_dependency(node, id);
+            }
+        }
+        PlanNode::Flatten(FlattenNode { node, .. }) => remove_deferred_dependency(node, id),
+        PlanNode::Defer(DeferNode { primary, deferred }) => {
+            if let Some(node) = &mut primary.node {
+                remove_deferred_dependency(node, id);
+            }
+            for block in deferred {
+                block.depends.retain(|dependency| dependency.id != id);
+                if let Some(node) = &mut block.node {
+                    remove_deferred_dependency(node, id);
+                }
+            }
+        }
+        PlanNode::Condition(condition) => {
+            if let Some(node) = &mut condition.if_clause {
+                remove_deferred_dependency(node, id);
+            }
+            if let Some(node) = &mut condition.else_clause {
+                remove_deferred_dependency(node, id);
+            }
+        }
+    }
+}
+
 /// Tracks fragments from the original operation, along with versions rebased on other subgraphs.
 pub(crate) struct RebasedFragments {
     original_fragments: NamedFragments,
@@ -1,3 +1,4 @@
+use std::cell::RefCell;
 use std::sync::Arc;
 
 use apollo_compiler::collections::IndexSet;
@@ -29,6 +30,7 @@ use crate::query_graph::graph_path::SimultaneousPathsWithLazyIndirectPaths;
 use crate::query_graph::path_tree::OpPathTree;
 use crate::query_graph::QueryGraph;
 use crate::query_graph::QueryGraphNodeType;
+use crate::query_plan::explain::QueryPlanExplanation;
 use crate::query_plan::fetch_dependency_graph::compute_nodes_for_tree;
 use crate::query_plan::fetch_dependency_graph::FetchDependencyGraph;
 use crate::query_plan::fetch_dependency_graph::FetchDependencyGraphNodePath;
@@ -76,6 +78,9 @@ pub(crate) struct QueryPlanningParameters<'a> {
     pub(crate) config: QueryPlannerConfig,
     pub(crate) statistics: &'a QueryPlanningStatistics,
     pub(crate) override_conditions: EnabledOverrideConditions,
+    /// Where to record the decisions taken while planning, if they were requested. This is only
+    /// set for top-level traversals, not for the ones resolving edge conditions.
+    pub(crate) explanation: Option<&'a RefCell<QueryPlanExplanation>>,
 }
 
 pub(crate) struct QueryPlanningTraversal<'a, 'b> {
@@ -583,7 +588,8 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
         &mut self,
         dependency_graph: &mut FetchDependencyGraph,
     ) -> Result<QueryPlanCost, FederationError> {
-        let (main, deferred) = dependency_graph.process(self.cost_processor, self.root_kind)?;
+        let (main, deferred) =
+            dependency_graph.process(&mut self.cost_processor, self.root_kind)?;
         if deferred.is_empty() {
             Ok(main)
         } else {
@@ -615,11 +621,30 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
             "closed_branches"
         );
 
+        if let Some(explanation) = self.parameters.explanation {
+            explanation.borrow_mut().start_traversal();
+        }
         if self.closed_branches.is_empty() {
             return Ok(());
         }
         self.sort_options_in_closed_branches()?;
+        // Keep the options of branches with a choice to make, to tell which were pruned.
+        let options_before_reduce: Vec<Vec<Arc<ClosedPath>>> = match self.parameters.explanation {
+            Some(_) => self
+                .closed_branches
+                .iter()
+                .filter(|branch| branch.0.len() > 1)
+                .map(|branch| branch.0.clone())
+                .collect(),
+            None => Vec::new(),
+        };
         self.reduce_options_if_needed();
+        if let Some(explanation) = self.parameters.explanation {
+            let mut explanation = explanation.borrow_mut();
+            for options in &options_before_reduce {
+                explanation.record_branch(options, &self.closed_branches)?;
+            }
+        }
 
         snapshot!(
             name = "ClosedBranches",
@@ -1020,6 +1045,7 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
             statistics: self.parameters.statistics,
             override_conditions: self.parameters.override_conditions.clone(),
             fetch_id_generator: self.parameters.fetch_id_generator.clone(),
+            explanation: None,
         };
         let best_plan_opt = QueryPlanningTraversal::new_inner(
             &parameters,
@@ -1027,7 +1053,7 @@ impl<'a: 'b, 'b> QueryPlanningTraversal<'a, 'b> {
             self.has_defers,
             self.id_generator.clone(),
             self.root_kind,
-            self.cost_processor,
+            self.cost_processor.clone(),
             context.clone(),
             excluded_destinations.clone(),
             excluded_conditions.add_item(edge_conditions),
@@ -1070,10 +1096,15 @@ impl<'a: 'b, 'b> PlanBuilder<PlanInfo, Arc<OpPathTree>> for QueryPlanningTravers
 
     fn on_plan_generated(
         &self,
-        _plan_info: &PlanInfo,
-        _cost: QueryPlanCost,
-        _prev_cost: Option<QueryPlanCost>,
+        plan_info: &PlanInfo,
+        cost: QueryPlanCost,
+        prev_cost: Option<QueryPlanCost>,
     ) {
+        if let Some(explanation) = self.parameters.explanation {
+            explanation
+                .borrow_mut()
+                .record_plan(&plan_info.path_tree, cost, prev_cost);
+        }
         // debug log
         // if prev_cost.is_none() {
         //     print!("Computed plan with cost {}: {}", cost, plan_tree);
@@ -0,0 +1,261 @@
+use apollo_compiler::validation::Valid;
+use apollo_compiler::Schema;
+use apollo_federation::error::FederationError;
+use apollo_federation::ContractFilter;
+use apollo_federation::Supergraph;
+
+const TAG_V03_HEADER: &str = r#"
+    directive @link(url: String!, as: String, import: [link__Import], for: link__Purpose) repeatable on SCHEMA
+
+    scalar link__Import
+
+    enum link__Purpose {
+      EXECUTION
+      SECURITY
+    }
+
+    directive @tag(name: String!) repeatable on FIELD_DEFINITION | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE | INPUT_OBJECT | INPUT_FIELD_DEFINITION
+
+    schema
+      @link(url: "https://specs.apollo.dev/link/v1.0")
+      @link(url: "https://specs.apollo.dev/join/v0.2", for: EXECUTION)
+      @link(url: "https://specs.apollo.dev/tag/v0.3")
+    {
+      query: Query
+      mutation: Mutation
+    }
+"#;
+
+fn to_contract_api_schema(
+    input: &str,
+    include: &[&str],
+    exclude: &[&str],
+) -> Result<Valid<Schema>, FederationError> {
+    let sdl = format!("{TAG_V03_HEADER}{input}");
+    let graph = Supergraph::new(&sdl)?;
+    let filter = ContractFilter {
+        include: include.iter().map(|tag| tag.to_string()).collect(),
+        exclude: exclude.iter().map(|tag| tag.to_string()).collect(),
+    };
+    Ok(graph
+        .to_contract_api_schema(&filter, Default::default())?
+        .schema()
+        .clone())
+}
+
+#[test]
+fn excludes_tagged_elements() {
+    let api_schema = to_contract_api_schema(
+        r#"
+      type Query {
+        products: [Product]
+        reviews: [Review] @tag(name: "internal")
+      }
+
+      type Mutation {
+        deleteProduct(id: ID!): Boolean @tag(name: "internal")
+      }
+
+      type Product {
+        id: ID!
+        price(currency: String @tag(name: "internal")): Int
+        status: Status
+        secret: Secret
+      }
+
+      type Secret @tag(name: "internal") {
+        value: String
+      }
+
+      type Review @tag(name: "public") {
+        body: String
+      }
+
+      enum Status {
+        ACTIVE
+        DELETED @tag(name: "internal")
+      }
+    "#,
+        &[],
+        &["internal"],
+    )
+    .expect("should succeed");
+
+    insta::assert_snapshot!(api_schema, @r###"
+    type Query {
+      products: [Product]
+    }
+
+    type Product {
+      id: ID!
+      price: Int
+      status: Status
+    }
+
+    type Review {
+      body: String
+    }
+
+    enum Status {
+      ACTIVE
+    }
+    "###);
+}
+
+#[test]
+fn includes_tagged_fields() {
+    let api_schema = to_contract_api_schema(
+        r#"
+      type Query {
+        products: [Product] @tag(name: "public")
+        users: [User] @tag(name: "public")
+        internalStats: Int
+      }
+
+      type Mutation {
+        deleteProduct(id: ID!): Boolean
+      }
+
+      type Product @tag(name: "public") {
+        id: ID!
+        name: String
+        inventory: Int @tag(name: "internal")
+      }
+
+      type User {
+        id: ID! @tag(name: "public")
+        email: String
+        reviews: [Review]
+      }
+
+      type Review {
+        body: String
+      }
+    "#,
+        &["public"],
+        &["internal"],
+    )
+    .expect("should succeed");
+
+    insta::assert_snapshot!(api_schema, @r###"
+    type Query {
+      products: [Product]
+      users: [User]
+    }
+
+    type Product {
+      id: ID!
+      name: String
+    }
+
+    type User {
+      id: ID!
+    }
+    "###);
+}
+
+#[test]
+fn propagates_removals_to_keep_the_contract_valid() {
+    let api_schema = to_contract_api_schema(
+        r#"
+      type Query {
+        search(filter: Filter): [Result]
+        node(id: ID!, scope: Scope!): Node
+        all: [Node]
+      }
+
+      type Mutation {
+        noop: Boolean
+      }
+
+      interface Node {
+        id: ID!
+        createdAt: String
+      }
+
+      type Book implements Node {
+        id: ID!
+        createdAt: String @tag(name: "internal")
+      }
+
+      type Movie implements Node @tag(name: "internal") {
+        id: ID!
+        createdAt: String
+      }
+
+      type Author implements Node {
+        id: ID!
+        createdAt: String
+      }
+
+      union Result = Book | Movie
+
+      input Filter {
+        text: String
+        scope: Scope
+      }
+
+      input Scope @tag(name: "internal") {
+        name: String
+      }
+    "#,
+        &[],
+        &["internal"],
+    )
+    .expect("should succeed");
+
+    insta::assert_snapshot!(api_schema, @r###"
+    type Query {
+      search(filter: Filter): [Result]
+      all: [Node]
+    }
+
+    type Mutation {
+      noop: Boolean
+    }
+
+    interface Node {
+      id: ID!
+      createdAt: String
+    }
+
+    type Book {
+      id: ID!
+    }
+
+    type Author implements Node {
+      id: ID!
+      createdAt: String
+    }
+
+    union Result = Book
+
+    input Filter {
+      text: String
+    }
+    "###);
+}
+
+#[test]
+fn rejects_contracts_without_query_fields() {
+    let errors = to_contract_api_schema(
+        r#"
+      type Query {
+        products: [Product] @tag(name: "internal")
+      }
+
+      type Mutation {
+        noop: Boolean
+      }
+
+      type Product {
+        id: ID!
+      }
+    "#,
+        &[],
+        &["internal"],
+    )
+    .expect_err("should return an error");
+
+    insta::assert_snapshot!(errors, @"The contract removes every field of the query root type `Query`");
+}
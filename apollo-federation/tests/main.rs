@@ -1,5 +1,6 @@
 mod api_schema;
 mod composition_tests;
+mod contracts;
 mod extract_subgraphs;
 mod query_plan;
 mod subgraph;
@@ -33,6 +33,7 @@ fn some_name() {
 
 mod debug_max_evaluated_plans_configuration;
 mod defer;
+mod explain;
 mod fetch_operation_names;
 mod field_merging_with_skip_and_include;
 mod fragment_autogeneration;
@@ -49,6 +50,7 @@ mod overrides;
 mod provides;
 mod requires;
 mod shareable_root_fields;
+mod subgraph_latency_weights;
 mod subscriptions;
 // TODO: port the rest of query-planner-js/src/__tests__/buildPlan.test.ts
 
@@ -0,0 +1,213 @@
+use std::num::NonZeroU32;
+
+use apollo_federation::query_plan::explain::EdgeKind;
+use apollo_federation::query_plan::query_planner::QueryPlanOptions;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::query_plan::query_planner::QueryPlannerDebugConfig;
+
+const EXPLAIN: QueryPlanOptions = QueryPlanOptions {
+    override_conditions: Vec::new(),
+    explain: true,
+    subgraph_latency_weights: None,
+};
+
+/// Two identical subgraphs, so that every field can be fetched from either of them.
+const SUBGRAPH: &str = r#"
+      type Query {
+        t: T @shareable
+      }
+
+      type T @key(fields: "id") @shareable {
+        id: ID!
+        v1: Int
+        v2: Int
+        v3: Int
+        v4: Int
+      }
+"#;
+
+#[test]
+fn explains_pruned_options() {
+    let planner = planner!(
+        config = QueryPlannerConfig {
+            debug: QueryPlannerDebugConfig {
+                max_evaluated_plans: NonZeroU32::new(4).unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        Subgraph1: SUBGRAPH,
+        Subgraph2: SUBGRAPH,
+    );
+    let plan = assert_plan!(
+        &planner,
+        r#"
+          {
+            t {
+              v1
+              v2
+              v3
+              v4
+            }
+          }
+        "#,
+        EXPLAIN,
+        @r###"
+    QueryPlan {
+      Fetch(service: "Subgraph1") {
+        {
+          t {
+            v4
+            v1
+            v3
+            v2
+          }
+        }
+      },
+    }
+    "###
+    );
+    let explanation = plan.explanation.unwrap();
+    assert_eq!(explanation.max_evaluated_plans, 4);
+    let [traversal] = &explanation.traversals[..] else {
+        panic!("expected a single traversal");
+    };
+    // Each field can be fetched from either subgraph, which makes 16 possible plans: one option
+    // of 2 of the branches has to go to get down to 4.
+    assert_eq!(traversal.branches.len(), 4);
+    let pruned = traversal
+        .branches
+        .iter()
+        .flat_map(|branch| &branch.options)
+        .filter(|option| option.pruned)
+        .count();
+    assert_eq!(pruned, 2);
+    assert!(traversal
+        .branches
+        .iter()
+        .all(|branch| branch.options.iter().any(|option| !option.pruned)));
+    // Plans whose partial cost already exceeds the best plan are abandoned before being complete
+    assert!(!traversal.evaluated_plans.is_empty());
+    assert!(traversal.evaluated_plans.len() <= 4);
+    assert!(traversal.evaluated_plans[0].best_so_far);
+    let best_cost = traversal
+        .evaluated_plans
+        .iter()
+        .map(|plan| plan.cost)
+        .fold(f64::INFINITY, f64::min);
+    assert_eq!(traversal.chosen_cost, Some(best_cost));
+    assert!(traversal.edges.is_empty());
+}
+
+#[test]
+fn explains_requires_and_key_edges() {
+    let planner = planner!(
+        Subgraph1: r#"
+          type Query {
+            t: T
+          }
+
+          type T @key(fields: "id") {
+            id: ID!
+            a: Int
+          }
+        "#,
+        Subgraph2: r#"
+          type T @key(fields: "id") {
+            id: ID!
+            a: Int @external
+            b: Int @requires(fields: "a")
+          }
+        "#,
+    );
+    let plan = assert_plan!(
+        &planner,
+        r#"
+          {
+            t {
+              b
+            }
+          }
+        "#,
+        EXPLAIN,
+        @r###"
+    QueryPlan {
+      Sequence {
+        Fetch(service: "Subgraph1") {
+          {
+            t {
+              __typename
+              id
+              a
+            }
+          }
+        },
+        Flatten(path: "t") {
+          Fetch(service: "Subgraph2") {
+            {
+              ... on T {
+                __typename
+                id
+                a
+              }
+            } =>
+            {
+              ... on T {
+                b
+              }
+            }
+          },
+        },
+      },
+    }
+    "###
+    );
+    let explanation = plan.explanation.unwrap();
+    let [traversal] = &explanation.traversals[..] else {
+        panic!("expected a single traversal");
+    };
+    // There is a single way to plan this
+    assert!(traversal.branches.is_empty());
+    assert!(traversal.evaluated_plans.is_empty());
+    assert!(traversal.chosen_cost.is_some());
+    let edges: Vec<_> = traversal
+        .edges
+        .iter()
+        .map(|edge| {
+            (
+                &edge.kind,
+                edge.from_subgraph.as_str(),
+                edge.to_subgraph.as_str(),
+                edge.target.as_str(),
+                edge.conditions.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        edges,
+        [
+            (
+                &EdgeKind::Key,
+                "Subgraph1",
+                "Subgraph2",
+                "T",
+                Some("{ id }")
+            ),
+            (
+                &EdgeKind::Requires,
+                "Subgraph2",
+                "Subgraph2",
+                "T.b",
+                Some("{ a }")
+            ),
+            // Fetching `a` from `Subgraph1` for the `@requires` conditions
+            (
+                &EdgeKind::Key,
+                "Subgraph2",
+                "Subgraph1",
+                "T",
+                Some("{ id }")
+            ),
+        ]
+    );
+}
@@ -45,7 +45,8 @@ fn it_overrides_to_s2_when_label_is_provided() {
           }
         "#,
         QueryPlanOptions {
-            override_conditions: vec!["test".to_string()]
+            override_conditions: vec!["test".to_string()],
+            ..Default::default()
         },
         @r###"
           QueryPlan {
@@ -157,7 +158,8 @@ fn it_overrides_f1_to_s3_when_label_is_provided() {
           }
         "#,
         QueryPlanOptions {
-            override_conditions: vec!["test".to_string()]
+            override_conditions: vec!["test".to_string()],
+            ..Default::default()
         },
         @r###"
           QueryPlan {
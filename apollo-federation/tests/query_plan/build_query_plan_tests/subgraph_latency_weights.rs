@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use apollo_federation::query_plan::query_planner::QueryPlanOptions;
+use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::query_plan::query_planner::SubgraphLatencyWeights;
+
+/// Both subgraphs can resolve the whole query, so the plan only depends on the fetch cost of each.
+const SUBGRAPH: &str = r#"
+      type Query {
+        t: T @shareable
+      }
+
+      type T @key(fields: "id") @shareable {
+        id: ID!
+        v1: Int
+        v2: Int
+        v3: Int
+        v4: Int
+      }
+"#;
+
+#[test]
+fn prefers_subgraphs_with_lower_latency_weight() {
+    let config = QueryPlannerConfig::default();
+    let weights = config.subgraph_latency_weights.clone();
+    let planner = planner!(
+        config = config,
+        Subgraph1: SUBGRAPH,
+        Subgraph2: SUBGRAPH,
+    );
+    let operation = r#"
+      {
+        t {
+          v1
+          v2
+        }
+      }
+    "#;
+    assert_plan!(
+        &planner,
+        operation,
+        @r###"
+    QueryPlan {
+      Fetch(service: "Subgraph1") {
+        {
+          t {
+            v1
+            v2
+          }
+        }
+      },
+    }
+    "###
+    );
+
+    // Weights set after the planner is created apply to the following plans
+    weights.set([(Arc::from("Subgraph1"), 3.0)]);
+    assert_plan!(
+        &planner,
+        operation,
+        @r###"
+    QueryPlan {
+      Fetch(service: "Subgraph2") {
+        {
+          t {
+            v1
+            v2
+          }
+        }
+      },
+    }
+    "###
+    );
+
+    // Weights passed with the plan options take precedence over the current weights
+    let snapshot = SubgraphLatencyWeights::default();
+    snapshot.set([(Arc::from("Subgraph2"), 3.0)]);
+    assert_plan!(
+        &planner,
+        operation,
+        QueryPlanOptions {
+            subgraph_latency_weights: Some(snapshot.snapshot()),
+            ..Default::default()
+        },
+        @r###"
+    QueryPlan {
+      Fetch(service: "Subgraph1") {
+        {
+          t {
+            v1
+            v2
+          }
+        }
+      },
+    }
+    "###
+    );
+}
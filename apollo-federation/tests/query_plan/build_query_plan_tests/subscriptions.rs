@@ -2,6 +2,8 @@ use apollo_compiler::name;
 use apollo_compiler::ExecutableDocument;
 use apollo_federation::query_plan::query_planner::QueryPlanIncrementalDeliveryConfig;
 use apollo_federation::query_plan::query_planner::QueryPlannerConfig;
+use apollo_federation::query_plan::PlanNode;
+use apollo_federation::query_plan::TopLevelPlanNode;
 
 #[test]
 fn basic_subscription_query_plan() {
@@ -138,7 +140,7 @@ fn basic_subscription_with_single_subgraph() {
 }
 
 #[test]
-fn trying_to_use_defer_with_a_subcription_results_in_an_error() {
+fn subscription_with_defer() {
     let config = QueryPlannerConfig {
         incremental_delivery: QueryPlanIncrementalDeliveryConfig { enable_defer: true },
         ..Default::default()
@@ -183,11 +185,23 @@ fn trying_to_use_defer_with_a_subcription_results_in_an_error() {
           }
         }
         "#,
-        "trying_to_use_defer_with_a_subcription_results_in_an_error.graphql",
+        "subscription_with_defer.graphql",
     )
     .unwrap();
 
-    planner
+    let plan = planner
         .build_query_plan(&document, Some(name!(MySubscription)), Default::default())
-        .expect_err("should return an error");
+        .unwrap();
+    let Some(TopLevelPlanNode::Subscription(subscription)) = &plan.node else {
+        panic!("expected a subscription node, got: {plan}");
+    };
+    assert_eq!(subscription.primary.subgraph_name.as_ref(), "SubgraphA");
+    // Each event gets an initial payload with `address`, followed by a deferred payload with `name`
+    let Some(PlanNode::Defer(defer)) = subscription.rest.as_deref() else {
+        panic!("expected the rest of the subscription to be deferred, got: {plan}");
+    };
+    assert!(defer.primary.node.is_some());
+    assert_eq!(defer.deferred.len(), 1);
+    // The subscription fetch does not run for events, so nothing can wait on it
+    assert!(defer.deferred[0].depends.is_empty());
 }
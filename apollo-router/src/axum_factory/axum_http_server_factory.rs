@@ -1,4 +1,5 @@
 //! Axum http server factory. Axum provides routing capability on top of Hyper HTTP.
+use std::collections::HashSet;
 use std::fmt::Display;
 use std::pin::Pin;
 use std::sync::atomic::AtomicBool;
@@ -609,6 +610,53 @@ where
         );
     }
 
+    // The handler dispatches the requests of hosted supergraphs to their own router
+    if !configuration.supergraph.path.contains('*') {
+        let mut paths = HashSet::from([configuration.supergraph.sanitized_path()]);
+        for hosted in &configuration.experimental_hosted_supergraphs {
+            let Some(path) = &hosted.path else { continue };
+            let prefix = path.trim_end_matches('/');
+            // `/` is already served, and a wildcard under it would conflict with the other routes
+            if prefix.is_empty() {
+                continue;
+            }
+            // the supergraph also serves the paths under its own
+            for hosted_path in [
+                prefix.to_string(),
+                format!("{prefix}/*hosted_supergraph_path"),
+            ] {
+                if !paths.insert(hosted_path.clone()) {
+                    continue;
+                }
+                router = router.route(
+                    &hosted_path,
+                    get({
+                        move |Extension(service): Extension<RF>,
+                              request: Request<DecompressionBody<Body>>| {
+                            handle_graphql(
+                                service.create().boxed(),
+                                early_cancel,
+                                experimental_log_on_broken_pipe,
+                                request,
+                            )
+                        }
+                    })
+                    .post({
+                        move |Extension(service): Extension<RF>,
+                              request: Request<DecompressionBody<Body>>| {
+                            handle_graphql(
+                                service.create().boxed(),
+                                early_cancel,
+                                experimental_log_on_broken_pipe,
+                                request,
+                            )
+                        }
+                    }),
+                );
+            }
+        }
+    }
+
     router
 }
 
@@ -13,7 +13,10 @@ use fred::prelude::RedisClient;
 use fred::prelude::RedisError;
 use fred::prelude::RedisErrorKind;
 use fred::prelude::RedisPool;
+use fred::prelude::SetsInterface;
+use fred::types::ClusterHash;
 use fred::types::ClusterRouting;
+use fred::types::CustomCommand;
 use fred::types::Expiration;
 use fred::types::FromRedis;
 use fred::types::PerformanceConfig;
@@ -581,6 +584,50 @@ impl RedisCacheStorage {
         Some(total)
     }
 
+    /// Adds `members` to the set stored at each key, and extends the expiration of those sets
+    ///
+    /// The expiration of a set is never shortened, so that it lives as long as the longest lived
+    /// member added to it. Requires Redis 7 or later
+    pub(crate) async fn add_to_sets<K: KeyType>(
+        &self,
+        sets: Vec<(RedisKey<K>, Vec<String>)>,
+        ttl: Option<Duration>,
+    ) {
+        tracing::trace!("adding members to redis sets: {:?}", sets);
+        let pipeline = self.inner.next().pipeline();
+
+        for (key, members) in sets {
+            if members.is_empty() {
+                continue;
+            }
+            let key = self.make_key(key);
+            let _ = pipeline.sadd::<(), _, _>(&key, members).await;
+            if let Some(ttl) = ttl.as_ref().or(self.ttl.as_ref()) {
+                let seconds = ttl.as_secs().to_string();
+                // NX sets the expiration of a new set, GT only extends an existing one
+                for condition in ["NX", "GT"] {
+                    let _ = pipeline
+                        .custom::<(), _>(
+                            CustomCommand::new_static("EXPIRE", ClusterHash::FirstKey, false),
+                            vec![key.clone(), seconds.clone(), condition.to_string()],
+                        )
+                        .await;
+                }
+            }
+        }
+
+        let r: Result<(), RedisError> = pipeline.last().await;
+        tracing::trace!("sadd result {:?}", r);
+    }
+
+    /// Returns the members of the set stored at `key`
+    pub(crate) async fn get_set_members<K: KeyType>(
+        &self,
+        key: RedisKey<K>,
+    ) -> Result<Vec<String>, RedisError> {
+        self.inner.smembers(self.make_key(key)).await
+    }
+
     pub(crate) fn scan(
         &self,
         pattern: String,
@@ -0,0 +1,236 @@
+//! Schema contracts: filtered variants of the API schema served by the same router
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::hosted_supergraphs::request_host;
+use crate::graphql;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::Context;
+
+/// Name of the contract variant selected for a request, if any
+pub(crate) const CONTRACT_VARIANT_CONTEXT_KEY: &str = "apollo::contracts::variant";
+
+/// Schema contracts configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Contracts {
+    /// Contract variants. A request uses the first variant with a matching selector
+    pub(crate) variants: Vec<ContractVariant>,
+
+    /// Variant used by requests that match no selector. If not set, these requests use the full
+    /// API schema
+    pub(crate) default: Option<String>,
+}
+
+/// A contract variant of the API schema
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ContractVariant {
+    /// Name of the variant
+    pub(crate) name: String,
+
+    /// Only keep the fields tagged with one of these tags, or defined on a type tagged with one of
+    /// them. If empty, all fields are kept
+    #[serde(default)]
+    pub(crate) include: Vec<String>,
+
+    /// Remove the elements tagged with one of these tags
+    #[serde(default)]
+    pub(crate) exclude: Vec<String>,
+
+    /// Requests matching any of these selectors use this variant
+    #[serde(default)]
+    pub(crate) selectors: Vec<ContractSelector>,
+}
+
+/// Selects the contract variant of a request
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum ContractSelector {
+    /// The request path is equal to this value
+    Path(String),
+    /// The request host, without the port, is equal to this value
+    Host(String),
+    /// The request header has this value
+    Header {
+        /// Header name
+        name: String,
+        /// Header value
+        value: String,
+    },
+    /// The JWT claim has this value, or is an array containing it. Requires the JWT
+    /// authentication plugin
+    JwtClaim {
+        /// Claim name
+        name: String,
+        /// Claim value
+        value: String,
+    },
+}
+
+impl Contracts {
+    /// Returns the name of the variant to use for a request
+    pub(crate) fn select(
+        &self,
+        request: &http::Request<graphql::Request>,
+        context: &Context,
+    ) -> Option<String> {
+        self.variants
+            .iter()
+            .find(|variant| {
+                variant
+                    .selectors
+                    .iter()
+                    .any(|selector| selector.matches(request, context))
+            })
+            .map(|variant| variant.name.clone())
+            .or_else(|| self.default.clone())
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        for (index, variant) in self.variants.iter().enumerate() {
+            if self.variants[..index]
+                .iter()
+                .any(|other| other.name == variant.name)
+            {
+                return Err(format!("duplicate contract variant '{}'", variant.name));
+            }
+        }
+        if let Some(default) = &self.default {
+            if !self.variants.iter().any(|variant| &variant.name == default) {
+                return Err(format!("unknown default contract variant '{default}'"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ContractSelector {
+    fn matches(&self, request: &http::Request<graphql::Request>, context: &Context) -> bool {
+        match self {
+            ContractSelector::Path(path) => request.uri().path() == path,
+            ContractSelector::Host(host) => request_host(request)
+                .is_some_and(|request_host| request_host.eq_ignore_ascii_case(host)),
+            ContractSelector::Header { name, value } => header_matches(request, name, value),
+            ContractSelector::JwtClaim { name, value } => jwt_claim_matches(context, name, value),
+        }
+    }
+}
+
+/// Returns `true` if the request header has this value
+pub(crate) fn header_matches<B>(request: &http::Request<B>, name: &str, value: &str) -> bool {
+    request
+        .headers()
+        .get_all(name)
+        .iter()
+        .any(|header| header.as_bytes() == value.as_bytes())
+}
+
+/// Returns `true` if the JWT claim has this value, or is an array containing it
+pub(crate) fn jwt_claim_matches(context: &Context, name: &str, value: &str) -> bool {
+    let claims: Option<serde_json::Value> =
+        context.get(APOLLO_AUTHENTICATION_JWT_CLAIMS).ok().flatten();
+    match claims.as_ref().and_then(|claims| claims.get(name)) {
+        Some(serde_json::Value::String(claim)) => claim == value,
+        Some(serde_json::Value::Array(claims)) => {
+            claims.iter().any(|claim| claim.as_str() == Some(value))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn contracts() -> Contracts {
+        serde_json::from_value(json!({
+            "variants": [
+                {
+                    "name": "partners",
+                    "exclude": ["internal"],
+                    "selectors": [
+                        { "header": { "name": "x-contract", "value": "partners" } },
+                        { "jwt_claim": { "name": "scope", "value": "partner" } }
+                    ]
+                },
+                {
+                    "name": "public",
+                    "include": ["public"],
+                    "selectors": [
+                        { "path": "/public" },
+                        { "host": "public.example.com" }
+                    ]
+                }
+            ],
+            "default": "public"
+        }))
+        .unwrap()
+    }
+
+    fn request(uri: &str, headers: &[(&str, &str)]) -> http::Request<graphql::Request> {
+        let mut builder = http::Request::builder().uri(uri);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(graphql::Request::default()).unwrap()
+    }
+
+    #[test]
+    fn selects_the_first_matching_variant() {
+        let contracts = contracts();
+        contracts.validate().unwrap();
+        let context = Context::new();
+
+        assert_eq!(
+            contracts.select(&request("/", &[("x-contract", "partners")]), &context),
+            Some("partners".to_string())
+        );
+        assert_eq!(
+            contracts.select(&request("/public", &[]), &context),
+            Some("public".to_string())
+        );
+        assert_eq!(
+            contracts.select(
+                &request("/", &[("host", "public.example.com:4000")]),
+                &context
+            ),
+            Some("public".to_string())
+        );
+
+        context
+            .insert(
+                APOLLO_AUTHENTICATION_JWT_CLAIMS,
+                json!({ "scope": ["read", "partner"] }),
+            )
+            .unwrap();
+        assert_eq!(
+            contracts.select(&request("/public", &[]), &context),
+            Some("partners".to_string())
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_default_variant() {
+        let mut contracts = contracts();
+        let context = Context::new();
+
+        assert_eq!(
+            contracts.select(&request("/", &[("x-contract", "other")]), &context),
+            Some("public".to_string())
+        );
+
+        contracts.default = None;
+        assert_eq!(
+            contracts.select(&request("/", &[("x-contract", "other")]), &context),
+            None
+        );
+
+        contracts.default = Some("unknown".to_string());
+        assert!(contracts.validate().is_err());
+    }
+}
@@ -0,0 +1,356 @@
+//! Hosted supergraphs: several named supergraphs served by the same router
+
+use http::header::HOST;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Map;
+use serde_json::Value;
+
+use super::expansion::Expansion;
+use super::schema::validate_yaml_configuration;
+use super::schema::Mode;
+use super::Configuration;
+use super::ConfigurationError;
+
+/// Span and metric attribute holding the name of the hosted supergraph serving a request
+pub(crate) const HOSTED_SUPERGRAPH_NAME_ATTRIBUTE: &str = "apollo.supergraph.name";
+
+const HOSTED_SUPERGRAPHS_KEY: &str = "experimental_hosted_supergraphs";
+
+/// A supergraph hosted alongside the main one
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct HostedSupergraph {
+    /// Name of the supergraph. Its schema source is registered under this name
+    pub(crate) name: String,
+
+    /// Requests to this path, or to paths under it, are served by this supergraph
+    pub(crate) path: Option<String>,
+
+    /// Requests with this host, without the port, are served by this supergraph
+    pub(crate) host: Option<String>,
+
+    /// Configuration merged on top of the shared router configuration for this supergraph
+    #[serde(default)]
+    pub(crate) overrides: Map<String, Value>,
+}
+
+impl HostedSupergraph {
+    /// Returns `true` if this supergraph serves the request. When both a path and a host are
+    /// set, the request must match both
+    pub(crate) fn matches<B>(&self, request: &http::Request<B>) -> bool {
+        let path_matches = self.path.as_deref().map_or(true, |prefix| {
+            let prefix = prefix.trim_end_matches('/');
+            request
+                .uri()
+                .path()
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        });
+        let host_matches = self.host.as_deref().map_or(true, |host| {
+            request_host(request)
+                .is_some_and(|request_host| request_host.eq_ignore_ascii_case(host))
+        });
+        path_matches && host_matches
+    }
+
+    /// Builds the configuration of this supergraph from the shared configuration
+    pub(crate) fn configuration(
+        &self,
+        shared: &Configuration,
+    ) -> Result<Configuration, ConfigurationError> {
+        // The configuration is rebuilt from YAML, which a configuration built in code does not have
+        let mut yaml = shared.validated_yaml.clone().ok_or_else(|| {
+            ConfigurationError::InvalidConfiguration {
+                message: "invalid 'experimental_hosted_supergraphs' configuration",
+                error: "hosted supergraphs require a configuration loaded from YAML".to_string(),
+            }
+        })?;
+        if let Value::Object(root) = &mut yaml {
+            root.remove(HOSTED_SUPERGRAPHS_KEY);
+        }
+        merge(&mut yaml, Value::Object(self.overrides.clone()));
+        // Tag the spans and metrics of this supergraph with its name. The name cannot be a
+        // resource attribute: the tracer and meter providers, and so their resource, are global
+        // and shared by every supergraph
+        merge(
+            &mut yaml,
+            serde_json::json!({
+                "telemetry": {
+                    "instrumentation": {
+                        "spans": {
+                            "router": {
+                                "attributes": {
+                                    HOSTED_SUPERGRAPH_NAME_ATTRIBUTE: { "static": self.name }
+                                }
+                            }
+                        }
+                    }
+                }
+            }),
+        );
+        let name_attribute = serde_json::json!({
+            "name": HOSTED_SUPERGRAPH_NAME_ATTRIBUTE,
+            "value": self.name,
+        });
+        for path in [&["supergraph"][..], &["subgraph", "all"][..]] {
+            let attributes = ["telemetry", "exporters", "metrics", "common", "attributes"]
+                .iter()
+                .chain(path)
+                .fold(&mut yaml, |value, key| object_entry(value, key));
+            append(object_entry(attributes, "static"), name_attribute.clone());
+        }
+
+        let raw_yaml =
+            serde_yaml::to_string(&yaml).map_err(|e| ConfigurationError::InvalidConfiguration {
+                message: "invalid 'experimental_hosted_supergraphs' configuration",
+                error: format!(
+                    "could not serialize the configuration of '{}': {e}",
+                    self.name
+                ),
+            })?;
+        let mut configuration =
+            validate_yaml_configuration(&raw_yaml, Expansion::default()?, Mode::Upgrade)?
+                .validate()?;
+
+        // Keep the query plans of each supergraph apart in a shared Redis instance
+        if let Some(redis) = &mut configuration.supergraph.query_planning.cache.redis {
+            redis.namespace = Some(match redis.namespace.take() {
+                Some(namespace) => format!("{namespace}:{}", self.name),
+                None => self.name.clone(),
+            });
+        }
+        configuration.uplink = shared.uplink.clone();
+        configuration.hosted_supergraph = Some(self.name.clone());
+        Ok(configuration)
+    }
+}
+
+/// Merges the overrides into the configuration: objects are merged, other values are replaced
+fn merge(configuration: &mut Value, overrides: Value) {
+    match (configuration, overrides) {
+        (Value::Object(configuration), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match configuration.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        configuration.insert(key, value);
+                    }
+                }
+            }
+        }
+        (configuration, overrides) => *configuration = overrides,
+    }
+}
+
+/// Returns the value at `key`, replacing it with an empty object if it is not an object or an array
+fn object_entry<'a>(value: &'a mut Value, key: &str) -> &'a mut Value {
+    if !value.is_object() {
+        *value = Value::Object(Map::new());
+    }
+    let entry = value
+        .as_object_mut()
+        .expect("value was just made an object")
+        .entry(key)
+        .or_insert(Value::Null);
+    if !entry.is_object() && !entry.is_array() {
+        *entry = Value::Object(Map::new());
+    }
+    entry
+}
+
+/// Appends the item to the array, replacing any other value with a new array
+fn append(value: &mut Value, item: Value) {
+    match value {
+        Value::Array(items) => items.push(item),
+        _ => *value = Value::Array(vec![item]),
+    }
+}
+
+/// Returns the host of a request, without the port
+pub(crate) fn request_host<B>(request: &http::Request<B>) -> Option<&str> {
+    request.uri().host().or_else(|| {
+        request
+            .headers()
+            .get(HOST)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.rsplit_once(':').map_or(value, |(host, _)| host))
+    })
+}
+
+pub(crate) fn validate(hosted_supergraphs: &[HostedSupergraph]) -> Result<(), String> {
+    for (index, hosted) in hosted_supergraphs.iter().enumerate() {
+        if hosted_supergraphs[..index]
+            .iter()
+            .any(|other| other.name == hosted.name)
+        {
+            return Err(format!("duplicate hosted supergraph '{}'", hosted.name));
+        }
+        if hosted.path.is_none() && hosted.host.is_none() {
+            return Err(format!(
+                "hosted supergraph '{}' must have a path or a host",
+                hosted.name
+            ));
+        }
+        if let Some(path) = &hosted.path {
+            if !path.starts_with('/') || path.contains('*') || path.contains(':') {
+                return Err(format!(
+                    "the path of hosted supergraph '{}' must be an absolute path without parameters",
+                    hosted.name
+                ));
+            }
+        }
+        // Telemetry exporters and resources are global, they cannot differ between supergraphs
+        if hosted.overrides.contains_key("telemetry") {
+            return Err(format!(
+                "the overrides of hosted supergraph '{}' cannot contain telemetry configuration",
+                hosted.name
+            ));
+        }
+        if hosted.overrides.contains_key(HOSTED_SUPERGRAPHS_KEY) {
+            return Err(format!(
+                "the overrides of hosted supergraph '{}' cannot contain hosted supergraphs",
+                hosted.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn request(uri: &str, host: Option<&str>) -> http::Request<()> {
+        let mut builder = http::Request::builder().uri(uri);
+        if let Some(host) = host {
+            builder = builder.header(HOST, host);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn matches_requests_by_path_and_host() {
+        let hosted: HostedSupergraph = serde_json::from_value(serde_json::json!({
+            "name": "products",
+            "path": "/products",
+        }))
+        .unwrap();
+        assert!(hosted.matches(&request("/products", None)));
+        assert!(hosted.matches(&request("http://example.com/products", None)));
+        assert!(hosted.matches(&request("/products/graphql", None)));
+        assert!(!hosted.matches(&request("/productsgraphql", None)));
+        assert!(!hosted.matches(&request("/", None)));
+
+        let hosted: HostedSupergraph = serde_json::from_value(serde_json::json!({
+            "name": "products",
+            "path": "/graphql",
+            "host": "products.example.com",
+        }))
+        .unwrap();
+        assert!(hosted.matches(&request("/graphql", Some("products.example.com:4000"))));
+        assert!(!hosted.matches(&request("/graphql", Some("example.com"))));
+        assert!(!hosted.matches(&request("/", Some("products.example.com"))));
+    }
+
+    #[test]
+    fn builds_the_configuration_of_a_hosted_supergraph() {
+        let shared = Configuration::from_str(
+            r#"
+supergraph:
+  query_planning:
+    cache:
+      redis:
+        urls: ["redis://localhost:6379"]
+        namespace: router
+experimental_hosted_supergraphs:
+  - name: products
+    path: /products
+    overrides:
+      supergraph:
+        introspection: true
+  - name: reviews
+    host: reviews.example.com
+"#,
+        )
+        .unwrap();
+        let products = shared.experimental_hosted_supergraphs[0]
+            .configuration(&shared)
+            .unwrap();
+        assert!(products.supergraph.introspection);
+        assert!(products.experimental_hosted_supergraphs.is_empty());
+        assert_eq!(products.hosted_supergraph.as_deref(), Some("products"));
+        assert_eq!(
+            products
+                .supergraph
+                .query_planning
+                .cache
+                .redis
+                .as_ref()
+                .and_then(|redis| redis.namespace.as_deref()),
+            Some("router:products")
+        );
+
+        let reviews = shared.experimental_hosted_supergraphs[1]
+            .configuration(&shared)
+            .unwrap();
+        assert!(!reviews.supergraph.introspection);
+        let telemetry = &reviews.apollo_plugins.plugins["telemetry"];
+        for path in [
+            "/exporters/metrics/common/attributes/supergraph/static/0/value",
+            "/exporters/metrics/common/attributes/subgraph/all/static/0/value",
+        ] {
+            assert_eq!(telemetry.pointer(path), Some(&Value::from("reviews")));
+        }
+
+        // a configuration built in code cannot be rebuilt for a hosted supergraph
+        let mut built = shared.clone();
+        built.validated_yaml = None;
+        assert!(shared.experimental_hosted_supergraphs[0]
+            .configuration(&built)
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_hosted_supergraphs() {
+        let error = Configuration::from_str(
+            r#"
+experimental_hosted_supergraphs:
+  - name: products
+    path: /products
+  - name: products
+    host: products.example.com
+"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("duplicate hosted supergraph"));
+
+        let error = Configuration::from_str(
+            r#"
+experimental_hosted_supergraphs:
+  - name: products
+"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("must have a path or a host"));
+
+        let error = Configuration::from_str(
+            r#"
+experimental_hosted_supergraphs:
+  - name: products
+    path: /products
+    overrides:
+      telemetry:
+        exporters:
+          tracing:
+            common:
+              service_name: products
+"#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("cannot contain telemetry"));
+    }
+}
@@ -0,0 +1,138 @@
+//! Introspection access control and limits
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::contracts::header_matches;
+use super::contracts::jwt_claim_matches;
+use crate::graphql;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::Context;
+
+/// Introspection access control and limits. Only applies when introspection is enabled
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct IntrospectionControl {
+    /// Only requests matching one of these conditions may introspect the schema. If empty, all
+    /// requests may
+    pub(crate) allow: Vec<IntrospectionCondition>,
+
+    /// Reject introspection queries nesting fields deeper than this maximum
+    pub(crate) max_depth: Option<u32>,
+
+    /// Reject introspection queries with more `__type` fields than this maximum
+    pub(crate) max_type_lookups: Option<u32>,
+
+    /// Reject introspection queries nesting more `ofType` fields than this maximum
+    pub(crate) max_of_type_depth: Option<u32>,
+}
+
+/// A condition a request must match to introspect the schema
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum IntrospectionCondition {
+    /// The request header has this value
+    Header {
+        /// Header name
+        name: String,
+        /// Header value
+        value: String,
+    },
+    /// The client name, read from the client name header configured in telemetry, is equal to
+    /// this value
+    ClientName(String),
+    /// The JWT claim has this value, or is an array containing it. Requires the JWT
+    /// authentication plugin
+    JwtClaim {
+        /// Claim name
+        name: String,
+        /// Claim value
+        value: String,
+    },
+}
+
+impl IntrospectionControl {
+    /// Returns `true` if the request may introspect the schema
+    pub(crate) fn allows(
+        &self,
+        request: &http::Request<graphql::Request>,
+        context: &Context,
+    ) -> bool {
+        self.allow.is_empty()
+            || self
+                .allow
+                .iter()
+                .any(|condition| condition.matches(request, context))
+    }
+
+    /// Returns `true` if any introspection limit is configured
+    pub(crate) fn has_limits(&self) -> bool {
+        self.max_depth.is_some()
+            || self.max_type_lookups.is_some()
+            || self.max_of_type_depth.is_some()
+    }
+}
+
+impl IntrospectionCondition {
+    fn matches(&self, request: &http::Request<graphql::Request>, context: &Context) -> bool {
+        match self {
+            IntrospectionCondition::Header { name, value } => header_matches(request, name, value),
+            IntrospectionCondition::ClientName(client_name) => context
+                .get::<_, String>(CLIENT_NAME)
+                .ok()
+                .flatten()
+                .is_some_and(|name| &name == client_name),
+            IntrospectionCondition::JwtClaim { name, value } => {
+                jwt_claim_matches(context, name, value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+
+    fn request(headers: &[(&str, &str)]) -> http::Request<graphql::Request> {
+        let mut builder = http::Request::builder().uri("/");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(graphql::Request::default()).unwrap()
+    }
+
+    #[test]
+    fn allows_requests_matching_a_condition() {
+        let control: IntrospectionControl = serde_json::from_value(json!({
+            "allow": [
+                { "header": { "name": "x-introspection", "value": "allowed" } },
+                { "client_name": "studio" },
+                { "jwt_claim": { "name": "role", "value": "admin" } }
+            ]
+        }))
+        .unwrap();
+
+        let context = Context::new();
+        assert!(control.allows(&request(&[("x-introspection", "allowed")]), &context));
+        assert!(!control.allows(&request(&[("x-introspection", "denied")]), &context));
+        assert!(!control.allows(&request(&[]), &context));
+
+        context.insert(CLIENT_NAME, "studio".to_string()).unwrap();
+        assert!(control.allows(&request(&[]), &context));
+
+        let context = Context::new();
+        context
+            .insert(
+                APOLLO_AUTHENTICATION_JWT_CLAIMS,
+                json!({ "role": ["admin"] }),
+            )
+            .unwrap();
+        assert!(control.allows(&request(&[]), &context));
+
+        assert!(IntrospectionControl::default().allows(&request(&[]), &Context::new()));
+    }
+}
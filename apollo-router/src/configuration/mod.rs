@@ -1,4 +1,5 @@
 //! Logic for loading configuration in to an object model
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
 use std::io::BufReader;
@@ -6,6 +7,7 @@ use std::iter;
 use std::net::IpAddr;
 use std::net::SocketAddr;
 use std::num::NonZeroUsize;
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -15,6 +17,7 @@ use displaydoc::Display;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 pub(crate) use persisted_queries::PersistedQueries;
+pub(crate) use persisted_queries::PersistedQueriesLearning;
 pub(crate) use persisted_queries::PersistedQueriesPrewarmQueryPlanCache;
 #[cfg(test)]
 pub(crate) use persisted_queries::PersistedQueriesSafelist;
@@ -37,9 +40,12 @@ use serde_json::Map;
 use serde_json::Value;
 use thiserror::Error;
 
+use self::contracts::Contracts;
 use self::cors::Cors;
 use self::expansion::Expansion;
 pub(crate) use self::experimental::Discussed;
+use self::hosted_supergraphs::HostedSupergraph;
+use self::introspection::IntrospectionControl;
 pub(crate) use self::schema::generate_config_schema;
 pub(crate) use self::schema::generate_upgrade;
 use self::subgraph::SubgraphConfiguration;
@@ -52,12 +58,16 @@ use crate::plugins::limits;
 use crate::plugins::subscription::SubscriptionConfig;
 use crate::plugins::subscription::APOLLO_SUBSCRIPTION_PLUGIN;
 use crate::plugins::subscription::APOLLO_SUBSCRIPTION_PLUGIN_NAME;
+use crate::spec::ScalarFormat;
 use crate::uplink::UplinkConfig;
 use crate::ApolloRouterError;
 
+pub(crate) mod contracts;
 pub(crate) mod cors;
 pub(crate) mod expansion;
 mod experimental;
+pub(crate) mod hosted_supergraphs;
+pub(crate) mod introspection;
 pub(crate) mod metrics;
 mod persisted_queries;
 mod schema;
@@ -192,6 +202,14 @@ pub struct Configuration {
     /// Type conditioned fetching configuration.
     #[serde(default)]
     pub(crate) experimental_type_conditioned_fetching: bool,
+
+    /// Supergraphs hosted by the router alongside the main one
+    #[serde(default)]
+    pub(crate) experimental_hosted_supergraphs: Vec<HostedSupergraph>,
+
+    /// Name of the hosted supergraph this configuration was built for.
+    #[serde(skip)]
+    pub(crate) hosted_supergraph: Option<String>,
 }
 
 impl PartialEq for Configuration {
@@ -273,6 +291,7 @@ impl<'de> serde::Deserialize<'de> for Configuration {
             experimental_type_conditioned_fetching: bool,
             experimental_query_planner_mode: QueryPlannerMode,
             experimental_introspection_mode: IntrospectionMode,
+            experimental_hosted_supergraphs: Vec<HostedSupergraph>,
         }
         let mut ad_hoc: AdHocConfiguration = serde::Deserialize::deserialize(deserializer)?;
 
@@ -304,11 +323,13 @@ impl<'de> serde::Deserialize<'de> for Configuration {
             plugins: ad_hoc.plugins,
             apollo_plugins: ad_hoc.apollo_plugins,
             batching: ad_hoc.batching,
+            experimental_hosted_supergraphs: ad_hoc.experimental_hosted_supergraphs,
 
             // serde(skip)
             notify,
             uplink: None,
             validated_yaml: None,
+            hosted_supergraph: None,
         }
         .validate()
         .map_err(|e| serde::de::Error::custom(e.to_string()))
@@ -348,6 +369,7 @@ impl Configuration {
         batching: Option<Batching>,
         experimental_query_planner_mode: Option<QueryPlannerMode>,
         experimental_introspection_mode: Option<IntrospectionMode>,
+        experimental_hosted_supergraphs: Vec<HostedSupergraph>,
     ) -> Result<Self, ConfigurationError> {
         let notify = Self::notify(&apollo_plugins)?;
 
@@ -375,6 +397,8 @@ impl Configuration {
             batching: batching.unwrap_or_default(),
             experimental_type_conditioned_fetching: experimental_type_conditioned_fetching
                 .unwrap_or_default(),
+            experimental_hosted_supergraphs,
+            hosted_supergraph: None,
             notify,
         };
 
@@ -435,6 +459,16 @@ impl Configuration {
             type_conditioned_fetching: self.experimental_type_conditioned_fetching,
         }
     }
+
+    /// Whether subgraph latency weights are applied to query planning. Only the Rust query
+    /// planner supports them, and they would cause mismatches when comparing both planners.
+    pub(crate) fn latency_aware_planning(&self) -> bool {
+        self.supergraph
+            .query_planning
+            .experimental_latency_aware_planning
+            .enabled
+            && self.experimental_query_planner_mode == QueryPlannerMode::New
+    }
 }
 
 impl Default for Configuration {
@@ -467,6 +501,7 @@ impl Configuration {
         experimental_type_conditioned_fetching: Option<bool>,
         experimental_query_planner_mode: Option<QueryPlannerMode>,
         experimental_introspection_mode: Option<IntrospectionMode>,
+        experimental_hosted_supergraphs: Vec<HostedSupergraph>,
     ) -> Result<Self, ConfigurationError> {
         let configuration = Self {
             validated_yaml: Default::default(),
@@ -493,6 +528,8 @@ impl Configuration {
             experimental_type_conditioned_fetching: experimental_type_conditioned_fetching
                 .unwrap_or_default(),
             batching: batching.unwrap_or_default(),
+            experimental_hosted_supergraphs,
+            hosted_supergraph: None,
         };
 
         configuration.validate()
@@ -548,6 +585,39 @@ impl Configuration {
                 },
             );
         }
+        if let Err(error) = self.supergraph.experimental_contracts.validate() {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "invalid 'supergraph.experimental_contracts' configuration",
+                error,
+            });
+        }
+        if self
+            .supergraph
+            .query_planning
+            .experimental_latency_aware_planning
+            .update_interval
+            .is_zero()
+        {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "invalid 'supergraph.query_planning.experimental_latency_aware_planning' configuration",
+                error: "update_interval must be greater than 0".to_string(),
+            });
+        }
+        if let Some(disk) = &self.supergraph.query_planning.cache.experimental_disk {
+            if disk.interval.is_zero() {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message:
+                        "invalid 'supergraph.query_planning.cache.experimental_disk' configuration",
+                    error: "interval must be greater than 0".to_string(),
+                });
+            }
+        }
+        if let Err(error) = hosted_supergraphs::validate(&self.experimental_hosted_supergraphs) {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "invalid 'experimental_hosted_supergraphs' configuration",
+                error,
+            });
+        }
 
         // PQs.
         if self.persisted_queries.enabled {
@@ -708,6 +778,15 @@ pub(crate) struct Supergraph {
     /// Log a message if the client closes the connection before the response is sent.
     /// Default: false.
     pub(crate) experimental_log_on_broken_pipe: bool,
+
+    /// Validation of custom scalars with a known format
+    pub(crate) custom_scalars: CustomScalars,
+
+    /// Contract variants of the API schema, selected per request
+    pub(crate) experimental_contracts: Contracts,
+
+    /// Restrict introspection to some requests, and limit introspection queries
+    pub(crate) experimental_introspection_control: IntrospectionControl,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
@@ -746,6 +825,9 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        custom_scalars: Option<CustomScalars>,
+        experimental_contracts: Option<Contracts>,
+        experimental_introspection_control: Option<IntrospectionControl>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(default_graphql_listen),
@@ -765,6 +847,10 @@ impl Supergraph {
             generate_query_fragments: generate_query_fragments.unwrap_or_default(),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            custom_scalars: custom_scalars.unwrap_or_default(),
+            experimental_contracts: experimental_contracts.unwrap_or_default(),
+            experimental_introspection_control: experimental_introspection_control
+                .unwrap_or_default(),
         }
     }
 }
@@ -783,6 +869,9 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        custom_scalars: Option<CustomScalars>,
+        experimental_contracts: Option<Contracts>,
+        experimental_introspection_control: Option<IntrospectionControl>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(test_listen),
@@ -802,6 +891,10 @@ impl Supergraph {
             generate_query_fragments: generate_query_fragments.unwrap_or_default(),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            custom_scalars: custom_scalars.unwrap_or_default(),
+            experimental_contracts: experimental_contracts.unwrap_or_default(),
+            experimental_introspection_control: experimental_introspection_control
+                .unwrap_or_default(),
         }
     }
 }
@@ -883,6 +976,32 @@ impl Default for Apq {
     }
 }
 
+/// Custom scalars validation configuration
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct CustomScalars {
+    /// Validates custom scalars declaring a known `@specifiedBy` URL, or listed in `formats`,
+    /// in input variables and subgraph responses
+    pub(crate) enabled: bool,
+
+    /// What to do when a subgraph returns a value that does not match the scalar's format
+    pub(crate) invalid_response: InvalidScalarResponse,
+
+    /// Formats of custom scalars, by type name. This takes precedence over `@specifiedBy`
+    pub(crate) formats: HashMap<String, ScalarFormat>,
+}
+
+/// Handling of invalid custom scalar values in subgraph responses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum InvalidScalarResponse {
+    /// Log a warning and pass the value through to the client
+    #[default]
+    Log,
+    /// Replace the value with null and add an error to the response
+    Nullify,
+}
+
 /// Query planning cache configuration
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, default)]
@@ -933,6 +1052,10 @@ pub(crate) struct QueryPlanning {
     /// the cache, this option can be used to deactivate it.
     /// Default: true
     pub(crate) legacy_introspection_caching: bool,
+
+    /// Weighs subgraph fetches by subgraph latency when choosing between alternative query plans.
+    /// Requires `experimental_query_planner_mode: new`
+    pub(crate) experimental_latency_aware_planning: LatencyAwarePlanning,
 }
 
 impl Default for QueryPlanning {
@@ -945,6 +1068,7 @@ impl Default for QueryPlanning {
             experimental_paths_limit: Default::default(),
             experimental_reuse_query_plans: Default::default(),
             legacy_introspection_caching: default_legacy_introspection_caching(),
+            experimental_latency_aware_planning: Default::default(),
         }
     }
 }
@@ -953,6 +1077,37 @@ const fn default_legacy_introspection_caching() -> bool {
     true
 }
 
+/// Latency aware query planning configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct LatencyAwarePlanning {
+    /// Activates latency aware query planning
+    pub(crate) enabled: bool,
+
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How often subgraph weights are recomputed from observed latencies (default: 30s)
+    pub(crate) update_interval: Duration,
+
+    /// Fixed weights per subgraph name, used instead of the observed latency. A weight of 2 makes
+    /// fetches to that subgraph twice as costly as the default
+    pub(crate) subgraphs: HashMap<String, f64>,
+}
+
+fn default_latency_update_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+impl Default for LatencyAwarePlanning {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            update_interval: default_latency_update_interval(),
+            subgraphs: Default::default(),
+        }
+    }
+}
+
 impl QueryPlanning {
     pub(crate) fn experimental_query_planner_parallelism(&self) -> io::Result<NonZeroUsize> {
         match self.experimental_parallelism {
@@ -970,6 +1125,29 @@ pub(crate) struct QueryPlanCache {
     pub(crate) in_memory: InMemoryCache,
     /// Configures and activates the Redis cache
     pub(crate) redis: Option<QueryPlanRedisCache>,
+    /// Configures and activates the on-disk snapshot of the cache
+    pub(crate) experimental_disk: Option<QueryPlanDiskCache>,
+}
+
+/// On-disk snapshot of the query plan cache, written periodically and on shutdown, and loaded on
+/// startup to warm up the cache
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct QueryPlanDiskCache {
+    /// Directory of the snapshot. It is created if it does not exist
+    pub(crate) path: PathBuf,
+
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_query_plan_disk_cache_interval"
+    )]
+    #[schemars(with = "String", default = "default_query_plan_disk_cache_interval")]
+    /// How often the snapshot is written (default: 5m)
+    pub(crate) interval: Duration,
+}
+
+fn default_query_plan_disk_cache_interval() -> Duration {
+    Duration::from_secs(300)
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
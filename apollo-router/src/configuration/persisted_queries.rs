@@ -1,3 +1,6 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -20,6 +23,9 @@ pub struct PersistedQueries {
 
     /// Enables using a local copy of the persisted query manifest to safelist operations
     pub experimental_local_manifests: Option<Vec<String>>,
+
+    /// Experimental feature to record the operations sent by clients into a persisted query manifest
+    pub experimental_learning: PersistedQueriesLearning,
 }
 
 #[cfg(test)]
@@ -32,6 +38,7 @@ impl PersistedQueries {
         safelist: Option<PersistedQueriesSafelist>,
         experimental_prewarm_query_plan_cache: Option<PersistedQueriesPrewarmQueryPlanCache>,
         experimental_local_manifests: Option<Vec<String>>,
+        experimental_learning: Option<PersistedQueriesLearning>,
     ) -> Self {
         Self {
             enabled: enabled.unwrap_or_else(default_pq),
@@ -40,6 +47,7 @@ impl PersistedQueries {
             experimental_prewarm_query_plan_cache: experimental_prewarm_query_plan_cache
                 .unwrap_or_default(),
             experimental_local_manifests,
+            experimental_learning: experimental_learning.unwrap_or_default(),
         }
     }
 }
@@ -78,6 +86,25 @@ pub struct PersistedQueriesPrewarmQueryPlanCache {
     pub on_reload: bool,
 }
 
+/// Persisted Queries (PQ) learning mode configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub struct PersistedQueriesLearning {
+    /// Records every distinct normalized operation sent by clients (disabled by default). This works even if persisted queries are disabled
+    pub enabled: bool,
+
+    /// Path of the manifest file where recorded operations are written. Operations already present in this file are kept
+    pub path: Option<PathBuf>,
+
+    /// Interval between writes of the manifest file (default: 30s)
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String")]
+    pub flush_interval: Duration,
+
+    /// Maximum number of distinct operations to record (default: 10000)
+    pub max_operations: usize,
+}
+
 impl Default for PersistedQueries {
     fn default() -> Self {
         Self {
@@ -86,6 +113,18 @@ impl Default for PersistedQueries {
             log_unknown: default_log_unknown(),
             experimental_prewarm_query_plan_cache: PersistedQueriesPrewarmQueryPlanCache::default(),
             experimental_local_manifests: None,
+            experimental_learning: PersistedQueriesLearning::default(),
+        }
+    }
+}
+
+impl Default for PersistedQueriesLearning {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: None,
+            flush_interval: Duration::from_secs(30),
+            max_operations: 10_000,
         }
     }
 }
@@ -712,6 +712,41 @@ fn test_configuration_validate_and_sanitize() {
         .is_err());
 }
 
+#[test]
+fn test_configuration_validate_latency_update_interval() {
+    let error = Configuration::from_str(
+        r#"
+supergraph:
+  query_planning:
+    experimental_latency_aware_planning:
+      enabled: true
+      update_interval: 0s
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("update_interval must be greater than 0"));
+}
+
+#[test]
+fn test_configuration_validate_disk_cache_interval() {
+    let error = Configuration::from_str(
+        r#"
+supergraph:
+  query_planning:
+    cache:
+      experimental_disk:
+        path: /tmp/query_plans
+        interval: 0s
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("interval must be greater than 0"));
+}
+
 #[test]
 fn load_tls() {
     let mut cert_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
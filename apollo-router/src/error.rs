@@ -23,6 +23,7 @@ use crate::graphql::Location as ErrorLocation;
 use crate::graphql::Response;
 use crate::json_ext::Path;
 use crate::json_ext::Value;
+use crate::plugins::authorization::UnauthorizedReason;
 use crate::spec::operation_limits::OperationLimits;
 use crate::spec::SpecError;
 
@@ -304,7 +305,7 @@ pub(crate) enum QueryPlannerError {
     LimitExceeded(OperationLimits<bool>),
 
     /// Unauthorized field or type
-    Unauthorized(Vec<Path>),
+    Unauthorized(Vec<Path>, Vec<UnauthorizedReason>),
 
     /// Query planner pool error: {0}
     PoolProcessing(String),
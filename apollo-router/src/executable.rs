@@ -1,6 +1,7 @@
 //! Main entry point for CLI command to start server.
 
 use std::cell::Cell;
+use std::collections::HashMap;
 use std::env;
 use std::fmt::Debug;
 use std::net::SocketAddr;
@@ -36,6 +37,7 @@ use crate::router::ConfigurationSource;
 use crate::router::RouterHttpServer;
 use crate::router::SchemaSource;
 use crate::router::ShutdownSource;
+use crate::services::layers::persisted_queries::LearnedManifest;
 use crate::uplink::Endpoints;
 use crate::uplink::UplinkConfig;
 use crate::LicenseSource;
@@ -116,6 +118,9 @@ extern "C" fn drop_ad_hoc_profiler() {
 enum Commands {
     /// Configuration subcommands.
     Config(ConfigSubcommandArgs),
+
+    /// Persisted queries subcommands.
+    PersistedQueries(PersistedQueriesSubcommandArgs),
 }
 
 #[derive(Args, Debug)]
@@ -146,6 +151,27 @@ enum ConfigSubcommand {
     Preview,
 }
 
+#[derive(Args, Debug)]
+struct PersistedQueriesSubcommandArgs {
+    /// Subcommands
+    #[clap(subcommand)]
+    command: PersistedQueriesSubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum PersistedQueriesSubcommand {
+    /// Merge and deduplicate persisted query manifests recorded in learning mode.
+    Merge {
+        /// The manifests to merge.
+        #[clap(value_parser, required = true)]
+        manifests: Vec<PathBuf>,
+
+        /// Where to write the merged manifest. It is printed if not set.
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
 /// Options for the router
 #[derive(Parser, Debug)]
 #[clap(name = "router", about = "Apollo federation router")]
@@ -201,6 +227,15 @@ pub struct Opt {
     #[clap(env = "APOLLO_ROUTER_SUPERGRAPH_URLS", value_delimiter = ',')]
     supergraph_urls: Option<Vec<Url>>,
 
+    /// Schema locations of the hosted supergraphs (comma separated), as `name=path` pairs relative to the project directory.
+    #[clap(
+        long = "hosted-supergraph",
+        value_parser = parse_hosted_supergraph,
+        env = "APOLLO_ROUTER_HOSTED_SUPERGRAPHS",
+        value_delimiter = ','
+    )]
+    hosted_supergraphs: Vec<(String, PathBuf)>,
+
     /// Prints the configuration schema.
     #[clap(long, action(ArgAction::SetTrue), hide(true))]
     schema: bool,
@@ -259,6 +294,17 @@ pub struct Opt {
 // Add a filter to global log level settings so that the level only applies to the router.
 //
 // If you want to set a complex logging filter which isn't modified in this way, use RUST_LOG.
+fn parse_hosted_supergraph(raw: &str) -> Result<(String, PathBuf), String> {
+    match raw.split_once('=') {
+        Some((name, path)) if !name.is_empty() && !path.is_empty() => {
+            Ok((name.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!(
+            "'{raw}' is invalid, hosted supergraphs must be specified as 'name=path'"
+        )),
+    }
+}
+
 fn add_log_filter(raw: &str) -> Result<String, String> {
     match std::env::var("RUST_LOG") {
         Ok(filter) => Ok(filter),
@@ -452,6 +498,23 @@ impl Executable {
                 Discussed::new().print_preview();
                 Ok(())
             }
+            Some(Commands::PersistedQueries(PersistedQueriesSubcommandArgs {
+                command: PersistedQueriesSubcommand::Merge { manifests, output },
+            })) => {
+                let manifests = manifests
+                    .iter()
+                    .map(|path| LearnedManifest::from_file(path))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| anyhow!("{e}"))?;
+                let merged = LearnedManifest::merge(manifests);
+                match output {
+                    Some(output) => merged
+                        .write_to_file(output)
+                        .map_err(|e| anyhow!("could not write merged manifest: {e}"))?,
+                    None => println!("{}", serde_json::to_string_pretty(&merged)?),
+                }
+                Ok(())
+            }
             None => Self::inner_start(shutdown, schema, config, license, opt).await,
         };
 
@@ -714,11 +777,32 @@ impl Executable {
             tracing::warn!("Only a single uplink endpoint is configured. We recommend specifying at least two endpoints so that a fallback exists.");
         }
 
+        let hosted_schemas: HashMap<String, SchemaSource> = opt
+            .hosted_supergraphs
+            .iter()
+            .map(|(name, path)| {
+                let path = if path.is_relative() {
+                    current_directory.join(path)
+                } else {
+                    path.clone()
+                };
+                (
+                    name.clone(),
+                    SchemaSource::File {
+                        path,
+                        watch: opt.hot_reload,
+                        delay: None,
+                    },
+                )
+            })
+            .collect();
+
         let router = RouterHttpServer::builder()
             .is_telemetry_disabled(opt.is_telemetry_disabled())
             .configuration(configuration)
             .and_uplink(uplink_config)
             .schema(schema_source)
+            .hosted_schemas(hosted_schemas)
             .license(license)
             .shutdown(shutdown.unwrap_or(ShutdownSource::CtrlC))
             .start();
@@ -0,0 +1,92 @@
+//! Serves the hosted supergraphs alongside the main one, from the same HTTP server
+
+use std::sync::Arc;
+use std::task::Poll;
+
+use futures::future::BoxFuture;
+use multimap::MultiMap;
+use tower::BoxError;
+use tower::Service;
+use tower::ServiceExt;
+
+use crate::configuration::hosted_supergraphs::HostedSupergraph;
+use crate::configuration::ListenAddr;
+use crate::router_factory::Endpoint;
+use crate::router_factory::RouterFactory;
+use crate::services::new_service::ServiceFactory;
+use crate::services::router;
+
+/// Dispatches each request to the router of the hosted supergraph serving it, or to the main
+/// router if no hosted supergraph matches
+#[derive(Clone)]
+pub(crate) struct HostedSupergraphsRouterFactory<RF> {
+    main: RF,
+    hosted: Arc<Vec<(HostedSupergraph, RF)>>,
+}
+
+impl<RF> HostedSupergraphsRouterFactory<RF>
+where
+    RF: RouterFactory,
+{
+    pub(crate) fn new(main: RF, hosted: Vec<(HostedSupergraph, RF)>) -> Self {
+        Self {
+            main,
+            hosted: Arc::new(hosted),
+        }
+    }
+
+    fn select(&self, request: &router::Request) -> &RF {
+        self.hosted
+            .iter()
+            .find(|(hosted, _)| hosted.matches(&request.router_request))
+            .map_or(&self.main, |(_, factory)| factory)
+    }
+}
+
+impl<RF> ServiceFactory<router::Request> for HostedSupergraphsRouterFactory<RF>
+where
+    RF: RouterFactory,
+{
+    type Service = HostedSupergraphsRouterService<RF>;
+
+    fn create(&self) -> Self::Service {
+        HostedSupergraphsRouterService {
+            factory: self.clone(),
+        }
+    }
+}
+
+impl<RF> RouterFactory for HostedSupergraphsRouterFactory<RF>
+where
+    RF: RouterFactory,
+{
+    type RouterService = HostedSupergraphsRouterService<RF>;
+    type Future = BoxFuture<'static, Result<router::Response, BoxError>>;
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        self.main.web_endpoints()
+    }
+}
+
+pub(crate) struct HostedSupergraphsRouterService<RF> {
+    factory: HostedSupergraphsRouterFactory<RF>,
+}
+
+impl<RF> Service<router::Request> for HostedSupergraphsRouterService<RF>
+where
+    RF: RouterFactory,
+{
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // The readiness of the selected router is checked when the request is dispatched
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: router::Request) -> Self::Future {
+        let service = self.factory.select(&request).create();
+        Box::pin(service.oneshot(request))
+    }
+}
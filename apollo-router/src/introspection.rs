@@ -1,15 +1,21 @@
-#[cfg(test)]
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 
+use apollo_compiler::executable;
+use apollo_compiler::executable::Operation;
+use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Name;
 use router_bridge::introspect::IntrospectionError;
 use router_bridge::planner::Planner;
 use tower::BoxError;
 
 use crate::cache::storage::CacheStorage;
+use crate::configuration::introspection::IntrospectionControl;
 use crate::graphql::Response;
 use crate::query_planner::QueryPlanResult;
+use crate::spec::operation_limits;
+use crate::spec::operation_limits::Computation;
 
 const DEFAULT_INTROSPECTION_CACHE_CAPACITY: NonZeroUsize =
     unsafe { NonZeroUsize::new_unchecked(5) };
@@ -86,6 +92,108 @@ impl Introspection {
     }
 }
 
+/// Returns `true` if the operation queries the schema with `__schema` or `__type` root fields
+pub(crate) fn is_schema_introspection(
+    document: &ExecutableDocument,
+    operation: &Operation,
+) -> bool {
+    operation.is_query()
+        && operation
+            .root_fields(document)
+            .any(|field| matches!(field.name.as_str(), "__schema" | "__type"))
+}
+
+/// Checks an introspection query against the introspection limits, and returns the exceeded
+/// limits if any
+pub(crate) fn check_limits(
+    control: &IntrospectionControl,
+    document: &ExecutableDocument,
+    operation: &Operation,
+) -> Result<(), String> {
+    let depth = operation_limits::measure(document, &operation.selection_set).depth;
+    let lookups = count_lookups(document, &mut HashMap::new(), &operation.selection_set);
+
+    let mut messages = Vec::new();
+    let measured = [
+        ("depth", depth, control.max_depth),
+        (
+            "type_lookups",
+            lookups.type_lookups,
+            control.max_type_lookups,
+        ),
+        (
+            "of_type_depth",
+            lookups.of_type_depth,
+            control.max_of_type_depth,
+        ),
+    ];
+    for (ident, measured, max) in measured {
+        if let Some(max) = max {
+            if measured > max {
+                messages.push(format!("{ident}: {measured}, max_{ident}: {max}"));
+            }
+        }
+    }
+    if messages.is_empty() {
+        Ok(())
+    } else {
+        Err(messages.join(", "))
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Lookups {
+    type_lookups: u32,
+    of_type_depth: u32,
+}
+
+/// Recursively counts the `__type` fields and the deepest chain of `ofType` fields
+fn count_lookups<'a>(
+    document: &'a ExecutableDocument,
+    fragment_cache: &mut HashMap<&'a Name, Computation<Lookups>>,
+    selection_set: &'a executable::SelectionSet,
+) -> Lookups {
+    let mut lookups = Lookups::default();
+    for selection in &selection_set.selections {
+        let nested = match selection {
+            executable::Selection::Field(field) => {
+                let mut nested = count_lookups(document, fragment_cache, &field.selection_set);
+                match field.name.as_str() {
+                    "__type" => nested.type_lookups = nested.type_lookups.saturating_add(1),
+                    "ofType" => nested.of_type_depth = nested.of_type_depth.saturating_add(1),
+                    _ => {}
+                }
+                nested
+            }
+            executable::Selection::InlineFragment(fragment) => {
+                count_lookups(document, fragment_cache, &fragment.selection_set)
+            }
+            executable::Selection::FragmentSpread(fragment) => {
+                let name = &fragment.fragment_name;
+                match fragment_cache.get(name) {
+                    None => {
+                        let Some(definition) = document.fragments.get(name) else {
+                            // Undefined fragment: the operation is rejected by validation
+                            continue;
+                        };
+                        fragment_cache.insert(name, Computation::InProgress);
+                        let nested =
+                            count_lookups(document, fragment_cache, &definition.selection_set);
+                        fragment_cache.insert(name, Computation::Done(nested));
+                        nested
+                    }
+                    // Fragment cycle: the operation is rejected by validation
+                    Some(Computation::InProgress) => continue,
+                    Some(Computation::Done(cached)) => *cached,
+                }
+            }
+        };
+        lookups.type_lookups = lookups.type_lookups.saturating_add(nested.type_lookups);
+        lookups.of_type_depth = lookups.of_type_depth.max(nested.of_type_depth);
+    }
+    lookups
+}
+
 #[cfg(test)]
 mod introspection_tests {
     use std::sync::Arc;
@@ -139,4 +247,54 @@ mod introspection_tests {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn checks_introspection_limits() {
+        let schema =
+            apollo_compiler::Schema::parse_and_validate("type Query { a: Int }", "schema.graphql")
+                .unwrap();
+        let query = r#"
+            query {
+                a: __type(name: "Query") { ...TypeRef }
+                b: __type(name: "Int") { ...TypeRef }
+            }
+            fragment TypeRef on __Type {
+                fields { type { ofType { ofType { name } } } }
+            }
+        "#;
+        let document =
+            ExecutableDocument::parse_and_validate(&schema, query, "query.graphql").unwrap();
+        let operation = document.operations.get(None).unwrap();
+        assert!(is_schema_introspection(&document, operation));
+
+        let control = |value: serde_json::Value| -> IntrospectionControl {
+            serde_json::from_value(value).unwrap()
+        };
+        assert!(check_limits(
+            &control(serde_json::json!({
+                "max_depth": 6,
+                "max_type_lookups": 2,
+                "max_of_type_depth": 2
+            })),
+            &document,
+            operation
+        )
+        .is_ok());
+        assert_eq!(
+            check_limits(
+                &control(serde_json::json!({
+                    "max_depth": 5,
+                    "max_type_lookups": 1,
+                    "max_of_type_depth": 1
+                })),
+                &document,
+                operation
+            ),
+            Err(
+                "depth: 6, max_depth: 5, type_lookups: 2, max_type_lookups: 1, \
+                 of_type_depth: 2, max_of_type_depth: 1"
+                    .to_string()
+            )
+        );
+    }
 }
@@ -62,6 +62,7 @@ mod error;
 mod executable;
 mod files;
 pub mod graphql;
+mod hosted_supergraphs;
 mod http_ext;
 mod http_server_factory;
 mod introspection;
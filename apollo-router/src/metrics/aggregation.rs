@@ -7,6 +7,7 @@ use std::sync::Mutex;
 
 use derive_more::From;
 use itertools::Itertools;
+use num_traits::ToPrimitive;
 use opentelemetry::metrics::Callback;
 use opentelemetry::metrics::Counter;
 use opentelemetry::metrics::Histogram;
@@ -26,6 +27,8 @@ use opentelemetry_api::metrics::AsyncInstrument;
 use opentelemetry_api::metrics::CallbackRegistration;
 use opentelemetry_api::metrics::Observer;
 
+use crate::metrics::exemplars::histogram_exemplars;
+use crate::metrics::exemplars::InstrumentExemplars;
 use crate::metrics::filter::FilterMeterProvider;
 
 // This meter provider enables us to combine multiple meter providers. The reasons we need this are:
@@ -263,14 +266,18 @@ impl<T: Copy> AsyncInstrument<T> for AggregateObservableCounter<T> {
 }
 
 pub(crate) struct AggregateHistogram<T> {
+    exemplars: Arc<InstrumentExemplars>,
     delegates: Vec<Histogram<T>>,
 }
 
-impl<T: Copy> SyncHistogram<T> for AggregateHistogram<T> {
+impl<T: Copy + ToPrimitive> SyncHistogram<T> for AggregateHistogram<T> {
     fn record(&self, value: T, attributes: &[KeyValue]) {
         for histogram in &self.delegates {
             histogram.record(value, attributes)
         }
+        if let Some(value) = value.to_f64() {
+            self.exemplars.record(value, attributes);
+        }
     }
 }
 
@@ -372,7 +379,7 @@ macro_rules! aggregate_observable_instrument_fn {
 struct DroppingUnregister(Box<dyn CallbackRegistration>);
 
 macro_rules! aggregate_instrument_fn {
-    ($name:ident, $ty:ty, $wrapper:ident, $implementation:ident) => {
+    ($name:ident, $ty:ty, $wrapper:ident, $implementation:ident $({ $field:ident: $init:path })?) => {
         fn $name(
             &self,
             name: Cow<'static, str>,
@@ -393,7 +400,10 @@ macro_rules! aggregate_instrument_fn {
                     b.try_init()
                 })
                 .try_collect()?;
-            Ok($wrapper::new(Arc::new($implementation { delegates })))
+            Ok($wrapper::new(Arc::new($implementation {
+                $($field: $init(&name),)?
+                delegates,
+            })))
         }
     };
 }
@@ -422,9 +432,30 @@ impl InstrumentProvider for AggregateInstrumentProvider {
         AggregateObservableCounter
     );
 
-    aggregate_instrument_fn!(u64_histogram, u64, Histogram, AggregateHistogram);
-    aggregate_instrument_fn!(f64_histogram, f64, Histogram, AggregateHistogram);
-    aggregate_instrument_fn!(i64_histogram, i64, Histogram, AggregateHistogram);
+    aggregate_instrument_fn!(
+        u64_histogram,
+        u64,
+        Histogram,
+        AggregateHistogram {
+            exemplars: histogram_exemplars
+        }
+    );
+    aggregate_instrument_fn!(
+        f64_histogram,
+        f64,
+        Histogram,
+        AggregateHistogram {
+            exemplars: histogram_exemplars
+        }
+    );
+    aggregate_instrument_fn!(
+        i64_histogram,
+        i64,
+        Histogram,
+        AggregateHistogram {
+            exemplars: histogram_exemplars
+        }
+    );
 
     aggregate_instrument_fn!(
         i64_up_down_counter,
@@ -0,0 +1,431 @@
+//! Exemplar capture for histograms.
+//!
+//! An exemplar is a single observation of a histogram bucket along with the trace that produced it.
+//! The OpenTelemetry SDK that we use has no support for exemplars, so we capture them here as histograms are recorded
+//! and keep the latest one for each bucket of each series until the prometheus endpoint is scraped.
+//! Each histogram instrument holds its own exemplars, so that recording only locks that instrument.
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use once_cell::sync::Lazy;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::trace::TraceId;
+use opentelemetry::Key;
+use opentelemetry::KeyValue;
+use regex::Regex;
+use tracing::Span;
+
+use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
+
+static EXEMPLARS: Lazy<ExemplarStore> = Lazy::new(ExemplarStore::default);
+
+/// The exemplar store that histograms record into.
+pub(crate) fn exemplars() -> &'static ExemplarStore {
+    &EXEMPLARS
+}
+
+/// The exemplars of a histogram instrument, held by the instrument so that recording does not go
+/// through the store.
+pub(crate) fn histogram_exemplars(name: &str) -> Arc<InstrumentExemplars> {
+    exemplars().instrument(name)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Exemplar {
+    pub(crate) trace_id: TraceId,
+    pub(crate) value: f64,
+    pub(crate) timestamp: SystemTime,
+}
+
+type Labels = Vec<(String, String)>;
+
+#[derive(Default)]
+struct Boundaries {
+    default: Vec<f64>,
+    overrides: Vec<(Regex, Vec<f64>)>,
+}
+
+impl Boundaries {
+    fn for_instrument(&self, name: &str) -> &[f64] {
+        self.overrides
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(name))
+            .map(|(_, boundaries)| boundaries.as_slice())
+            .unwrap_or(&self.default)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ExemplarStore {
+    enabled: Arc<AtomicBool>,
+    boundaries: RwLock<Boundaries>,
+    // Prometheus metric name -> exemplars of the instrument
+    instruments: RwLock<HashMap<String, Arc<InstrumentExemplars>>>,
+}
+
+impl ExemplarStore {
+    /// Enable or disable exemplar capture.
+    /// The bucket boundaries must match the ones used by the prometheus exporter so that exemplars land in the right bucket,
+    /// `overrides` are the per instrument boundaries from metric views, where instrument names may contain `*` and `?` wildcards.
+    pub(crate) fn configure(
+        &self,
+        enabled: bool,
+        default: Vec<f64>,
+        overrides: impl IntoIterator<Item = (String, Vec<f64>)>,
+    ) {
+        let overrides = overrides
+            .into_iter()
+            .filter_map(|(name, boundaries)| Some((wildcard(&name)?, boundaries)))
+            .collect();
+        let mut boundaries = self.boundaries.write().expect("lock poisoned");
+        *boundaries = Boundaries { default, overrides };
+        // Bucket indexes may no longer be valid, so start afresh.
+        for instrument in self.instruments.read().expect("lock poisoned").values() {
+            *instrument.state.lock().expect("lock poisoned") =
+                InstrumentState::new(boundaries.for_instrument(&instrument.name));
+        }
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// The exemplars of an instrument, created on first use.
+    pub(crate) fn instrument(&self, name: &str) -> Arc<InstrumentExemplars> {
+        let metric_name = sanitize(name, true);
+        if let Some(instrument) = self
+            .instruments
+            .read()
+            .expect("lock poisoned")
+            .get(&metric_name)
+        {
+            return instrument.clone();
+        }
+        let boundaries = self.boundaries.read().expect("lock poisoned");
+        self.instruments
+            .write()
+            .expect("lock poisoned")
+            .entry(metric_name)
+            .or_insert_with(|| {
+                Arc::new(InstrumentExemplars {
+                    name: name.to_string(),
+                    enabled: self.enabled.clone(),
+                    state: Mutex::new(InstrumentState::new(boundaries.for_instrument(name))),
+                })
+            })
+            .clone()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn insert(&self, name: &str, attributes: &[KeyValue], exemplar: Exemplar) {
+        self.instrument(name).insert(attributes, exemplar)
+    }
+
+    /// The exemplars for each bucket of a prometheus histogram series, the last entry being the `+Inf` bucket.
+    /// `family` is the prometheus metric name, which may carry a unit suffix.
+    pub(crate) fn lookup(&self, family: &str, labels: &[(&str, &str)]) -> Vec<Option<Exemplar>> {
+        let instruments = self.instruments.read().expect("lock poisoned");
+        let mut name = family;
+        loop {
+            if let Some(instrument) = instruments.get(name) {
+                return instrument.lookup(labels);
+            }
+            match name.rfind('_') {
+                Some(index) => name = &name[..index],
+                None => return Vec::new(),
+            }
+        }
+    }
+}
+
+/// The exemplars of a histogram instrument.
+pub(crate) struct InstrumentExemplars {
+    name: String,
+    enabled: Arc<AtomicBool>,
+    state: Mutex<InstrumentState>,
+}
+
+struct InstrumentState {
+    boundaries: Vec<f64>,
+    // Prometheus label names of the recorded attribute keys
+    label_names: HashMap<Key, String>,
+    // Hash of the labels -> series with those labels and the latest exemplar for each bucket
+    series: HashMap<u64, Vec<(Labels, Vec<Option<Exemplar>>)>>,
+}
+
+impl InstrumentState {
+    fn new(boundaries: &[f64]) -> Self {
+        Self {
+            boundaries: boundaries.to_vec(),
+            label_names: HashMap::new(),
+            series: HashMap::new(),
+        }
+    }
+}
+
+impl InstrumentExemplars {
+    /// Record an exemplar if the current span is sampled.
+    pub(crate) fn record(&self, value: f64, attributes: &[KeyValue]) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let context = Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() || !span_context.is_sampled() {
+            return;
+        }
+        self.insert(
+            attributes,
+            Exemplar {
+                trace_id: span_context.trace_id(),
+                value,
+                timestamp: SystemTime::now(),
+            },
+        );
+    }
+
+    fn insert(&self, attributes: &[KeyValue], exemplar: Exemplar) {
+        let mut state = self.state.lock().expect("lock poisoned");
+        let InstrumentState {
+            boundaries,
+            label_names,
+            series,
+        } = &mut *state;
+        // Prometheus buckets are inclusive of their upper bound
+        let bucket = boundaries.partition_point(|boundary| *boundary < exemplar.value);
+        for kv in attributes {
+            if !label_names.contains_key(&kv.key) {
+                label_names.insert(kv.key.clone(), sanitize(kv.key.as_str(), false));
+            }
+        }
+        let labels: Vec<_> = attributes
+            .iter()
+            .map(|kv| (label_names[&kv.key].as_str(), kv.value.as_str()))
+            .collect();
+        let same_series = series.entry(labels_hash(&labels)).or_default();
+        let index = match same_series
+            .iter()
+            .position(|(recorded, _)| recorded.len() == labels.len() && matches(recorded, &labels))
+        {
+            Some(index) => index,
+            None => {
+                same_series.push((
+                    labels
+                        .iter()
+                        .map(|(name, value)| (name.to_string(), value.to_string()))
+                        .collect(),
+                    vec![None; boundaries.len() + 1],
+                ));
+                same_series.len() - 1
+            }
+        };
+        if let Some(slot) = same_series[index].1.get_mut(bucket) {
+            *slot = Some(exemplar);
+        }
+    }
+
+    fn lookup(&self, labels: &[(&str, &str)]) -> Vec<Option<Exemplar>> {
+        let labels: Vec<_> = labels
+            .iter()
+            .filter(|(key, _)| !key.starts_with("otel_scope_"))
+            .map(|(key, value)| (*key, Cow::Borrowed(*value)))
+            .collect();
+        let state = self.state.lock().expect("lock poisoned");
+        if let Some((_, buckets)) = state
+            .series
+            .get(&labels_hash(&labels))
+            .into_iter()
+            .flatten()
+            .find(|(recorded, _)| recorded.len() == labels.len() && matches(recorded, &labels))
+        {
+            return buckets.clone();
+        }
+
+        // Views may have dropped attributes, in which case several recorded attribute sets feed the same series.
+        let mut result: Vec<Option<Exemplar>> = Vec::new();
+        for (_, buckets) in state
+            .series
+            .values()
+            .flatten()
+            .filter(|(recorded, _)| matches(recorded, &labels))
+        {
+            result.resize(result.len().max(buckets.len()), None);
+            for (current, candidate) in result.iter_mut().zip(buckets) {
+                if let Some(candidate) = candidate {
+                    if current
+                        .as_ref()
+                        .map_or(true, |current| current.timestamp < candidate.timestamp)
+                    {
+                        *current = Some(candidate.clone());
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+// Whether the recorded labels contain all the given labels
+fn matches(recorded: &Labels, labels: &[(&str, Cow<'_, str>)]) -> bool {
+    labels.iter().all(|(key, value)| {
+        recorded
+            .iter()
+            .any(|(k, v)| k.as_str() == *key && v.as_str() == value.as_ref())
+    })
+}
+
+// A hash of the labels that does not depend on their order
+fn labels_hash(labels: &[(&str, Cow<'_, str>)]) -> u64 {
+    labels
+        .iter()
+        .map(|label| {
+            let mut hasher = DefaultHasher::new();
+            label.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+// Mirrors the name sanitization of the prometheus exporter
+fn sanitize(name: &str, metric_name: bool) -> String {
+    let prefix = if metric_name && name.starts_with(|c: char| c.is_ascii_digit()) {
+        "_"
+    } else {
+        ""
+    };
+    prefix
+        .chars()
+        .chain(name.chars().map(|c| {
+            if c.is_ascii_alphanumeric() || c == ':' || (metric_name && c == '_') {
+                c
+            } else {
+                '_'
+            }
+        }))
+        .collect()
+}
+
+// Mirrors the instrument name matching of metric views
+fn wildcard(name: &str) -> Option<Regex> {
+    let pattern = regex::escape(name).replace(r"\?", ".").replace(r"\*", ".*");
+    Regex::new(&format!("^{pattern}$")).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn exemplar(trace_id: u128, value: f64) -> Exemplar {
+        Exemplar {
+            trace_id: TraceId::from(trace_id),
+            value,
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_secs(trace_id as u64),
+        }
+    }
+
+    fn trace_ids(exemplars: Vec<Option<Exemplar>>) -> Vec<Option<u128>> {
+        exemplars
+            .into_iter()
+            .map(|e| e.map(|e| u128::from_be_bytes(e.trace_id.to_bytes())))
+            .collect()
+    }
+
+    #[test]
+    fn test_exemplar_buckets() {
+        let store = ExemplarStore::default();
+        store.configure(true, vec![0.1, 1.0], [("custom.*".to_string(), vec![5.0])]);
+        let attributes = [KeyValue::new("http.response.status_code", 200)];
+        store.insert("http.server.duration", &attributes, exemplar(1, 0.05));
+        store.insert("http.server.duration", &attributes, exemplar(2, 1.0));
+        store.insert("http.server.duration", &attributes, exemplar(3, 2.0));
+        store.insert("http.server.duration", &attributes, exemplar(4, 0.5));
+        store.insert("custom.duration", &attributes, exemplar(5, 6.0));
+
+        let labels = [
+            ("http_response_status_code", "200"),
+            ("otel_scope_name", "apollo/router"),
+        ];
+        assert_eq!(
+            trace_ids(store.lookup("http_server_duration_seconds", &labels)),
+            vec![Some(1), Some(4), Some(3)]
+        );
+        assert_eq!(
+            trace_ids(store.lookup("custom_duration", &labels)),
+            vec![None, Some(5)]
+        );
+        assert!(store
+            .lookup(
+                "http_server_duration",
+                &[("http_response_status_code", "500")]
+            )
+            .is_empty());
+        assert!(store.lookup("http_server", &labels).is_empty());
+    }
+
+    #[test]
+    fn test_exemplar_dropped_attributes() {
+        let store = ExemplarStore::default();
+        store.configure(true, vec![1.0], []);
+        store.insert(
+            "duration",
+            &[KeyValue::new("a", "1"), KeyValue::new("b", "1")],
+            exemplar(1, 0.5),
+        );
+        store.insert(
+            "duration",
+            &[KeyValue::new("a", "1"), KeyValue::new("b", "2")],
+            exemplar(2, 0.5),
+        );
+        store.insert(
+            "duration",
+            &[KeyValue::new("a", "1"), KeyValue::new("b", "2")],
+            exemplar(3, 5.0),
+        );
+        assert_eq!(
+            trace_ids(store.lookup("duration", &[("a", "1")])),
+            vec![Some(2), Some(3)]
+        );
+        assert_eq!(
+            trace_ids(store.lookup("duration", &[("a", "1"), ("b", "1")])),
+            vec![Some(1), None]
+        );
+    }
+
+    #[test]
+    fn test_exemplar_attribute_order() {
+        let store = ExemplarStore::default();
+        store.configure(true, vec![1.0], []);
+        let instrument = store.instrument("duration");
+        instrument.insert(
+            &[KeyValue::new("a", "1"), KeyValue::new("b.c", "2")],
+            exemplar(1, 0.5),
+        );
+        instrument.insert(
+            &[KeyValue::new("b.c", "2"), KeyValue::new("a", "1")],
+            exemplar(2, 0.5),
+        );
+        assert_eq!(
+            trace_ids(store.lookup("duration_seconds", &[("b_c", "2"), ("a", "1")])),
+            vec![Some(2), None]
+        );
+        assert_eq!(instrument.state.lock().unwrap().series.len(), 1);
+    }
+
+    #[test]
+    fn test_exemplar_disabled() {
+        let store = ExemplarStore::default();
+        store.configure(false, vec![1.0], []);
+        store.instrument("duration").record(0.5, &[]);
+        assert!(store.lookup("duration", &[]).is_empty());
+    }
+}
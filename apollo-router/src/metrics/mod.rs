@@ -10,6 +10,7 @@ use futures::FutureExt;
 use crate::metrics::aggregation::AggregateMeterProvider;
 
 pub(crate) mod aggregation;
+pub(crate) mod exemplars;
 pub(crate) mod filter;
 pub(crate) mod layer;
 
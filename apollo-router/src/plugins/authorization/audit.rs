@@ -0,0 +1,436 @@
+//! Audit log of authorization decisions
+//!
+//! Each request subject to authorization generates an audit event describing the subject, the
+//! operation, the paths that were filtered and the reason why each of them was unauthorized. Events are
+//! emitted through the router logs and can be appended as JSON lines to a dedicated file.
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use opentelemetry::Array;
+use opentelemetry::KeyValue;
+use opentelemetry::Value as OtelValue;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tower::BoxError;
+
+use super::UnauthorizedReason;
+use super::AUTHENTICATED_KEY;
+use super::REQUIRED_POLICIES_KEY;
+use super::REQUIRED_SCOPES_KEY;
+use crate::context::OPERATION_KIND;
+use crate::context::OPERATION_NAME;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::plugins::telemetry::config_new::events::log_event;
+use crate::plugins::telemetry::config_new::events::EventLevel;
+use crate::Context;
+
+const AUDIT_EVENT_KIND: &str = "authorization.audit";
+const AUDIT_CHANNEL_SIZE: usize = 1024;
+
+/// Audit log of authorization decisions
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct AuditConfig {
+    /// generate an audit event for each request subject to authorization
+    pub(crate) enabled: bool,
+    /// level of the audit events in the router logs
+    pub(crate) level: EventLevel,
+    /// claim used to identify the subject of the request
+    pub(crate) subject_claim: String,
+    /// file where the audit events are appended as JSON lines
+    pub(crate) path: Option<PathBuf>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            level: EventLevel::Info,
+            subject_claim: "sub".to_string(),
+            path: None,
+        }
+    }
+}
+
+/// Final outcome of the authorization process for a request
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AuditOutcome {
+    /// the whole query was authorized
+    Allowed,
+    /// unauthorized parts of the query were removed before execution
+    Filtered,
+    /// unauthorized parts were found, but the query was executed unmodified
+    DryRun,
+    /// the request was refused
+    Rejected,
+}
+
+impl AuditOutcome {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditOutcome::Allowed => "allowed",
+            AuditOutcome::Filtered => "filtered",
+            AuditOutcome::DryRun => "dry_run",
+            AuditOutcome::Rejected => "rejected",
+        }
+    }
+}
+
+/// Unauthorized paths recorded during execution, so that the audit event can be generated
+/// when the response is sent
+#[derive(Clone, Debug, Default)]
+pub(crate) struct AuditedPaths(pub(crate) Vec<AuditedPath>);
+
+/// Path removed from the query
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct AuditedPath {
+    pub(crate) path: String,
+    /// why the path was unauthorized
+    #[serde(flatten)]
+    pub(crate) reason: UnauthorizedReason,
+}
+
+impl AuditedPath {
+    /// Describes why the path was unauthorized, as a JSON object
+    fn reason_attribute(&self) -> String {
+        serde_json::to_string(&self.reason).expect("reason serialization should not fail")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub(crate) struct AuditEvent {
+    pub(crate) timestamp: String,
+    pub(crate) subject: Option<String>,
+    pub(crate) authenticated: bool,
+    pub(crate) operation_name: Option<String>,
+    pub(crate) operation_kind: Option<String>,
+    pub(crate) outcome: AuditOutcome,
+    pub(crate) unauthorized_paths: Vec<AuditedPath>,
+    /// the query requires authentication but the request is not authenticated
+    pub(crate) missing_authentication: bool,
+}
+
+impl AuditEvent {
+    /// Generates the audit event from the authorization data stored in the context.
+    ///
+    /// Returns `None` if the request was not subject to authorization
+    pub(crate) fn new(
+        context: &Context,
+        subject_claim: &str,
+        outcome: AuditOutcome,
+        unauthorized_paths: Vec<AuditedPath>,
+    ) -> Option<Self> {
+        let claims = context.get_json_value(APOLLO_AUTHENTICATION_JWT_CLAIMS);
+        let requires_authentication = context.contains_key(AUTHENTICATED_KEY);
+        let requires_scopes = context.contains_key(REQUIRED_SCOPES_KEY);
+        let requires_policies = context.contains_key(REQUIRED_POLICIES_KEY);
+
+        if !requires_authentication
+            && !requires_scopes
+            && !requires_policies
+            && unauthorized_paths.is_empty()
+            && outcome != AuditOutcome::Rejected
+        {
+            return None;
+        }
+
+        let authenticated = claims.is_some();
+        let subject = claims.as_ref().and_then(|claims| {
+            claims
+                .as_object()
+                .and_then(|claims| claims.get(subject_claim))
+                .and_then(|value| match value {
+                    Value::String(s) => Some(s.as_str().to_string()),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+        });
+
+        Some(AuditEvent {
+            timestamp: humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            subject,
+            authenticated,
+            operation_name: context.get::<_, String>(OPERATION_NAME).ok().flatten(),
+            operation_kind: context.get::<_, String>(OPERATION_KIND).ok().flatten(),
+            outcome,
+            unauthorized_paths,
+            missing_authentication: requires_authentication && !authenticated,
+        })
+    }
+
+    fn attributes(&self) -> Vec<KeyValue> {
+        let paths = self
+            .unauthorized_paths
+            .iter()
+            .map(|path| path.path.clone().into())
+            .collect();
+        let reasons = self
+            .unauthorized_paths
+            .iter()
+            .map(|path| path.reason_attribute().into())
+            .collect();
+
+        let mut attributes = vec![
+            KeyValue::new("authorization.outcome", self.outcome.as_str()),
+            KeyValue::new("authorization.authenticated", self.authenticated),
+            KeyValue::new(
+                "authorization.unauthorized_paths",
+                OtelValue::Array(Array::String(paths)),
+            ),
+            // aligned with `authorization.unauthorized_paths`
+            KeyValue::new(
+                "authorization.unauthorized_reasons",
+                OtelValue::Array(Array::String(reasons)),
+            ),
+            KeyValue::new(
+                "authorization.missing_authentication",
+                self.missing_authentication,
+            ),
+        ];
+        if let Some(subject) = &self.subject {
+            attributes.push(KeyValue::new("authorization.subject", subject.clone()));
+        }
+        if let Some(operation_name) = &self.operation_name {
+            attributes.push(KeyValue::new(
+                "graphql.operation.name",
+                operation_name.clone(),
+            ));
+        }
+        if let Some(operation_kind) = &self.operation_kind {
+            attributes.push(KeyValue::new(
+                "graphql.operation.type",
+                operation_kind.clone(),
+            ));
+        }
+        attributes
+    }
+}
+
+/// Emits the audit events to the router logs and the audit file
+#[derive(Debug)]
+pub(crate) struct AuditLog {
+    level: EventLevel,
+    pub(crate) subject_claim: String,
+    sink: Option<mpsc::Sender<AuditEvent>>,
+}
+
+impl AuditLog {
+    pub(crate) async fn new(config: &AuditConfig) -> Result<Self, BoxError> {
+        let sink = match &config.path {
+            None => None,
+            Some(path) => {
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| -> BoxError {
+                        format!(
+                            "could not open the authorization audit file {}: {}",
+                            path.display(),
+                            e
+                        )
+                        .into()
+                    })?;
+
+                let (sender, mut receiver) = mpsc::channel::<AuditEvent>(AUDIT_CHANNEL_SIZE);
+                let path = path.clone();
+                tokio::task::spawn(async move {
+                    let mut writer = tokio::io::BufWriter::new(file);
+                    while let Some(event) = receiver.recv().await {
+                        let mut res = write_event(&mut writer, &event).await;
+                        // write the events that are already waiting before flushing
+                        while res.is_ok() {
+                            match receiver.try_recv() {
+                                Ok(event) => res = write_event(&mut writer, &event).await,
+                                Err(_) => break,
+                            }
+                        }
+                        if let Err(e) = res.and(writer.flush().await) {
+                            tracing::error!(
+                                path = %path.display(),
+                                error = %e,
+                                "could not write to the authorization audit file"
+                            );
+                        }
+                    }
+                });
+
+                Some(sender)
+            }
+        };
+
+        Ok(Self {
+            level: config.level,
+            subject_claim: config.subject_claim.clone(),
+            sink,
+        })
+    }
+
+    pub(crate) fn emit(&self, event: AuditEvent) {
+        log_event(
+            self.level,
+            AUDIT_EVENT_KIND,
+            event.attributes(),
+            "authorization decision",
+        );
+
+        if let Some(sink) = &self.sink {
+            if let Err(e) = sink.try_send(event) {
+                tracing::error!(error = %e, "could not send the event to the authorization audit file");
+            }
+        }
+    }
+}
+
+async fn write_event(
+    writer: &mut tokio::io::BufWriter<tokio::fs::File>,
+    event: &AuditEvent,
+) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(event).expect("audit event serialization should not fail");
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    #[test]
+    fn event_from_context() {
+        let context = Context::new();
+        context
+            .insert(
+                APOLLO_AUTHENTICATION_JWT_CLAIMS,
+                json!({ "sub": "user-1", "scope": "read:user" }),
+            )
+            .unwrap();
+        context
+            .insert(REQUIRED_SCOPES_KEY, vec!["read:user", "read:email"])
+            .unwrap();
+        context
+            .insert(REQUIRED_POLICIES_KEY, json!({ "admin": false }))
+            .unwrap();
+        context.insert(OPERATION_NAME, "MyQuery").unwrap();
+
+        let event = AuditEvent::new(
+            &context,
+            "sub",
+            AuditOutcome::Filtered,
+            vec![
+                AuditedPath {
+                    path: "/me/email".to_string(),
+                    reason: UnauthorizedReason::Scopes {
+                        missing_scopes: vec![vec!["read:email".to_string()]],
+                    },
+                },
+                AuditedPath {
+                    path: "/me/admin".to_string(),
+                    reason: UnauthorizedReason::Policy {
+                        failed_policies: vec!["admin".to_string()],
+                    },
+                },
+                AuditedPath {
+                    path: "/me/id".to_string(),
+                    reason: UnauthorizedReason::Authentication,
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(event.subject.as_deref(), Some("user-1"));
+        assert!(event.authenticated);
+        assert!(!event.missing_authentication);
+        assert_eq!(event.operation_name.as_deref(), Some("MyQuery"));
+
+        let serialized = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            serialized["unauthorized_paths"],
+            serde_json::json!([
+                { "path": "/me/email", "reason": "scopes", "missing_scopes": [["read:email"]] },
+                { "path": "/me/admin", "reason": "policy", "failed_policies": ["admin"] },
+                { "path": "/me/id", "reason": "authentication" },
+            ])
+        );
+
+        let attributes = event.attributes();
+        let attribute = |key: &str| {
+            attributes
+                .iter()
+                .find(|kv| kv.key.as_str() == key)
+                .map(|kv| kv.value.clone())
+        };
+        assert_eq!(
+            attribute("authorization.unauthorized_paths"),
+            Some(OtelValue::Array(Array::String(vec![
+                "/me/email".into(),
+                "/me/admin".into(),
+                "/me/id".into(),
+            ])))
+        );
+        assert_eq!(
+            attribute("authorization.unauthorized_reasons"),
+            Some(OtelValue::Array(Array::String(vec![
+                r#"{"reason":"scopes","missing_scopes":[["read:email"]]}"#.into(),
+                r#"{"reason":"policy","failed_policies":["admin"]}"#.into(),
+                r#"{"reason":"authentication"}"#.into(),
+            ])))
+        );
+        assert!(!attributes
+            .iter()
+            .any(|kv| kv.key.as_str().starts_with("authorization.unauthorized.")));
+    }
+
+    #[test]
+    fn no_event_without_authorization() {
+        let context = Context::new();
+        assert!(AuditEvent::new(&context, "sub", AuditOutcome::Allowed, vec![]).is_none());
+    }
+
+    #[tokio::test]
+    async fn write_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let audit = AuditLog::new(&AuditConfig {
+            enabled: true,
+            path: Some(path.clone()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+        let context = Context::new();
+        context.insert(AUTHENTICATED_KEY, true).unwrap();
+        let event = AuditEvent::new(
+            &context,
+            "sub",
+            AuditOutcome::Rejected,
+            vec![AuditedPath {
+                path: "".to_string(),
+                reason: UnauthorizedReason::Authentication,
+            }],
+        )
+        .unwrap();
+        assert!(event.missing_authentication);
+        audit.emit(event.clone());
+        audit.emit(event);
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["outcome"], "rejected");
+        assert_eq!(lines[0]["missing_authentication"], true);
+    }
+}
@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::ops::ControlFlow;
+use std::sync::Arc;
 
 use apollo_compiler::ast;
 use apollo_compiler::ExecutableDocument;
@@ -15,6 +16,12 @@ use tower::BoxError;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 
+use self::audit::AuditConfig;
+use self::audit::AuditEvent;
+use self::audit::AuditLog;
+use self::audit::AuditOutcome;
+use self::audit::AuditedPath;
+use self::audit::AuditedPaths;
 use self::authenticated::AuthenticatedCheckVisitor;
 use self::authenticated::AuthenticatedVisitor;
 use self::authenticated::AUTHENTICATED_SPEC_BASE_URL;
@@ -48,6 +55,7 @@ use crate::spec::SpecError;
 use crate::Configuration;
 use crate::Context;
 
+pub(crate) mod audit;
 pub(crate) mod authenticated;
 pub(crate) mod policy;
 pub(crate) mod scopes;
@@ -73,6 +81,9 @@ pub(crate) struct Conf {
     /// `@authenticated`, `@requiresScopes` and `@policy` directives
     #[serde(default)]
     directives: Directives,
+    /// audit log of authorization decisions
+    #[serde(default)]
+    audit: AuditConfig,
 }
 
 #[derive(Clone, Debug, serde_derive_default::Default, Deserialize, JsonSchema)]
@@ -124,15 +135,42 @@ pub(crate) enum ErrorLocation {
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub(crate) struct UnauthorizedPaths {
     pub(crate) paths: Vec<Path>,
+    /// why each path was removed, in the same order as `paths`
+    #[serde(default)]
+    pub(crate) reasons: Vec<UnauthorizedReason>,
     pub(crate) errors: ErrorConfig,
 }
 
+/// Paths of a query rejected during query planning, with the reason each of them was
+/// unauthorized. They are stored in the context extensions for the audit log
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct RejectedPaths {
+    pub(crate) paths: Vec<Path>,
+    /// why each path was unauthorized, in the same order as `paths`
+    pub(crate) reasons: Vec<UnauthorizedReason>,
+}
+
+/// Why a path was removed from the query
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub(crate) enum UnauthorizedReason {
+    /// `@authenticated`, the request is not authenticated
+    Authentication,
+    /// `@requiresScopes`, with the scopes missing from the request for each scope set that
+    /// would have authorized the path
+    Scopes { missing_scopes: Vec<Vec<String>> },
+    /// `@policy`, with the policies that were not granted
+    Policy { failed_policies: Vec<String> },
+}
+
 fn default_enable_directives() -> bool {
     true
 }
 
 pub(crate) struct AuthorizationPlugin {
     require_authentication: bool,
+    dry_run: bool,
+    audit: Option<Arc<AuditLog>>,
 }
 
 impl AuthorizationPlugin {
@@ -363,17 +401,23 @@ impl AuthorizationPlugin {
 
         let mut is_filtered = false;
         let mut unauthorized_paths: Vec<Path> = vec![];
+        let mut unauthorized_reasons: Vec<UnauthorizedReason> = vec![];
 
         let filter_res = Self::authenticated_filter_query(schema, dry_run, &doc, is_authenticated)?;
 
         let doc = match filter_res {
             None => doc,
             Some((filtered_doc, paths)) => {
+                unauthorized_reasons
+                    .extend(paths.iter().map(|_| UnauthorizedReason::Authentication));
                 unauthorized_paths.extend(paths);
 
                 // FIXME: consider only `filtered_doc.operations.get(key.operation_name)`?
                 if filtered_doc.definitions.is_empty() {
-                    return Err(QueryPlannerError::Unauthorized(unauthorized_paths));
+                    return Err(QueryPlannerError::Unauthorized(
+                        unauthorized_paths,
+                        unauthorized_reasons,
+                    ));
                 }
 
                 is_filtered = true;
@@ -386,12 +430,18 @@ impl AuthorizationPlugin {
 
         let doc = match filter_res {
             None => doc,
-            Some((filtered_doc, paths)) => {
+            Some((filtered_doc, paths, missing_scopes)) => {
+                unauthorized_reasons.extend(paths.iter().map(|path| UnauthorizedReason::Scopes {
+                    missing_scopes: missing_scopes.get(path).cloned().unwrap_or_default(),
+                }));
                 unauthorized_paths.extend(paths);
 
                 // FIXME: consider only `filtered_doc.operations.get(key.operation_name)`?
                 if filtered_doc.definitions.is_empty() {
-                    return Err(QueryPlannerError::Unauthorized(unauthorized_paths));
+                    return Err(QueryPlannerError::Unauthorized(
+                        unauthorized_paths,
+                        unauthorized_reasons,
+                    ));
                 }
 
                 is_filtered = true;
@@ -404,12 +454,18 @@ impl AuthorizationPlugin {
 
         let doc = match filter_res {
             None => doc,
-            Some((filtered_doc, paths)) => {
+            Some((filtered_doc, paths, failed_policies)) => {
+                unauthorized_reasons.extend(paths.iter().map(|path| UnauthorizedReason::Policy {
+                    failed_policies: failed_policies.get(path).cloned().unwrap_or_default(),
+                }));
                 unauthorized_paths.extend(paths);
 
                 // FIXME: consider only `filtered_doc.operations.get(key.operation_name)`?
                 if filtered_doc.definitions.is_empty() {
-                    return Err(QueryPlannerError::Unauthorized(unauthorized_paths));
+                    return Err(QueryPlannerError::Unauthorized(
+                        unauthorized_paths,
+                        unauthorized_reasons,
+                    ));
                 }
 
                 is_filtered = true;
@@ -419,11 +475,14 @@ impl AuthorizationPlugin {
         };
 
         if reject_unauthorized && !unauthorized_paths.is_empty() {
-            return Err(QueryPlannerError::Unauthorized(unauthorized_paths));
+            return Err(QueryPlannerError::Unauthorized(
+                unauthorized_paths,
+                unauthorized_reasons,
+            ));
         }
 
         if is_filtered {
-            Ok(Some((unauthorized_paths, doc)))
+            Ok(Some((unauthorized_paths, unauthorized_reasons, doc)))
         } else {
             Ok(None)
         }
@@ -471,7 +530,10 @@ impl AuthorizationPlugin {
         dry_run: bool,
         doc: &ast::Document,
         scopes: &[String],
-    ) -> Result<Option<(ast::Document, Vec<Path>)>, QueryPlannerError> {
+    ) -> Result<
+        Option<(ast::Document, Vec<Path>, HashMap<Path, Vec<Vec<String>>>)>,
+        QueryPlannerError,
+    > {
         if let Some(mut visitor) = ScopeFilteringVisitor::new(
             schema.supergraph_schema(),
             &schema.implementers_map,
@@ -488,7 +550,11 @@ impl AuthorizationPlugin {
                     .map(|path| path.to_string())
                     .collect::<Vec<_>>()
             );
-                Ok(Some((modified_query, visitor.unauthorized_paths)))
+                Ok(Some((
+                    modified_query,
+                    visitor.unauthorized_paths,
+                    visitor.missing_scopes,
+                )))
             } else {
                 tracing::debug!("the query does not require scopes");
                 Ok(None)
@@ -505,7 +571,8 @@ impl AuthorizationPlugin {
 
         doc: &ast::Document,
         policies: &[String],
-    ) -> Result<Option<(ast::Document, Vec<Path>)>, QueryPlannerError> {
+    ) -> Result<Option<(ast::Document, Vec<Path>, HashMap<Path, Vec<String>>)>, QueryPlannerError>
+    {
         if let Some(mut visitor) = PolicyFilteringVisitor::new(
             schema.supergraph_schema(),
             &schema.implementers_map,
@@ -523,7 +590,11 @@ impl AuthorizationPlugin {
                     .map(|path| path.to_string())
                     .collect::<Vec<_>>()
             );
-                Ok(Some((modified_query, visitor.unauthorized_paths)))
+                Ok(Some((
+                    modified_query,
+                    visitor.unauthorized_paths,
+                    visitor.failed_policies,
+                )))
             } else {
                 tracing::debug!("the query does not require policies");
                 Ok(None)
@@ -540,13 +611,22 @@ impl Plugin for AuthorizationPlugin {
     type Config = Conf;
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let audit = if init.config.audit.enabled {
+            Some(Arc::new(AuditLog::new(&init.config.audit).await?))
+        } else {
+            None
+        };
+
         Ok(AuthorizationPlugin {
             require_authentication: init.config.require_authentication,
+            dry_run: init.config.directives.dry_run,
+            audit,
         })
     }
 
     fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
-        if self.require_authentication {
+        let audit = self.audit.clone();
+        let service = if self.require_authentication {
             ServiceBuilder::new()
                 .checkpoint(move |request: supergraph::Request| {
                     if request
@@ -560,6 +640,17 @@ impl Plugin for AuthorizationPlugin {
                             monotonic_counter.apollo_require_authentication_failure_count = 1u64,
                         );
                         tracing::error!("rejecting unauthenticated request");
+                        if let Some(audit) = &audit {
+                            if let Some(mut event) = AuditEvent::new(
+                                &request.context,
+                                &audit.subject_claim,
+                                AuditOutcome::Rejected,
+                                vec![],
+                            ) {
+                                event.missing_authentication = true;
+                                audit.emit(event);
+                            }
+                        }
                         let response = supergraph::Response::error_builder()
                             .error(
                                 graphql::Error::builder()
@@ -577,12 +668,67 @@ impl Plugin for AuthorizationPlugin {
                 .boxed()
         } else {
             service
+        };
+
+        match self.audit.clone() {
+            None => service,
+            Some(audit) => {
+                let dry_run = self.dry_run;
+                ServiceBuilder::new()
+                    .map_response(move |response: supergraph::Response| {
+                        let (audited_paths, rejected_paths) =
+                            response.context.extensions().with_lock(|mut lock| {
+                                (
+                                    lock.remove::<AuditedPaths>(),
+                                    lock.remove::<RejectedPaths>(),
+                                )
+                            });
+
+                        let (outcome, paths) = match (audited_paths, rejected_paths) {
+                            (Some(AuditedPaths(paths)), _) => {
+                                let outcome = if paths.is_empty() {
+                                    AuditOutcome::Allowed
+                                } else if dry_run {
+                                    AuditOutcome::DryRun
+                                } else {
+                                    AuditOutcome::Filtered
+                                };
+                                (outcome, paths)
+                            }
+                            // the query was rejected during query planning
+                            (None, Some(rejected)) => (
+                                AuditOutcome::Rejected,
+                                rejected
+                                    .paths
+                                    .iter()
+                                    .zip(rejected.reasons)
+                                    .map(|(path, reason)| AuditedPath {
+                                        path: path.to_string(),
+                                        reason,
+                                    })
+                                    .collect(),
+                            ),
+                            // the execution service was not called for another reason
+                            (None, None) => return response,
+                        };
+
+                        if let Some(event) =
+                            AuditEvent::new(&response.context, &audit.subject_claim, outcome, paths)
+                        {
+                            audit.emit(event);
+                        }
+                        response
+                    })
+                    .service(service)
+                    .boxed()
+            }
         }
     }
 
     fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        let audit = self.audit.is_some();
         ServiceBuilder::new()
-            .map_request(|request: execution::Request| {
+            .map_request(move |request: execution::Request| {
                 let filtered = !request.query_plan.query.unauthorized.paths.is_empty();
                 let needs_authenticated = request.context.contains_key(AUTHENTICATED_KEY);
                 let needs_requires_scopes = request.context.contains_key(REQUIRED_SCOPES_KEY);
@@ -596,6 +742,23 @@ impl Plugin for AuthorizationPlugin {
                     );
                 }
 
+                if audit {
+                    let unauthorized = &request.query_plan.query.unauthorized;
+                    let paths = unauthorized
+                        .paths
+                        .iter()
+                        .zip(unauthorized.reasons.iter())
+                        .map(|(path, reason)| AuditedPath {
+                            path: path.to_string(),
+                            reason: reason.clone(),
+                        })
+                        .collect();
+                    request
+                        .context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(AuditedPaths(paths)));
+                }
+
                 request
             })
             .service(service)
@@ -194,9 +194,11 @@ pub(crate) struct PolicyFilteringVisitor<'a> {
     request_policies: HashSet<String>,
     pub(crate) query_requires_policies: bool,
     pub(crate) unauthorized_paths: Vec<Path>,
+    /// policies that were not granted for each unauthorized path
+    pub(crate) failed_policies: HashMap<Path, Vec<String>>,
     // store the error paths from fragments so we can  add them at
     // the point of application
-    fragments_unauthorized_paths: HashMap<String, Vec<Path>>,
+    fragments_unauthorized_paths: HashMap<String, Vec<(Path, Vec<String>)>>,
     current_path: Path,
     policy_directive_name: String,
 }
@@ -235,6 +237,7 @@ impl<'a> PolicyFilteringVisitor<'a> {
             request_policies: successful_policies,
             query_requires_policies: false,
             unauthorized_paths: vec![],
+            failed_policies: HashMap::new(),
             fragments_unauthorized_paths: HashMap::new(),
             current_path: Path::default(),
             policy_directive_name: Schema::directive_name(
@@ -290,6 +293,68 @@ impl<'a> PolicyFilteringVisitor<'a> {
         }
     }
 
+    /// Policies of the directive that were not granted. Returns nothing if one of the policy
+    /// sets is satisfied
+    fn failed_policies_sets(&self, directive: Option<&impl AsRef<ast::Directive>>) -> Vec<String> {
+        let Some(directive) = directive else {
+            return Vec::new();
+        };
+
+        let mut failed = Vec::new();
+        for policies_set in policies_sets_argument(directive.as_ref()) {
+            let len = failed.len();
+            failed.extend(policies_set.difference(&self.request_policies).cloned());
+            if failed.len() == len {
+                return Vec::new();
+            }
+        }
+        failed.sort();
+        failed.dedup();
+        failed
+    }
+
+    fn type_failed_policies(&self, type_name: &str) -> Vec<String> {
+        self.schema
+            .types
+            .get(type_name)
+            .map(|ty| self.failed_policies_sets(ty.directives().get(&self.policy_directive_name)))
+            .unwrap_or_default()
+    }
+
+    fn field_failed_policies(
+        &self,
+        parent_type: &str,
+        field_def: &ast::FieldDefinition,
+    ) -> Vec<String> {
+        let field_type = field_def.ty.inner_named_type();
+        let mut failed =
+            self.failed_policies_sets(field_def.directives.get(&self.policy_directive_name));
+        failed.extend(self.type_failed_policies(field_type));
+
+        // otherwise the field was removed because the implementors have different requirements
+        if failed.is_empty() {
+            for ty in self.implementors(field_type) {
+                failed.extend(self.type_failed_policies(ty));
+            }
+            for ty in self.implementors(parent_type) {
+                if let Ok(f) = self.schema.type_field(ty, &field_def.name) {
+                    failed.extend(
+                        self.failed_policies_sets(f.directives.get(&self.policy_directive_name)),
+                    );
+                }
+            }
+        }
+
+        failed.sort();
+        failed.dedup();
+        failed
+    }
+
+    fn add_unauthorized_path(&mut self, path: Path, failed_policies: Vec<String>) {
+        self.failed_policies.insert(path.clone(), failed_policies);
+        self.unauthorized_paths.push(path);
+    }
+
     fn implementors(&self, type_name: &str) -> impl Iterator<Item = &Name> {
         self.implementers_map
             .get(type_name)
@@ -448,7 +513,10 @@ impl<'a> transform::Visitor for PolicyFilteringVisitor<'a> {
         if is_authorized {
             transform::operation(self, root_type, node)
         } else {
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.type_failed_policies(root_type),
+            );
             self.query_requires_policies = true;
 
             if self.dry_run {
@@ -487,7 +555,10 @@ impl<'a> transform::Visitor for PolicyFilteringVisitor<'a> {
         {
             transform::field(self, field_def, node)
         } else {
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.field_failed_policies(parent_type, field_def),
+            );
             self.query_requires_policies = true;
 
             if self.dry_run {
@@ -520,16 +591,25 @@ impl<'a> transform::Visitor for PolicyFilteringVisitor<'a> {
         let res = if fragment_is_authorized || self.dry_run {
             transform::fragment_definition(self, node)
         } else {
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.type_failed_policies(&node.type_condition),
+            );
             Ok(None)
         };
 
         if self.unauthorized_paths.len() > current_unauthorized_paths_index {
-            self.fragments_unauthorized_paths.insert(
-                node.name.as_str().to_string(),
-                self.unauthorized_paths
-                    .split_off(current_unauthorized_paths_index),
-            );
+            let paths = self
+                .unauthorized_paths
+                .split_off(current_unauthorized_paths_index)
+                .into_iter()
+                .map(|path| {
+                    let failed_policies = self.failed_policies.remove(&path).unwrap_or_default();
+                    (path, failed_policies)
+                })
+                .collect();
+            self.fragments_unauthorized_paths
+                .insert(node.name.as_str().to_string(), paths);
         }
 
         res
@@ -543,10 +623,11 @@ impl<'a> transform::Visitor for PolicyFilteringVisitor<'a> {
         if let Some(paths) = self
             .fragments_unauthorized_paths
             .get(node.fragment_name.as_str())
+            .cloned()
         {
-            for path in paths {
+            for (path, failed_policies) in paths {
                 let path = self.current_path.join(path);
-                self.unauthorized_paths.push(path);
+                self.add_unauthorized_path(path, failed_policies);
             }
         }
 
@@ -571,7 +652,10 @@ impl<'a> transform::Visitor for PolicyFilteringVisitor<'a> {
 
         let res = if !fragment_is_authorized {
             self.query_requires_policies = true;
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.type_failed_policies(condition.as_str()),
+            );
 
             if self.dry_run {
                 transform::fragment_spread(self, node)
@@ -610,7 +694,10 @@ impl<'a> transform::Visitor for PolicyFilteringVisitor<'a> {
 
                 let res = if !fragment_is_authorized {
                     self.query_requires_policies = true;
-                    self.unauthorized_paths.push(self.current_path.clone());
+                    self.add_unauthorized_path(
+                        self.current_path.clone(),
+                        self.type_failed_policies(name),
+                    );
 
                     if self.dry_run {
                         transform::inline_fragment(self, parent_type, node)
@@ -1622,4 +1709,33 @@ mod tests {
         insta::assert_snapshot!(doc);
         insta::assert_debug_snapshot!(paths);
     }
+
+    #[test]
+    fn failed_policies_per_path() {
+        static QUERY: &str = r#"
+        {
+            customer {
+                id
+            }
+        }
+        "#;
+
+        let schema = Schema::parse_and_validate(BASIC_SCHEMA, "schema.graphql").unwrap();
+        let doc = ast::Document::parse(QUERY, "query.graphql").unwrap();
+        let map = schema.implementers_map();
+        let mut visitor = PolicyFilteringVisitor::new(
+            &schema,
+            &map,
+            ["read user".to_string()].into_iter().collect(),
+            false,
+        )
+        .unwrap();
+        transform::document(&mut visitor, &doc).unwrap();
+
+        assert_eq!(visitor.unauthorized_paths.len(), 1);
+        assert_eq!(
+            visitor.failed_policies.get(&visitor.unauthorized_paths[0]),
+            Some(&vec!["admin".to_string(), "internal".to_string()])
+        );
+    }
 }
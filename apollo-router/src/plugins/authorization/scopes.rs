@@ -210,9 +210,12 @@ pub(crate) struct ScopeFilteringVisitor<'a> {
     request_scopes: HashSet<String>,
     pub(crate) query_requires_scopes: bool,
     pub(crate) unauthorized_paths: Vec<Path>,
+    /// scopes missing from the request for each unauthorized path, one list per scope set
+    /// that would have authorized it
+    pub(crate) missing_scopes: HashMap<Path, Vec<Vec<String>>>,
     // store the error paths from fragments so we can  add them at
     // the point of application
-    fragments_unauthorized_paths: HashMap<String, Vec<Path>>,
+    fragments_unauthorized_paths: HashMap<String, Vec<(Path, Vec<Vec<String>>)>>,
     current_path: Path,
     requires_scopes_directive_name: String,
     dry_run: bool,
@@ -233,6 +236,7 @@ impl<'a> ScopeFilteringVisitor<'a> {
             dry_run,
             query_requires_scopes: false,
             unauthorized_paths: vec![],
+            missing_scopes: HashMap::new(),
             fragments_unauthorized_paths: HashMap::new(),
             current_path: Path::default(),
             requires_scopes_directive_name: Schema::directive_name(
@@ -288,6 +292,78 @@ impl<'a> ScopeFilteringVisitor<'a> {
         }
     }
 
+    /// Scopes missing from the request for each scope set of the directive. Returns nothing if
+    /// one of the sets is satisfied
+    fn missing_scopes_sets(
+        &self,
+        directive: Option<&impl AsRef<ast::Directive>>,
+    ) -> Vec<Vec<String>> {
+        let Some(directive) = directive else {
+            return Vec::new();
+        };
+
+        let mut missing = Vec::new();
+        for scopes_set in scopes_sets_argument(directive.as_ref()) {
+            let mut scopes: Vec<String> = scopes_set
+                .difference(&self.request_scopes)
+                .cloned()
+                .collect();
+            if scopes.is_empty() {
+                return Vec::new();
+            }
+            scopes.sort();
+            missing.push(scopes);
+        }
+        missing
+    }
+
+    fn type_missing_scopes(&self, type_name: &str) -> Vec<Vec<String>> {
+        self.schema
+            .types
+            .get(type_name)
+            .map(|ty| {
+                self.missing_scopes_sets(ty.directives().get(&self.requires_scopes_directive_name))
+            })
+            .unwrap_or_default()
+    }
+
+    fn field_missing_scopes(
+        &self,
+        parent_type: &str,
+        field_def: &ast::FieldDefinition,
+    ) -> Vec<Vec<String>> {
+        let field_type = field_def.ty.inner_named_type();
+        let mut missing = self.missing_scopes_sets(
+            field_def
+                .directives
+                .get(&self.requires_scopes_directive_name),
+        );
+        missing.extend(self.type_missing_scopes(field_type));
+
+        // otherwise the field was removed because the implementors have different requirements
+        if missing.is_empty() {
+            for ty in self.implementors(field_type) {
+                missing.extend(self.type_missing_scopes(ty));
+            }
+            for ty in self.implementors(parent_type) {
+                if let Ok(f) = self.schema.type_field(ty, &field_def.name) {
+                    missing.extend(self.missing_scopes_sets(
+                        f.directives.get(&self.requires_scopes_directive_name),
+                    ));
+                }
+            }
+        }
+
+        missing.sort();
+        missing.dedup();
+        missing
+    }
+
+    fn add_unauthorized_path(&mut self, path: Path, missing_scopes: Vec<Vec<String>>) {
+        self.missing_scopes.insert(path.clone(), missing_scopes);
+        self.unauthorized_paths.push(path);
+    }
+
     fn implementors(&self, type_name: &str) -> impl Iterator<Item = &Name> {
         self.implementers_map
             .get(type_name)
@@ -449,7 +525,10 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
         if is_authorized {
             transform::operation(self, root_type, node)
         } else {
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.type_missing_scopes(root_type),
+            );
             self.query_requires_scopes = true;
 
             if self.dry_run {
@@ -489,7 +568,10 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
         {
             transform::field(self, field_def, node)
         } else {
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.field_missing_scopes(parent_type, field_def),
+            );
             self.query_requires_scopes = true;
 
             if self.dry_run {
@@ -522,16 +604,25 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
         let res = if fragment_is_authorized || self.dry_run {
             transform::fragment_definition(self, node)
         } else {
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.type_missing_scopes(&node.type_condition),
+            );
             Ok(None)
         };
 
         if self.unauthorized_paths.len() > current_unauthorized_paths_index {
-            self.fragments_unauthorized_paths.insert(
-                node.name.as_str().to_string(),
-                self.unauthorized_paths
-                    .split_off(current_unauthorized_paths_index),
-            );
+            let paths = self
+                .unauthorized_paths
+                .split_off(current_unauthorized_paths_index)
+                .into_iter()
+                .map(|path| {
+                    let missing_scopes = self.missing_scopes.remove(&path).unwrap_or_default();
+                    (path, missing_scopes)
+                })
+                .collect();
+            self.fragments_unauthorized_paths
+                .insert(node.name.as_str().to_string(), paths);
         }
 
         res
@@ -545,10 +636,11 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
         if let Some(paths) = self
             .fragments_unauthorized_paths
             .get(node.fragment_name.as_str())
+            .cloned()
         {
-            for path in paths {
+            for (path, missing_scopes) in paths {
                 let path = self.current_path.join(path);
-                self.unauthorized_paths.push(path);
+                self.add_unauthorized_path(path, missing_scopes);
             }
         }
 
@@ -573,7 +665,10 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
 
         let res = if !fragment_is_authorized {
             self.query_requires_scopes = true;
-            self.unauthorized_paths.push(self.current_path.clone());
+            self.add_unauthorized_path(
+                self.current_path.clone(),
+                self.type_missing_scopes(condition.as_str()),
+            );
 
             if self.dry_run {
                 transform::fragment_spread(self, node)
@@ -612,7 +707,10 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
 
                 let res = if !fragment_is_authorized {
                     self.query_requires_scopes = true;
-                    self.unauthorized_paths.push(self.current_path.clone());
+                    self.add_unauthorized_path(
+                        self.current_path.clone(),
+                        self.type_missing_scopes(name),
+                    );
 
                     if self.dry_run {
                         transform::inline_fragment(self, parent_type, node)
@@ -641,6 +739,7 @@ impl<'a> transform::Visitor for ScopeFilteringVisitor<'a> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::collections::BTreeSet;
     use std::collections::HashSet;
 
@@ -1658,4 +1757,47 @@ mod tests {
             paths
         });
     }
+
+    #[test]
+    fn missing_scopes_per_path() {
+        static QUERY: &str = r#"
+        {
+            topProducts {
+                internal
+            }
+
+            me {
+                id
+            }
+        }
+        "#;
+
+        let schema = Schema::parse_and_validate(BASIC_SCHEMA, "schema.graphql").unwrap();
+        let doc = Document::parse(QUERY, "query.graphql").unwrap();
+        let map = schema.implementers_map();
+        let mut visitor = ScopeFilteringVisitor::new(
+            &schema,
+            &map,
+            ["profile".to_string()].into_iter().collect(),
+            false,
+        )
+        .unwrap();
+        transform::document(&mut visitor, &doc).unwrap();
+
+        let missing_scopes = visitor
+            .missing_scopes
+            .iter()
+            .map(|(path, scopes)| (path.to_string(), scopes.clone()))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(
+            missing_scopes,
+            BTreeMap::from([
+                ("/me".to_string(), vec![vec!["read:user".to_string()]]),
+                (
+                    "/topProducts/internal".to_string(),
+                    vec![vec!["internal".to_string(), "test".to_string()]]
+                ),
+            ])
+        );
+    }
 }
@@ -419,6 +419,173 @@ async fn authenticated_directive_reject_unauthorized() {
     insta::assert_json_snapshot!(response);
 }
 
+#[tokio::test]
+async fn audit_log() {
+    let subgraphs = MockedSubgraphs([
+    ("orga", MockSubgraph::builder().with_json(
+        serde_json::json!{{"query":"{orga(id:1){id creatorUser{id name phone}}}"}},
+        serde_json::json!{{"data": {"orga": { "id": 1, "creatorUser": {"id": 0, "name":"Ada", "phone": "1234" } }}}}
+    ).build())
+].into_iter().collect());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({
+        "include_subgraph_errors": {
+            "all": true
+        },
+        "authorization": {
+            "directives": {
+                "enabled": true,
+                "reject_unauthorized": true
+            },
+            "audit": {
+                "enabled": true,
+                "path": path
+            }
+        }}))
+        .unwrap()
+        .schema(AUTHENTICATED_SCHEMA)
+        .extra_plugin(subgraphs)
+        .build_router()
+        .await
+        .unwrap();
+
+    let req = graphql::Request {
+        query: Some(
+            "query MyQuery { orga(id: 1) { id creatorUser { id name phone } } }".to_string(),
+        ),
+        ..Default::default()
+    };
+
+    let request = router::Request {
+        context: Context::new(),
+        router_request: http::Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json")
+            .body(serde_json::to_vec(&req).unwrap().into())
+            .unwrap(),
+    };
+
+    let _response = service
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap()
+        .into_graphql_response_stream()
+        .await
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let content = std::fs::read_to_string(&path).unwrap();
+    let events: Vec<serde_json::Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["outcome"], "rejected");
+    assert_eq!(events[0]["operation_name"], "MyQuery");
+    assert_eq!(events[0]["authenticated"], false);
+    assert_eq!(events[0]["missing_authentication"], true);
+    assert_eq!(
+        events[0]["unauthorized_paths"],
+        serde_json::json!([
+            { "path": "/orga/id", "reason": "authentication" },
+            { "path": "/orga/creatorUser/phone", "reason": "authentication" },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn audit_log_filtered() {
+    let subgraphs = MockedSubgraphs([
+    ("user", MockSubgraph::builder().with_json(
+            serde_json::json!{{
+                "query": "query($representations:[_Any!]!){_entities(representations:$representations){...on User{name}}}",
+                "variables": {"representations": [{ "__typename": "User", "id":0 }],}
+            }},
+            serde_json::json! {{ "data": {"_entities":[{ "name":"Ada" }] }}},
+        ).build()),
+    ("orga", MockSubgraph::builder().with_json(
+        serde_json::json!{{"query":"{orga(id:1){creatorUser{__typename id}}}"}},
+        serde_json::json!{{"data": {"orga": { "creatorUser": { "__typename": "User", "id": 0 } }}}}
+    ).build())
+].into_iter().collect());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("audit.jsonl");
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({
+        "include_subgraph_errors": {
+            "all": true
+        },
+        "authorization": {
+            "directives": {
+                "enabled": true
+            },
+            "audit": {
+                "enabled": true,
+                "path": path
+            }
+        }}))
+        .unwrap()
+        .schema(AUTHENTICATED_SCHEMA)
+        .extra_plugin(subgraphs)
+        .build_router()
+        .await
+        .unwrap();
+
+    let req = graphql::Request {
+        query: Some("query { orga(id: 1) { id creatorUser { id name phone } } }".to_string()),
+        ..Default::default()
+    };
+
+    let request = router::Request {
+        context: Context::new(),
+        router_request: http::Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json")
+            .body(serde_json::to_vec(&req).unwrap().into())
+            .unwrap(),
+    };
+
+    let _response = service
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap()
+        .into_graphql_response_stream()
+        .await
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let content = std::fs::read_to_string(&path).unwrap();
+    let events: Vec<serde_json::Value> = content
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0]["outcome"], "filtered");
+    assert_eq!(
+        events[0]["unauthorized_paths"],
+        serde_json::json!([
+            { "path": "/orga/id", "reason": "authentication" },
+            { "path": "/orga/creatorUser/phone", "reason": "authentication" },
+        ])
+    );
+}
+
 #[tokio::test]
 async fn authenticated_directive_dry_run() {
     let subgraphs = MockedSubgraphs([
@@ -25,6 +25,7 @@ use tracing::Instrument;
 use tracing::Level;
 
 use super::cache_control::CacheControl;
+use super::invalidation::tag_index_key;
 use super::invalidation::Invalidation;
 use super::invalidation::InvalidationOrigin;
 use super::invalidation_endpoint::InvalidationEndpointConfig;
@@ -32,6 +33,8 @@ use super::invalidation_endpoint::InvalidationService;
 use super::invalidation_endpoint::SubgraphInvalidationConfig;
 use super::metrics::CacheMetricContextKey;
 use super::metrics::CacheMetricsService;
+use super::response::ResponseCache;
+use super::response::ResponseCacheConfig;
 use crate::batching::BatchQuery;
 use crate::cache::redis::RedisCacheStorage;
 use crate::cache::redis::RedisKey;
@@ -50,6 +53,7 @@ use crate::plugin::PluginInit;
 use crate::plugins::authorization::CacheKeyMetadata;
 use crate::query_planner::fetch::QueryHash;
 use crate::query_planner::OperationKind;
+use crate::services::execution;
 use crate::services::subgraph;
 use crate::services::supergraph;
 use crate::spec::TYPENAME;
@@ -62,6 +66,8 @@ pub(crate) const ENTITY_CACHE_VERSION: &str = "1.0";
 pub(crate) const ENTITIES: &str = "_entities";
 pub(crate) const REPRESENTATIONS: &str = "representations";
 pub(crate) const CONTEXT_CACHE_KEY: &str = "apollo_entity_cache::key";
+pub(crate) const CACHE_TAGS: &str = "cacheTags";
+pub(crate) const SURROGATE_KEY: &str = "surrogate-key";
 
 register_plugin!("apollo", "preview_entity_cache", EntityCache);
 
@@ -75,6 +81,7 @@ pub(crate) struct EntityCache {
     metrics: Metrics,
     private_queries: Arc<RwLock<HashSet<String>>>,
     pub(crate) invalidation: Invalidation,
+    pub(crate) response_cache: Option<ResponseCache>,
 }
 
 pub(crate) struct Storage {
@@ -86,6 +93,11 @@ impl Storage {
     pub(crate) fn get(&self, subgraph: &str) -> Option<&RedisCacheStorage> {
         self.subgraphs.get(subgraph).or(self.all.as_ref())
     }
+
+    /// The storage shared by all subgraphs, then the subgraph specific ones
+    pub(crate) fn all_storages(&self) -> Vec<&RedisCacheStorage> {
+        self.all.iter().chain(self.subgraphs.values()).collect()
+    }
 }
 
 /// Configuration for entity caching
@@ -105,6 +117,10 @@ pub(crate) struct Config {
     /// Entity caching evaluation metrics
     #[serde(default)]
     metrics: Metrics,
+
+    /// Cache complete responses of query operations, in front of query execution
+    #[serde(default)]
+    response: ResponseCacheConfig,
 }
 
 /// Per subgraph configuration for entity caching
@@ -162,11 +178,13 @@ struct Metrics {
     pub(crate) separate_per_type: bool,
 }
 
-#[derive(Default, Serialize, Deserialize, Debug)]
+/// Entity cache hits and misses by type. The last lookup of each subgraph is stored in the context,
+/// and the lookup of a fetch is also stored in the extensions of its subgraph response.
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub(crate) struct CacheSubgraph(pub(crate) HashMap<String, CacheHitMiss>);
 
-#[derive(Default, Serialize, Deserialize, Debug)]
+#[derive(Clone, Default, Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub(crate) struct CacheHitMiss {
     pub(crate) hit: usize,
@@ -273,6 +291,12 @@ impl Plugin for EntityCache {
             );
         }
 
+        let response_cache = if init.config.response.enabled {
+            Some(ResponseCache::new(&init.config.response, all.as_ref()).await?)
+        } else {
+            None
+        };
+
         let storage = Arc::new(Storage {
             all,
             subgraphs: subgraph_storages,
@@ -302,6 +326,7 @@ impl Plugin for EntityCache {
             metrics: init.config.metrics,
             private_queries: Arc::new(RwLock::new(HashSet::new())),
             invalidation,
+            response_cache,
         })
     }
 
@@ -322,6 +347,13 @@ impl Plugin for EntityCache {
             .boxed()
     }
 
+    fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        match &self.response_cache {
+            Some(response_cache) => response_cache.service(service),
+            None => service,
+        }
+    }
+
     fn subgraph_service(
         &self,
         name: &str,
@@ -483,6 +515,7 @@ impl EntityCache {
                 concurrent_requests: 10,
             })),
             invalidation,
+            response_cache: None,
         })
     }
 }
@@ -572,26 +605,30 @@ impl InnerCacheService {
                 .instrument(tracing::info_span!("cache.entity.lookup"))
                 .await?
                 {
-                    ControlFlow::Break(response) => {
+                    ControlFlow::Break(mut response) => {
                         cache_hit.insert("Query".to_string(), CacheHitMiss { hit: 1, miss: 0 });
+                        let cache_hit = CacheSubgraph(cache_hit);
                         let _ = response.context.insert(
                             CacheMetricContextKey::new(
                                 response.subgraph_name.clone().unwrap_or_default(),
                             ),
-                            CacheSubgraph(cache_hit),
+                            cache_hit.clone(),
                         );
+                        response.response.extensions_mut().insert(cache_hit);
                         Ok(response)
                     }
                     ControlFlow::Continue((request, mut root_cache_key)) => {
                         cache_hit.insert("Query".to_string(), CacheHitMiss { hit: 0, miss: 1 });
+                        let cache_hit = CacheSubgraph(cache_hit);
                         let _ = request.context.insert(
                             CacheMetricContextKey::new(
                                 request.subgraph_name.clone().unwrap_or_default(),
                             ),
-                            CacheSubgraph(cache_hit),
+                            cache_hit.clone(),
                         );
 
                         let mut response = self.service.call(request).await?;
+                        response.response.extensions_mut().insert(cache_hit);
 
                         let cache_control =
                             if response.response.headers().contains_key(CACHE_CONTROL) {
@@ -631,6 +668,8 @@ impl InnerCacheService {
                             .await;
                         }
 
+                        let cache_tags = CacheTags::from_response(&mut response);
+
                         if cache_control.should_store() {
                             cache_store_root_from_response(
                                 self.storage,
@@ -638,6 +677,7 @@ impl InnerCacheService {
                                 &response,
                                 cache_control,
                                 root_cache_key,
+                                cache_tags,
                             )
                             .await?;
                         }
@@ -676,8 +716,12 @@ impl InnerCacheService {
                 ControlFlow::Break(response) => Ok(response),
                 ControlFlow::Continue((request, mut cache_result)) => {
                     let context = request.context.clone();
+                    let cache_hit = std::mem::take(&mut cache_result.2);
                     let mut response = match self.service.call(request).await {
-                        Ok(response) => response,
+                        Ok(mut response) => {
+                            response.response.extensions_mut().insert(cache_hit);
+                            response
+                        }
                         Err(e) => {
                             let e = match e.downcast::<FetchError>() {
                                 Ok(inner) => match *inner {
@@ -712,6 +756,7 @@ impl InnerCacheService {
                                 .extensions(Object::new())
                                 .build();
                             CacheControl::no_store().to_headers(response.response.headers_mut())?;
+                            response.response.extensions_mut().insert(cache_hit);
 
                             return Ok(response);
                         }
@@ -745,12 +790,15 @@ impl InnerCacheService {
                         .await;
                     }
 
+                    let cache_tags = CacheTags::from_response(&mut response);
+
                     cache_store_entities_from_response(
                         self.storage,
                         self.subgraph_ttl,
                         &mut response,
                         cache_control.clone(),
                         cache_result.0,
+                        cache_tags,
                         is_known_private,
                         private_id,
                     )
@@ -843,7 +891,7 @@ async fn cache_lookup_root(
     }
 }
 
-struct EntityCacheResults(Vec<IntermediateResult>, Option<CacheControl>);
+struct EntityCacheResults(Vec<IntermediateResult>, Option<CacheControl>, CacheSubgraph);
 
 async fn cache_lookup_entities(
     name: String,
@@ -890,7 +938,7 @@ async fn cache_lookup_entities(
         .and_then(|value| value.as_array_mut())
         .expect("we already checked that representations exist");
     // remove from representations the entities we already obtained from the cache
-    let (new_representations, cache_result, cache_control) =
+    let (new_representations, cache_result, cache_control, cache_hit) =
         filter_representations(&name, representations, keys, cache_result, &request.context)?;
 
     if !new_representations.is_empty() {
@@ -899,7 +947,7 @@ async fn cache_lookup_entities(
 
         Ok(ControlFlow::Continue((
             request,
-            EntityCacheResults(cache_result, cache_control),
+            EntityCacheResults(cache_result, cache_control, cache_hit),
         )))
     } else {
         let entities = cache_result
@@ -920,6 +968,7 @@ async fn cache_lookup_entities(
         cache_control
             .unwrap_or_default()
             .to_headers(response.response.headers_mut())?;
+        response.response.extensions_mut().insert(cache_hit);
 
         Ok(ControlFlow::Break(response))
     }
@@ -948,12 +997,86 @@ impl ValueType for CacheEntry {
     }
 }
 
+/// Surrogate tags attached by a subgraph to its response, used for tag based invalidation
+///
+/// Tags come from the `Surrogate-Key` header (space separated list) and from the `cacheTags`
+/// response extension, which is either a list of tags applying to the whole response, or
+/// a list of tag lists, one per entity in the `_entities` field
+#[derive(Debug, Default)]
+struct CacheTags {
+    response: Vec<String>,
+    entities: Vec<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CacheTagsExtension {
+    Response(Vec<String>),
+    Entities(Vec<Vec<String>>),
+}
+
+impl CacheTags {
+    fn from_response(response: &mut subgraph::Response) -> Self {
+        let mut tags = CacheTags::default();
+
+        for value in response.response.headers().get_all(SURROGATE_KEY) {
+            if let Ok(value) = value.to_str() {
+                tags.response
+                    .extend(value.split_whitespace().map(|tag| tag.to_string()));
+            }
+        }
+
+        if let Some(extension) = response.response.body_mut().extensions.remove(CACHE_TAGS) {
+            match from_value(extension) {
+                Ok(CacheTagsExtension::Response(response_tags)) => {
+                    tags.response.extend(response_tags)
+                }
+                Ok(CacheTagsExtension::Entities(entity_tags)) => tags.entities = entity_tags,
+                Err(e) => {
+                    tracing::error!(error = %e, message = "invalid cacheTags extension");
+                }
+            }
+        }
+
+        tags
+    }
+
+    fn is_empty(&self) -> bool {
+        self.response.is_empty() && self.entities.is_empty()
+    }
+
+    /// tags applying to the entity at `index` in the subgraph response
+    fn for_entity(&self, index: usize) -> impl Iterator<Item = &String> {
+        self.response
+            .iter()
+            .chain(self.entities.get(index).into_iter().flatten())
+    }
+}
+
+/// Builds the list of tag indexes to update from a list of `(cache key, tags)`
+fn tag_indexes<'a>(
+    tagged_keys: impl Iterator<Item = (&'a str, Vec<&'a String>)>,
+) -> Vec<(RedisKey<String>, Vec<String>)> {
+    let mut indexes: HashMap<&String, Vec<String>> = HashMap::new();
+    for (key, tags) in tagged_keys {
+        for tag in tags {
+            indexes.entry(tag).or_default().push(key.to_string());
+        }
+    }
+
+    indexes
+        .into_iter()
+        .map(|(tag, keys)| (RedisKey(tag_index_key(tag)), keys))
+        .collect()
+}
+
 async fn cache_store_root_from_response(
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
     response: &subgraph::Response,
     cache_control: CacheControl,
     cache_key: String,
+    cache_tags: CacheTags,
 ) -> Result<(), BoxError> {
     if let Some(data) = response.response.body().data.as_ref() {
         let ttl: Option<Duration> = cache_control
@@ -964,6 +1087,10 @@ async fn cache_store_root_from_response(
         if response.response.body().errors.is_empty() && cache_control.should_store() {
             let span = tracing::info_span!("cache.entity.store");
             let data = data.clone();
+            let indexes = tag_indexes(std::iter::once((
+                cache_key.as_str(),
+                cache_tags.response.iter().collect(),
+            )));
             tokio::spawn(async move {
                 cache
                     .insert(
@@ -976,6 +1103,10 @@ async fn cache_store_root_from_response(
                     )
                     .instrument(span)
                     .await;
+                if !indexes.is_empty() {
+                    // the tag index must live at least as long as the entries it references
+                    cache.add_to_sets(indexes, ttl.max(subgraph_ttl)).await;
+                }
             });
         }
     }
@@ -983,12 +1114,14 @@ async fn cache_store_root_from_response(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cache_store_entities_from_response(
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
     response: &mut subgraph::Response,
     cache_control: CacheControl,
     mut result_from_cache: Vec<IntermediateResult>,
+    cache_tags: CacheTags,
     is_known_private: bool,
     private_id: Option<String>,
 ) -> Result<(), BoxError> {
@@ -1019,6 +1152,7 @@ async fn cache_store_entities_from_response(
             subgraph_ttl,
             cache_control,
             &mut result_from_cache,
+            &cache_tags,
             update_key_private,
             should_cache_private,
         )
@@ -1227,7 +1361,15 @@ fn filter_representations(
     keys: Vec<String>,
     mut cache_result: Vec<Option<CacheEntry>>,
     context: &Context,
-) -> Result<(Vec<Value>, Vec<IntermediateResult>, Option<CacheControl>), BoxError> {
+) -> Result<
+    (
+        Vec<Value>,
+        Vec<IntermediateResult>,
+        Option<CacheControl>,
+        CacheSubgraph,
+    ),
+    BoxError,
+> {
     let mut new_representations: Vec<Value> = Vec::new();
     let mut result = Vec::new();
     let mut cache_hit: HashMap<String, CacheHitMiss> = HashMap::new();
@@ -1276,12 +1418,13 @@ fn filter_representations(
         });
     }
 
+    let cache_hit = CacheSubgraph(cache_hit);
     let _ = context.insert(
         CacheMetricContextKey::new(subgraph_name.to_string()),
-        CacheSubgraph(cache_hit),
+        cache_hit.clone(),
     );
 
-    Ok((new_representations, result, cache_control))
+    Ok((new_representations, result, cache_control, cache_hit))
 }
 
 // fill in the entities for the response
@@ -1293,6 +1436,7 @@ async fn insert_entities_in_result(
     subgraph_ttl: Option<Duration>,
     cache_control: CacheControl,
     result: &mut Vec<IntermediateResult>,
+    cache_tags: &CacheTags,
     update_key_private: Option<String>,
     should_cache_private: bool,
 ) -> Result<(Vec<Value>, Vec<Error>), BoxError> {
@@ -1306,6 +1450,7 @@ async fn insert_entities_in_result(
 
     let mut inserted_types: HashMap<String, usize> = HashMap::new();
     let mut to_insert: Vec<_> = Vec::new();
+    let mut to_tag: Vec<(usize, usize)> = Vec::new();
     let mut entities_it = entities.drain(..).enumerate();
 
     // insert requested entities and cached entities in the same order as
@@ -1360,6 +1505,7 @@ async fn insert_entities_in_result(
                 }
 
                 if !has_errors && cache_control.should_store() && should_cache_private {
+                    to_tag.push((to_insert.len(), entity_idx));
                     to_insert.push((
                         RedisKey(key),
                         RedisValue(CacheEntry {
@@ -1376,12 +1522,26 @@ async fn insert_entities_in_result(
 
     if !to_insert.is_empty() {
         let span = tracing::info_span!("cache_store");
+        let indexes = if cache_tags.is_empty() {
+            Vec::new()
+        } else {
+            tag_indexes(to_tag.into_iter().map(|(insert_idx, entity_idx)| {
+                (
+                    to_insert[insert_idx].0 .0.as_str(),
+                    cache_tags.for_entity(entity_idx).collect(),
+                )
+            }))
+        };
 
         tokio::spawn(async move {
             cache
                 .insert_multiple(&to_insert, ttl)
                 .instrument(span)
                 .await;
+            if !indexes.is_empty() {
+                // the tag index must live at least as long as the entries it references
+                cache.add_to_sets(indexes, ttl.max(subgraph_ttl)).await;
+            }
         });
     }
 
@@ -6,6 +6,7 @@ use fred::types::Scanner;
 use futures::stream;
 use futures::StreamExt;
 use itertools::Itertools;
+use opentelemetry::KeyValue;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json_bytes::Value;
@@ -103,13 +104,74 @@ impl Invalidation {
         origin: &'static str,
         request: &InvalidationRequest,
     ) -> Result<u64, InvalidationError> {
-        let key_prefix = request.key_prefix();
-        let subgraph = request.subgraph_name();
+        let (count, error) = match request {
+            InvalidationRequest::Subgraph { subgraph } => {
+                self.handle_prefix(
+                    redis_storage,
+                    request,
+                    format!("version:{ENTITY_CACHE_VERSION}:subgraph:{subgraph}:*"),
+                )
+                .await
+            }
+            InvalidationRequest::Type { subgraph, r#type } => {
+                self.handle_prefix(
+                    redis_storage,
+                    request,
+                    format!("version:{ENTITY_CACHE_VERSION}:subgraph:{subgraph}:type:{type}:*"),
+                )
+                .await
+            }
+            InvalidationRequest::Entity {
+                subgraph,
+                r#type,
+                key,
+            } => {
+                let entity_key = hash_entity_key(key);
+                self.handle_prefix(
+                    redis_storage,
+                    request,
+                    format!("version:{ENTITY_CACHE_VERSION}:subgraph:{subgraph}:type:{type}:entity:{entity_key}:*"),
+                )
+                .await
+            }
+            InvalidationRequest::Tags { tags } => self.handle_tags(redis_storage, tags).await,
+        };
+
+        let mut attributes = vec![KeyValue::new("origin", origin)];
+        // tag requests are not specific to a subgraph
+        if let Some(subgraph) = request.subgraph_name() {
+            attributes.push(KeyValue::new("subgraph.name", subgraph.clone()));
+        }
+        u64_counter!(
+            "apollo.router.operations.entity.invalidation.entry",
+            "Entity cache counter for invalidated entries",
+            count,
+            attributes
+        );
+
+        u64_histogram!(
+            "apollo.router.cache.invalidation.keys",
+            "Number of invalidated keys per invalidation request.",
+            count
+        );
+
+        match error {
+            Some(err) => Err(err.into()),
+            None => Ok(count),
+        }
+    }
+
+    /// Deletes all the keys matching a prefix
+    async fn handle_prefix(
+        &self,
+        redis_storage: &RedisCacheStorage,
+        request: &InvalidationRequest,
+        key_prefix: String,
+    ) -> (u64, Option<RedisError>) {
         tracing::debug!(
             "got invalidation request: {request:?}, will scan for: {}",
             key_prefix
         );
-
         let mut stream = redis_storage.scan(key_prefix.clone(), Some(self.scan_count));
         let mut count = 0u64;
         let mut error = None;
@@ -137,29 +199,53 @@ impl Invalidation {
                             count += deleted;
                         }
                     }
-                    scan_res.next()?;
+                    if let Err(e) = scan_res.next() {
+                        error = Some(e);
+                        break;
+                    }
                 }
             }
         }
 
-        u64_counter!(
-            "apollo.router.operations.entity.invalidation.entry",
-            "Entity cache counter for invalidated entries",
-            count,
-            "origin" = origin,
-            "subgraph.name" = subgraph.clone()
-        );
+        (count, error)
+    }
 
-        u64_histogram!(
-            "apollo.router.cache.invalidation.keys",
-            "Number of invalidated keys per invalidation request.",
-            count
-        );
+    /// Deletes all the keys referenced by the tag indexes, then the indexes themselves
+    async fn handle_tags(
+        &self,
+        redis_storage: &RedisCacheStorage,
+        tags: &[String],
+    ) -> (u64, Option<RedisError>) {
+        let mut count = 0u64;
+        let mut error = None;
 
-        match error {
-            Some(err) => Err(err.into()),
-            None => Ok(count),
+        for tag in tags {
+            let tag_key = tag_index_key(tag);
+            tracing::debug!("got tag invalidation request for {tag}, will read index {tag_key}");
+
+            match redis_storage
+                .get_set_members(RedisKey(tag_key.clone()))
+                .await
+            {
+                Err(e) => {
+                    tracing::error!(
+                        tag = tag,
+                        error = %e,
+                        message = "error reading the tag index",
+                    );
+                    error = Some(e);
+                }
+                Ok(keys) => {
+                    if !keys.is_empty() {
+                        let keys = keys.into_iter().map(RedisKey).collect::<Vec<_>>();
+                        count += redis_storage.delete(keys).await.unwrap_or(0) as u64;
+                    }
+                    redis_storage.delete(vec![RedisKey(tag_key)]).await;
+                }
+            }
         }
+
+        (count, error)
     }
 
     async fn handle_request_batch(
@@ -171,31 +257,35 @@ impl Invalidation {
         let mut errors = Vec::new();
         let mut futures = Vec::new();
         for request in requests {
-            let redis_storage = match self.storage.get(request.subgraph_name()) {
-                Some(s) => s,
-                None => continue,
+            // tags are indexed in every cache storage, so a tag request is sent to all of them
+            let redis_storages: Vec<&RedisCacheStorage> = match request.subgraph_name() {
+                Some(subgraph) => self.storage.get(subgraph).into_iter().collect(),
+                None => self.storage.all_storages(),
             };
 
-            let semaphore = self.semaphore.clone();
-            let f = async move {
-                // limit the number of invalidation requests executing at any point in time
-                let _ = semaphore.acquire().await;
+            for redis_storage in redis_storages {
+                let semaphore = self.semaphore.clone();
+                let request = request.clone();
+                let f = async move {
+                    // limit the number of invalidation requests executing at any point in time
+                    let _ = semaphore.acquire().await;
 
-                let start = Instant::now();
+                    let start = Instant::now();
 
-                let res = self
-                    .handle_request(redis_storage, origin, &request)
-                    .instrument(tracing::info_span!("cache.invalidation.request"))
-                    .await;
+                    let res = self
+                        .handle_request(redis_storage, origin, &request)
+                        .instrument(tracing::info_span!("cache.invalidation.request"))
+                        .await;
 
-                f64_histogram!(
-                    "apollo.router.cache.invalidation.duration",
-                    "Duration of the invalidation event execution.",
-                    start.elapsed().as_secs_f64()
-                );
-                res
-            };
-            futures.push(f);
+                    f64_histogram!(
+                        "apollo.router.cache.invalidation.duration",
+                        "Duration of the invalidation event execution.",
+                        start.elapsed().as_secs_f64()
+                    );
+                    res
+                };
+                futures.push(f);
+            }
         }
         let mut stream: stream::FuturesUnordered<_> = futures.into_iter().collect();
         while let Some(res) = stream.next().await {
@@ -230,33 +320,27 @@ pub(crate) enum InvalidationRequest {
         r#type: String,
         key: Value,
     },
+    /// Invalidates the entries tagged with any of `tags`. Tags are not specific to a subgraph:
+    /// the root field and entity entries of every subgraph tagged with them are removed
+    Tags {
+        tags: Vec<String>,
+    },
 }
 
-impl InvalidationRequest {
-    fn key_prefix(&self) -> String {
-        match self {
-            InvalidationRequest::Subgraph { subgraph } => {
-                format!("version:{ENTITY_CACHE_VERSION}:subgraph:{subgraph}:*",)
-            }
-            InvalidationRequest::Type { subgraph, r#type } => {
-                format!("version:{ENTITY_CACHE_VERSION}:subgraph:{subgraph}:type:{type}:*",)
-            }
-            InvalidationRequest::Entity {
-                subgraph,
-                r#type,
-                key,
-            } => {
-                let entity_key = hash_entity_key(key);
-                format!("version:{ENTITY_CACHE_VERSION}:subgraph:{subgraph}:type:{type}:entity:{entity_key}:*")
-            }
-        }
-    }
+/// Key of the Redis set listing all the cache entries tagged with `tag`, for every subgraph
+/// using the same Redis storage
+pub(crate) fn tag_index_key(tag: &str) -> String {
+    format!("version:{ENTITY_CACHE_VERSION}:tag:{tag}")
+}
 
-    pub(super) fn subgraph_name(&self) -> &String {
+impl InvalidationRequest {
+    /// Subgraph targeted by this request. Tag requests apply to every subgraph
+    pub(super) fn subgraph_name(&self) -> Option<&String> {
         match self {
             InvalidationRequest::Subgraph { subgraph }
             | InvalidationRequest::Type { subgraph, .. }
-            | InvalidationRequest::Entity { subgraph, .. } => subgraph,
+            | InvalidationRequest::Entity { subgraph, .. } => Some(subgraph),
+            InvalidationRequest::Tags { .. } => None,
         }
     }
 }
@@ -194,10 +194,12 @@ impl Service<router::Request> for InvalidationService {
     }
 }
 
+/// Checks the shared key of all subgraphs, then the one of the subgraph targeted by the request.
+/// Tag requests apply to every subgraph, so they only accept the shared key of all subgraphs
 fn valid_shared_key(
     config: &SubgraphConfiguration<Subgraph>,
     shared_key: &str,
-    subgraph_name: &str,
+    subgraph_name: Option<&String>,
 ) -> bool {
     config
         .all
@@ -205,9 +207,8 @@ fn valid_shared_key(
         .as_ref()
         .map(|i| i.shared_key == shared_key)
         .unwrap_or_default()
-        || config
-            .subgraphs
-            .get(subgraph_name)
+        || subgraph_name
+            .and_then(|subgraph_name| config.subgraphs.get(subgraph_name))
             .and_then(|s| s.invalidation.as_ref())
             .map(|i| i.shared_key == shared_key)
             .unwrap_or_default()
@@ -324,4 +325,59 @@ mod tests {
         let res = service.oneshot(req).await.unwrap();
         assert_eq!(res.response.status(), StatusCode::UNAUTHORIZED);
     }
+
+    #[tokio::test]
+    async fn test_invalidation_service_tags_subgraph_shared_key() {
+        let redis_cache = RedisCacheStorage::from_mocks(Arc::new(MockStore::new()))
+            .await
+            .unwrap();
+        let storage = Arc::new(Storage {
+            all: Some(redis_cache),
+            subgraphs: HashMap::new(),
+        });
+        let invalidation = Invalidation::new(storage.clone(), 1000, 10).await.unwrap();
+
+        let config = Arc::new(SubgraphConfiguration {
+            all: Subgraph {
+                ttl: None,
+                enabled: true,
+                redis: None,
+                private_id: None,
+                invalidation: Some(SubgraphInvalidationConfig {
+                    enabled: true,
+                    shared_key: String::from("test"),
+                }),
+            },
+            subgraphs: [(
+                String::from("test"),
+                Subgraph {
+                    ttl: None,
+                    enabled: true,
+                    redis: None,
+                    private_id: None,
+                    invalidation: Some(SubgraphInvalidationConfig {
+                        enabled: true,
+                        shared_key: String::from("test_test"),
+                    }),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        });
+        // tags apply to every subgraph, the shared key of one subgraph is not enough
+        let service = InvalidationService::new(config, invalidation);
+        let req = router::Request::fake_builder()
+            .method(http::Method::POST)
+            .header(AUTHORIZATION, "test_test")
+            .body(
+                serde_json::to_vec(&[InvalidationRequest::Tags {
+                    tags: vec![String::from("product:1")],
+                }])
+                .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let res = service.oneshot(req).await.unwrap();
+        assert_eq!(res.response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
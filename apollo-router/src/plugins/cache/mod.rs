@@ -3,5 +3,6 @@ pub(crate) mod entity;
 pub(crate) mod invalidation;
 pub(crate) mod invalidation_endpoint;
 pub(crate) mod metrics;
+pub(crate) mod response;
 #[cfg(test)]
 pub(crate) mod tests;
@@ -0,0 +1,371 @@
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::stream::once;
+use futures::StreamExt;
+use http::HeaderName;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::Value;
+use sha2::Digest;
+use sha2::Sha256;
+use tokio::sync::RwLock;
+use tower::BoxError;
+use tower::Service;
+use tracing::Instrument;
+
+use super::cache_control::CacheControl;
+use super::entity::Ttl;
+use super::entity::CONTEXT_CACHE_KEY;
+use super::entity::ENTITY_CACHE_VERSION;
+use crate::cache::redis::RedisCacheStorage;
+use crate::cache::redis::RedisKey;
+use crate::cache::redis::RedisValue;
+use crate::cache::storage::ValueType;
+use crate::configuration::RedisCache;
+use crate::json_ext::Object;
+use crate::plugins::authorization::CacheKeyMetadata;
+use crate::services::execution;
+use crate::Context;
+
+/// Whole response caching configuration
+#[derive(Clone, Debug, Default, JsonSchema, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+pub(crate) struct ResponseCacheConfig {
+    /// Enable or disable caching of complete responses for query operations
+    pub(crate) enabled: bool,
+
+    /// Redis configuration, defaults to the Redis configuration for all subgraphs
+    pub(crate) redis: Option<RedisCache>,
+
+    /// expiration for responses, unless overriden by the `Cache-Control` headers of subgraph responses
+    pub(crate) ttl: Option<Ttl>,
+
+    /// Client request headers used to separate cache entries
+    pub(crate) vary_headers: Vec<String>,
+
+    /// Context key used to separate cache sections per user
+    pub(crate) private_id: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    control: CacheControl,
+    data: Value,
+}
+
+impl ValueType for CachedResponse {
+    fn estimated_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Caches the complete response of query operations in front of execution
+///
+/// The response is only stored if it has no errors and the merged `Cache-Control` of the
+/// subgraph responses allows it
+#[derive(Clone)]
+pub(crate) struct ResponseCache {
+    storage: RedisCacheStorage,
+    ttl: Option<Duration>,
+    vary_headers: Arc<Vec<HeaderName>>,
+    private_id: Option<String>,
+    private_queries: Arc<RwLock<HashSet<String>>>,
+}
+
+impl ResponseCache {
+    pub(crate) async fn new(
+        config: &ResponseCacheConfig,
+        default_storage: Option<&RedisCacheStorage>,
+    ) -> Result<Self, BoxError> {
+        let storage = match &config.redis {
+            Some(redis) => {
+                // we need to explicitely disable TTL reset because it is managed directly by this plugin
+                let mut redis_config = redis.clone();
+                redis_config.reset_ttl = false;
+                RedisCacheStorage::new(redis_config).await?
+            }
+            None => default_storage
+                .cloned()
+                .ok_or("response caching requires a Redis configuration")?,
+        };
+
+        let vary_headers = config
+            .vary_headers
+            .iter()
+            .map(|name| HeaderName::try_from(name.as_str()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            ttl: config.ttl.as_ref().map(|t| t.0).or_else(|| storage.ttl()),
+            storage,
+            vary_headers: Arc::new(vary_headers),
+            private_id: config.private_id.clone(),
+            private_queries: Default::default(),
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_mocks(storage: RedisCacheStorage, config: ResponseCacheConfig) -> Self {
+        Self {
+            ttl: config.ttl.as_ref().map(|t| t.0),
+            storage,
+            vary_headers: Arc::new(
+                config
+                    .vary_headers
+                    .iter()
+                    .map(|name| HeaderName::try_from(name.as_str()).unwrap())
+                    .collect(),
+            ),
+            private_id: config.private_id,
+            private_queries: Default::default(),
+        }
+    }
+
+    pub(crate) fn service(&self, service: execution::BoxService) -> execution::BoxService {
+        execution::BoxService::new(ResponseCacheService(Some(InnerResponseCacheService {
+            cache: self.clone(),
+            service,
+        })))
+    }
+
+    fn get_private_id(&self, context: &Context) -> Option<String> {
+        self.private_id.as_ref().and_then(|key| {
+            context.get_json_value(key).and_then(|value| {
+                value.as_str().map(|s| {
+                    let mut digest = Sha256::new();
+                    digest.update(s);
+                    hex::encode(digest.finalize().as_slice())
+                })
+            })
+        })
+    }
+}
+
+struct ResponseCacheService(Option<InnerResponseCacheService>);
+struct InnerResponseCacheService {
+    cache: ResponseCache,
+    service: execution::BoxService,
+}
+
+impl Service<execution::Request> for ResponseCacheService {
+    type Response = execution::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        match &mut self.0 {
+            Some(s) => s.service.poll_ready(cx),
+            None => panic!("service should have been called only once"),
+        }
+    }
+
+    fn call(&mut self, request: execution::Request) -> Self::Future {
+        match self.0.take() {
+            None => panic!("service should have been called only once"),
+            Some(s) => Box::pin(s.call_inner(request)),
+        }
+    }
+}
+
+impl InnerResponseCacheService {
+    async fn call_inner(
+        self,
+        request: execution::Request,
+    ) -> Result<execution::Response, BoxError> {
+        let InnerResponseCacheService { cache, mut service } = self;
+
+        let body = request.supergraph_request.body();
+        let operation_name = body.operation_name.as_deref();
+        // only complete responses of query operations can be cached
+        if request.query_plan.contains_mutations()
+            || request.query_plan.is_subscription(operation_name)
+            || request
+                .query_plan
+                .is_deferred(operation_name, &body.variables)
+        {
+            return service.call(request).await;
+        }
+
+        cache_query_response(cache, service, request).await
+    }
+}
+
+async fn cache_query_response(
+    cache: ResponseCache,
+    mut service: execution::BoxService,
+    request: execution::Request,
+) -> Result<execution::Response, BoxError> {
+    let key = extract_cache_key(&request, &cache.vary_headers);
+    let is_known_private = { cache.private_queries.read().await.contains(&key) };
+    let private_id = cache.get_private_id(&request.context);
+
+    // the response will have a private scope but we don't have a way to differentiate users, so we know we will not get or store anything in the cache
+    if is_known_private && private_id.is_none() {
+        return service.call(request).await;
+    }
+
+    let lookup_key = match (is_known_private, private_id.as_ref()) {
+        (true, Some(id)) => format!("{key}:{id}"),
+        _ => key.clone(),
+    };
+
+    match cache_lookup(&cache.storage, &lookup_key, request)
+        .instrument(tracing::info_span!("cache.response.lookup"))
+        .await?
+    {
+        ControlFlow::Break(response) => {
+            u64_counter!(
+                "apollo.router.operations.response_cache",
+                "Whole response cache lookups",
+                1u64,
+                "cache.hit" = true
+            );
+            Ok(response)
+        }
+        ControlFlow::Continue(request) => {
+            u64_counter!(
+                "apollo.router.operations.response_cache",
+                "Whole response cache lookups",
+                1u64,
+                "cache.hit" = false
+            );
+            let mut response = service.call(request).await?;
+
+            // queries without @defer produce exactly one response
+            let first = match response.response.body_mut().next().await {
+                Some(first) => first,
+                None => return Ok(response),
+            };
+
+            let cache_control = response
+                .context
+                .extensions()
+                .with_lock(|lock| lock.get::<CacheControl>().cloned())
+                .unwrap_or_else(CacheControl::no_store);
+
+            let store_key = if cache_control.private() {
+                if !is_known_private {
+                    cache.private_queries.write().await.insert(key.clone());
+                }
+                // the response has a private scope but we don't have a way to differentiate users, so we do not store the response in cache
+                private_id.map(|id| format!("{key}:{id}"))
+            } else {
+                Some(key)
+            };
+
+            if let (Some(store_key), Some(data)) = (store_key, first.data.as_ref()) {
+                if first.errors.is_empty() && cache_control.should_store() {
+                    let ttl = cache_control
+                        .ttl()
+                        .map(|secs| Duration::from_secs(secs as u64))
+                        .or(cache.ttl);
+                    let entry = CachedResponse {
+                        control: cache_control,
+                        data: data.clone(),
+                    };
+                    let storage = cache.storage.clone();
+                    let span = tracing::info_span!("cache.response.store");
+                    tokio::spawn(async move {
+                        storage
+                            .insert(RedisKey(store_key), RedisValue(entry), ttl)
+                            .instrument(span)
+                            .await;
+                    });
+                }
+            }
+
+            let (parts, rest) = response.response.into_parts();
+            Ok(execution::Response {
+                response: http::Response::from_parts(
+                    parts,
+                    once(async { first }).chain(rest).boxed(),
+                ),
+                context: response.context,
+            })
+        }
+    }
+}
+
+async fn cache_lookup(
+    storage: &RedisCacheStorage,
+    key: &str,
+    request: execution::Request,
+) -> Result<ControlFlow<execution::Response, execution::Request>, BoxError> {
+    let cache_result: Option<RedisValue<CachedResponse>> =
+        storage.get(RedisKey(key.to_string())).await;
+
+    match cache_result {
+        Some(RedisValue(entry)) if entry.control.can_use() => {
+            let control = entry.control.clone();
+            request
+                .context
+                .extensions()
+                .with_lock(|mut lock| lock.insert(control));
+
+            let response = execution::Response::builder()
+                .data(entry.data)
+                .context(request.context)
+                .build()?;
+            Ok(ControlFlow::Break(response))
+        }
+        _ => Ok(ControlFlow::Continue(request)),
+    }
+}
+
+// build a cache key for the complete response
+fn extract_cache_key(request: &execution::Request, vary_headers: &[HeaderName]) -> String {
+    let body = request.supergraph_request.body();
+
+    // hash the query and operation name
+    let mut digest = Sha256::new();
+    digest.update(&request.query_plan.query.schema_aware_hash);
+    digest.update(&[0u8; 1][..]);
+    digest.update(body.operation_name.as_deref().unwrap_or("-").as_bytes());
+    digest.update(&[0u8; 1][..]);
+    let query_hash = hex::encode(digest.finalize().as_slice());
+
+    // hash more data like variables, authorization status and vary headers
+    let mut digest = Sha256::new();
+    digest.update(serde_json::to_vec(&body.variables).unwrap());
+    let cache_key = request
+        .context
+        .extensions()
+        .with_lock(|lock| lock.get::<CacheKeyMetadata>().cloned())
+        .unwrap_or_default();
+    digest.update(serde_json::to_vec(&cache_key).unwrap());
+    for name in vary_headers {
+        digest.update(name.as_str().as_bytes());
+        digest.update(&[0u8; 1][..]);
+        for value in request.supergraph_request.headers().get_all(name) {
+            digest.update(value.as_bytes());
+            digest.update(&[0u8; 1][..]);
+        }
+    }
+    if let Ok(Some(cache_data)) = request.context.get::<&str, Object>(CONTEXT_CACHE_KEY) {
+        if let Some(v) = cache_data.get("all") {
+            digest.update(serde_json::to_vec(v).unwrap())
+        }
+        if let Some(v) = body
+            .operation_name
+            .as_ref()
+            .and_then(|op| cache_data.get(op.as_str()))
+        {
+            digest.update(serde_json::to_vec(v).unwrap())
+        }
+    }
+    let additional_data_hash = hex::encode(digest.finalize().as_slice());
+
+    // the cache key is written to easily find keys matching a prefix for deletion:
+    // - entity cache version: current version of the hash
+    // - query hash: invalidate the entry for a specific query and operation name
+    // - additional data: separate cache entries depending on info like authorization status
+    format!("version:{ENTITY_CACHE_VERSION}:response:hash:{query_hash}:data:{additional_data_hash}")
+}
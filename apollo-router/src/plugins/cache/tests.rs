@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::Bytes;
 use fred::error::RedisErrorKind;
@@ -8,11 +10,17 @@ use fred::mocks::Mocks;
 use fred::prelude::RedisError;
 use fred::prelude::RedisValue;
 use http::header::CACHE_CONTROL;
+use http::HeaderName;
 use http::HeaderValue;
 use parking_lot::Mutex;
 use tower::ServiceExt;
 
 use super::entity::EntityCache;
+use super::invalidation::tag_index_key;
+use super::invalidation::InvalidationOrigin;
+use super::invalidation::InvalidationRequest;
+use super::response::ResponseCache;
+use super::response::ResponseCacheConfig;
 use crate::cache::redis::RedisCacheStorage;
 use crate::plugin::test::MockSubgraph;
 use crate::plugin::test::MockSubgraphService;
@@ -24,15 +32,17 @@ use crate::MockedSubgraphs;
 use crate::TestHarness;
 
 const SCHEMA: &str = include_str!("../../testdata/orga_supergraph.graphql");
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct MockStore {
     map: Arc<Mutex<HashMap<Bytes, Bytes>>>,
+    sets: Arc<Mutex<HashMap<Bytes, HashSet<Bytes>>>>,
 }
 
 impl MockStore {
     pub(crate) fn new() -> MockStore {
         MockStore {
             map: Arc::new(Mutex::new(HashMap::new())),
+            sets: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 }
@@ -80,6 +90,47 @@ impl Mocks for MockStore {
                 }
                 return Ok(RedisValue::Null);
             }
+            "SADD" => {
+                let mut args_it = command.args.iter();
+                if let Some(RedisValue::Bytes(key)) = args_it.next() {
+                    let mut sets = self.sets.lock();
+                    let set = sets.entry(key.clone()).or_default();
+                    let mut added = 0;
+                    for member in args_it {
+                        if let RedisValue::Bytes(member) = member {
+                            if set.insert(member.clone()) {
+                                added += 1;
+                            }
+                        }
+                    }
+                    return Ok(RedisValue::Integer(added));
+                }
+            }
+            "SMEMBERS" => {
+                if let Some(RedisValue::Bytes(key)) = command.args.first() {
+                    let members = self
+                        .sets
+                        .lock()
+                        .get(key)
+                        .map(|set| set.iter().cloned().map(RedisValue::Bytes).collect())
+                        .unwrap_or_default();
+                    return Ok(RedisValue::Array(members));
+                }
+            }
+            "EXPIRE" => return Ok(RedisValue::Integer(1)),
+            "DEL" => {
+                let mut deleted = 0;
+                for key in command.args.iter() {
+                    if let RedisValue::Bytes(key) = key {
+                        if self.map.lock().remove(key).is_some()
+                            || self.sets.lock().remove(key).is_some()
+                        {
+                            deleted += 1;
+                        }
+                    }
+                }
+                return Ok(RedisValue::Integer(deleted));
+            }
             //FIXME: this is not working because fred's mock never sends the response to SCAN to the client
             /*"SCAN" => {
                 let mut args_it = command.args.iter();
@@ -817,6 +868,230 @@ async fn missing_entities() {
     insta::assert_json_snapshot!(response);
 }
 
+#[tokio::test]
+async fn invalidate_by_tags() {
+    let query = "query { currentUser { activeOrganization { id creatorUser { __typename id } } } }";
+
+    let subgraphs = MockedSubgraphs([
+        ("user", MockSubgraph::builder().with_json(
+                serde_json::json!{{"query":"{currentUser{activeOrganization{__typename id}}}"}},
+                serde_json::json!{{"data": {"currentUser": { "activeOrganization": {
+                    "__typename": "Organization",
+                    "id": "1"
+                } }}}}
+        ).with_header(CACHE_CONTROL, HeaderValue::from_static("public"))
+        .with_header(HeaderName::from_static("surrogate-key"), HeaderValue::from_static("user:current organization:1"))
+        .build()),
+        ("orga", MockSubgraph::builder().with_json(
+            serde_json::json!{{
+                "query": "query($representations:[_Any!]!){_entities(representations:$representations){...on Organization{creatorUser{__typename id}}}}",
+            "variables": {
+                "representations": [
+                    {
+                        "id": "1",
+                        "__typename": "Organization",
+                    }
+                ]
+            }}},
+            serde_json::json!{{"data": {
+                "_entities": [{
+                    "creatorUser": {
+                        "__typename": "User",
+                        "id": 2
+                    }
+                }]
+            }, "extensions": { "cacheTags": [["organization:1", "user:2"]] }}}
+        ).with_header(CACHE_CONTROL, HeaderValue::from_static("public")).build())
+    ].into_iter().collect());
+
+    let store = MockStore::new();
+    let redis_cache = RedisCacheStorage::from_mocks(Arc::new(store.clone()))
+        .await
+        .unwrap();
+    let entity_cache = EntityCache::with_mocks(redis_cache.clone(), HashMap::new())
+        .await
+        .unwrap();
+    let invalidation = entity_cache.invalidation.clone();
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache)
+        .extra_plugin(subgraphs)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(Context::new())
+        .build()
+        .unwrap();
+    let mut response = service.oneshot(request).await.unwrap();
+    let response = response.next_response().await.unwrap();
+    assert!(response.errors.is_empty());
+    assert!(response.extensions.get("cacheTags").is_none());
+
+    // cache entries are stored in spawned tasks
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(store.map.lock().len(), 2);
+    let tag_index = |tag: &str| {
+        store
+            .sets
+            .lock()
+            .get(tag_index_key(tag).as_bytes())
+            .map(|set| set.len())
+            .unwrap_or_default()
+    };
+    assert_eq!(tag_index("user:current"), 1);
+    // the same tag indexes the entries of both subgraphs
+    assert_eq!(tag_index("organization:1"), 2);
+    assert_eq!(tag_index("user:2"), 1);
+
+    // one request removes the entries of every subgraph
+    let count = invalidation
+        .invalidate(
+            InvalidationOrigin::Endpoint,
+            vec![InvalidationRequest::Tags {
+                tags: vec!["organization:1".to_string()],
+            }],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(count, 2);
+    assert!(store.map.lock().is_empty());
+    assert_eq!(tag_index("organization:1"), 0);
+    // other tag indexes are left untouched, they will expire with their TTL
+    assert_eq!(tag_index("user:2"), 1);
+}
+
+#[tokio::test]
+async fn response_cache() {
+    let query = "query { currentUser { activeOrganization { id creatorUser { __typename id } } } }";
+
+    let subgraphs = MockedSubgraphs([
+        ("user", MockSubgraph::builder().with_json(
+                serde_json::json!{{"query":"{currentUser{activeOrganization{__typename id}}}"}},
+                serde_json::json!{{"data": {"currentUser": { "activeOrganization": {
+                    "__typename": "Organization",
+                    "id": "1"
+                } }}}}
+        ).with_header(CACHE_CONTROL, HeaderValue::from_static("public, max-age=60")).build()),
+        ("orga", MockSubgraph::builder().with_json(
+            serde_json::json!{{
+                "query": "query($representations:[_Any!]!){_entities(representations:$representations){...on Organization{creatorUser{__typename id}}}}",
+            "variables": {
+                "representations": [
+                    {
+                        "id": "1",
+                        "__typename": "Organization",
+                    }
+                ]
+            }}},
+            serde_json::json!{{"data": {
+                "_entities": [{
+                    "creatorUser": {
+                        "__typename": "User",
+                        "id": 2
+                    }
+                }]
+            }}}
+        ).with_header(CACHE_CONTROL, HeaderValue::from_static("public, max-age=30")).build())
+    ].into_iter().collect());
+
+    let response_store = MockStore::new();
+    let response_storage = RedisCacheStorage::from_mocks(Arc::new(response_store.clone()))
+        .await
+        .unwrap();
+    let redis_cache = RedisCacheStorage::from_mocks(Arc::new(MockStore::new()))
+        .await
+        .unwrap();
+    let mut entity_cache = EntityCache::with_mocks(redis_cache, HashMap::new())
+        .await
+        .unwrap();
+    entity_cache.response_cache = Some(ResponseCache::with_mocks(
+        response_storage.clone(),
+        ResponseCacheConfig::default(),
+    ));
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache)
+        .extra_plugin(subgraphs)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(Context::new())
+        .build()
+        .unwrap();
+    let mut response = service.oneshot(request).await.unwrap();
+    let first_response = response.next_response().await.unwrap();
+    assert!(first_response.errors.is_empty());
+
+    // cache entries are stored in spawned tasks
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    {
+        let map = response_store.map.lock();
+        assert_eq!(map.len(), 1);
+        assert!(map.keys().all(|key| std::str::from_utf8(key)
+            .unwrap()
+            .contains(":response:hash:")));
+    }
+
+    // Now testing without any mock subgraphs and an empty entity cache, all the data should come from the response cache
+    let redis_cache = RedisCacheStorage::from_mocks(Arc::new(MockStore::new()))
+        .await
+        .unwrap();
+    let mut entity_cache = EntityCache::with_mocks(redis_cache, HashMap::new())
+        .await
+        .unwrap();
+    entity_cache.response_cache = Some(ResponseCache::with_mocks(
+        response_storage,
+        ResponseCacheConfig::default(),
+    ));
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(Context::new())
+        .build()
+        .unwrap();
+    let mut response = service.oneshot(request).await.unwrap();
+    let cache_control = response
+        .response
+        .headers()
+        .get(CACHE_CONTROL)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+        .unwrap();
+    let response = response.next_response().await.unwrap();
+
+    assert_eq!(response.data, first_response.data);
+    assert!(response.errors.is_empty());
+    // the response expiration comes from the merged Cache-Control of subgraph responses
+    let max_age: u32 = cache_control
+        .split(',')
+        .find_map(|directive| directive.trim().strip_prefix("max-age="))
+        .and_then(|max_age| max_age.parse().ok())
+        .unwrap();
+    assert!(max_age <= 30);
+}
+
 /*FIXME: reactivate test if we manage to make fred return the response to SCAN in mocks
 #[tokio::test(flavor = "multi_thread")]
 async fn invalidate() {
@@ -21,6 +21,9 @@ const ENABLE_EXPOSE_QUERY_PLAN_ENV: &str = "APOLLO_EXPOSE_QUERY_PLAN";
 const QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.plan";
 const FORMATTED_QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.formatted_plan";
 const ENABLED_CONTEXT_KEY: &str = "experimental::expose_query_plan.enabled";
+const EXPLANATION_CONTEXT_KEY: &str = "experimental::expose_query_plan.explanation";
+/// Set when the query planner should explain how it chose the query plan
+pub(crate) const EXPLAIN_QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.explain";
 
 #[derive(Debug, Clone)]
 struct ExposeQueryPlan {
@@ -65,6 +68,11 @@ impl Plugin for ExposeQueryPlan {
                             req.query_plan.formatted_query_plan.clone(),
                         )
                         .unwrap();
+                    if let Some(explanation) = &req.query_plan.explanation {
+                        req.context
+                            .insert(EXPLANATION_CONTEXT_KEY, explanation.clone())
+                            .unwrap();
+                    }
                 }
 
                 req
@@ -76,9 +84,14 @@ impl Plugin for ExposeQueryPlan {
         let conf_enabled = self.enabled;
         service
             .map_future_with_request_data(move |req: &supergraph::Request| {
-                let is_enabled = conf_enabled && req.supergraph_request.headers().get(EXPOSE_QUERY_PLAN_HEADER_NAME) == Some(&HeaderValue::from_static("true"));
+                let header = req.supergraph_request.headers().get(EXPOSE_QUERY_PLAN_HEADER_NAME);
+                let explain = header == Some(&HeaderValue::from_static("explain"));
+                let is_enabled = conf_enabled && (explain || header == Some(&HeaderValue::from_static("true")));
                 if is_enabled {
                     req.context.insert(ENABLED_CONTEXT_KEY, true).unwrap();
+                    if explain {
+                        req.context.insert(EXPLAIN_QUERY_PLAN_CONTEXT_KEY, true).unwrap();
+                    }
                 }
 
                 is_enabled
@@ -95,9 +108,13 @@ impl Plugin for ExposeQueryPlan {
                                 if let Some(plan) =
                                     res.context.get_json_value(QUERY_PLAN_CONTEXT_KEY)
                                 {
+                                    let mut query_plan = json!({ "object": { "kind": "QueryPlan", "node": plan }, "text": res.context.get_json_value(FORMATTED_QUERY_PLAN_CONTEXT_KEY) });
+                                    if let Some(explanation) = res.context.get_json_value(EXPLANATION_CONTEXT_KEY) {
+                                        query_plan.as_object_mut().expect("query plan extension is an object").insert("explanation", explanation);
+                                    }
                                     first
                                         .extensions
-                                        .insert("apolloQueryPlan", json!({ "object": { "kind": "QueryPlan", "node": plan }, "text": res.context.get_json_value(FORMATTED_QUERY_PLAN_CONTEXT_KEY) }));
+                                        .insert("apolloQueryPlan", query_plan);
                                 }
                             }
                             res.response = http::Response::from_parts(
@@ -189,13 +206,21 @@ mod tests {
     }
 
     async fn execute_supergraph_test(
+        query: &str,
+        supergraph_service: supergraph::BoxCloneService,
+    ) -> Response {
+        execute_supergraph_test_with_header(query, supergraph_service, "true").await
+    }
+
+    async fn execute_supergraph_test_with_header(
         query: &str,
         mut supergraph_service: supergraph::BoxCloneService,
+        header_value: &str,
     ) -> Response {
         let request = supergraph::Request::fake_builder()
             .query(query.to_string())
             .variable("first", 2usize)
-            .header(EXPOSE_QUERY_PLAN_HEADER_NAME, "true")
+            .header(EXPOSE_QUERY_PLAN_HEADER_NAME, header_value)
             .build()
             .expect("expecting valid request");
 
@@ -252,4 +277,30 @@ mod tests {
 
         insta::assert_json_snapshot!(serde_json::to_value(response).unwrap());
     }
+
+    #[tokio::test]
+    async fn it_explains_query_plan() {
+        let supergraph = build_mock_supergraph(serde_json::json! {{
+            "experimental_query_planner_mode": "new",
+            "plugins": {
+                "experimental.expose_query_plan": true
+            }
+        }})
+        .await;
+        let response =
+            execute_supergraph_test_with_header(VALID_QUERY, supergraph.clone(), "explain").await;
+        let query_plan = response.extensions.get("apolloQueryPlan").unwrap();
+        let explanation = query_plan.as_object().unwrap().get("explanation").unwrap();
+        let traversals = explanation.get("traversals").unwrap().as_array().unwrap();
+        assert_eq!(traversals.len(), 1);
+        let edges = traversals[0].get("edges").unwrap().as_array().unwrap();
+        assert!(edges
+            .iter()
+            .any(|edge| edge.get("kind") == Some(&Value::from("key"))));
+
+        // Plans are only explained when asked to
+        let response = execute_supergraph_test(VALID_QUERY, supergraph).await;
+        let query_plan = response.extensions.get("apolloQueryPlan").unwrap();
+        assert!(query_plan.as_object().unwrap().get("explanation").is_none());
+    }
 }
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use bytesize::ByteSize;
 use schemars::JsonSchema;
 use serde::Deserialize;
@@ -25,7 +27,7 @@ impl Default for MultipartRequestLimits {
 }
 
 /// Supported mode for a multipart request
-#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "lowercase")]
 pub(crate) enum MultipartRequestMode {
     /// The multipart request will not be loaded into memory and instead will
@@ -37,6 +39,36 @@ pub(crate) enum MultipartRequestMode {
     /// files.
     #[default]
     Stream,
+
+    /// The files of the multipart request are buffered as they are received, in memory or
+    /// in temporary files on disk, and removed when the request ends. Files can then be sent to
+    /// any number of subgraphs, in whatever order the query plan needs them.
+    ///
+    /// Buffered files are subject to the same limits as streamed ones.
+    Buffer,
+}
+
+/// Storage of the files of a multipart request in [MultipartRequestMode::Buffer] mode
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct MultipartBufferConfig {
+    /// Files up to this size are kept in memory, larger files are written to temporary
+    /// files on disk (default: 256KB)
+    #[serde(deserialize_with = "bytesize::ByteSize::deserialize")]
+    #[schemars(with = "String")]
+    pub(crate) max_in_memory_file_size: ByteSize,
+
+    /// The directory where temporary files are written (default: the system temporary directory)
+    pub(crate) directory: Option<PathBuf>,
+}
+
+impl Default for MultipartBufferConfig {
+    fn default() -> Self {
+        Self {
+            max_in_memory_file_size: ByteSize::kb(256),
+            directory: None,
+        }
+    }
 }
 
 /// Configuration for a multipart request for file uploads.
@@ -53,6 +85,9 @@ pub(crate) struct MultipartRequest {
 
     /// Resource limits for multipart requests
     pub(crate) limits: MultipartRequestLimits,
+
+    /// Storage of the files in [MultipartRequestMode::Buffer] mode
+    pub(crate) buffer: MultipartBufferConfig,
 }
 
 impl Default for MultipartRequest {
@@ -61,6 +96,7 @@ impl Default for MultipartRequest {
             enabled: true,
             mode: Default::default(),
             limits: Default::default(),
+            buffer: Default::default(),
         }
     }
 }
@@ -52,6 +52,9 @@ pub(super) enum FileUploadError {
     #[error("Exceeded the limit of {limit} on {filename} file.")]
     MaxFileSizeLimitExceeded { limit: ByteSize, filename: String },
 
+    #[error("Failed to buffer uploaded files: {0}")]
+    BufferingFailed(#[from] std::io::Error),
+
     #[error("{0}")]
     HyperBodyErrorWrapper(#[from] hyper::Error),
 }
@@ -67,6 +70,7 @@ impl From<FileUploadError> for graphql::Error {
                 FileUploadError::MaxFileSizeLimitExceeded { .. } => {
                     "FILE_UPLOADS_LIMITS_MAX_FILE_SIZE_EXCEEDED".to_string()
                 }
+                FileUploadError::BufferingFailed(_) => "FILE_UPLOADS_BUFFERING_FAILED".to_string(),
                 _ => "FILE_UPLOADS_OPERATION_CANNOT_STREAM".to_string(),
             })
             .build()
@@ -16,7 +16,9 @@ use tower::ServiceBuilder;
 use tower::ServiceExt;
 
 use self::config::FileUploadsConfig;
+use self::config::MultipartBufferConfig;
 use self::config::MultipartRequestLimits;
+use self::config::MultipartRequestMode;
 use self::error::FileUploadError;
 use self::map_field::MapField;
 use self::multipart_form_data::MultipartFormData;
@@ -47,6 +49,8 @@ type Result<T> = std::result::Result<T, error::FileUploadError>;
 struct FileUploadsPlugin {
     enabled: bool,
     limits: MultipartRequestLimits,
+    mode: MultipartRequestMode,
+    buffer_config: MultipartBufferConfig,
 }
 
 register_private_plugin!("apollo", "preview_file_uploads", FileUploadsPlugin);
@@ -59,7 +63,14 @@ impl PluginPrivate for FileUploadsPlugin {
         let config = init.config;
         let enabled = config.enabled && config.protocols.multipart.enabled;
         let limits = config.protocols.multipart.limits;
-        Ok(Self { enabled, limits })
+        let mode = config.protocols.multipart.mode;
+        let buffer_config = config.protocols.multipart.buffer;
+        Ok(Self {
+            enabled,
+            limits,
+            mode,
+            buffer_config,
+        })
     }
 
     fn router_service(&self, service: router::BoxService) -> router::BoxService {
@@ -67,11 +78,14 @@ impl PluginPrivate for FileUploadsPlugin {
             return service;
         }
         let limits = self.limits;
+        let mode = self.mode;
+        let buffer_config = self.buffer_config.clone();
         ServiceBuilder::new()
             .oneshot_checkpoint_async(move |req: router::Request| {
+                let buffer_config = buffer_config.clone();
                 async move {
                     let context = req.context.clone();
-                    Ok(match router_layer(req, limits).await {
+                    Ok(match router_layer(req, limits, mode, buffer_config).await {
                         Ok(req) => ControlFlow::Continue(req),
                         Err(err) => ControlFlow::Break(
                             router::Response::error_builder()
@@ -115,10 +129,11 @@ impl PluginPrivate for FileUploadsPlugin {
         if !self.enabled {
             return service;
         }
+        let mode = self.mode;
         ServiceBuilder::new()
-            .checkpoint(|req: execution::Request| {
+            .checkpoint(move |req: execution::Request| {
                 let context = req.context.clone();
-                Ok(match execution_layer(req) {
+                Ok(match execution_layer(req, mode) {
                     Ok(req) => ControlFlow::Continue(req),
                     Err(err) => ControlFlow::Break(
                         execution::Response::error_builder()
@@ -165,6 +180,8 @@ fn get_multipart_mime(req: &router::Request) -> Option<MediaType> {
 async fn router_layer(
     req: router::Request,
     limits: MultipartRequestLimits,
+    mode: MultipartRequestMode,
+    buffer_config: MultipartBufferConfig,
 ) -> Result<router::Request> {
     if let Some(mime) = get_multipart_mime(&req) {
         let boundary = mime
@@ -174,7 +191,8 @@ async fn router_layer(
 
         let (mut request_parts, request_body) = req.router_request.into_parts();
 
-        let mut multipart = MultipartRequest::new(request_body.into(), boundary, limits);
+        let mut multipart =
+            MultipartRequest::new(request_body.into(), boundary, limits, mode, buffer_config);
         let operations_stream = multipart.operations_field().await?;
 
         req.context
@@ -301,7 +319,14 @@ struct SupergraphLayerResult {
     map: Arc<MapField>,
 }
 
-fn execution_layer(req: execution::Request) -> Result<execution::Request> {
+fn execution_layer(
+    req: execution::Request,
+    mode: MultipartRequestMode,
+) -> Result<execution::Request> {
+    // Buffered files can be sent in any order, so the query plan can be executed as is
+    if mode == MultipartRequestMode::Buffer {
+        return Ok(req);
+    }
     let supergraph_result = req
         .context
         .extensions()
@@ -1,22 +1,34 @@
 use core::task;
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::mem;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Poll;
 
 use bytes::Bytes;
+use bytes::BytesMut;
+use futures::future;
+use futures::stream;
+use futures::stream::BoxStream;
 use futures::Stream;
+use futures::StreamExt;
+use futures::TryStreamExt;
 use http::HeaderMap;
+use indexmap::IndexSet;
 use itertools::Itertools;
 use multer::Constraints;
 use multer::Multipart;
 use multer::SizeLimit;
 use pin_project_lite::pin_project;
+use tempfile::TempPath;
+use tokio::io::AsyncWriteExt;
 use tokio::sync::Mutex;
 use tokio::sync::OwnedMutexGuard;
+use tokio_util::io::ReaderStream;
 
+use super::config::MultipartBufferConfig;
 use super::config::MultipartRequestLimits;
+use super::config::MultipartRequestMode;
 use super::error::FileUploadError;
 use super::map_field::MapField;
 use super::map_field::MapFieldRaw;
@@ -36,6 +48,12 @@ pub(super) struct MultipartRequest {
 struct MultipartRequestState {
     multer: multer::Multipart<'static>,
     limits: MultipartRequestLimits,
+    mode: MultipartRequestMode,
+    buffer_config: MultipartBufferConfig,
+    // The files expected from the 'map' field
+    expected_files: IndexSet<String>,
+    // The files read so far in buffer mode
+    buffered_files: HashMap<String, Arc<BufferedFile>>,
     read_files_counter: usize,
     file_sizes: Vec<usize>,
     max_files_exceeded: bool,
@@ -72,6 +90,8 @@ impl MultipartRequest {
         request_body: RouterBody,
         boundary: String,
         limits: MultipartRequestLimits,
+        mode: MultipartRequestMode,
+        buffer_config: MultipartBufferConfig,
     ) -> Self {
         let multer = Multipart::with_constraints(
             request_body,
@@ -82,6 +102,10 @@ impl MultipartRequest {
             state: Arc::new(Mutex::new(MultipartRequestState {
                 multer,
                 limits,
+                mode,
+                buffer_config,
+                expected_files: IndexSet::new(),
+                buffered_files: HashMap::new(),
                 read_files_counter: 0,
                 file_sizes: Vec::new(),
                 max_files_exceeded: false,
@@ -120,26 +144,176 @@ impl MultipartRequest {
             state.max_files_exceeded = true;
             return Err(FileUploadError::MaxFilesLimitExceeded(limit));
         }
+        state.expected_files = map_field.keys().cloned().collect();
         MapField::new(map_field)
     }
 
+    /// The files to send to a subgraph, each preceded by the prefix generated from its headers.
     pub(super) async fn subgraph_stream<FilePrefixFn>(
         &mut self,
-        file_names: HashSet<String>,
+        file_names: IndexSet<String>,
         file_prefix_fn: FilePrefixFn,
-    ) -> SubgraphFileProxyStream<FilePrefixFn>
+    ) -> BoxStream<'static, UploadResult<Bytes>>
     where
-        FilePrefixFn: Fn(&HeaderMap) -> Bytes,
+        FilePrefixFn: Fn(&HeaderMap) -> Bytes + Send + 'static,
     {
-        let state = self.state.clone().lock_owned().await;
-        SubgraphFileProxyStream::new(state, file_names, file_prefix_fn)
+        let mut state = self.state.clone().lock_owned().await;
+        match state.mode {
+            MultipartRequestMode::Stream => {
+                SubgraphFileProxyStream::new(state, file_names, file_prefix_fn).boxed()
+            }
+            MultipartRequestMode::Buffer => {
+                let files = state.buffer_files(&file_names).await;
+                drop(state);
+                match files {
+                    Ok(files) => stream::iter(files)
+                        .flat_map(move |file| {
+                            stream::once(future::ready(Ok(file_prefix_fn(&file.headers))))
+                                .chain(file.content())
+                        })
+                        .boxed(),
+                    Err(err) => stream::once(future::ready(Err(err))).boxed(),
+                }
+            }
+        }
+    }
+}
+
+/// A file of the multipart request, buffered so it can be sent to several subgraphs.
+#[derive(Debug)]
+struct BufferedFile {
+    headers: HeaderMap,
+    content: BufferedContent,
+}
+
+#[derive(Debug)]
+enum BufferedContent {
+    Memory(Bytes),
+    // The temporary file is removed when dropped, at the end of the request
+    Disk(TempPath),
+}
+
+impl BufferedFile {
+    fn content(&self) -> BoxStream<'static, UploadResult<Bytes>> {
+        match &self.content {
+            BufferedContent::Memory(bytes) => {
+                stream::once(future::ready(Ok(bytes.clone()))).boxed()
+            }
+            BufferedContent::Disk(path) => {
+                let path = path.to_path_buf();
+                stream::once(async move { tokio::fs::File::open(path).await })
+                    .map_ok(ReaderStream::new)
+                    .try_flatten()
+                    .map_err(FileUploadError::BufferingFailed)
+                    .boxed()
+            }
+        }
+    }
+}
+
+impl MultipartRequestState {
+    /// Reads the request until all the given files are buffered, and returns them in order.
+    async fn buffer_files(
+        &mut self,
+        file_names: &IndexSet<String>,
+    ) -> UploadResult<Vec<Arc<BufferedFile>>> {
+        while file_names
+            .iter()
+            .any(|name| !self.buffered_files.contains_key(name))
+        {
+            let Some(field) = self.multer.next_field().await? else {
+                return Err(FileUploadError::MissingFiles(
+                    file_names
+                        .iter()
+                        .filter(|name| !self.buffered_files.contains_key(*name))
+                        .map(|file| format!("'{}'", file))
+                        .join(", "),
+                ));
+            };
+            let limit = self.limits.max_files;
+            if self.read_files_counter == limit {
+                self.max_files_exceeded = true;
+                return Err(FileUploadError::MaxFilesLimitExceeded(limit));
+            }
+            self.read_files_counter += 1;
+
+            // Extraneous files are skipped, as in stream mode
+            let Some(name) = field
+                .name()
+                .filter(|name| self.expected_files.contains(*name))
+                .map(str::to_owned)
+            else {
+                continue;
+            };
+            let file = self.buffer_field(field).await?;
+            self.buffered_files.insert(name, Arc::new(file));
+        }
+
+        Ok(file_names
+            .iter()
+            .filter_map(|name| self.buffered_files.get(name).cloned())
+            .collect())
+    }
+
+    /// Buffers a file in memory, moving it to a temporary file once it exceeds the in-memory limit.
+    async fn buffer_field(
+        &mut self,
+        mut field: multer::Field<'static>,
+    ) -> UploadResult<BufferedFile> {
+        let filename = field
+            .file_name()
+            .or_else(|| field.name())
+            .map(|name| format!("'{}'", name))
+            .unwrap_or_else(|| "unknown".to_owned());
+        let headers = field.headers().clone();
+        let max_file_size = self.limits.max_file_size.as_u64() as usize;
+        let max_in_memory_size = self.buffer_config.max_in_memory_file_size.as_u64() as usize;
+
+        let mut size = 0;
+        let mut memory = BytesMut::new();
+        let mut disk: Option<(tokio::fs::File, TempPath)> = None;
+        while let Some(chunk) = field.chunk().await? {
+            size += chunk.len();
+            if size > max_file_size {
+                self.max_files_size_exceeded = true;
+                return Err(FileUploadError::MaxFileSizeLimitExceeded {
+                    limit: self.limits.max_file_size,
+                    filename,
+                });
+            }
+            if disk.is_none() && size > max_in_memory_size {
+                let temp_file = match &self.buffer_config.directory {
+                    Some(directory) => tempfile::NamedTempFile::new_in(directory)?,
+                    None => tempfile::NamedTempFile::new()?,
+                };
+                let (file, path) = temp_file.into_parts();
+                let mut file = tokio::fs::File::from_std(file);
+                file.write_all(&memory).await?;
+                memory.clear();
+                disk = Some((file, path));
+            }
+            match &mut disk {
+                Some((file, _)) => file.write_all(&chunk).await?,
+                None => memory.extend_from_slice(&chunk),
+            }
+        }
+        self.file_sizes.push(size);
+
+        let content = match disk {
+            Some((mut file, path)) => {
+                file.flush().await?;
+                BufferedContent::Disk(path)
+            }
+            None => BufferedContent::Memory(memory.freeze()),
+        };
+        Ok(BufferedFile { headers, content })
     }
 }
 
 pin_project! {
     pub(super) struct SubgraphFileProxyStream<FilePrefixFn> {
         state: OwnedMutexGuard<MultipartRequestState>,
-        file_names: HashSet<String>,
+        file_names: IndexSet<String>,
         file_prefix_fn: FilePrefixFn,
         #[pin]
         current_field: Option<multer::Field<'static>>,
@@ -153,7 +327,7 @@ where
 {
     fn new(
         state: OwnedMutexGuard<MultipartRequestState>,
-        file_names: HashSet<String>,
+        file_names: IndexSet<String>,
         file_prefix_fn: FilePrefixFn,
     ) -> Self {
         Self {
@@ -242,7 +416,7 @@ where
                         self.state.read_files_counter += 1;
 
                         if let Some(name) = field.name() {
-                            if self.file_names.remove(name) {
+                            if self.file_names.shift_remove(name) {
                                 let prefix = (self.file_prefix_fn)(field.headers());
                                 self.current_field = Some(field);
                                 return Poll::Ready(Some(Ok(prefix)));
@@ -46,6 +46,8 @@ pub(super) fn rearrange_query_plan(
         query: query_plan.query.clone(),
         query_metrics: query_plan.query_metrics,
         estimated_size: Default::default(),
+        evaluated_plan_count: query_plan.evaluated_plan_count,
+        explanation: query_plan.explanation.clone(),
     })
 }
 
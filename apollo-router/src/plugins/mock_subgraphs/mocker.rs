@@ -0,0 +1,403 @@
+//! Generation of subgraph responses from the supergraph schema.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::Arc;
+
+use apollo_compiler::ast;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::Name;
+use apollo_compiler::Node;
+use apollo_compiler::Schema;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use serde_json_bytes::json;
+
+use crate::graphql;
+use crate::json_ext::Object;
+use crate::json_ext::Value;
+use crate::query_planner::fetch::OperationKind;
+use crate::spec::query::parse_hir_value;
+use crate::spec::ScalarFormat;
+
+const MOCK_DIRECTIVE_NAME: &str = "mock";
+const MOCK_VALUE_ARGUMENT: &str = "value";
+const ENTITIES: &str = "_entities";
+const REPRESENTATIONS: &str = "representations";
+const TYPENAME: &str = "__typename";
+
+/// Generates type-correct responses to subgraph operations
+pub(crate) struct Mocker {
+    schema: Arc<Valid<Schema>>,
+    /// Object types that can be returned for each interface and union
+    possible_types: HashMap<Name, Vec<Name>>,
+    seed: u64,
+    list_length: usize,
+    /// Values keyed by `Type.field`, or by scalar or enum type name
+    overrides: HashMap<String, Value>,
+}
+
+impl Mocker {
+    pub(crate) fn new(
+        schema: Arc<Valid<Schema>>,
+        seed: u64,
+        list_length: usize,
+        overrides: HashMap<String, Value>,
+    ) -> Self {
+        let mut possible_types: HashMap<Name, Vec<Name>> = HashMap::new();
+        for (name, ty) in &schema.types {
+            match ty {
+                ExtendedType::Object(object) => {
+                    for interface in &object.implements_interfaces {
+                        possible_types
+                            .entry(interface.name.clone())
+                            .or_default()
+                            .push(name.clone());
+                    }
+                }
+                ExtendedType::Union(union) => {
+                    possible_types.insert(
+                        name.clone(),
+                        union.members.iter().map(|m| m.name.clone()).collect(),
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            schema,
+            possible_types,
+            seed,
+            list_length,
+            overrides,
+        }
+    }
+
+    /// Generates the response to a subgraph request. The same request always gets the same
+    /// response for a given seed.
+    pub(crate) fn mock_response(
+        &self,
+        subgraph_name: &str,
+        request: &graphql::Request,
+        operation_kind: OperationKind,
+    ) -> graphql::Response {
+        if operation_kind == OperationKind::Subscription {
+            return error_response("subscriptions cannot be mocked");
+        }
+        let Some(query) = request.query.as_deref() else {
+            return error_response("missing query in the subgraph request");
+        };
+        let document = match ast::Document::parse(query, "mock.graphql") {
+            Ok(document) => document,
+            Err(invalid) => return error_response(&format!("invalid subgraph query: {invalid}")),
+        };
+
+        let mut fragments = HashMap::new();
+        let mut operation = None;
+        for definition in &document.definitions {
+            match definition {
+                ast::Definition::FragmentDefinition(fragment) => {
+                    fragments.insert(fragment.name.clone(), fragment.clone());
+                }
+                ast::Definition::OperationDefinition(op) => {
+                    let matches_name = match (&request.operation_name, &op.name) {
+                        (Some(expected), Some(name)) => expected == name.as_str(),
+                        (Some(_), None) => false,
+                        (None, _) => true,
+                    };
+                    if operation.is_none() && matches_name {
+                        operation = Some(op.clone());
+                    }
+                }
+                _ => {}
+            }
+        }
+        let Some(operation) = operation else {
+            return error_response("operation not found in the subgraph query");
+        };
+        let Some(root_type) = self.schema.root_operation(operation.operation_type) else {
+            return error_response("the supergraph has no root type for this operation");
+        };
+
+        let mut hasher = DefaultHasher::new();
+        subgraph_name.hash(&mut hasher);
+        query.hash(&mut hasher);
+        serde_json::to_string(&request.variables)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+
+        let mut generation = Generation {
+            mocker: self,
+            fragments,
+            variables: &request.variables,
+            rng: StdRng::seed_from_u64(self.seed ^ hasher.finish()),
+        };
+        let mut data = Object::new();
+        generation.mock_selection_set(root_type, &operation.selection_set, &mut data, true);
+
+        graphql::Response::builder()
+            .data(Value::Object(data))
+            .build()
+    }
+}
+
+fn error_response(message: &str) -> graphql::Response {
+    graphql::Response::builder()
+        .error(
+            graphql::Error::builder()
+                .message(format!("cannot mock subgraph response: {message}"))
+                .extension_code("MOCK_SUBGRAPH_ERROR")
+                .build(),
+        )
+        .build()
+}
+
+/// State of the generation of a single response
+struct Generation<'a> {
+    mocker: &'a Mocker,
+    fragments: HashMap<Name, Node<ast::FragmentDefinition>>,
+    variables: &'a Object,
+    rng: StdRng,
+}
+
+impl<'a> Generation<'a> {
+    fn mock_selection_set(
+        &mut self,
+        type_name: &str,
+        selection_set: &[ast::Selection],
+        output: &mut Object,
+        is_root: bool,
+    ) {
+        for selection in selection_set {
+            match selection {
+                ast::Selection::Field(field) => {
+                    if !self.is_included(&field.directives) {
+                        continue;
+                    }
+                    let key = field.alias.as_ref().unwrap_or(&field.name).as_str();
+                    let value = if field.name == TYPENAME {
+                        Value::String(type_name.into())
+                    } else if is_root && field.name == ENTITIES {
+                        self.mock_entities(&field.selection_set)
+                    } else {
+                        self.mock_field(type_name, field)
+                    };
+                    match (output.get_mut(key), value) {
+                        // the same field can be selected from several fragments
+                        (Some(Value::Object(existing)), Value::Object(value)) => {
+                            for (key, value) in value {
+                                existing.entry(key).or_insert(value);
+                            }
+                        }
+                        (Some(_), _) => {}
+                        (None, value) => {
+                            output.insert(key, value);
+                        }
+                    }
+                }
+                ast::Selection::InlineFragment(fragment) => {
+                    let applies = fragment
+                        .type_condition
+                        .as_ref()
+                        .map_or(true, |condition| self.applies(condition, type_name));
+                    if applies && self.is_included(&fragment.directives) {
+                        self.mock_selection_set(
+                            type_name,
+                            &fragment.selection_set,
+                            output,
+                            is_root,
+                        );
+                    }
+                }
+                ast::Selection::FragmentSpread(spread) => {
+                    let Some(fragment) = self.fragments.get(&spread.fragment_name).cloned() else {
+                        continue;
+                    };
+                    if self.applies(&fragment.type_condition, type_name)
+                        && self.is_included(&spread.directives)
+                    {
+                        self.mock_selection_set(
+                            type_name,
+                            &fragment.selection_set,
+                            output,
+                            is_root,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn mock_entities(&mut self, selection_set: &[ast::Selection]) -> Value {
+        let representations = self
+            .variables
+            .get(REPRESENTATIONS)
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+
+        Value::Array(
+            representations
+                .into_iter()
+                .map(|representation| {
+                    let Some(type_name) = representation.get(TYPENAME).and_then(|t| t.as_str())
+                    else {
+                        return Value::Null;
+                    };
+                    let mut entity = Object::new();
+                    self.mock_selection_set(type_name, selection_set, &mut entity, false);
+                    // keep the key fields consistent with the entity that was asked for
+                    if let Some(representation) = representation.as_object() {
+                        for (key, value) in representation {
+                            if let Some(field) = entity.get_mut(key) {
+                                *field = value.clone();
+                            }
+                        }
+                    }
+                    Value::Object(entity)
+                })
+                .collect(),
+        )
+    }
+
+    fn mock_field(&mut self, type_name: &str, field: &ast::Field) -> Value {
+        let Ok(definition) = self.mocker.schema.type_field(type_name, &field.name) else {
+            return Value::Null;
+        };
+        if let Some(value) = self
+            .mocker
+            .overrides
+            .get(&format!("{type_name}.{}", field.name))
+        {
+            return value.clone();
+        }
+        if let Some(value) = definition
+            .directives
+            .get(MOCK_DIRECTIVE_NAME)
+            .and_then(|directive| directive.specified_argument_by_name(MOCK_VALUE_ARGUMENT))
+            .and_then(|value| parse_hir_value(value))
+        {
+            return value;
+        }
+        self.mock_type(&definition.ty, &field.name, &field.selection_set)
+    }
+
+    fn mock_type(
+        &mut self,
+        ty: &ast::Type,
+        field_name: &str,
+        selection_set: &[ast::Selection],
+    ) -> Value {
+        let type_name = match ty {
+            ast::Type::List(inner) | ast::Type::NonNullList(inner) => {
+                return Value::Array(
+                    (0..self.mocker.list_length)
+                        .map(|_| self.mock_type(inner, field_name, selection_set))
+                        .collect(),
+                );
+            }
+            ast::Type::Named(name) | ast::Type::NonNullNamed(name) => name,
+        };
+        if let Some(value) = self.mocker.overrides.get(type_name.as_str()) {
+            return value.clone();
+        }
+
+        match self.mocker.schema.types.get(type_name) {
+            Some(ExtendedType::Scalar(scalar)) => self.mock_scalar(type_name, scalar, field_name),
+            Some(ExtendedType::Enum(enum_type)) => {
+                let index = self.rng.gen_range(0..enum_type.values.len().max(1));
+                enum_type
+                    .values
+                    .keys()
+                    .nth(index)
+                    .map(|value| Value::String(value.as_str().into()))
+                    .unwrap_or_default()
+            }
+            Some(ExtendedType::Object(_)) => {
+                let mut object = Object::new();
+                self.mock_selection_set(type_name, selection_set, &mut object, false);
+                Value::Object(object)
+            }
+            Some(ExtendedType::Interface(_) | ExtendedType::Union(_)) => {
+                let possible_types = self
+                    .mocker
+                    .possible_types
+                    .get(type_name)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                if possible_types.is_empty() {
+                    return Value::Null;
+                }
+                let concrete_type =
+                    possible_types[self.rng.gen_range(0..possible_types.len())].clone();
+                let mut object = Object::new();
+                self.mock_selection_set(&concrete_type, selection_set, &mut object, false);
+                Value::Object(object)
+            }
+            _ => Value::Null,
+        }
+    }
+
+    fn mock_scalar(
+        &mut self,
+        type_name: &str,
+        scalar: &apollo_compiler::schema::ScalarType,
+        field_name: &str,
+    ) -> Value {
+        match type_name {
+            "Int" => json!(self.rng.gen_range(0..100)),
+            "Float" => json!((self.rng.gen_range(0.0..100.0_f64) * 100.0).round() / 100.0),
+            "Boolean" => json!(self.rng.gen_bool(0.5)),
+            "ID" => json!(self.rng.gen_range(1..10_000).to_string()),
+            "String" => json!(format!("{field_name} {}", self.rng.gen_range(1..1_000))),
+            _ => {
+                let format = scalar
+                    .directives
+                    .get("specifiedBy")
+                    .and_then(|directive| directive.specified_argument_by_name("url"))
+                    .and_then(|url| url.as_str())
+                    .and_then(ScalarFormat::from_specified_by);
+                match format {
+                    Some(ScalarFormat::DateTime) => json!("2024-01-01T00:00:00Z"),
+                    Some(ScalarFormat::Date) => json!("2024-01-01"),
+                    Some(ScalarFormat::Uuid) => json!(uuid::Uuid::from_u128(self.rng.gen())
+                        .hyphenated()
+                        .to_string()),
+                    Some(ScalarFormat::Email) => {
+                        json!(format!("user{}@example.com", self.rng.gen_range(1..1_000)))
+                    }
+                    Some(ScalarFormat::Url) => json!("https://example.com/"),
+                    Some(ScalarFormat::BigInt) => json!(self.rng.gen::<u32>()),
+                    Some(ScalarFormat::Json) => json!({}),
+                    None => json!(format!("{type_name} {}", self.rng.gen_range(1..1_000))),
+                }
+            }
+        }
+    }
+
+    /// Whether a fragment with this type condition applies to an object of type `type_name`
+    fn applies(&self, condition: &str, type_name: &str) -> bool {
+        condition == type_name || self.mocker.schema.is_subtype(condition, type_name)
+    }
+
+    /// Evaluates `@skip` and `@include`
+    fn is_included(&self, directives: &ast::DirectiveList) -> bool {
+        let condition = |name: &str| {
+            let value = directives.get(name)?.specified_argument_by_name("if")?;
+            match value.as_ref() {
+                ast::Value::Boolean(b) => Some(*b),
+                ast::Value::Variable(variable) => self
+                    .variables
+                    .get(variable.as_str())
+                    .and_then(|v| v.as_bool()),
+                _ => None,
+            }
+        };
+        condition("skip") != Some(true) && condition("include") != Some(false)
+    }
+}
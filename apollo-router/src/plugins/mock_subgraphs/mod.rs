@@ -0,0 +1,143 @@
+//! Replaces subgraphs with responses generated from the supergraph schema.
+//!
+//! This lets frontend teams build against the supergraph before the subgraphs exist.
+
+mod mocker;
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use self::mocker::Mocker;
+use crate::json_ext::Value;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::subgraph;
+
+/// Schema driven subgraph mocking configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct MockSubgraphsConfig {
+    /// The subgraphs to mock: `all` (default), or a list of subgraph names
+    pub(crate) subgraphs: MockedSubgraphs,
+
+    /// Seed of the value generator. For a given seed, the same subgraph request always gets the
+    /// same response (default: 0)
+    pub(crate) seed: u64,
+
+    /// Number of items generated for list fields (default: 2)
+    pub(crate) list_length: usize,
+
+    /// Path to a YAML or JSON file of values to use instead of generated ones, keyed by
+    /// `Type.field`, or by scalar or enum type name
+    pub(crate) overrides: Option<PathBuf>,
+}
+
+impl Default for MockSubgraphsConfig {
+    fn default() -> Self {
+        Self {
+            subgraphs: MockedSubgraphs::All(AllSubgraphs::All),
+            seed: 0,
+            list_length: 2,
+            overrides: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum MockedSubgraphs {
+    All(AllSubgraphs),
+    Only(Vec<String>),
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AllSubgraphs {
+    All,
+}
+
+impl MockedSubgraphs {
+    fn contains(&self, subgraph_name: &str) -> bool {
+        match self {
+            MockedSubgraphs::All(_) => true,
+            MockedSubgraphs::Only(names) => names.iter().any(|name| name == subgraph_name),
+        }
+    }
+}
+
+struct MockSubgraphs {
+    subgraphs: MockedSubgraphs,
+    mocker: Arc<Mocker>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for MockSubgraphs {
+    type Config = MockSubgraphsConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let overrides: HashMap<String, Value> = match &init.config.overrides {
+            Some(path) => {
+                let content = tokio::fs::read_to_string(path).await.map_err(|e| {
+                    format!("cannot read mock overrides file {}: {e}", path.display())
+                })?;
+                // JSON is a subset of YAML, so this handles both formats
+                serde_yaml::from_str(&content).map_err(|e| {
+                    format!("cannot parse mock overrides file {}: {e}", path.display())
+                })?
+            }
+            None => HashMap::new(),
+        };
+
+        tracing::warn!(
+            "subgraph mocking is enabled: the router will not send requests to the mocked subgraphs"
+        );
+
+        Ok(MockSubgraphs {
+            subgraphs: init.config.subgraphs,
+            mocker: Arc::new(Mocker::new(
+                init.supergraph_schema,
+                init.config.seed,
+                init.config.list_length,
+                overrides,
+            )),
+        })
+    }
+
+    fn subgraph_service(
+        &self,
+        subgraph_name: &str,
+        service: subgraph::BoxService,
+    ) -> subgraph::BoxService {
+        if !self.subgraphs.contains(subgraph_name) {
+            return service;
+        }
+
+        let mocker = self.mocker.clone();
+        let subgraph_name = subgraph_name.to_string();
+        tower::service_fn(move |request: subgraph::Request| {
+            let response = mocker.mock_response(
+                &subgraph_name,
+                request.subgraph_request.body(),
+                request.operation_kind,
+            );
+            let response = subgraph::Response::new_from_response(
+                http::Response::new(response),
+                request.context,
+                subgraph_name.clone(),
+            );
+            async move { Ok(response) }
+        })
+        .boxed()
+    }
+}
+
+register_plugin!("apollo", "experimental_mock_subgraphs", MockSubgraphs);
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json_bytes::json;
+use tower::ServiceExt;
+
+use super::mocker::Mocker;
+use crate::graphql;
+use crate::query_planner::fetch::OperationKind;
+use crate::services::supergraph;
+use crate::TestHarness;
+
+const SCHEMA: &str = r#"
+directive @mock(value: String) on FIELD_DEFINITION
+
+type Query {
+  products(first: Int): [Product]
+  search: [Result]
+}
+
+type Product {
+  upc: String!
+  name: String @mock(value: "Table")
+  price: Int
+  status: Status
+  reviews: [Review]
+}
+
+type Review {
+  body: String
+}
+
+type Author {
+  name: String
+}
+
+union Result = Product | Author
+
+enum Status {
+  AVAILABLE
+  SOLD_OUT
+}
+"#;
+
+fn mocker(overrides: HashMap<String, crate::json_ext::Value>) -> Mocker {
+    let schema = apollo_compiler::Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+    Mocker::new(Arc::new(schema), 0, 3, overrides)
+}
+
+fn subgraph_request(query: &str, variables: serde_json_bytes::Value) -> graphql::Request {
+    graphql::Request::builder()
+        .query(query)
+        .variables(variables.as_object().unwrap().clone())
+        .build()
+}
+
+#[test]
+fn generates_type_correct_values() {
+    let mocker = mocker(HashMap::new());
+    let request = subgraph_request(
+        "{ products { __typename upc name price status reviews { body } } }",
+        json!({}),
+    );
+    let response = mocker.mock_response("products", &request, OperationKind::Query);
+
+    assert!(response.errors.is_empty());
+    let products = response.data.as_ref().unwrap()["products"]
+        .as_array()
+        .unwrap();
+    assert_eq!(products.len(), 3);
+    for product in products {
+        assert_eq!(product["__typename"], json!("Product"));
+        assert!(product["upc"].is_string());
+        assert_eq!(product["name"], json!("Table"));
+        assert!(product["price"].is_i64());
+        assert!(["AVAILABLE", "SOLD_OUT"].contains(&product["status"].as_str().unwrap()));
+        assert_eq!(product["reviews"].as_array().unwrap().len(), 3);
+    }
+
+    // the same request always gets the same response
+    let again = mocker.mock_response("products", &request, OperationKind::Query);
+    assert_eq!(response.data, again.data);
+}
+
+#[test]
+fn mocks_entities_and_abstract_types() {
+    let mocker = mocker(
+        [("Product.price".to_string(), json!(42))]
+            .into_iter()
+            .collect(),
+    );
+    let request = subgraph_request(
+        "query($representations:[_Any!]!){_entities(representations:$representations){...on Product{upc price}}}",
+        json!({
+            "representations": [
+                { "__typename": "Product", "upc": "1" },
+                { "__typename": "Product", "upc": "2" },
+            ]
+        }),
+    );
+    let response = mocker.mock_response("products", &request, OperationKind::Query);
+    assert_eq!(
+        response.data.unwrap(),
+        json!({
+            "_entities": [
+                { "upc": "1", "price": 42 },
+                { "upc": "2", "price": 42 },
+            ]
+        })
+    );
+
+    let request = subgraph_request(
+        "{ search { __typename ... on Product { upc } ... on Author { name } } }",
+        json!({}),
+    );
+    let response = mocker.mock_response("products", &request, OperationKind::Query);
+    for result in response.data.unwrap()["search"].as_array().unwrap() {
+        match result["__typename"].as_str().unwrap() {
+            "Product" => assert!(result["upc"].is_string()),
+            "Author" => assert!(result["name"].is_string()),
+            other => panic!("unexpected type {other}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn replaces_subgraphs() {
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({
+            "experimental_mock_subgraphs": {
+                "subgraphs": ["orga", "user"],
+                "list_length": 1
+            }
+        }))
+        .unwrap()
+        .schema(include_str!("../../testdata/orga_supergraph.graphql"))
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query("query { orga(id: 1) { id name creatorUser { id name } suborga { id } } }")
+        .build()
+        .unwrap();
+    let response = service
+        .oneshot(request)
+        .await
+        .unwrap()
+        .next_response()
+        .await
+        .unwrap();
+
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    let orga = &response.data.as_ref().unwrap()["orga"];
+    assert!(orga["name"].is_string());
+    assert!(orga["creatorUser"]["id"].is_string());
+    // fetched from the mocked user subgraph
+    assert!(orga["creatorUser"]["name"].is_string());
+    assert_eq!(orga["suborga"].as_array().unwrap().len(), 1);
+}
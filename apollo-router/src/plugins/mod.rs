@@ -26,12 +26,13 @@ pub(crate) mod cache;
 mod coprocessor;
 pub(crate) mod csrf;
 mod demand_control;
-mod expose_query_plan;
+pub(crate) mod expose_query_plan;
 pub(crate) mod file_uploads;
 mod forbid_mutations;
 mod headers;
 mod include_subgraph_errors;
 pub(crate) mod limits;
+mod mock_subgraphs;
 pub(crate) mod override_url;
 pub(crate) mod progressive_override;
 mod record_replay;
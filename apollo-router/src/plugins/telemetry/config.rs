@@ -24,6 +24,7 @@ use super::*;
 use crate::plugin::serde::deserialize_option_header_name;
 use crate::plugins::telemetry::metrics;
 use crate::plugins::telemetry::resource::ConfigResource;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSampling;
 use crate::Configuration;
 
 #[derive(thiserror::Error, Debug)]
@@ -112,6 +113,8 @@ pub(crate) struct Metrics {
     pub(crate) otlp: otlp::Config,
     /// Prometheus exporter configuration
     pub(crate) prometheus: metrics::prometheus::Config,
+    /// StatsD exporter configuration
+    pub(crate) statsd: metrics::statsd::Config,
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
@@ -361,6 +364,8 @@ pub(crate) struct TracingCommon {
     pub(crate) max_attributes_per_link: u32,
     /// The Open Telemetry resource
     pub(crate) resource: BTreeMap<String, AttributeValue>,
+    /// Tail based sampling, decided once the router request is complete
+    pub(crate) tail_sampling: TailSampling,
 }
 
 impl ConfigResource for TracingCommon {
@@ -408,6 +413,7 @@ impl Default for TracingCommon {
             max_attributes_per_event: default_max_attributes_per_event(),
             max_attributes_per_link: default_max_attributes_per_link(),
             resource: Default::default(),
+            tail_sampling: Default::default(),
         }
     }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 
@@ -19,11 +20,17 @@ use crate::plugins::telemetry::config_new::attributes::RouterAttributes;
 use crate::plugins::telemetry::config_new::attributes::SubgraphAttributes;
 use crate::plugins::telemetry::config_new::attributes::SupergraphAttributes;
 use crate::plugins::telemetry::config_new::conditions::Condition;
+use crate::plugins::telemetry::config_new::execution::attributes::ExecutionAttributes;
+use crate::plugins::telemetry::config_new::execution::selectors::ExecutionSelector;
 use crate::plugins::telemetry::config_new::extendable::Extendable;
+use crate::plugins::telemetry::config_new::query_planner::attributes::QueryPlannerAttributes;
+use crate::plugins::telemetry::config_new::query_planner::selectors::QueryPlannerSelector;
 use crate::plugins::telemetry::config_new::selectors::RouterSelector;
 use crate::plugins::telemetry::config_new::selectors::SubgraphSelector;
 use crate::plugins::telemetry::config_new::selectors::SupergraphSelector;
 use crate::plugins::telemetry::dynamic_attribute::EventDynAttribute;
+use crate::services::execution;
+use crate::services::query_planner;
 use crate::services::router;
 use crate::services::subgraph;
 use crate::services::supergraph;
@@ -37,6 +44,11 @@ pub(crate) struct Events {
     router: Extendable<RouterEventsConfig, Event<RouterAttributes, RouterSelector>>,
     /// Subgraph service events
     supergraph: Extendable<SupergraphEventsConfig, Event<SupergraphAttributes, SupergraphSelector>>,
+    /// Query planner service events
+    query_planner:
+        Extendable<QueryPlannerEventsConfig, Event<QueryPlannerAttributes, QueryPlannerSelector>>,
+    /// Execution service events
+    execution: Extendable<ExecutionEventsConfig, Event<ExecutionAttributes, ExecutionSelector>>,
     /// Supergraph service events
     subgraph: Extendable<SubgraphEventsConfig, Event<SubgraphAttributes, SubgraphSelector>>,
 }
@@ -100,6 +112,24 @@ impl Events {
         }
     }
 
+    pub(crate) fn new_query_planner_events(&self) -> QueryPlannerEvents {
+        QueryPlannerEvents {
+            request: StandardEvent::Level(EventLevel::Off),
+            response: StandardEvent::Level(EventLevel::Off),
+            error: self.query_planner.attributes.error.clone().into(),
+            custom: new_custom_events(&self.query_planner.custom),
+        }
+    }
+
+    pub(crate) fn new_execution_events(&self) -> ExecutionEvents {
+        ExecutionEvents {
+            request: StandardEvent::Level(EventLevel::Off),
+            response: StandardEvent::Level(EventLevel::Off),
+            error: self.execution.attributes.error.clone().into(),
+            custom: new_custom_events(&self.execution.custom),
+        }
+    }
+
     pub(crate) fn new_subgraph_events(&self) -> SubgraphEvents {
         let custom_events = self
             .subgraph
@@ -168,6 +198,25 @@ impl Events {
                 format!("configuration error for supergraph custom event {name:?}: {err}")
             })?;
         }
+        if let StandardEventConfig::Conditional { condition, .. } =
+            &self.query_planner.attributes.error
+        {
+            condition.validate(Some(Stage::Error))?;
+        }
+        if let StandardEventConfig::Conditional { condition, .. } = &self.execution.attributes.error
+        {
+            condition.validate(Some(Stage::Error))?;
+        }
+        for (name, custom_event) in &self.query_planner.custom {
+            custom_event.validate().map_err(|err| {
+                format!("configuration error for query planner custom event {name:?}: {err}")
+            })?;
+        }
+        for (name, custom_event) in &self.execution.custom {
+            custom_event.validate().map_err(|err| {
+                format!("configuration error for execution custom event {name:?}: {err}")
+            })?;
+        }
         for (name, custom_event) in &self.subgraph.custom {
             custom_event.validate().map_err(|err| {
                 format!("configuration error for subgraph custom event {name:?}: {err}")
@@ -188,9 +237,45 @@ pub(crate) type SupergraphEvents = CustomEvents<
     SupergraphSelector,
 >;
 
+pub(crate) type QueryPlannerEvents = CustomEvents<
+    query_planner::CachingRequest,
+    query_planner::Response,
+    QueryPlannerAttributes,
+    QueryPlannerSelector,
+>;
+
+pub(crate) type ExecutionEvents =
+    CustomEvents<execution::Request, execution::Response, ExecutionAttributes, ExecutionSelector>;
+
 pub(crate) type SubgraphEvents =
     CustomEvents<subgraph::Request, subgraph::Response, SubgraphAttributes, SubgraphSelector>;
 
+fn new_custom_events<Request, Response, A, T>(
+    config: &HashMap<String, Event<A, T>>,
+) -> Vec<CustomEvent<Request, Response, A, T>>
+where
+    A: Selectors<Request = Request, Response = Response> + Default + Debug,
+    T: Selector<Request = Request, Response = Response> + Debug + Clone,
+{
+    config
+        .iter()
+        .filter_map(|(event_name, event_cfg)| match &event_cfg.level {
+            EventLevel::Off => None,
+            _ => Some(CustomEvent {
+                inner: Mutex::new(CustomEventInner {
+                    name: event_name.clone(),
+                    level: event_cfg.level,
+                    event_on: event_cfg.on,
+                    message: event_cfg.message.clone(),
+                    selectors: event_cfg.attributes.clone().into(),
+                    condition: event_cfg.condition.clone(),
+                    attributes: Vec::new(),
+                }),
+            }),
+        })
+        .collect()
+}
+
 pub(crate) struct CustomEvents<Request, Response, Attributes, Sel>
 where
     Attributes: Selectors<Request = Request, Response = Response> + Default,
@@ -495,6 +580,100 @@ impl Instrumented
     }
 }
 
+impl Instrumented
+    for CustomEvents<
+        query_planner::CachingRequest,
+        query_planner::Response,
+        QueryPlannerAttributes,
+        QueryPlannerSelector,
+    >
+{
+    type Request = query_planner::CachingRequest;
+    type Response = query_planner::Response;
+    type EventResponse = ();
+
+    fn on_request(&self, request: &Self::Request) {
+        for custom_event in &self.custom {
+            custom_event.on_request(request);
+        }
+    }
+
+    fn on_response(&self, response: &Self::Response) {
+        for custom_event in &self.custom {
+            custom_event.on_response(response);
+        }
+    }
+
+    fn on_error(&self, error: &BoxError, ctx: &Context) {
+        if self.error.level() != EventLevel::Off {
+            if let Some(condition) = self.error.condition() {
+                if !condition.lock().evaluate_error(error, ctx) {
+                    return;
+                }
+            }
+            log_event(
+                self.error.level(),
+                "query_planner.error",
+                vec![KeyValue::new(
+                    Key::from_static_str("error"),
+                    opentelemetry::Value::String(error.to_string().into()),
+                )],
+                "",
+            );
+        }
+        for custom_event in &self.custom {
+            custom_event.on_error(error, ctx);
+        }
+    }
+}
+
+impl Instrumented
+    for CustomEvents<
+        execution::Request,
+        execution::Response,
+        ExecutionAttributes,
+        ExecutionSelector,
+    >
+{
+    type Request = execution::Request;
+    type Response = execution::Response;
+    type EventResponse = ();
+
+    fn on_request(&self, request: &Self::Request) {
+        for custom_event in &self.custom {
+            custom_event.on_request(request);
+        }
+    }
+
+    fn on_response(&self, response: &Self::Response) {
+        for custom_event in &self.custom {
+            custom_event.on_response(response);
+        }
+    }
+
+    fn on_error(&self, error: &BoxError, ctx: &Context) {
+        if self.error.level() != EventLevel::Off {
+            if let Some(condition) = self.error.condition() {
+                if !condition.lock().evaluate_error(error, ctx) {
+                    return;
+                }
+            }
+            log_event(
+                self.error.level(),
+                "execution.error",
+                vec![KeyValue::new(
+                    Key::from_static_str("error"),
+                    opentelemetry::Value::String(error.to_string().into()),
+                )],
+                "",
+            );
+        }
+        for custom_event in &self.custom {
+            custom_event.on_error(error, ctx);
+        }
+    }
+}
+
 #[derive(Clone, Deserialize, JsonSchema, Debug, Default)]
 #[serde(deny_unknown_fields, default)]
 struct RouterEventsConfig {
@@ -524,6 +703,20 @@ struct SupergraphEventsConfig {
     error: StandardEventConfig<SupergraphSelector>,
 }
 
+#[derive(Clone, Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+struct QueryPlannerEventsConfig {
+    /// Log the query planner error
+    error: StandardEventConfig<QueryPlannerSelector>,
+}
+
+#[derive(Clone, Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+struct ExecutionEventsConfig {
+    /// Log the execution error
+    error: StandardEventConfig<ExecutionSelector>,
+}
+
 #[derive(Clone, Deserialize, JsonSchema, Debug, Default)]
 #[serde(deny_unknown_fields, default)]
 struct SubgraphEventsConfig {
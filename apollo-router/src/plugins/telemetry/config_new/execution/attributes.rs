@@ -0,0 +1,107 @@
+use opentelemetry_api::KeyValue;
+use opentelemetry_semantic_conventions::trace::GRAPHQL_OPERATION_NAME;
+use opentelemetry_semantic_conventions::trace::GRAPHQL_OPERATION_TYPE;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+
+use crate::context::OPERATION_KIND;
+use crate::context::OPERATION_NAME;
+use crate::plugins::telemetry::config_new::attributes::StandardAttribute;
+use crate::plugins::telemetry::config_new::DefaultAttributeRequirementLevel;
+use crate::plugins::telemetry::config_new::DefaultForLevel;
+use crate::plugins::telemetry::config_new::Selectors;
+use crate::plugins::telemetry::otlp::TelemetryDataKind;
+use crate::services::execution;
+use crate::Context;
+
+#[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct ExecutionAttributes {
+    /// The name of the operation being executed.
+    /// Examples:
+    ///
+    /// * findBookById
+    ///
+    /// Requirement level: Recommended
+    #[serde(rename = "graphql.operation.name")]
+    pub(crate) graphql_operation_name: Option<StandardAttribute>,
+
+    /// The type of the operation being executed.
+    /// Examples:
+    ///
+    /// * query
+    /// * subscription
+    /// * mutation
+    ///
+    /// Requirement level: Recommended
+    #[serde(rename = "graphql.operation.type")]
+    pub(crate) graphql_operation_type: Option<StandardAttribute>,
+}
+
+impl DefaultForLevel for ExecutionAttributes {
+    fn defaults_for_level(
+        &mut self,
+        requirement_level: DefaultAttributeRequirementLevel,
+        _kind: TelemetryDataKind,
+    ) {
+        match requirement_level {
+            DefaultAttributeRequirementLevel::Required => {}
+            DefaultAttributeRequirementLevel::Recommended => {
+                if self.graphql_operation_name.is_none() {
+                    self.graphql_operation_name = Some(StandardAttribute::Bool(true));
+                }
+                if self.graphql_operation_type.is_none() {
+                    self.graphql_operation_type = Some(StandardAttribute::Bool(true));
+                }
+            }
+            DefaultAttributeRequirementLevel::None => {}
+        }
+    }
+}
+
+impl Selectors for ExecutionAttributes {
+    type Request = execution::Request;
+    type Response = execution::Response;
+    type EventResponse = ();
+
+    fn on_request(&self, request: &Self::Request) -> Vec<KeyValue> {
+        let mut attrs = Vec::new();
+        if let Some(key) = self
+            .graphql_operation_name
+            .as_ref()
+            .and_then(|a| a.key(GRAPHQL_OPERATION_NAME))
+        {
+            if let Some(operation_name) = &request
+                .context
+                .get::<_, String>(OPERATION_NAME)
+                .unwrap_or_default()
+            {
+                attrs.push(KeyValue::new(key, operation_name.clone()));
+            }
+        }
+        if let Some(key) = self
+            .graphql_operation_type
+            .as_ref()
+            .and_then(|a| a.key(GRAPHQL_OPERATION_TYPE))
+        {
+            if let Some(operation_type) = &request
+                .context
+                .get::<_, String>(OPERATION_KIND)
+                .unwrap_or_default()
+            {
+                attrs.push(KeyValue::new(key, operation_type.clone()));
+            }
+        }
+
+        attrs
+    }
+
+    fn on_response(&self, _response: &Self::Response) -> Vec<KeyValue> {
+        Vec::default()
+    }
+
+    fn on_error(&self, _error: &BoxError, _ctx: &Context) -> Vec<KeyValue> {
+        Vec::default()
+    }
+}
@@ -0,0 +1,36 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::instruments::CustomInstruments;
+use crate::plugins::telemetry::config_new::attributes::DefaultAttributeRequirementLevel;
+use crate::plugins::telemetry::config_new::execution::attributes::ExecutionAttributes;
+use crate::plugins::telemetry::config_new::execution::selectors::ExecutionSelector;
+use crate::plugins::telemetry::config_new::execution::selectors::ExecutionValue;
+use crate::plugins::telemetry::config_new::DefaultForLevel;
+use crate::plugins::telemetry::otlp::TelemetryDataKind;
+use crate::services::execution;
+
+pub(crate) mod attributes;
+pub(crate) mod selectors;
+
+/// Execution has no standard instruments yet, only custom ones.
+#[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct ExecutionInstrumentsConfig {}
+
+impl DefaultForLevel for ExecutionInstrumentsConfig {
+    fn defaults_for_level(
+        &mut self,
+        _requirement_level: DefaultAttributeRequirementLevel,
+        _kind: TelemetryDataKind,
+    ) {
+    }
+}
+
+pub(crate) type ExecutionCustomInstruments = CustomInstruments<
+    execution::Request,
+    execution::Response,
+    ExecutionAttributes,
+    ExecutionSelector,
+    ExecutionValue,
+>;
@@ -0,0 +1,250 @@
+use opentelemetry_api::Value;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+
+use crate::context::OPERATION_NAME;
+use crate::plugins::telemetry::config::AttributeValue;
+use crate::plugins::telemetry::config_new::instruments::InstrumentValue;
+use crate::plugins::telemetry::config_new::instruments::Standard;
+use crate::plugins::telemetry::config_new::selectors::ErrorRepr;
+use crate::plugins::telemetry::config_new::selectors::OperationName;
+use crate::plugins::telemetry::config_new::Selector;
+use crate::plugins::telemetry::config_new::Stage;
+use crate::plugins::telemetry::config_new::ToOtelValue;
+use crate::services::execution;
+use crate::Context;
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
+pub(crate) enum ExecutionValue {
+    Standard(Standard),
+    Custom(ExecutionSelector),
+}
+
+impl From<&ExecutionValue> for InstrumentValue<ExecutionSelector> {
+    fn from(value: &ExecutionValue) -> Self {
+        match value {
+            ExecutionValue::Standard(s) => InstrumentValue::Standard(s.clone()),
+            ExecutionValue::Custom(selector) => InstrumentValue::Custom(selector.clone()),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
+pub(crate) enum ExecutionSelector {
+    OperationName {
+        /// The operation name from the query.
+        operation_name: OperationName,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<String>,
+    },
+    FetchNodes {
+        /// The number of subgraph fetches in the query plan
+        fetch_nodes: bool,
+    },
+    DeferredChunks {
+        /// The number of deferred chunks in the query plan
+        deferred_chunks: bool,
+    },
+    RequestContext {
+        /// The request context key.
+        request_context: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<AttributeValue>,
+    },
+    ResponseContext {
+        /// The response context key.
+        response_context: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<AttributeValue>,
+    },
+    /// A value from an environment variable.
+    Env {
+        /// The name of the environment variable
+        env: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<String>,
+    },
+    StaticField {
+        /// A static value
+        r#static: AttributeValue,
+    },
+    Error {
+        #[allow(dead_code)]
+        /// Critical error if it happens
+        error: ErrorRepr,
+    },
+}
+
+impl Selector for ExecutionSelector {
+    type Request = execution::Request;
+    type Response = execution::Response;
+    type EventResponse = ();
+
+    fn on_request(&self, request: &Self::Request) -> Option<opentelemetry::Value> {
+        match self {
+            ExecutionSelector::OperationName {
+                operation_name,
+                default,
+                ..
+            } => operation_name.format(request.context.get(OPERATION_NAME).ok().flatten(), default),
+            ExecutionSelector::FetchNodes { fetch_nodes } if *fetch_nodes => Some(
+                opentelemetry::Value::I64(request.query_plan.root.subgraph_fetches() as i64),
+            ),
+            ExecutionSelector::DeferredChunks { deferred_chunks } if *deferred_chunks => Some(
+                opentelemetry::Value::I64(request.query_plan.root.deferred_chunks() as i64),
+            ),
+            ExecutionSelector::RequestContext {
+                request_context,
+                default,
+                ..
+            } => request
+                .context
+                .get::<_, serde_json_bytes::Value>(request_context)
+                .ok()
+                .flatten()
+                .as_ref()
+                .and_then(|v| v.maybe_to_otel_value())
+                .or_else(|| default.maybe_to_otel_value()),
+            ExecutionSelector::Env { env, default, .. } => std::env::var(env)
+                .ok()
+                .or_else(|| default.clone())
+                .map(opentelemetry::Value::from),
+            ExecutionSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            // Related to Response
+            _ => None,
+        }
+    }
+
+    fn on_response(&self, response: &Self::Response) -> Option<opentelemetry::Value> {
+        match self {
+            ExecutionSelector::OperationName {
+                operation_name,
+                default,
+                ..
+            } => {
+                operation_name.format(response.context.get(OPERATION_NAME).ok().flatten(), default)
+            }
+            ExecutionSelector::ResponseContext {
+                response_context,
+                default,
+                ..
+            } => response
+                .context
+                .get_json_value(response_context)
+                .as_ref()
+                .and_then(|v| v.maybe_to_otel_value())
+                .or_else(|| default.maybe_to_otel_value()),
+            ExecutionSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn on_error(&self, error: &BoxError, ctx: &Context) -> Option<opentelemetry::Value> {
+        match self {
+            ExecutionSelector::Error { .. } => Some(error.to_string().into()),
+            ExecutionSelector::OperationName {
+                operation_name,
+                default,
+                ..
+            } => operation_name.format(ctx.get(OPERATION_NAME).ok().flatten(), default),
+            ExecutionSelector::ResponseContext {
+                response_context,
+                default,
+                ..
+            } => ctx
+                .get_json_value(response_context)
+                .as_ref()
+                .and_then(|v| v.maybe_to_otel_value())
+                .or_else(|| default.maybe_to_otel_value()),
+            ExecutionSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn on_drop(&self) -> Option<Value> {
+        match self {
+            ExecutionSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn is_active(&self, stage: Stage) -> bool {
+        match stage {
+            Stage::Request => matches!(
+                self,
+                ExecutionSelector::OperationName { .. }
+                    | ExecutionSelector::FetchNodes { .. }
+                    | ExecutionSelector::DeferredChunks { .. }
+                    | ExecutionSelector::RequestContext { .. }
+                    | ExecutionSelector::Env { .. }
+                    | ExecutionSelector::StaticField { .. }
+            ),
+            Stage::Response => matches!(
+                self,
+                ExecutionSelector::OperationName { .. }
+                    | ExecutionSelector::ResponseContext { .. }
+                    | ExecutionSelector::StaticField { .. }
+            ),
+            Stage::ResponseEvent | Stage::ResponseField => false,
+            Stage::Error => matches!(
+                self,
+                ExecutionSelector::OperationName { .. }
+                    | ExecutionSelector::ResponseContext { .. }
+                    | ExecutionSelector::StaticField { .. }
+                    | ExecutionSelector::Error { .. }
+            ),
+            Stage::Drop => matches!(self, ExecutionSelector::StaticField { .. }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use opentelemetry::Value;
+
+    use super::ExecutionSelector;
+    use crate::plugins::telemetry::config_new::Selector;
+    use crate::query_planner::PlanNode;
+    use crate::query_planner::QueryPlan;
+    use crate::services::execution;
+
+    fn query_plan() -> QueryPlan {
+        let root: PlanNode = serde_json::from_str(include_str!(
+            "../../../../query_planner/testdata/defer_clause_plan.json"
+        ))
+        .unwrap();
+        QueryPlan::fake_builder().root(root).build()
+    }
+
+    #[test]
+    fn execution_fetch_nodes_and_deferred_chunks() {
+        let request = execution::Request::fake_builder()
+            .query_plan(query_plan())
+            .build();
+        let fetch_nodes = ExecutionSelector::FetchNodes { fetch_nodes: true };
+        let deferred_chunks = ExecutionSelector::DeferredChunks {
+            deferred_chunks: true,
+        };
+        assert_eq!(fetch_nodes.on_request(&request), Some(Value::I64(2)));
+        assert_eq!(deferred_chunks.on_request(&request), Some(Value::I64(1)));
+    }
+}
@@ -25,10 +25,20 @@ use super::cache::attributes::CacheAttributes;
 use super::cache::CacheInstruments;
 use super::cache::CacheInstrumentsConfig;
 use super::cache::CACHE_METRIC;
+use super::execution::attributes::ExecutionAttributes;
+use super::execution::selectors::ExecutionSelector;
+use super::execution::selectors::ExecutionValue;
+use super::execution::ExecutionCustomInstruments;
+use super::execution::ExecutionInstrumentsConfig;
 use super::graphql::selectors::ListLength;
 use super::graphql::GraphQLInstruments;
 use super::graphql::FIELD_EXECUTION;
 use super::graphql::FIELD_LENGTH;
+use super::query_planner::attributes::QueryPlannerAttributes;
+use super::query_planner::selectors::QueryPlannerSelector;
+use super::query_planner::selectors::QueryPlannerValue;
+use super::query_planner::QueryPlannerCustomInstruments;
+use super::query_planner::QueryPlannerInstrumentsConfig;
 use super::selectors::CacheKind;
 use super::DefaultForLevel;
 use super::Selector;
@@ -76,6 +86,16 @@ pub(crate) struct InstrumentsConfig {
         SupergraphInstrumentsConfig,
         Instrument<SupergraphAttributes, SupergraphSelector, SupergraphValue>,
     >,
+    /// Query planner service instruments. For more information see documentation on Router lifecycle.
+    pub(crate) query_planner: Extendable<
+        QueryPlannerInstrumentsConfig,
+        Instrument<QueryPlannerAttributes, QueryPlannerSelector, QueryPlannerValue>,
+    >,
+    /// Execution service instruments. For more information see documentation on Router lifecycle.
+    pub(crate) execution: Extendable<
+        ExecutionInstrumentsConfig,
+        Instrument<ExecutionAttributes, ExecutionSelector, ExecutionValue>,
+    >,
     /// Subgraph service instruments. For more information see documentation on Router lifecycle.
     pub(crate) subgraph: Extendable<
         SubgraphInstrumentsConfig,
@@ -114,6 +134,16 @@ impl InstrumentsConfig {
                 format!("error for custom supergraph instrument {name:?} in condition: {err}")
             })?;
         }
+        for (name, custom) in &self.query_planner.custom {
+            custom.condition.validate(None).map_err(|err| {
+                format!("error for custom query planner instrument {name:?} in condition: {err}")
+            })?;
+        }
+        for (name, custom) in &self.execution.custom {
+            custom.condition.validate(None).map_err(|err| {
+                format!("error for custom execution instrument {name:?} in condition: {err}")
+            })?;
+        }
         for (name, custom) in &self.subgraph.custom {
             custom.condition.validate(None).map_err(|err| {
                 format!("error for custom subgraph instrument {name:?} in condition: {err}")
@@ -140,6 +170,10 @@ impl InstrumentsConfig {
             .defaults_for_levels(self.default_requirement_level, TelemetryDataKind::Metrics);
         self.supergraph
             .defaults_for_levels(self.default_requirement_level, TelemetryDataKind::Metrics);
+        self.query_planner
+            .defaults_for_levels(self.default_requirement_level, TelemetryDataKind::Metrics);
+        self.execution
+            .defaults_for_levels(self.default_requirement_level, TelemetryDataKind::Metrics);
         self.subgraph
             .defaults_for_levels(self.default_requirement_level, TelemetryDataKind::Metrics);
         self.graphql
@@ -464,6 +498,30 @@ impl InstrumentsConfig {
         }
     }
 
+    pub(crate) fn new_builtin_query_planner_instruments(
+        &self,
+    ) -> HashMap<String, StaticInstrument> {
+        new_custom_static_instruments(&self.query_planner.custom)
+    }
+
+    pub(crate) fn new_query_planner_instruments(
+        &self,
+        static_instruments: Arc<HashMap<String, StaticInstrument>>,
+    ) -> QueryPlannerCustomInstruments {
+        CustomInstruments::new(&self.query_planner.custom, static_instruments)
+    }
+
+    pub(crate) fn new_builtin_execution_instruments(&self) -> HashMap<String, StaticInstrument> {
+        new_custom_static_instruments(&self.execution.custom)
+    }
+
+    pub(crate) fn new_execution_instruments(
+        &self,
+        static_instruments: Arc<HashMap<String, StaticInstrument>>,
+    ) -> ExecutionCustomInstruments {
+        CustomInstruments::new(&self.execution.custom, static_instruments)
+    }
+
     pub(crate) fn new_builtin_subgraph_instruments(&self) -> HashMap<String, StaticInstrument> {
         let meter = metrics::meter_provider().meter(METER_NAME);
         let mut static_instruments = HashMap::with_capacity(self.subgraph.custom.len());
@@ -889,6 +947,49 @@ impl InstrumentsConfig {
     }
 }
 
+/// Creates the static instruments for a stage that only has custom instruments
+fn new_custom_static_instruments<A, E, V>(
+    custom: &HashMap<String, Instrument<A, E, V>>,
+) -> HashMap<String, StaticInstrument>
+where
+    A: Default + Debug,
+    E: Debug,
+    for<'a> &'a V: Into<InstrumentValue<E>>,
+{
+    let meter = metrics::meter_provider().meter(METER_NAME);
+    let mut static_instruments = HashMap::with_capacity(custom.len());
+    for (instrument_name, instrument) in custom {
+        match instrument.ty {
+            InstrumentType::Counter => {
+                static_instruments.insert(
+                    instrument_name.clone(),
+                    StaticInstrument::CounterF64(
+                        meter
+                            .f64_counter(instrument_name.clone())
+                            .with_description(instrument.description.clone())
+                            .with_unit(Unit::new(instrument.unit.clone()))
+                            .init(),
+                    ),
+                );
+            }
+            InstrumentType::Histogram => {
+                static_instruments.insert(
+                    instrument_name.clone(),
+                    StaticInstrument::Histogram(
+                        meter
+                            .f64_histogram(instrument_name.clone())
+                            .with_description(instrument.description.clone())
+                            .with_unit(Unit::new(instrument.unit.clone()))
+                            .init(),
+                    ),
+                );
+            }
+        }
+    }
+
+    static_instruments
+}
+
 #[derive(Debug)]
 pub(crate) enum StaticInstrument {
     CounterF64(Counter<f64>),
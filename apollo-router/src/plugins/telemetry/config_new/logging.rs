@@ -21,6 +21,8 @@ use crate::configuration::ConfigurationError;
 use crate::plugins::telemetry::config::AttributeValue;
 use crate::plugins::telemetry::config::TraceIdFormat;
 use crate::plugins::telemetry::config_new::experimental_when_header::HeaderLoggingCondition;
+use crate::plugins::telemetry::logging::slow_operations;
+use crate::plugins::telemetry::otlp;
 use crate::plugins::telemetry::resource::ConfigResource;
 use crate::services::SupergraphRequest;
 
@@ -32,6 +34,10 @@ pub(crate) struct Logging {
     pub(crate) common: LoggingCommon,
     /// Settings for logging to stdout.
     pub(crate) stdout: StdOut,
+    /// Settings for exporting logs over OTLP.
+    pub(crate) otlp: otlp::Config,
+    /// Log operations slower than a threshold, with their query plan and fetch timings.
+    pub(crate) slow_operations: slow_operations::Config,
     #[serde(skip)]
     /// Settings for logging to a file.
     pub(crate) file: File,
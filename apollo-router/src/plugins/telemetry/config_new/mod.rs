@@ -22,11 +22,13 @@ pub(crate) mod cache;
 mod conditional;
 pub(crate) mod cost;
 pub(crate) mod events;
+pub(crate) mod execution;
 mod experimental_when_header;
 pub(crate) mod extendable;
 pub(crate) mod graphql;
 pub(crate) mod instruments;
 pub(crate) mod logging;
+pub(crate) mod query_planner;
 pub(crate) mod selectors;
 pub(crate) mod spans;
 
@@ -0,0 +1,36 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use super::instruments::CustomInstruments;
+use crate::plugins::telemetry::config_new::attributes::DefaultAttributeRequirementLevel;
+use crate::plugins::telemetry::config_new::query_planner::attributes::QueryPlannerAttributes;
+use crate::plugins::telemetry::config_new::query_planner::selectors::QueryPlannerSelector;
+use crate::plugins::telemetry::config_new::query_planner::selectors::QueryPlannerValue;
+use crate::plugins::telemetry::config_new::DefaultForLevel;
+use crate::plugins::telemetry::otlp::TelemetryDataKind;
+use crate::services::query_planner;
+
+pub(crate) mod attributes;
+pub(crate) mod selectors;
+
+/// Query planning has no standard instruments yet, only custom ones.
+#[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct QueryPlannerInstrumentsConfig {}
+
+impl DefaultForLevel for QueryPlannerInstrumentsConfig {
+    fn defaults_for_level(
+        &mut self,
+        _requirement_level: DefaultAttributeRequirementLevel,
+        _kind: TelemetryDataKind,
+    ) {
+    }
+}
+
+pub(crate) type QueryPlannerCustomInstruments = CustomInstruments<
+    query_planner::CachingRequest,
+    query_planner::Response,
+    QueryPlannerAttributes,
+    QueryPlannerSelector,
+    QueryPlannerValue,
+>;
@@ -0,0 +1,343 @@
+use opentelemetry_api::Value;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+
+use crate::context::OPERATION_NAME;
+use crate::plugins::telemetry::config::AttributeValue;
+use crate::plugins::telemetry::config_new::instruments::InstrumentValue;
+use crate::plugins::telemetry::config_new::instruments::Standard;
+use crate::plugins::telemetry::config_new::selectors::ErrorRepr;
+use crate::plugins::telemetry::config_new::selectors::OperationName;
+use crate::plugins::telemetry::config_new::Selector;
+use crate::plugins::telemetry::config_new::Stage;
+use crate::plugins::telemetry::config_new::ToOtelValue;
+use crate::query_planner::QueryPlanningInfo;
+use crate::services::query_planner;
+use crate::services::QueryPlannerContent;
+use crate::Context;
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
+pub(crate) enum QueryPlannerValue {
+    Standard(Standard),
+    Custom(QueryPlannerSelector),
+}
+
+impl From<&QueryPlannerValue> for InstrumentValue<QueryPlannerSelector> {
+    fn from(value: &QueryPlannerValue) -> Self {
+        match value {
+            QueryPlannerValue::Standard(s) => InstrumentValue::Standard(s.clone()),
+            QueryPlannerValue::Custom(selector) => InstrumentValue::Custom(selector.clone()),
+        }
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug)]
+#[serde(deny_unknown_fields, rename_all = "snake_case", untagged)]
+pub(crate) enum QueryPlannerSelector {
+    OperationName {
+        /// The operation name from the query.
+        operation_name: OperationName,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<String>,
+    },
+    CacheHit {
+        /// Boolean set to true if the query plan was found in the query plan cache
+        cache_hit: bool,
+    },
+    PlannerMode {
+        /// The query planner mode (new|legacy|both|both_best_effort)
+        planner_mode: bool,
+    },
+    EvaluatedPlans {
+        /// The number of plans evaluated by the query planner before choosing the best one
+        evaluated_plans: bool,
+    },
+    RequestContext {
+        /// The request context key.
+        request_context: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<AttributeValue>,
+    },
+    ResponseContext {
+        /// The response context key.
+        response_context: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<AttributeValue>,
+    },
+    /// A value from an environment variable.
+    Env {
+        /// The name of the environment variable
+        env: String,
+        #[serde(skip)]
+        #[allow(dead_code)]
+        /// Optional redaction pattern.
+        redact: Option<String>,
+        /// Optional default value.
+        default: Option<String>,
+    },
+    StaticField {
+        /// A static value
+        r#static: AttributeValue,
+    },
+    Error {
+        #[allow(dead_code)]
+        /// Critical error if it happens
+        error: ErrorRepr,
+    },
+}
+
+impl QueryPlannerSelector {
+    fn planning_info_value(&self, ctx: &Context) -> Option<opentelemetry::Value> {
+        let info = ctx
+            .extensions()
+            .with_lock(|lock| lock.get::<QueryPlanningInfo>().copied())?;
+        match self {
+            QueryPlannerSelector::CacheHit { cache_hit } if *cache_hit => {
+                Some(opentelemetry::Value::Bool(info.cache_hit))
+            }
+            QueryPlannerSelector::PlannerMode { planner_mode } if *planner_mode => {
+                Some(info.planner_mode.into())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Selector for QueryPlannerSelector {
+    type Request = query_planner::CachingRequest;
+    type Response = query_planner::Response;
+    type EventResponse = ();
+
+    fn on_request(&self, request: &Self::Request) -> Option<opentelemetry::Value> {
+        match self {
+            QueryPlannerSelector::OperationName {
+                operation_name,
+                default,
+                ..
+            } => operation_name.format(request.operation_name.clone(), default),
+            QueryPlannerSelector::RequestContext {
+                request_context,
+                default,
+                ..
+            } => request
+                .context
+                .get::<_, serde_json_bytes::Value>(request_context)
+                .ok()
+                .flatten()
+                .as_ref()
+                .and_then(|v| v.maybe_to_otel_value())
+                .or_else(|| default.maybe_to_otel_value()),
+            QueryPlannerSelector::Env { env, default, .. } => std::env::var(env)
+                .ok()
+                .or_else(|| default.clone())
+                .map(opentelemetry::Value::from),
+            QueryPlannerSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            // Related to Response
+            _ => None,
+        }
+    }
+
+    fn on_response(&self, response: &Self::Response) -> Option<opentelemetry::Value> {
+        match self {
+            QueryPlannerSelector::OperationName {
+                operation_name,
+                default,
+                ..
+            } => {
+                operation_name.format(response.context.get(OPERATION_NAME).ok().flatten(), default)
+            }
+            QueryPlannerSelector::CacheHit { .. } | QueryPlannerSelector::PlannerMode { .. } => {
+                self.planning_info_value(&response.context)
+            }
+            QueryPlannerSelector::EvaluatedPlans { evaluated_plans } if *evaluated_plans => {
+                match &response.content {
+                    Some(QueryPlannerContent::Plan { plan }) => {
+                        Some(opentelemetry::Value::I64(plan.evaluated_plan_count as i64))
+                    }
+                    _ => None,
+                }
+            }
+            QueryPlannerSelector::ResponseContext {
+                response_context,
+                default,
+                ..
+            } => response
+                .context
+                .get_json_value(response_context)
+                .as_ref()
+                .and_then(|v| v.maybe_to_otel_value())
+                .or_else(|| default.maybe_to_otel_value()),
+            QueryPlannerSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn on_error(&self, error: &BoxError, ctx: &Context) -> Option<opentelemetry::Value> {
+        match self {
+            QueryPlannerSelector::Error { .. } => Some(error.to_string().into()),
+            QueryPlannerSelector::OperationName {
+                operation_name,
+                default,
+                ..
+            } => operation_name.format(ctx.get(OPERATION_NAME).ok().flatten(), default),
+            QueryPlannerSelector::CacheHit { .. } | QueryPlannerSelector::PlannerMode { .. } => {
+                self.planning_info_value(ctx)
+            }
+            QueryPlannerSelector::ResponseContext {
+                response_context,
+                default,
+                ..
+            } => ctx
+                .get_json_value(response_context)
+                .as_ref()
+                .and_then(|v| v.maybe_to_otel_value())
+                .or_else(|| default.maybe_to_otel_value()),
+            QueryPlannerSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn on_drop(&self) -> Option<Value> {
+        match self {
+            QueryPlannerSelector::StaticField { r#static } => Some(r#static.clone().into()),
+            _ => None,
+        }
+    }
+
+    fn is_active(&self, stage: Stage) -> bool {
+        match stage {
+            Stage::Request => matches!(
+                self,
+                QueryPlannerSelector::OperationName { .. }
+                    | QueryPlannerSelector::RequestContext { .. }
+                    | QueryPlannerSelector::Env { .. }
+                    | QueryPlannerSelector::StaticField { .. }
+            ),
+            Stage::Response => matches!(
+                self,
+                QueryPlannerSelector::OperationName { .. }
+                    | QueryPlannerSelector::CacheHit { .. }
+                    | QueryPlannerSelector::PlannerMode { .. }
+                    | QueryPlannerSelector::EvaluatedPlans { .. }
+                    | QueryPlannerSelector::ResponseContext { .. }
+                    | QueryPlannerSelector::StaticField { .. }
+            ),
+            Stage::ResponseEvent | Stage::ResponseField => false,
+            Stage::Error => matches!(
+                self,
+                QueryPlannerSelector::OperationName { .. }
+                    | QueryPlannerSelector::CacheHit { .. }
+                    | QueryPlannerSelector::PlannerMode { .. }
+                    | QueryPlannerSelector::ResponseContext { .. }
+                    | QueryPlannerSelector::StaticField { .. }
+                    | QueryPlannerSelector::Error { .. }
+            ),
+            Stage::Drop => matches!(self, QueryPlannerSelector::StaticField { .. }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use opentelemetry::Value;
+
+    use super::QueryPlannerSelector;
+    use crate::plugins::telemetry::config_new::selectors::OperationName;
+    use crate::plugins::telemetry::config_new::Selector;
+    use crate::query_planner::QueryPlan;
+    use crate::query_planner::QueryPlanningInfo;
+    use crate::services::query_planner;
+    use crate::services::QueryPlannerContent;
+    use crate::Context;
+
+    fn response(context: Context, evaluated_plan_count: u64) -> query_planner::Response {
+        let mut plan = QueryPlan::fake_builder().build();
+        plan.evaluated_plan_count = evaluated_plan_count;
+        query_planner::Response::builder()
+            .content(QueryPlannerContent::Plan {
+                plan: Arc::new(plan),
+            })
+            .context(context)
+            .build()
+    }
+
+    #[test]
+    fn query_planner_operation_name() {
+        let selector = QueryPlannerSelector::OperationName {
+            operation_name: OperationName::String,
+            redact: None,
+            default: Some("defaulted".to_string()),
+        };
+        let request = query_planner::CachingRequest::builder()
+            .query("query topProducts { name }")
+            .operation_name("topProducts")
+            .context(Context::new())
+            .build();
+        assert_eq!(
+            selector.on_request(&request),
+            Some(Value::from("topProducts"))
+        );
+        let request = query_planner::CachingRequest::builder()
+            .query("{ name }")
+            .context(Context::new())
+            .build();
+        assert_eq!(
+            selector.on_request(&request),
+            Some(Value::from("defaulted"))
+        );
+    }
+
+    #[test]
+    fn query_planner_cache_hit_and_mode() {
+        let context = Context::new();
+        let cache_hit = QueryPlannerSelector::CacheHit { cache_hit: true };
+        let planner_mode = QueryPlannerSelector::PlannerMode { planner_mode: true };
+        assert_eq!(cache_hit.on_response(&response(context.clone(), 0)), None);
+
+        context.extensions().with_lock(|mut lock| {
+            lock.insert(QueryPlanningInfo {
+                cache_hit: true,
+                planner_mode: "legacy",
+            })
+        });
+        assert_eq!(
+            cache_hit.on_response(&response(context.clone(), 0)),
+            Some(Value::Bool(true))
+        );
+        assert_eq!(
+            planner_mode.on_response(&response(context.clone(), 0)),
+            Some(Value::from("legacy"))
+        );
+        assert_eq!(
+            cache_hit.on_error(&"planning failed".into(), &context),
+            Some(Value::Bool(true))
+        );
+    }
+
+    #[test]
+    fn query_planner_evaluated_plans() {
+        let selector = QueryPlannerSelector::EvaluatedPlans {
+            evaluated_plans: true,
+        };
+        assert_eq!(
+            selector.on_response(&response(Context::new(), 12)),
+            Some(Value::I64(12))
+        );
+    }
+}
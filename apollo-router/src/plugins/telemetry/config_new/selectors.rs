@@ -41,6 +41,26 @@ pub(crate) enum OperationName {
     Hash,
 }
 
+impl OperationName {
+    /// Formats the operation name, falling back to the default value if it is absent
+    pub(crate) fn format(
+        &self,
+        op_name: Option<String>,
+        default: &Option<String>,
+    ) -> Option<opentelemetry::Value> {
+        match self {
+            OperationName::String => op_name.or_else(|| default.clone()),
+            OperationName::Hash => op_name.or_else(|| default.clone()).map(|op_name| {
+                let mut hasher = sha2::Sha256::new();
+                hasher.update(op_name.as_bytes());
+                let result = hasher.finalize();
+                hex::encode(result)
+            }),
+        }
+        .map(opentelemetry::Value::from)
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize, JsonSchema, Clone, Debug, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "snake_case")]
@@ -6,7 +6,11 @@ use crate::plugins::telemetry::config_new::attributes::DefaultAttributeRequireme
 use crate::plugins::telemetry::config_new::attributes::RouterAttributes;
 use crate::plugins::telemetry::config_new::attributes::SubgraphAttributes;
 use crate::plugins::telemetry::config_new::attributes::SupergraphAttributes;
+use crate::plugins::telemetry::config_new::execution::attributes::ExecutionAttributes;
+use crate::plugins::telemetry::config_new::execution::selectors::ExecutionSelector;
 use crate::plugins::telemetry::config_new::extendable::Extendable;
+use crate::plugins::telemetry::config_new::query_planner::attributes::QueryPlannerAttributes;
+use crate::plugins::telemetry::config_new::query_planner::selectors::QueryPlannerSelector;
 use crate::plugins::telemetry::config_new::selectors::RouterSelector;
 use crate::plugins::telemetry::config_new::selectors::SubgraphSelector;
 use crate::plugins::telemetry::config_new::selectors::SupergraphSelector;
@@ -32,6 +36,14 @@ pub(crate) struct Spans {
     /// Supergraph spans contain information about the graphql request and response and therefore contain graphql specific attributes.
     pub(crate) supergraph: SupergraphSpans,
 
+    /// Attributes to include on the query planning span.
+    /// Query planning spans contain information about the query plan cache and the query planner.
+    pub(crate) query_planner: QueryPlannerSpans,
+
+    /// Attributes to include on the execution span.
+    /// Execution spans contain information about the query plan being executed.
+    pub(crate) execution: ExecutionSpans,
+
     /// Attributes to include on the subgraph span.
     /// Subgraph spans contain information about the subgraph request and response and therefore contain subgraph specific attributes.
     pub(crate) subgraph: SubgraphSpans,
@@ -48,6 +60,14 @@ impl Spans {
             self.default_attribute_requirement_level,
             TelemetryDataKind::Traces,
         );
+        self.query_planner.defaults_for_levels(
+            self.default_attribute_requirement_level,
+            TelemetryDataKind::Traces,
+        );
+        self.execution.defaults_for_levels(
+            self.default_attribute_requirement_level,
+            TelemetryDataKind::Traces,
+        );
         self.subgraph.defaults_for_levels(
             self.default_attribute_requirement_level,
             TelemetryDataKind::Traces,
@@ -65,6 +85,16 @@ impl Spans {
                 .validate()
                 .map_err(|err| format!("error for supergraph span attribute {name:?}: {err}"))?;
         }
+        for (name, custom) in &self.query_planner.attributes.custom {
+            custom
+                .validate()
+                .map_err(|err| format!("error for query planner span attribute {name:?}: {err}"))?;
+        }
+        for (name, custom) in &self.execution.attributes.custom {
+            custom
+                .validate()
+                .map_err(|err| format!("error for execution span attribute {name:?}: {err}"))?;
+        }
         for (name, custom) in &self.subgraph.attributes.custom {
             custom
                 .validate()
@@ -108,6 +138,40 @@ impl DefaultForLevel for SupergraphSpans {
     }
 }
 
+#[derive(Deserialize, JsonSchema, Clone, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct QueryPlannerSpans {
+    /// Custom attributes that are attached to the query planning span.
+    pub(crate) attributes: Extendable<QueryPlannerAttributes, Conditional<QueryPlannerSelector>>,
+}
+
+impl DefaultForLevel for QueryPlannerSpans {
+    fn defaults_for_level(
+        &mut self,
+        requirement_level: DefaultAttributeRequirementLevel,
+        kind: TelemetryDataKind,
+    ) {
+        self.attributes.defaults_for_level(requirement_level, kind);
+    }
+}
+
+#[derive(Deserialize, JsonSchema, Clone, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct ExecutionSpans {
+    /// Custom attributes that are attached to the execution span.
+    pub(crate) attributes: Extendable<ExecutionAttributes, Conditional<ExecutionSelector>>,
+}
+
+impl DefaultForLevel for ExecutionSpans {
+    fn defaults_for_level(
+        &mut self,
+        requirement_level: DefaultAttributeRequirementLevel,
+        kind: TelemetryDataKind,
+    ) {
+        self.attributes.defaults_for_level(requirement_level, kind);
+    }
+}
+
 #[derive(Deserialize, JsonSchema, Clone, Default, Debug)]
 #[serde(deny_unknown_fields, default)]
 pub(crate) struct SubgraphSpans {
@@ -220,6 +220,10 @@ impl EventAttributes {
         self.attributes.extend(other);
     }
 
+    pub(crate) fn attributes(&self) -> &Vec<KeyValue> {
+        &self.attributes
+    }
+
     pub(crate) fn take(&mut self) -> Vec<KeyValue> {
         std::mem::take(&mut self.attributes)
     }
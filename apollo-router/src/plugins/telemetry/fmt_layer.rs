@@ -4,6 +4,7 @@ use std::collections::HashSet;
 use std::io::IsTerminal;
 use std::marker::PhantomData;
 
+use opentelemetry::sdk::logs::LoggerProvider;
 use opentelemetry::Key;
 use opentelemetry::KeyValue;
 use tracing::field;
@@ -22,16 +23,20 @@ use super::formatters::EXCLUDED_ATTRIBUTES;
 use super::reload::IsSampled;
 use crate::plugins::telemetry::config;
 use crate::plugins::telemetry::config_new::logging::Format;
+use crate::plugins::telemetry::config_new::logging::RateLimit;
 use crate::plugins::telemetry::config_new::logging::StdOut;
 use crate::plugins::telemetry::formatters::filter_metric_events;
 use crate::plugins::telemetry::formatters::json::Json;
 use crate::plugins::telemetry::formatters::text::Text;
 use crate::plugins::telemetry::formatters::FilteringFormatter;
+use crate::plugins::telemetry::logging::otlp::Discard;
+use crate::plugins::telemetry::logging::otlp::OtlpLogs;
 use crate::plugins::telemetry::reload::LayeredTracer;
 use crate::plugins::telemetry::resource::ConfigResource;
 
 pub(crate) fn create_fmt_layer(
     config: &config::Conf,
+    logger_provider: Option<LoggerProvider>,
 ) -> Box<dyn Layer<LayeredTracer> + Send + Sync> {
     match &config.exporters.logging.stdout {
         StdOut {
@@ -53,11 +58,7 @@ pub(crate) fn create_fmt_layer(
                         config.exporters.logging.common.to_resource(),
                         format_config.clone(),
                     );
-                    FmtLayer::new(
-                        FilteringFormatter::new(format, filter_metric_events, rate_limit),
-                        std::io::stdout,
-                    )
-                    .boxed()
+                    stdout_layer(format, rate_limit, logger_provider)
                 }
 
                 Format::Text(format_config) => {
@@ -65,15 +66,49 @@ pub(crate) fn create_fmt_layer(
                         config.exporters.logging.common.to_resource(),
                         format_config.clone(),
                     );
-                    FmtLayer::new(
-                        FilteringFormatter::new(format, filter_metric_events, rate_limit),
-                        std::io::stdout,
-                    )
-                    .boxed()
+                    stdout_layer(format, rate_limit, logger_provider)
                 }
             }
         }
-        _ => NoOpLayer.boxed(),
+        // Logs are only exported over OTLP, the stdout rate limiting still applies
+        StdOut { rate_limit, .. } => match logger_provider {
+            Some(logger_provider) => FmtLayer::new(
+                FilteringFormatter::new(
+                    OtlpLogs::new(Discard, logger_provider),
+                    filter_metric_events,
+                    rate_limit,
+                ),
+                std::io::sink,
+            )
+            .boxed(),
+            None => NoOpLayer.boxed(),
+        },
+    }
+}
+
+fn stdout_layer<T>(
+    format: T,
+    rate_limit: &RateLimit,
+    logger_provider: Option<LoggerProvider>,
+) -> Box<dyn Layer<LayeredTracer> + Send + Sync>
+where
+    T: EventFormatter<LayeredTracer> + Send + Sync + 'static,
+{
+    match logger_provider {
+        Some(logger_provider) => FmtLayer::new(
+            FilteringFormatter::new(
+                OtlpLogs::new(format, logger_provider),
+                filter_metric_events,
+                rate_limit,
+            ),
+            std::io::stdout,
+        )
+        .boxed(),
+        None => FmtLayer::new(
+            FilteringFormatter::new(format, filter_metric_events, rate_limit),
+            std::io::stdout,
+        )
+        .boxed(),
     }
 }
 
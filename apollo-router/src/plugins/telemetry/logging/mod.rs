@@ -1,4 +1,7 @@
 //TODO move telemetry logging functionality to this file
+pub(crate) mod otlp;
+pub(crate) mod slow_operations;
+
 #[cfg(test)]
 mod test {
     use tracing_futures::WithSubscriber;
@@ -0,0 +1,445 @@
+//! Export logs over OTLP
+//!
+//! Log records are emitted from the same formatting pipeline as the stdout logs, so they go
+//! through the same filtering and rate limiting, and carry the same span and event attributes.
+
+use std::fmt;
+use std::time::SystemTime;
+
+use opentelemetry::sdk::logs::BatchLogProcessor;
+use opentelemetry::sdk::logs::Logger;
+use opentelemetry::sdk::logs::LoggerProvider;
+use opentelemetry::Key;
+use opentelemetry::OrderMap;
+use opentelemetry_api::logs::AnyValue;
+use opentelemetry_api::logs::LogRecord;
+use opentelemetry_api::logs::Logger as _;
+use opentelemetry_api::logs::LoggerProvider as _;
+use opentelemetry_api::logs::Severity;
+use opentelemetry_api::trace::SpanContext;
+use opentelemetry_api::trace::TraceFlags;
+use opentelemetry_api::trace::TraceState;
+use opentelemetry_otlp::LogExporterBuilder;
+use tower::BoxError;
+use tracing::field::Visit;
+use tracing_core::Event;
+use tracing_core::Field;
+use tracing_core::Level;
+use tracing_core::Subscriber;
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::plugins::telemetry::config_new::logging::LoggingCommon;
+use crate::plugins::telemetry::dynamic_attribute::EventAttributes;
+use crate::plugins::telemetry::dynamic_attribute::LogAttributes;
+use crate::plugins::telemetry::formatters::get_trace_and_span_id;
+use crate::plugins::telemetry::formatters::EventFormatter;
+use crate::plugins::telemetry::formatters::APOLLO_PRIVATE_PREFIX;
+use crate::plugins::telemetry::formatters::EXCLUDED_ATTRIBUTES;
+use crate::plugins::telemetry::otel::OtelData;
+use crate::plugins::telemetry::otlp;
+use crate::plugins::telemetry::otlp::TelemetryDataKind;
+use crate::plugins::telemetry::reload::IsSampled;
+use crate::plugins::telemetry::resource::ConfigResource;
+use crate::plugins::telemetry::Telemetry;
+
+const LOGGER_NAME: &str = "apollo-router";
+
+pub(crate) fn create_logger_provider(
+    config: &otlp::Config,
+    common: &LoggingCommon,
+) -> Result<LoggerProvider, BoxError> {
+    let exporter: LogExporterBuilder = config.exporter(TelemetryDataKind::Logs)?;
+    let batch_processor = &config.batch_processor;
+    let processor = BatchLogProcessor::builder(
+        exporter.build_log_exporter()?,
+        opentelemetry::runtime::Tokio,
+    )
+    .with_scheduled_delay(batch_processor.scheduled_delay)
+    .with_max_queue_size(batch_processor.max_queue_size)
+    .with_max_export_batch_size(batch_processor.max_export_batch_size)
+    .with_max_timeout(batch_processor.max_export_timeout)
+    .build();
+
+    Ok(LoggerProvider::builder()
+        .with_config(
+            opentelemetry::sdk::logs::Config::default().with_resource(common.to_resource()),
+        )
+        .with_log_processor(processor)
+        .build())
+}
+
+/// Emits a log record for each event, then hands it to the inner formatter
+pub(crate) struct OtlpLogs<T> {
+    inner: T,
+    logger: Logger,
+    provider: Option<LoggerProvider>,
+}
+
+impl<T> OtlpLogs<T> {
+    pub(crate) fn new(inner: T, provider: LoggerProvider) -> Self {
+        Self {
+            inner,
+            logger: provider.versioned_logger(
+                LOGGER_NAME,
+                Some(env!("CARGO_PKG_VERSION").into()),
+                None,
+                None,
+            ),
+            provider: Some(provider),
+        }
+    }
+
+    fn emit<S>(&self, ctx: &Context<'_, S>, event: &Event<'_>)
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let meta = event.metadata();
+        let mut attributes: OrderMap<Key, AnyValue> = OrderMap::new();
+        let mut builder = LogRecord::builder()
+            .with_timestamp(SystemTime::now())
+            .with_severity_number(severity(meta.level()))
+            .with_severity_text(meta.level().as_str());
+
+        let current_span = event
+            .parent()
+            .and_then(|id| ctx.span(id))
+            .or_else(|| ctx.lookup_current());
+        if let Some(span) = &current_span {
+            if let Some((trace_id, span_id)) = get_trace_and_span_id(span) {
+                let trace_flags = if span.is_sampled() {
+                    TraceFlags::SAMPLED
+                } else {
+                    TraceFlags::default()
+                };
+                builder = builder.with_span_context(&SpanContext::new(
+                    trace_id,
+                    span_id,
+                    trace_flags,
+                    false,
+                    TraceState::default(),
+                ));
+            }
+
+            // Attributes of the enclosing spans, the innermost span wins
+            for span in span.scope().from_root() {
+                let ext = span.extensions();
+                if let Some(otel_attributes) = ext
+                    .get::<OtelData>()
+                    .and_then(|otel_data| otel_data.builder.attributes.as_ref())
+                {
+                    attributes.extend(
+                        otel_attributes
+                            .iter()
+                            .filter(|(key, _)| is_exported(key))
+                            .map(|(key, value)| (key.clone(), value.clone().into())),
+                    );
+                }
+                if let Some(log_attributes) = ext.get::<LogAttributes>() {
+                    attributes.extend(
+                        log_attributes
+                            .attributes()
+                            .iter()
+                            .filter(|kv| is_exported(&kv.key))
+                            .map(|kv| (kv.key.clone(), kv.value.clone().into())),
+                    );
+                }
+            }
+
+            // Attributes of custom events. They are not consumed here, the inner formatter does it
+            let ext = span.extensions();
+            match ext
+                .get::<OtelData>()
+                .and_then(|otel_data| otel_data.event_attributes.as_ref())
+            {
+                Some(event_attributes) => attributes.extend(
+                    event_attributes
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.clone().into())),
+                ),
+                None => {
+                    if let Some(event_attributes) = ext.get::<EventAttributes>() {
+                        attributes.extend(
+                            event_attributes
+                                .attributes()
+                                .iter()
+                                .map(|kv| (kv.key.clone(), kv.value.clone().into())),
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut visitor = EventVisitor {
+            body: None,
+            attributes,
+        };
+        event.record(&mut visitor);
+        if let Some(body) = visitor.body {
+            builder = builder.with_body(body.into());
+        }
+        builder = builder.with_attributes(visitor.attributes.into_iter().collect());
+
+        self.logger.emit(builder.build());
+    }
+}
+
+impl<T> Drop for OtlpLogs<T> {
+    fn drop(&mut self) {
+        // Shutting down the provider flushes the batch processor, which blocks
+        if let Some(provider) = self.provider.take() {
+            Telemetry::checked_logger_shutdown(provider);
+        }
+    }
+}
+
+impl<S, T> EventFormatter<S> for OtlpLogs<T>
+where
+    T: EventFormatter<S>,
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn format_event<W>(
+        &self,
+        ctx: &Context<'_, S>,
+        writer: &mut W,
+        event: &Event<'_>,
+    ) -> fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        self.emit(ctx, event);
+        self.inner.format_event(ctx, writer, event)
+    }
+}
+
+/// Formatter used when logs are only exported over OTLP. It does not write anything, but
+/// consumes the custom event attributes like the other formatters.
+pub(crate) struct Discard;
+
+impl<S> EventFormatter<S> for Discard
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn format_event<W>(
+        &self,
+        ctx: &Context<'_, S>,
+        _writer: &mut W,
+        event: &Event<'_>,
+    ) -> fmt::Result
+    where
+        W: std::fmt::Write,
+    {
+        if let Some(span) = event
+            .parent()
+            .and_then(|id| ctx.span(id))
+            .or_else(|| ctx.lookup_current())
+        {
+            let mut extensions = span.extensions_mut();
+            if let Some(otel_data) = extensions.get_mut::<OtelData>() {
+                otel_data.event_attributes.take();
+            }
+            if let Some(event_attributes) = extensions.get_mut::<EventAttributes>() {
+                event_attributes.take();
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_exported(key: &Key) -> bool {
+    let key = key.as_str();
+    !key.starts_with(APOLLO_PRIVATE_PREFIX) && !EXCLUDED_ATTRIBUTES.contains(&key)
+}
+
+fn severity(level: &Level) -> Severity {
+    match *level {
+        Level::TRACE => Severity::Trace,
+        Level::DEBUG => Severity::Debug,
+        Level::INFO => Severity::Info,
+        Level::WARN => Severity::Warn,
+        Level::ERROR => Severity::Error,
+    }
+}
+
+/// Uses the message as the body of the record, and the other fields as attributes
+struct EventVisitor {
+    body: Option<String>,
+    attributes: OrderMap<Key, AnyValue>,
+}
+
+impl EventVisitor {
+    fn insert(&mut self, field: &Field, value: AnyValue) {
+        self.attributes.insert(Key::new(field.name()), value);
+    }
+}
+
+impl Visit for EventVisitor {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match i64::try_from(value) {
+            Ok(value) => self.insert(field, value.into()),
+            Err(_) => self.insert(field, value.to_string().into()),
+        }
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.body = Some(value.to_string());
+        } else {
+            self.insert(field, value.to_string().into());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.body = Some(format!("{value:?}"));
+        } else {
+            self.insert(field, format!("{value:?}").into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use opentelemetry::sdk::export::logs::LogData;
+    use opentelemetry::sdk::export::logs::LogExporter;
+    use opentelemetry::KeyValue;
+    use opentelemetry_api::logs::LogResult;
+    use parking_lot::Mutex;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+    use crate::plugins::telemetry::config_new::events::log_event;
+    use crate::plugins::telemetry::config_new::events::EventLevel;
+    use crate::plugins::telemetry::config_new::logging::RateLimit;
+    use crate::plugins::telemetry::dynamic_attribute::DynAttributeLayer;
+    use crate::plugins::telemetry::fmt_layer::FmtLayer;
+    use crate::plugins::telemetry::formatters::filter_metric_events;
+    use crate::plugins::telemetry::formatters::FilteringFormatter;
+
+    #[derive(Debug, Clone, Default)]
+    struct InMemoryExporter(Arc<Mutex<Vec<LogData>>>);
+
+    #[async_trait]
+    impl LogExporter for InMemoryExporter {
+        async fn export(&mut self, batch: Vec<LogData>) -> LogResult<()> {
+            self.0.lock().extend(batch);
+            Ok(())
+        }
+    }
+
+    fn attribute<'a>(record: &'a LogRecord, key: &str) -> Option<&'a AnyValue> {
+        record
+            .attributes
+            .as_ref()?
+            .iter()
+            .find(|(k, _)| k.as_str() == key)
+            .map(|(_, v)| v)
+    }
+
+    fn string(value: Option<&AnyValue>) -> Option<String> {
+        match value {
+            Some(AnyValue::String(value)) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn exports_events_with_attributes() {
+        let exporter = InMemoryExporter::default();
+        let provider = LoggerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(DynAttributeLayer::new())
+            .with(FmtLayer::new(
+                FilteringFormatter::new(
+                    OtlpLogs::new(Discard, provider),
+                    filter_metric_events,
+                    &RateLimit::default(),
+                ),
+                std::io::sink,
+            ));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("router", "http.request.method" = "POST");
+            let _guard = span.enter();
+            log_event(
+                EventLevel::Warn,
+                "my.event",
+                vec![KeyValue::new("custom", "value")],
+                "something happened",
+            );
+            tracing::info!(count = 3u64, "plain log");
+        });
+
+        let logs = exporter.0.lock();
+        assert_eq!(logs.len(), 2);
+
+        let record = &logs[0].record;
+        assert_eq!(record.severity_number, Some(Severity::Warn));
+        assert!(
+            matches!(&record.body, Some(AnyValue::String(body)) if body.as_str() == "something happened")
+        );
+        assert_eq!(
+            string(attribute(record, "http.request.method")).as_deref(),
+            Some("POST")
+        );
+        assert_eq!(
+            string(attribute(record, "custom")).as_deref(),
+            Some("value")
+        );
+        assert_eq!(
+            string(attribute(record, "kind")).as_deref(),
+            Some("my.event")
+        );
+
+        // custom event attributes are only sent with their event
+        let record = &logs[1].record;
+        assert_eq!(record.severity_number, Some(Severity::Info));
+        assert!(attribute(record, "custom").is_none());
+        assert!(matches!(attribute(record, "count"), Some(AnyValue::Int(3))));
+    }
+
+    #[test]
+    fn rate_limited() {
+        let exporter = InMemoryExporter::default();
+        let provider = LoggerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry().with(FmtLayer::new(
+            FilteringFormatter::new(
+                OtlpLogs::new(Discard, provider),
+                filter_metric_events,
+                &RateLimit {
+                    enabled: true,
+                    capacity: 1,
+                    interval: std::time::Duration::from_secs(60),
+                },
+            ),
+            std::io::sink,
+        ));
+
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..3 {
+                tracing::info!("repeated");
+            }
+        });
+
+        assert_eq!(exporter.0.lock().len(), 1);
+    }
+}
@@ -0,0 +1,482 @@
+//! Slow operation log.
+//!
+//! Every request collects its query plan and the timings of its subgraph fetches in the context
+//! extensions. When the router-level latency exceeds the configured threshold, the collected data
+//! is emitted as a single `slow_operation` event.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use opentelemetry::KeyValue;
+use rand::Rng;
+use router_bridge::planner::UsageReporting;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::ByteString;
+use serde_json_bytes::Value;
+
+use crate::context::OPERATION_NAME;
+use crate::graphql;
+use crate::plugins::cache::entity::CacheSubgraph;
+use crate::plugins::telemetry::config_new::events::log_event;
+use crate::plugins::telemetry::config_new::events::EventLevel;
+use crate::query_planner::QueryPlan;
+use crate::services::ExecutionRequest;
+use crate::services::SubgraphRequest;
+use crate::services::SubgraphResponse;
+use crate::Context;
+
+const SLOW_OPERATION_EVENT_KIND: &str = "slow_operation";
+const REDACTED: &str = "[REDACTED]";
+
+/// Slow operation log configuration
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// Set to true to log operations slower than the threshold
+    pub(crate) enabled: bool,
+    /// Router latency above which an operation is logged
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    pub(crate) threshold: Duration,
+    /// Thresholds overriding the global one, by operation name
+    #[schemars(with = "HashMap<String, String>")]
+    #[serde(deserialize_with = "deserialize_operation_thresholds")]
+    pub(crate) operations: HashMap<String, Duration>,
+    /// Ratio of slow operations that are logged, between 0.0 and 1.0
+    pub(crate) sampler: f64,
+    /// Level of the slow operation event
+    pub(crate) level: EventLevel,
+    /// Operation variables in the event
+    pub(crate) variables: VariablesConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: Duration::from_secs(1),
+            operations: HashMap::new(),
+            sampler: 1.0,
+            level: EventLevel::Warn,
+            variables: VariablesConfig::default(),
+        }
+    }
+}
+
+/// Operation variables in the slow operation event
+#[derive(Clone, Debug, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct VariablesConfig {
+    /// Set to true to add the operation variables to the event
+    pub(crate) include: bool,
+    /// Replace the values of all variables with `[REDACTED]`
+    pub(crate) redact_all: bool,
+    /// Names of the variables whose values are replaced with `[REDACTED]`
+    pub(crate) redact: Vec<String>,
+}
+
+fn deserialize_operation_thresholds<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, Duration>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Threshold(#[serde(with = "humantime_serde")] Duration);
+
+    let thresholds = HashMap::<String, Threshold>::deserialize(deserializer)?;
+    Ok(thresholds
+        .into_iter()
+        .map(|(name, Threshold(threshold))| (name, threshold))
+        .collect())
+}
+
+/// Timing of a single subgraph fetch
+#[derive(Clone, Debug, Serialize)]
+struct FetchTiming {
+    subgraph: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    operation_name: Option<String>,
+    duration_ms: f64,
+    response_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache: Option<FetchCacheStatus>,
+}
+
+/// Entity cache hits and misses observed during a fetch
+#[derive(Clone, Debug, PartialEq, Serialize)]
+struct FetchCacheStatus {
+    hit: usize,
+    miss: usize,
+}
+
+/// A subgraph fetch in flight
+pub(crate) struct PendingFetch {
+    operation_name: Option<String>,
+}
+
+/// Data collected in the context extensions while the request runs
+#[derive(Default)]
+struct SlowOperationRecord {
+    query_plan: Option<Arc<QueryPlan>>,
+    variables: Option<serde_json_bytes::Map<ByteString, Value>>,
+    fetches: Vec<FetchTiming>,
+}
+
+impl Config {
+    /// Starts collecting data for the request
+    pub(crate) fn on_router_request(&self, context: &Context) {
+        if self.enabled {
+            context
+                .extensions()
+                .with_lock(|mut lock| lock.insert(SlowOperationRecord::default()));
+        }
+    }
+
+    /// Records the query plan and the variables of the operation
+    pub(crate) fn on_execution_request(&self, request: &ExecutionRequest) {
+        if !self.enabled {
+            return;
+        }
+        let variables = self
+            .variables
+            .include
+            .then(|| self.redact_variables(&request.supergraph_request.body().variables));
+        request.context.extensions().with_lock(|mut lock| {
+            if let Some(record) = lock.get_mut::<SlowOperationRecord>() {
+                record.query_plan = Some(request.query_plan.clone());
+                record.variables = variables;
+            }
+        });
+    }
+
+    /// Captures the subgraph operation before the fetch
+    pub(crate) fn on_subgraph_request(&self, request: &SubgraphRequest) -> Option<PendingFetch> {
+        if !self.enabled {
+            return None;
+        }
+        Some(PendingFetch {
+            operation_name: request.subgraph_request.body().operation_name.clone(),
+        })
+    }
+
+    /// Records the timing of a subgraph fetch
+    pub(crate) fn on_subgraph_response(
+        &self,
+        pending: Option<PendingFetch>,
+        response: &SubgraphResponse,
+        duration: Duration,
+    ) {
+        let Some(pending) = pending else {
+            return;
+        };
+        // the entity cache records the lookup of each fetch in its response
+        let cache = response
+            .response
+            .extensions()
+            .get::<CacheSubgraph>()
+            .map(|cache_hit| {
+                let (hit, miss) = cache_hit.0.values().fold((0, 0), |(hit, miss), counters| {
+                    (hit + counters.hit, miss + counters.miss)
+                });
+                FetchCacheStatus { hit, miss }
+            });
+        let timing = FetchTiming {
+            subgraph: response.subgraph_name.clone().unwrap_or_default(),
+            operation_name: pending.operation_name,
+            duration_ms: duration.as_secs_f64() * 1000.0,
+            response_size: response_size(response.response.body()),
+            cache,
+        };
+        response.context.extensions().with_lock(|mut lock| {
+            if let Some(record) = lock.get_mut::<SlowOperationRecord>() {
+                record.fetches.push(timing);
+            }
+        });
+    }
+
+    /// Emits the slow operation event if the router latency is above the threshold
+    pub(crate) fn on_router_response(&self, context: &Context, duration: Duration) {
+        if !self.enabled {
+            return;
+        }
+        let record = context
+            .extensions()
+            .with_lock(|mut lock| lock.remove::<SlowOperationRecord>());
+        let Some(record) = record else {
+            return;
+        };
+        let operation_name = context.get::<_, String>(OPERATION_NAME).ok().flatten();
+        let threshold = self.threshold_for(operation_name.as_deref());
+        if duration < threshold || !rand::thread_rng().gen_bool(self.sampler.clamp(0.0, 1.0)) {
+            return;
+        }
+
+        let signature = context.extensions().with_lock(|lock| {
+            lock.get::<Arc<UsageReporting>>()
+                .map(|u| u.stats_report_key.clone())
+        });
+        let attributes = attributes(operation_name, signature, duration, threshold, record);
+        log_event(
+            self.level,
+            SLOW_OPERATION_EVENT_KIND,
+            attributes,
+            "slow operation",
+        );
+    }
+
+    fn threshold_for(&self, operation_name: Option<&str>) -> Duration {
+        operation_name
+            .and_then(|name| self.operations.get(name))
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+
+    fn redact_variables(
+        &self,
+        variables: &serde_json_bytes::Map<ByteString, Value>,
+    ) -> serde_json_bytes::Map<ByteString, Value> {
+        variables
+            .iter()
+            .map(|(name, value)| {
+                if self.variables.redact_all
+                    || self.variables.redact.iter().any(|r| r == name.as_str())
+                {
+                    (name.clone(), Value::String(REDACTED.into()))
+                } else {
+                    (name.clone(), value.clone())
+                }
+            })
+            .collect()
+    }
+}
+
+/// Size in bytes of the deserialized subgraph response, serialized back to JSON. The
+/// `Content-Length` header is missing from chunked responses and counts compressed bytes.
+fn response_size(response: &graphql::Response) -> u64 {
+    struct Counter(u64);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len() as u64;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    let _ = serde_json::to_writer(&mut counter, response);
+    counter.0
+}
+
+fn attributes(
+    operation_name: Option<String>,
+    signature: Option<String>,
+    duration: Duration,
+    threshold: Duration,
+    record: SlowOperationRecord,
+) -> Vec<KeyValue> {
+    let mut attributes = Vec::with_capacity(7);
+    if let Some(operation_name) = operation_name {
+        attributes.push(KeyValue::new("graphql.operation.name", operation_name));
+    }
+    if let Some(signature) = signature {
+        attributes.push(KeyValue::new("graphql.operation.signature", signature));
+    }
+    attributes.push(KeyValue::new(
+        "duration_ms",
+        duration.as_secs_f64() * 1000.0,
+    ));
+    attributes.push(KeyValue::new(
+        "threshold_ms",
+        threshold.as_secs_f64() * 1000.0,
+    ));
+    if let Some(variables) = record.variables {
+        attributes.push(KeyValue::new(
+            "graphql.variables",
+            serde_json::to_string(&variables).unwrap_or_default(),
+        ));
+    }
+    if let Some(query_plan) = record.query_plan {
+        let formatted = match &query_plan.formatted_query_plan {
+            Some(formatted) => formatted.to_string(),
+            None => serde_json::to_string(&query_plan.root).unwrap_or_default(),
+        };
+        attributes.push(KeyValue::new("query_plan", formatted));
+    }
+    attributes.push(KeyValue::new(
+        "fetches",
+        serde_json::to_string(&record.fetches).unwrap_or_default(),
+    ));
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use http::header::CONTENT_LENGTH;
+    use http::HeaderValue;
+    use serde_json_bytes::json;
+
+    use super::*;
+    use crate::plugins::cache::entity::CacheHitMiss;
+    use crate::services::subgraph;
+
+    fn config() -> Config {
+        serde_yaml::from_str(
+            r#"
+            enabled: true
+            threshold: 500ms
+            operations:
+              GetProducts: 2s
+            variables:
+              include: true
+              redact: [password]
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn threshold_per_operation() {
+        let config = config();
+        assert_eq!(config.threshold_for(None), Duration::from_millis(500));
+        assert_eq!(
+            config.threshold_for(Some("GetUser")),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            config.threshold_for(Some("GetProducts")),
+            Duration::from_secs(2)
+        );
+    }
+
+    #[test]
+    fn redact_variables() {
+        let mut config = config();
+        let variables = json!({"id": 1, "password": "secret"});
+        let variables = variables.as_object().unwrap();
+        assert_eq!(
+            Value::Object(config.redact_variables(variables)),
+            json!({"id": 1, "password": "[REDACTED]"})
+        );
+
+        config.variables.redact_all = true;
+        assert_eq!(
+            Value::Object(config.redact_variables(variables)),
+            json!({"id": "[REDACTED]", "password": "[REDACTED]"})
+        );
+    }
+
+    #[test]
+    fn fetch_timings() {
+        let config = config();
+        let context = Context::new();
+        config.on_router_request(&context);
+
+        // two parallel fetches to the same subgraph
+        let request = subgraph::Request::fake_builder()
+            .subgraph_name("products")
+            .context(context.clone())
+            .build();
+        let first = config.on_subgraph_request(&request);
+        let second = config.on_subgraph_request(&request);
+
+        let response = |hit, miss, data| {
+            // the size is measured on the body, whatever the Content-Length of the compressed
+            // response is
+            let mut headers = http::HeaderMap::new();
+            headers.insert(CONTENT_LENGTH, HeaderValue::from_static("4"));
+            let mut response = subgraph::Response::fake_builder()
+                .subgraph_name("products")
+                .data(data)
+                .headers(headers)
+                .context(context.clone())
+                .build();
+            response
+                .response
+                .extensions_mut()
+                .insert(CacheSubgraph(HashMap::from([(
+                    "Product".to_string(),
+                    CacheHitMiss { hit, miss },
+                )])));
+            response
+        };
+        config.on_subgraph_response(
+            second,
+            &response(0, 2, json!({"topProducts": []})),
+            Duration::from_millis(30),
+        );
+        config.on_subgraph_response(
+            first,
+            &response(3, 0, json!({"topProducts": [{"upc": "1"}]})),
+            Duration::from_millis(20),
+        );
+
+        let fetches = context.extensions().with_lock(|lock| {
+            lock.get::<SlowOperationRecord>()
+                .map(|record| record.fetches.clone())
+                .unwrap()
+        });
+        assert_eq!(fetches.len(), 2);
+        assert_eq!(fetches[0].subgraph, "products");
+        assert_eq!(fetches[0].duration_ms, 30.0);
+        assert_eq!(
+            fetches[0].response_size,
+            r#"{"data":{"topProducts":[]}}"#.len() as u64
+        );
+        assert_eq!(fetches[0].cache, Some(FetchCacheStatus { hit: 0, miss: 2 }));
+        assert_eq!(fetches[1].duration_ms, 20.0);
+        assert_eq!(
+            fetches[1].response_size,
+            r#"{"data":{"topProducts":[{"upc":"1"}]}}"#.len() as u64
+        );
+        assert_eq!(fetches[1].cache, Some(FetchCacheStatus { hit: 3, miss: 0 }));
+
+        // a fast operation consumes the record without logging
+        config.on_router_response(&context, Duration::from_millis(10));
+        assert!(context
+            .extensions()
+            .with_lock(|lock| lock.get::<SlowOperationRecord>().is_none()));
+    }
+
+    #[test]
+    fn event_attributes() {
+        let record = SlowOperationRecord {
+            query_plan: None,
+            variables: None,
+            fetches: vec![FetchTiming {
+                subgraph: "products".to_string(),
+                operation_name: Some("TopProducts__products__0".to_string()),
+                duration_ms: 12.5,
+                response_size: 42,
+                cache: None,
+            }],
+        };
+        let attributes = attributes(
+            Some("TopProducts".to_string()),
+            Some("# TopProducts\n{topProducts{name}}".to_string()),
+            Duration::from_millis(1500),
+            Duration::from_secs(1),
+            record,
+        );
+        let attributes: HashMap<_, _> = attributes
+            .into_iter()
+            .map(|kv| (kv.key.to_string(), kv.value.to_string()))
+            .collect();
+        assert_eq!(attributes["graphql.operation.name"], "TopProducts");
+        assert_eq!(attributes["duration_ms"], "1500");
+        assert_eq!(attributes["threshold_ms"], "1000");
+        assert_eq!(
+            attributes["fetches"],
+            r#"[{"subgraph":"products","operation_name":"TopProducts__products__0","duration_ms":12.5,"response_size":42}]"#
+        );
+    }
+}
@@ -9,6 +9,7 @@ use multimap::MultiMap;
 use opentelemetry::sdk::metrics::reader::AggregationSelector;
 use opentelemetry::sdk::metrics::Aggregation;
 use opentelemetry::sdk::metrics::InstrumentKind;
+use opentelemetry::sdk::metrics::View;
 use opentelemetry::sdk::Resource;
 use regex::Regex;
 use schemars::JsonSchema;
@@ -35,6 +36,7 @@ pub(crate) mod local_type_stats;
 pub(crate) mod otlp;
 pub(crate) mod prometheus;
 pub(crate) mod span_metrics_exporter;
+pub(crate) mod statsd;
 
 #[derive(Debug, Clone, Deserialize, JsonSchema, Default)]
 #[serde(deny_unknown_fields, default)]
@@ -434,6 +436,8 @@ pub(crate) struct MetricsBuilder {
     pub(crate) custom_endpoints: MultiMap<ListenAddr, Endpoint>,
     pub(crate) apollo_metrics_sender: Sender,
     pub(crate) resource: Resource,
+    /// Views apply to every reader of the public meter provider, so they must only be added once
+    public_views_applied: bool,
 }
 
 impl MetricsBuilder {
@@ -448,8 +452,26 @@ impl MetricsBuilder {
             prometheus_meter_provider: None,
             custom_endpoints: MultiMap::new(),
             apollo_metrics_sender: Sender::default(),
+            public_views_applied: false,
         }
     }
+
+    /// Adds the configured views to the public meter provider, unless another exporter already did
+    pub(crate) fn with_public_views(
+        &mut self,
+        metrics_config: &MetricsCommon,
+    ) -> Result<(), BoxError> {
+        if self.public_views_applied {
+            return Ok(());
+        }
+        for metric_view in metrics_config.views.clone() {
+            let view: Box<dyn View> = metric_view.try_into()?;
+            self.public_meter_provider_builder =
+                std::mem::take(&mut self.public_meter_provider_builder).with_view(view);
+        }
+        self.public_views_applied = true;
+        Ok(())
+    }
 }
 
 pub(crate) trait MetricsConfigurator {
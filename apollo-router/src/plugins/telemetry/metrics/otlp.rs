@@ -1,6 +1,5 @@
 use opentelemetry::runtime;
 use opentelemetry::sdk::metrics::PeriodicReader;
-use opentelemetry::sdk::metrics::View;
 use opentelemetry_otlp::MetricsExporterBuilder;
 use tower::BoxError;
 
@@ -39,11 +38,7 @@ impl MetricsConfigurator for super::super::otlp::Config {
                 .with_timeout(self.batch_processor.max_export_timeout)
                 .build(),
         );
-        for metric_view in metrics_config.views.clone() {
-            let view: Box<dyn View> = metric_view.try_into()?;
-            builder.public_meter_provider_builder =
-                builder.public_meter_provider_builder.with_view(view);
-        }
+        builder.with_public_views(metrics_config)?;
         Ok(builder)
     }
 }
@@ -1,6 +1,9 @@
+use std::fmt::Display;
+use std::fmt::Write;
 use std::sync::Mutex;
 use std::task::Context;
 use std::task::Poll;
+use std::time::UNIX_EPOCH;
 
 use futures::future::BoxFuture;
 use http::StatusCode;
@@ -8,6 +11,8 @@ use once_cell::sync::Lazy;
 use opentelemetry::sdk::metrics::MeterProvider;
 use opentelemetry::sdk::metrics::View;
 use opentelemetry::sdk::Resource;
+use prometheus::proto::MetricFamily;
+use prometheus::proto::MetricType;
 use prometheus::Encoder;
 use prometheus::Registry;
 use prometheus::TextEncoder;
@@ -17,6 +22,9 @@ use tower::BoxError;
 use tower::ServiceExt;
 use tower_service::Service;
 
+use crate::metrics::exemplars::exemplars;
+use crate::metrics::exemplars::ExemplarStore;
+use crate::plugins::telemetry::config::MetricAggregation;
 use crate::plugins::telemetry::config::MetricView;
 use crate::plugins::telemetry::config::MetricsCommon;
 use crate::plugins::telemetry::metrics::CustomAggregationSelector;
@@ -37,6 +45,9 @@ pub(crate) struct Config {
     pub(crate) listen: ListenAddr,
     /// The path where prometheus will be exposed
     pub(crate) path: String,
+    /// Attach the trace id of sampled requests to histogram buckets as exemplars.
+    /// Exemplars are only exposed to scrapers that negotiate the OpenMetrics format.
+    pub(crate) exemplars: bool,
 }
 
 impl Default for Config {
@@ -45,6 +56,7 @@ impl Default for Config {
             enabled: false,
             listen: ListenAddr::SocketAddr("127.0.0.1:9090".parse().expect("valid listenAddr")),
             path: "/metrics".to_string(),
+            exemplars: false,
         }
     }
 }
@@ -74,6 +86,24 @@ pub(crate) fn commit_prometheus() {
     }
 }
 
+/// Configure exemplar capture to match the prometheus exporter.
+/// This must be called on activation as exemplars are captured globally.
+pub(crate) fn configure_exemplars(config: &Config, metrics_config: &MetricsCommon) {
+    exemplars().configure(
+        config.enabled && config.exemplars,
+        metrics_config.buckets.clone(),
+        metrics_config
+            .views
+            .iter()
+            .filter_map(|view| match &view.aggregation {
+                Some(MetricAggregation::Histogram { buckets }) => {
+                    Some((view.name.clone(), buckets.clone()))
+                }
+                _ => None,
+            }),
+    );
+}
+
 impl MetricsConfigurator for Config {
     fn enabled(&self) -> bool {
         self.enabled
@@ -187,7 +217,28 @@ impl Service<router::Request> for PrometheusService {
 
     fn call(&mut self, req: router::Request) -> Self::Future {
         let metric_families = self.registry.gather();
+        let openmetrics = req
+            .router_request
+            .headers()
+            .get_all(http::header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.contains(OPENMETRICS_CONTENT_TYPE));
         Box::pin(async move {
+            if openmetrics {
+                return Ok(router::Response {
+                    response: http::Response::builder()
+                        .status(StatusCode::OK)
+                        .header(
+                            http::header::CONTENT_TYPE,
+                            "application/openmetrics-text; version=1.0.0; charset=utf-8",
+                        )
+                        .body::<Body>(encode_openmetrics(&metric_families, exemplars()).into())
+                        .map_err(BoxError::from)?,
+                    context: req.context,
+                });
+            }
+
             let encoder = TextEncoder::new();
             let mut result = Vec::new();
             encoder.encode(&metric_families, &mut result)?;
@@ -206,3 +257,249 @@ impl Service<router::Request> for PrometheusService {
         })
     }
 }
+
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text";
+
+/// Encode metrics in the OpenMetrics text format, which unlike the prometheus text format supports exemplars.
+fn encode_openmetrics(metric_families: &[MetricFamily], exemplars: &ExemplarStore) -> String {
+    let mut result = String::new();
+    for family in metric_families {
+        let metric_type = family.get_field_type();
+        let name = match metric_type {
+            // The exporter appends `_total` to counters, but in OpenMetrics it belongs to the sample name
+            MetricType::COUNTER => family.get_name().trim_end_matches("_total"),
+            _ => family.get_name(),
+        };
+        let type_name = match metric_type {
+            MetricType::COUNTER => "counter",
+            MetricType::GAUGE => "gauge",
+            MetricType::HISTOGRAM => "histogram",
+            MetricType::SUMMARY => "summary",
+            MetricType::UNTYPED => "unknown",
+        };
+        let _ = writeln!(result, "# TYPE {name} {type_name}");
+        if !family.get_help().is_empty() {
+            let help = family
+                .get_help()
+                .replace('\\', r"\\")
+                .replace('\n', r"\n")
+                .replace('"', r#"\""#);
+            let _ = writeln!(result, "# HELP {name} {help}");
+        }
+
+        for metric in family.get_metric() {
+            let labels: Vec<(&str, &str)> = metric
+                .get_label()
+                .iter()
+                .map(|label| (label.get_name(), label.get_value()))
+                .collect();
+            match metric_type {
+                MetricType::COUNTER => {
+                    write_sample(
+                        &mut result,
+                        &format!("{name}_total"),
+                        &labels,
+                        format_value(metric.get_counter().get_value()),
+                    );
+                }
+                MetricType::GAUGE => {
+                    write_sample(
+                        &mut result,
+                        name,
+                        &labels,
+                        format_value(metric.get_gauge().get_value()),
+                    );
+                }
+                MetricType::UNTYPED => {
+                    write_sample(
+                        &mut result,
+                        name,
+                        &labels,
+                        format_value(metric.get_untyped().get_value()),
+                    );
+                }
+                MetricType::SUMMARY => {
+                    let summary = metric.get_summary();
+                    for quantile in summary.get_quantile() {
+                        let value = format_value(quantile.get_quantile());
+                        let mut labels = labels.clone();
+                        labels.push(("quantile", &value));
+                        write_sample(
+                            &mut result,
+                            name,
+                            &labels,
+                            format_value(quantile.get_value()),
+                        );
+                    }
+                    write_sample(
+                        &mut result,
+                        &format!("{name}_sum"),
+                        &labels,
+                        format_value(summary.get_sample_sum()),
+                    );
+                    write_sample(
+                        &mut result,
+                        &format!("{name}_count"),
+                        &labels,
+                        summary.get_sample_count(),
+                    );
+                }
+                MetricType::HISTOGRAM => {
+                    let histogram = metric.get_histogram();
+                    let bucket_exemplars = exemplars.lookup(name, &labels);
+                    let bucket_name = format!("{name}_bucket");
+                    let mut buckets = histogram
+                        .get_bucket()
+                        .iter()
+                        .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+                        .collect::<Vec<_>>();
+                    if buckets
+                        .last()
+                        .map_or(true, |(upper_bound, _)| *upper_bound != f64::INFINITY)
+                    {
+                        buckets.push((f64::INFINITY, histogram.get_sample_count()));
+                    }
+                    for (index, (upper_bound, count)) in buckets.into_iter().enumerate() {
+                        let upper_bound = format_value(upper_bound);
+                        let mut labels = labels.clone();
+                        labels.push(("le", &upper_bound));
+                        write_labels(&mut result, &bucket_name, &labels);
+                        let _ = write!(result, " {count}");
+                        if let Some(Some(exemplar)) = bucket_exemplars.get(index) {
+                            let timestamp = exemplar
+                                .timestamp
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap_or_default()
+                                .as_secs_f64();
+                            let _ = write!(
+                                result,
+                                " # {{trace_id=\"{}\"}} {} {:.3}",
+                                exemplar.trace_id,
+                                format_value(exemplar.value),
+                                timestamp
+                            );
+                        }
+                        result.push('\n');
+                    }
+                    write_sample(
+                        &mut result,
+                        &format!("{name}_sum"),
+                        &labels,
+                        format_value(histogram.get_sample_sum()),
+                    );
+                    write_sample(
+                        &mut result,
+                        &format!("{name}_count"),
+                        &labels,
+                        histogram.get_sample_count(),
+                    );
+                }
+            }
+        }
+    }
+    result.push_str("# EOF\n");
+    result
+}
+
+fn write_sample(result: &mut String, name: &str, labels: &[(&str, &str)], value: impl Display) {
+    write_labels(result, name, labels);
+    let _ = writeln!(result, " {value}");
+}
+
+fn write_labels(result: &mut String, name: &str, labels: &[(&str, &str)]) {
+    result.push_str(name);
+    if labels.is_empty() {
+        return;
+    }
+    result.push('{');
+    for (index, (key, value)) in labels.iter().enumerate() {
+        if index > 0 {
+            result.push(',');
+        }
+        let value = value
+            .replace('\\', r"\\")
+            .replace('\n', r"\n")
+            .replace('"', r#"\""#);
+        let _ = write!(result, "{key}=\"{value}\"");
+    }
+    result.push('}');
+}
+
+fn format_value(value: f64) -> String {
+    if value == f64::INFINITY {
+        "+Inf".to_string()
+    } else if value == f64::NEG_INFINITY {
+        "-Inf".to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{value:.1}")
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use std::time::SystemTime;
+
+    use opentelemetry::trace::TraceId;
+    use opentelemetry::KeyValue;
+    use prometheus::HistogramOpts;
+    use prometheus::HistogramVec;
+    use prometheus::IntCounterVec;
+    use prometheus::Opts;
+
+    use super::*;
+    use crate::metrics::exemplars::Exemplar;
+
+    #[test]
+    fn test_openmetrics_encoding() {
+        let registry = Registry::new();
+        let counter = IntCounterVec::new(
+            Opts::new("apollo_router_requests_total", "Requests"),
+            &["status"],
+        )
+        .unwrap();
+        let histogram = HistogramVec::new(
+            HistogramOpts::new("http_server_duration_seconds", "Duration").buckets(vec![0.1, 1.0]),
+            &["status"],
+        )
+        .unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        registry.register(Box::new(histogram.clone())).unwrap();
+        counter.with_label_values(&["200"]).inc();
+        histogram.with_label_values(&["200"]).observe(0.5);
+        histogram.with_label_values(&["200"]).observe(2.0);
+
+        let store = ExemplarStore::default();
+        store.configure(true, vec![0.1, 1.0], []);
+        store.insert(
+            "http.server.duration",
+            &[KeyValue::new("status", "200")],
+            Exemplar {
+                trace_id: TraceId::from(0x4bf92f3577b34da6a3ce929d0e0e4736),
+                value: 0.5,
+                timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(1694183999123),
+            },
+        );
+
+        let encoded = encode_openmetrics(&registry.gather(), &store);
+        assert_eq!(
+            encoded,
+            r#"# TYPE apollo_router_requests counter
+# HELP apollo_router_requests Requests
+apollo_router_requests_total{status="200"} 1.0
+# TYPE http_server_duration_seconds histogram
+# HELP http_server_duration_seconds Duration
+http_server_duration_seconds_bucket{status="200",le="0.1"} 0
+http_server_duration_seconds_bucket{status="200",le="1.0"} 1 # {trace_id="4bf92f3577b34da6a3ce929d0e0e4736"} 0.5 1694183999.123
+http_server_duration_seconds_bucket{status="200",le="+Inf"} 2
+http_server_duration_seconds_sum{status="200"} 2.5
+http_server_duration_seconds_count{status="200"} 2
+# EOF
+"#
+        );
+    }
+}
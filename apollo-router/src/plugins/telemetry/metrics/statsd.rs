@@ -0,0 +1,557 @@
+//! StatsD and DogStatsD metrics exporter.
+//!
+//! Metrics are aggregated by the OpenTelemetry SDK and flushed periodically as StatsD lines,
+//! packed into datagrams sent over UDP or a Unix domain socket.
+use std::fmt::Display;
+use std::fmt::Write;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::net::UdpSocket;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use num_traits::ToPrimitive;
+use opentelemetry::metrics::MetricsError;
+use opentelemetry::runtime;
+use opentelemetry::sdk::metrics::data::Gauge;
+use opentelemetry::sdk::metrics::data::Histogram;
+use opentelemetry::sdk::metrics::data::Metric;
+use opentelemetry::sdk::metrics::data::ResourceMetrics;
+use opentelemetry::sdk::metrics::data::Sum;
+use opentelemetry::sdk::metrics::data::Temporality;
+use opentelemetry::sdk::metrics::exporter::PushMetricsExporter;
+use opentelemetry::sdk::metrics::reader::AggregationSelector;
+use opentelemetry::sdk::metrics::reader::TemporalitySelector;
+use opentelemetry::sdk::metrics::Aggregation;
+use opentelemetry::sdk::metrics::InstrumentKind;
+use opentelemetry::sdk::metrics::PeriodicReader;
+use opentelemetry::sdk::AttributeSet;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+
+use crate::plugins::telemetry::config::MetricsCommon;
+use crate::plugins::telemetry::metrics::CustomAggregationSelector;
+use crate::plugins::telemetry::metrics::MetricsBuilder;
+use crate::plugins::telemetry::metrics::MetricsConfigurator;
+
+/// StatsD exporter configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct Config {
+    /// Set to true to enable
+    pub(crate) enabled: bool,
+    /// The agent address: `host:port` for UDP, or `unix:///path/to/socket` for a Unix domain socket
+    pub(crate) endpoint: String,
+    /// The line format: `dogstatsd` sends attributes as tags, `statsd` drops them
+    pub(crate) flavor: Flavor,
+    /// Prefix added to every metric name
+    pub(crate) prefix: Option<String>,
+    /// How often aggregated metrics are sent to the agent
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    pub(crate) flush_interval: Duration,
+    /// Maximum size of a datagram. Lines are packed into datagrams up to this size
+    pub(crate) max_packet_size: usize,
+    /// How histograms are sent
+    pub(crate) histogram_aggregation: HistogramAggregation,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            endpoint: "127.0.0.1:8125".to_string(),
+            flavor: Flavor::default(),
+            prefix: None,
+            flush_interval: Duration::from_secs(10),
+            max_packet_size: 1432,
+            histogram_aggregation: HistogramAggregation::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum Flavor {
+    /// DogStatsD, with attributes sent as tags
+    #[default]
+    Dogstatsd,
+    /// Plain StatsD, without tags
+    Statsd,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum HistogramAggregation {
+    /// Send `<name>.count` and `<name>.sum` counters, and `<name>.min`, `<name>.max` and `<name>.avg` gauges
+    #[default]
+    Summary,
+    /// Send a `<name>.bucket` counter per histogram bucket, with the upper bound in an `le` tag
+    Buckets,
+}
+
+impl MetricsConfigurator for Config {
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    fn apply(
+        &self,
+        mut builder: MetricsBuilder,
+        metrics_config: &MetricsCommon,
+    ) -> Result<MetricsBuilder, BoxError> {
+        if !self.enabled {
+            return Ok(builder);
+        }
+        let exporter = StatsdExporter {
+            transport: Transport::connect(&self.endpoint)?,
+            formatter: Formatter {
+                flavor: self.flavor,
+                prefix: self.prefix.clone(),
+                histogram_aggregation: self.histogram_aggregation,
+            },
+            max_packet_size: self.max_packet_size,
+            aggregation_selector: CustomAggregationSelector::builder()
+                .boundaries(metrics_config.buckets.clone())
+                .build(),
+        };
+
+        builder.public_meter_provider_builder = builder.public_meter_provider_builder.with_reader(
+            PeriodicReader::builder(exporter, runtime::Tokio)
+                .with_interval(self.flush_interval)
+                .build(),
+        );
+        builder.with_public_views(metrics_config)?;
+        tracing::info!("StatsD metrics exported to {}", self.endpoint);
+        Ok(builder)
+    }
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    Uds(UnixDatagram, PathBuf),
+}
+
+impl Transport {
+    fn connect(endpoint: &str) -> Result<Self, BoxError> {
+        if let Some(path) = endpoint.strip_prefix("unix://") {
+            #[cfg(unix)]
+            {
+                let socket = UnixDatagram::unbound()?;
+                socket.set_nonblocking(true)?;
+                return Ok(Transport::Uds(socket, PathBuf::from(path)));
+            }
+            #[cfg(not(unix))]
+            return Err(format!("unix domain sockets are not supported: {path}").into());
+        }
+
+        let address = endpoint
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| format!("cannot resolve the StatsD endpoint {endpoint}"))?;
+        let socket = if address.is_ipv4() {
+            UdpSocket::bind("0.0.0.0:0")?
+        } else {
+            UdpSocket::bind("[::]:0")?
+        };
+        socket.set_nonblocking(true)?;
+        socket.connect(address)?;
+        Ok(Transport::Udp(socket))
+    }
+
+    fn send(&self, packet: &[u8]) -> io::Result<()> {
+        let result = match self {
+            Transport::Udp(socket) => socket.send(packet),
+            #[cfg(unix)]
+            Transport::Uds(socket, path) => socket.send_to(packet, path),
+        };
+        match result {
+            // Like any StatsD client, drop the packet rather than block when the agent is slow
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            Err(e) => Err(e),
+            Ok(_) => Ok(()),
+        }
+    }
+}
+
+struct StatsdExporter {
+    transport: Transport,
+    formatter: Formatter,
+    max_packet_size: usize,
+    aggregation_selector: CustomAggregationSelector,
+}
+
+impl AggregationSelector for StatsdExporter {
+    fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+        self.aggregation_selector.aggregation(kind)
+    }
+}
+
+impl TemporalitySelector for StatsdExporter {
+    fn temporality(&self, kind: InstrumentKind) -> Temporality {
+        // StatsD counters are deltas, gauges are absolute values
+        match kind {
+            InstrumentKind::Counter
+            | InstrumentKind::ObservableCounter
+            | InstrumentKind::Histogram => Temporality::Delta,
+            InstrumentKind::UpDownCounter
+            | InstrumentKind::ObservableUpDownCounter
+            | InstrumentKind::ObservableGauge => Temporality::Cumulative,
+        }
+    }
+}
+
+#[async_trait]
+impl PushMetricsExporter for StatsdExporter {
+    async fn export(&self, metrics: &mut ResourceMetrics) -> opentelemetry::metrics::Result<()> {
+        let mut lines = Vec::new();
+        for scope_metrics in &metrics.scope_metrics {
+            for metric in &scope_metrics.metrics {
+                self.formatter.format(metric, &mut lines);
+            }
+        }
+        for packet in pack(&lines, self.max_packet_size) {
+            self.transport
+                .send(packet.as_bytes())
+                .map_err(|e| MetricsError::Other(format!("cannot send StatsD metrics: {e}")))?;
+        }
+        Ok(())
+    }
+
+    async fn force_flush(&self) -> opentelemetry::metrics::Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self) -> opentelemetry::metrics::Result<()> {
+        Ok(())
+    }
+}
+
+/// Packs lines into newline separated datagrams no larger than `max_packet_size`.
+/// A line larger than the limit is sent in its own datagram.
+fn pack(lines: &[String], max_packet_size: usize) -> Vec<String> {
+    let mut packets = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + 1 + line.len() > max_packet_size {
+            packets.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        packets.push(current);
+    }
+    packets
+}
+
+struct Formatter {
+    flavor: Flavor,
+    prefix: Option<String>,
+    histogram_aggregation: HistogramAggregation,
+}
+
+impl Formatter {
+    fn format(&self, metric: &Metric, lines: &mut Vec<String>) {
+        let data = metric.data.as_any();
+        if let Some(sum) = data.downcast_ref::<Sum<u64>>() {
+            self.format_sum(&metric.name, sum, lines);
+        } else if let Some(sum) = data.downcast_ref::<Sum<i64>>() {
+            self.format_sum(&metric.name, sum, lines);
+        } else if let Some(sum) = data.downcast_ref::<Sum<f64>>() {
+            self.format_sum(&metric.name, sum, lines);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<u64>>() {
+            self.format_gauge(&metric.name, gauge, lines);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<i64>>() {
+            self.format_gauge(&metric.name, gauge, lines);
+        } else if let Some(gauge) = data.downcast_ref::<Gauge<f64>>() {
+            self.format_gauge(&metric.name, gauge, lines);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<u64>>() {
+            self.format_histogram(&metric.name, histogram, lines);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<i64>>() {
+            self.format_histogram(&metric.name, histogram, lines);
+        } else if let Some(histogram) = data.downcast_ref::<Histogram<f64>>() {
+            self.format_histogram(&metric.name, histogram, lines);
+        }
+    }
+
+    fn format_sum<T: Display>(&self, name: &str, sum: &Sum<T>, lines: &mut Vec<String>) {
+        let metric_type = if sum.is_monotonic { "c" } else { "g" };
+        for data_point in &sum.data_points {
+            lines.push(self.line(
+                name,
+                &data_point.value,
+                metric_type,
+                &data_point.attributes,
+                None,
+            ));
+        }
+    }
+
+    fn format_gauge<T: Display>(&self, name: &str, gauge: &Gauge<T>, lines: &mut Vec<String>) {
+        for data_point in &gauge.data_points {
+            lines.push(self.line(name, &data_point.value, "g", &data_point.attributes, None));
+        }
+    }
+
+    fn format_histogram<T: Display + ToPrimitive>(
+        &self,
+        name: &str,
+        histogram: &Histogram<T>,
+        lines: &mut Vec<String>,
+    ) {
+        for data_point in &histogram.data_points {
+            if data_point.count == 0 {
+                continue;
+            }
+            let attributes = &data_point.attributes;
+            match self.histogram_aggregation {
+                HistogramAggregation::Summary => {
+                    let count_name = format!("{name}.count");
+                    let sum_name = format!("{name}.sum");
+                    lines.push(self.line(&count_name, &data_point.count, "c", attributes, None));
+                    lines.push(self.line(&sum_name, &data_point.sum, "c", attributes, None));
+                    if let Some(min) = &data_point.min {
+                        lines.push(self.line(&format!("{name}.min"), min, "g", attributes, None));
+                    }
+                    if let Some(max) = &data_point.max {
+                        lines.push(self.line(&format!("{name}.max"), max, "g", attributes, None));
+                    }
+                    let avg = data_point.sum.to_f64().unwrap_or_default() / data_point.count as f64;
+                    lines.push(self.line(&format!("{name}.avg"), &avg, "g", attributes, None));
+                }
+                HistogramAggregation::Buckets => {
+                    let bucket_name = format!("{name}.bucket");
+                    let bounds = data_point
+                        .bounds
+                        .iter()
+                        .map(|bound| bound.to_string())
+                        .chain(std::iter::once("+Inf".to_string()));
+                    for (bound, count) in bounds.zip(&data_point.bucket_counts) {
+                        lines.push(self.line(
+                            &bucket_name,
+                            count,
+                            "c",
+                            attributes,
+                            Some(("le", &bound)),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    fn line(
+        &self,
+        name: &str,
+        value: &dyn Display,
+        metric_type: &str,
+        attributes: &AttributeSet,
+        extra_tag: Option<(&str, &str)>,
+    ) -> String {
+        let mut line = String::new();
+        if let Some(prefix) = &self.prefix {
+            let _ = write!(line, "{prefix}.");
+        }
+        let _ = write!(line, "{}:{value}|{metric_type}", sanitize(name));
+        if self.flavor == Flavor::Dogstatsd {
+            let mut tags = attributes
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}:{}", sanitize(key.as_str()), sanitize(&value.as_str()))
+                })
+                .chain(extra_tag.map(|(key, value)| format!("{key}:{value}")))
+                .peekable();
+            if tags.peek().is_some() {
+                line.push_str("|#");
+                line.push_str(&tags.collect::<Vec<_>>().join(","));
+            }
+        }
+        line
+    }
+}
+
+/// Replaces the characters that are reserved by the StatsD line protocol.
+fn sanitize(value: &str) -> String {
+    value.replace(['|', ',', '#', ':', '\n'], "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::time::SystemTime;
+
+    use opentelemetry::sdk::metrics::data::DataPoint;
+    use opentelemetry::sdk::metrics::data::HistogramDataPoint;
+    use opentelemetry::KeyValue;
+
+    use super::*;
+
+    fn formatter(flavor: Flavor, histogram_aggregation: HistogramAggregation) -> Formatter {
+        Formatter {
+            flavor,
+            prefix: Some("router".to_string()),
+            histogram_aggregation,
+        }
+    }
+
+    fn metric(
+        name: &'static str,
+        data: Box<dyn opentelemetry::sdk::metrics::data::Aggregation>,
+    ) -> Metric {
+        Metric {
+            name: Cow::Borrowed(name),
+            description: Cow::Borrowed(""),
+            unit: Default::default(),
+            data,
+        }
+    }
+
+    fn attributes() -> AttributeSet {
+        AttributeSet::from(&[KeyValue::new("subgraph.name", "products")][..])
+    }
+
+    fn histogram() -> Metric {
+        metric(
+            "http.client.request.duration",
+            Box::new(Histogram {
+                data_points: vec![HistogramDataPoint {
+                    attributes: attributes(),
+                    start_time: SystemTime::now(),
+                    time: SystemTime::now(),
+                    count: 4,
+                    bounds: vec![0.1, 1.0],
+                    bucket_counts: vec![1, 3, 0],
+                    min: Some(0.05),
+                    max: Some(0.5),
+                    sum: 1.0,
+                    exemplars: vec![],
+                }],
+                temporality: Temporality::Delta,
+            }),
+        )
+    }
+
+    #[test]
+    fn counters_and_gauges() {
+        let counter = metric(
+            "apollo.router.operations",
+            Box::new(Sum {
+                data_points: vec![DataPoint {
+                    attributes: attributes(),
+                    start_time: None,
+                    time: None,
+                    value: 3u64,
+                    exemplars: vec![],
+                }],
+                temporality: Temporality::Delta,
+                is_monotonic: true,
+            }),
+        );
+        let gauge = metric(
+            "apollo.router.cache.size",
+            Box::new(Gauge {
+                data_points: vec![DataPoint {
+                    attributes: AttributeSet::from(&[][..]),
+                    start_time: None,
+                    time: None,
+                    value: 12i64,
+                    exemplars: vec![],
+                }],
+            }),
+        );
+
+        let mut lines = Vec::new();
+        let formatter = formatter(Flavor::Dogstatsd, HistogramAggregation::Summary);
+        formatter.format(&counter, &mut lines);
+        formatter.format(&gauge, &mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "router.apollo.router.operations:3|c|#subgraph.name:products",
+                "router.apollo.router.cache.size:12|g",
+            ]
+        );
+
+        let mut lines = Vec::new();
+        formatter(Flavor::Statsd, HistogramAggregation::Summary).format(&counter, &mut lines);
+        assert_eq!(lines, vec!["router.apollo.router.operations:3|c"]);
+    }
+
+    #[test]
+    fn histogram_summary() {
+        let mut lines = Vec::new();
+        formatter(Flavor::Dogstatsd, HistogramAggregation::Summary)
+            .format(&histogram(), &mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "router.http.client.request.duration.count:4|c|#subgraph.name:products",
+                "router.http.client.request.duration.sum:1|c|#subgraph.name:products",
+                "router.http.client.request.duration.min:0.05|g|#subgraph.name:products",
+                "router.http.client.request.duration.max:0.5|g|#subgraph.name:products",
+                "router.http.client.request.duration.avg:0.25|g|#subgraph.name:products",
+            ]
+        );
+    }
+
+    #[test]
+    fn histogram_buckets() {
+        let mut lines = Vec::new();
+        formatter(Flavor::Dogstatsd, HistogramAggregation::Buckets)
+            .format(&histogram(), &mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "router.http.client.request.duration.bucket:1|c|#subgraph.name:products,le:0.1",
+                "router.http.client.request.duration.bucket:3|c|#subgraph.name:products,le:1",
+                "router.http.client.request.duration.bucket:0|c|#subgraph.name:products,le:+Inf",
+            ]
+        );
+    }
+
+    #[test]
+    fn packets() {
+        let lines = vec![
+            "a:1|c".to_string(),
+            "b:2|c".to_string(),
+            "c:3|c".to_string(),
+        ];
+        assert_eq!(pack(&lines, 11), vec!["a:1|c\nb:2|c", "c:3|c"]);
+        assert_eq!(pack(&lines, 3), vec!["a:1|c", "b:2|c", "c:3|c"]);
+        assert!(pack(&[], 100).is_empty());
+    }
+
+    #[tokio::test]
+    async fn export_over_udp() {
+        let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let exporter = StatsdExporter {
+            transport: Transport::connect(&agent.local_addr().unwrap().to_string()).unwrap(),
+            formatter: formatter(Flavor::Dogstatsd, HistogramAggregation::Summary),
+            max_packet_size: 1432,
+            aggregation_selector: CustomAggregationSelector::default(),
+        };
+        let mut metrics = ResourceMetrics {
+            resource: Default::default(),
+            scope_metrics: vec![opentelemetry::sdk::metrics::data::ScopeMetrics {
+                scope: Default::default(),
+                metrics: vec![histogram()],
+            }],
+        };
+        exporter.export(&mut metrics).await.unwrap();
+
+        let mut buf = [0; 1432];
+        let len = agent.recv(&mut buf).unwrap();
+        let packet = String::from_utf8_lossy(&buf[..len]);
+        assert_eq!(packet.lines().count(), 5);
+        assert!(packet.starts_with("router.http.client.request.duration.count:4|c"));
+    }
+}
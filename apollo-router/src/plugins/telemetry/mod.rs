@@ -68,12 +68,16 @@ use self::config::Conf;
 use self::config::Sampler;
 use self::config::SamplerOption;
 use self::config::TraceIdFormat;
+use self::config_new::events::ExecutionEvents;
+use self::config_new::events::QueryPlannerEvents;
 use self::config_new::events::RouterEvents;
 use self::config_new::events::SubgraphEvents;
 use self::config_new::events::SupergraphEvents;
+use self::config_new::execution::ExecutionCustomInstruments;
 use self::config_new::instruments::Instrumented;
 use self::config_new::instruments::RouterInstruments;
 use self::config_new::instruments::SubgraphInstruments;
+use self::config_new::query_planner::QueryPlannerCustomInstruments;
 use self::config_new::spans::Spans;
 use self::metrics::apollo::studio::SingleTypeStat;
 use self::metrics::AttributesForwardConf;
@@ -87,6 +91,7 @@ use crate::apollo_studio_interop::ReferencedEnums;
 use crate::context::CONTAINS_GRAPHQL_ERROR;
 use crate::context::OPERATION_KIND;
 use crate::context::OPERATION_NAME;
+use crate::error::CacheResolverError;
 use crate::graphql::ResponseVisitor;
 use crate::layers::instrument::InstrumentLayer;
 use crate::layers::ServiceBuilderExt;
@@ -115,6 +120,7 @@ use crate::plugins::telemetry::consts::REQUEST_SPAN_NAME;
 use crate::plugins::telemetry::consts::ROUTER_SPAN_NAME;
 use crate::plugins::telemetry::dynamic_attribute::SpanDynAttribute;
 use crate::plugins::telemetry::fmt_layer::create_fmt_layer;
+use crate::plugins::telemetry::logging::slow_operations::PendingFetch;
 use crate::plugins::telemetry::metrics::apollo::histogram::ListLengthHistogram;
 use crate::plugins::telemetry::metrics::apollo::studio::LocalTypeStat;
 use crate::plugins::telemetry::metrics::apollo::studio::SingleContextualizedStats;
@@ -123,6 +129,7 @@ use crate::plugins::telemetry::metrics::apollo::studio::SingleQueryLatencyStats;
 use crate::plugins::telemetry::metrics::apollo::studio::SingleStats;
 use crate::plugins::telemetry::metrics::apollo::studio::SingleStatsReport;
 use crate::plugins::telemetry::metrics::prometheus::commit_prometheus;
+use crate::plugins::telemetry::metrics::prometheus::configure_exemplars;
 use crate::plugins::telemetry::metrics::MetricsBuilder;
 use crate::plugins::telemetry::metrics::MetricsConfigurator;
 use crate::plugins::telemetry::otel::OpenTelemetrySpanExt;
@@ -130,18 +137,25 @@ use crate::plugins::telemetry::reload::metrics_layer;
 use crate::plugins::telemetry::reload::OPENTELEMETRY_TRACER_HANDLE;
 use crate::plugins::telemetry::tracing::apollo_telemetry::decode_ftv1_trace;
 use crate::plugins::telemetry::tracing::apollo_telemetry::APOLLO_PRIVATE_OPERATION_SIGNATURE;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSamplingCondition;
+use crate::plugins::telemetry::tracing::tail_sampling::TAIL_SAMPLING_GRAPHQL_ERROR;
+use crate::plugins::telemetry::tracing::tail_sampling::TAIL_SAMPLING_KEEP;
+use crate::plugins::telemetry::tracing::tail_sampling::TAIL_SAMPLING_ROOT;
 use crate::plugins::telemetry::tracing::TracingConfigurator;
 use crate::plugins::telemetry::utils::TracingUtils;
+use crate::query_planner::subgraph_latency::SubgraphLatencies;
 use crate::query_planner::OperationKind;
 use crate::register_plugin;
 use crate::router_factory::Endpoint;
 use crate::services::execution;
+use crate::services::query_planner;
 use crate::services::router;
 use crate::services::subgraph;
 use crate::services::subgraph::Request;
 use crate::services::subgraph::Response;
 use crate::services::supergraph;
 use crate::services::ExecutionRequest;
+use crate::services::ExecutionResponse;
 use crate::services::SubgraphRequest;
 use crate::services::SubgraphResponse;
 use crate::services::SupergraphRequest;
@@ -172,8 +186,8 @@ pub(crate) mod tracing;
 pub(crate) mod utils;
 
 // Tracing consts
-const CLIENT_NAME: &str = "apollo_telemetry::client_name";
-const CLIENT_VERSION: &str = "apollo_telemetry::client_version";
+pub(crate) const CLIENT_NAME: &str = "apollo_telemetry::client_name";
+pub(crate) const CLIENT_VERSION: &str = "apollo_telemetry::client_version";
 const SUBGRAPH_FTV1: &str = "apollo_telemetry::subgraph_ftv1";
 pub(crate) const STUDIO_EXCLUDE: &str = "apollo_telemetry::studio::exclude";
 pub(crate) const LOGGING_DISPLAY_HEADERS: &str = "apollo_telemetry::logging::display_headers";
@@ -204,6 +218,8 @@ pub(crate) struct Telemetry {
     pub(crate) graphql_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
     router_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
     supergraph_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
+    query_planner_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
+    execution_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
     subgraph_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
     cache_custom_instruments: RwLock<Arc<HashMap<String, StaticInstrument>>>,
     activation: Mutex<TelemetryActivation>,
@@ -211,6 +227,7 @@ pub(crate) struct Telemetry {
 
 struct TelemetryActivation {
     tracer_provider: Option<opentelemetry::sdk::trace::TracerProvider>,
+    logger_provider: Option<opentelemetry::sdk::logs::LoggerProvider>,
     // We have to have separate meter providers for prometheus metrics so that they don't get zapped on router reload.
     public_meter_provider: Option<FilterMeterProvider>,
     public_prometheus_meter_provider: Option<FilterMeterProvider>,
@@ -250,12 +267,16 @@ impl Drop for Telemetry {
             activation.public_prometheus_meter_provider.take(),
         ];
         let tracer_provider = activation.tracer_provider.take();
+        let logger_provider = activation.logger_provider.take();
         drop(activation);
         TelemetryActivation::checked_meter_shutdown(metrics_providers);
 
         if let Some(tracer_provider) = tracer_provider {
             Self::checked_tracer_shutdown(tracer_provider);
         }
+        if let Some(logger_provider) = logger_provider {
+            Self::checked_logger_shutdown(logger_provider);
+        }
     }
 }
 
@@ -263,6 +284,8 @@ struct BuiltinInstruments {
     graphql_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
     router_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
     supergraph_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
+    query_planner_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
+    execution_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
     subgraph_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
     cache_custom_instruments: Arc<HashMap<String, StaticInstrument>>,
 }
@@ -272,6 +295,8 @@ fn create_builtin_instruments(config: &InstrumentsConfig) -> BuiltinInstruments
         graphql_custom_instruments: Arc::new(config.new_builtin_graphql_instruments()),
         router_custom_instruments: Arc::new(config.new_builtin_router_instruments()),
         supergraph_custom_instruments: Arc::new(config.new_builtin_supergraph_instruments()),
+        query_planner_custom_instruments: Arc::new(config.new_builtin_query_planner_instruments()),
+        execution_custom_instruments: Arc::new(config.new_builtin_execution_instruments()),
         subgraph_custom_instruments: Arc::new(config.new_builtin_subgraph_instruments()),
         cache_custom_instruments: Arc::new(config.new_builtin_cache_instruments()),
     }
@@ -298,6 +323,14 @@ impl Plugin for Telemetry {
         let metrics_builder = Self::create_metrics_builder(&config)?;
 
         let (sampling_filter_ratio, tracer_provider) = Self::create_tracer_provider(&config)?;
+        let logger_provider = if config.exporters.logging.otlp.enabled {
+            Some(logging::otlp::create_logger_provider(
+                &config.exporters.logging.otlp,
+                &config.exporters.logging.common,
+            )?)
+        } else {
+            None
+        };
 
         if config.instrumentation.spans.mode == SpanMode::Deprecated {
             ::tracing::warn!("telemetry.instrumentation.spans.mode is currently set to 'deprecated', either explicitly or via defaulting. Set telemetry.instrumentation.spans.mode explicitly in your router.yaml to 'spec_compliant' for log and span attributes that follow OpenTelemetry semantic conventions. This option will be defaulted to 'spec_compliant' in a future release and eventually removed altogether");
@@ -307,6 +340,8 @@ impl Plugin for Telemetry {
             graphql_custom_instruments,
             router_custom_instruments,
             supergraph_custom_instruments,
+            query_planner_custom_instruments,
+            execution_custom_instruments,
             subgraph_custom_instruments,
             cache_custom_instruments,
         } = create_builtin_instruments(&config.instrumentation.instruments);
@@ -317,6 +352,7 @@ impl Plugin for Telemetry {
             field_level_instrumentation_ratio,
             activation: Mutex::new(TelemetryActivation {
                 tracer_provider: Some(tracer_provider),
+                logger_provider,
                 public_meter_provider: Some(FilterMeterProvider::public(
                     metrics_builder.public_meter_provider_builder.build(),
                 )),
@@ -331,6 +367,8 @@ impl Plugin for Telemetry {
             graphql_custom_instruments: RwLock::new(graphql_custom_instruments),
             router_custom_instruments: RwLock::new(router_custom_instruments),
             supergraph_custom_instruments: RwLock::new(supergraph_custom_instruments),
+            query_planner_custom_instruments: RwLock::new(query_planner_custom_instruments),
+            execution_custom_instruments: RwLock::new(execution_custom_instruments),
             subgraph_custom_instruments: RwLock::new(subgraph_custom_instruments),
             cache_custom_instruments: RwLock::new(cache_custom_instruments),
             sampling_filter_ratio,
@@ -420,6 +458,12 @@ impl Plugin for Telemetry {
                         let _ = request.context.insert(CLIENT_VERSION, version.to_owned());
                     }
 
+                    config_request
+                        .exporters
+                        .logging
+                        .slow_operations
+                        .on_router_request(&request.context);
+
                     let mut custom_attributes = config_request
                         .instrumentation
                         .spans
@@ -449,17 +493,34 @@ impl Plugin for Telemetry {
                         config_request.instrumentation.events.new_router_events();
                     custom_events.on_request(request);
 
+                    let tail_sampling_condition = config_request
+                        .exporters
+                        .tracing
+                        .common
+                        .tail_sampling
+                        .condition
+                        .as_ref()
+                        .map(|condition| TailSamplingCondition::on_request(condition, request));
+
                     (
                         custom_attributes,
                         custom_instruments,
                         custom_events,
+                        tail_sampling_condition,
                         request.context.clone(),
                     )
                 },
-                move |(custom_attributes, custom_instruments, custom_events, ctx): (
+                move |(
+                    custom_attributes,
+                    custom_instruments,
+                    custom_events,
+                    tail_sampling_condition,
+                    ctx,
+                ): (
                     Vec<KeyValue>,
                     RouterInstruments,
                     RouterEvents,
+                    Option<TailSamplingCondition>,
                     Context,
                 ),
                       fut| {
@@ -478,6 +539,11 @@ impl Plugin for Telemetry {
                             APOLLO_PRIVATE_DURATION_NS,
                             start.elapsed().as_nanos() as i64,
                         );
+                        config
+                            .exporters
+                            .logging
+                            .slow_operations
+                            .on_router_response(&ctx, start.elapsed());
 
                         let expose_trace_id = &config.exporters.tracing.response_trace_id;
                         if let Ok(response) = &response {
@@ -555,6 +621,27 @@ impl Plugin for Telemetry {
                             custom_events.on_error(err, &ctx);
                         }
 
+                        let tail_sampling = &config.exporters.tracing.common.tail_sampling;
+                        if tail_sampling.enabled {
+                            let mut attributes = vec![KeyValue::new(TAIL_SAMPLING_ROOT, true)];
+                            if let Ok(response) = &response {
+                                let graphql_error =
+                                    response.context.get_json_value(CONTAINS_GRAPHQL_ERROR)
+                                        == Some(serde_json_bytes::Value::Bool(true));
+                                attributes.push(KeyValue::new(
+                                    TAIL_SAMPLING_GRAPHQL_ERROR,
+                                    graphql_error,
+                                ));
+                            }
+                            if let Some(keep) = tail_sampling_condition
+                                .as_ref()
+                                .and_then(|condition| condition.keep(&response))
+                            {
+                                attributes.push(KeyValue::new(TAIL_SAMPLING_KEEP, keep));
+                            }
+                            span.set_span_dyn_attributes(attributes);
+                        }
+
                         response
                     }
                 },
@@ -696,6 +783,9 @@ impl Plugin for Telemetry {
     }
 
     fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        let config = self.config.clone();
+        let config_map_res = self.config.clone();
+        let static_execution_instruments = self.execution_custom_instruments.read().clone();
         ServiceBuilder::new()
             .instrument(move |req: &ExecutionRequest| {
                 let operation_kind = req
@@ -724,6 +814,77 @@ impl Plugin for Telemetry {
                     }
                 }
             })
+            .map_future_with_request_data(
+                move |req: &ExecutionRequest| {
+                    config
+                        .exporters
+                        .logging
+                        .slow_operations
+                        .on_execution_request(req);
+                    let custom_attributes = config
+                        .instrumentation
+                        .spans
+                        .execution
+                        .attributes
+                        .on_request(req);
+                    let custom_instruments = config
+                        .instrumentation
+                        .instruments
+                        .new_execution_instruments(static_execution_instruments.clone());
+                    custom_instruments.on_request(req);
+                    let custom_events = config.instrumentation.events.new_execution_events();
+                    custom_events.on_request(req);
+
+                    (
+                        req.context.clone(),
+                        custom_attributes,
+                        custom_instruments,
+                        custom_events,
+                    )
+                },
+                move |(ctx, custom_attributes, custom_instruments, custom_events): (
+                    Context,
+                    Vec<KeyValue>,
+                    ExecutionCustomInstruments,
+                    ExecutionEvents,
+                ),
+                      fut| {
+                    let config = config_map_res.clone();
+                    async move {
+                        let span = Span::current();
+                        span.set_span_dyn_attributes(custom_attributes);
+                        let result: Result<ExecutionResponse, BoxError> = fut.await;
+                        match &result {
+                            Ok(resp) => {
+                                span.set_span_dyn_attributes(
+                                    config
+                                        .instrumentation
+                                        .spans
+                                        .execution
+                                        .attributes
+                                        .on_response(resp),
+                                );
+                                custom_instruments.on_response(resp);
+                                custom_events.on_response(resp);
+                            }
+                            Err(err) => {
+                                span.set_span_dyn_attributes(
+                                    config
+                                        .instrumentation
+                                        .spans
+                                        .execution
+                                        .attributes
+                                        .on_error(err, &ctx),
+                                );
+                                custom_instruments.on_error(err, &ctx);
+                                custom_events.on_error(err, &ctx);
+                            }
+                        }
+
+                        result
+                    }
+                },
+            )
             .service(service)
             .boxed()
     }
@@ -770,12 +931,19 @@ impl Plugin for Telemetry {
                         .new_cache_instruments(static_cache_instruments.clone());
                     custom_cache_instruments.on_request(sub_request);
 
+                    let pending_fetch = config
+                        .exporters
+                        .logging
+                        .slow_operations
+                        .on_subgraph_request(sub_request);
+
                     (
                         sub_request.context.clone(),
                         custom_instruments,
                         custom_attributes,
                         custom_events,
                         custom_cache_instruments,
+                        pending_fetch,
                     )
                 },
                 move |(
@@ -784,12 +952,14 @@ impl Plugin for Telemetry {
                     custom_attributes,
                     custom_events,
                     custom_cache_instruments,
+                    pending_fetch,
                 ): (
                     Context,
                     SubgraphInstruments,
                     Vec<KeyValue>,
                     SubgraphEvents,
                     CacheInstruments,
+                    Option<PendingFetch>,
                 ),
                       f: BoxFuture<'static, Result<SubgraphResponse, BoxError>>| {
                     let subgraph_attribute = subgraph_attribute.clone();
@@ -819,6 +989,11 @@ impl Plugin for Telemetry {
                                 custom_cache_instruments.on_response(resp);
                                 custom_instruments.on_response(resp);
                                 custom_events.on_response(resp);
+                                conf.exporters.logging.slow_operations.on_subgraph_response(
+                                    pending_fetch,
+                                    resp,
+                                    now.elapsed(),
+                                );
                             }
                             Err(err) => {
                                 span.record(OTEL_STATUS_CODE, OTEL_STATUS_CODE_ERROR);
@@ -857,6 +1032,87 @@ impl Plugin for Telemetry {
 }
 
 impl Telemetry {
+    /// Query planning is not a plugin stage, so the supergraph service calls this directly
+    /// to apply the `query_planner` spans, instruments and events.
+    pub(crate) fn query_planner_service(
+        &self,
+        service: query_planner::BoxCloneCachingService,
+    ) -> query_planner::BoxCloneCachingService {
+        let config = self.config.clone();
+        let config_map_res = self.config.clone();
+        let static_query_planner_instruments = self.query_planner_custom_instruments.read().clone();
+        ServiceBuilder::new()
+            .map_future_with_request_data(
+                move |req: &query_planner::CachingRequest| {
+                    let custom_attributes = config
+                        .instrumentation
+                        .spans
+                        .query_planner
+                        .attributes
+                        .on_request(req);
+                    let custom_instruments = config
+                        .instrumentation
+                        .instruments
+                        .new_query_planner_instruments(static_query_planner_instruments.clone());
+                    custom_instruments.on_request(req);
+                    let custom_events = config.instrumentation.events.new_query_planner_events();
+                    custom_events.on_request(req);
+
+                    (
+                        req.context.clone(),
+                        custom_attributes,
+                        custom_instruments,
+                        custom_events,
+                    )
+                },
+                move |(ctx, custom_attributes, custom_instruments, custom_events): (
+                    Context,
+                    Vec<KeyValue>,
+                    QueryPlannerCustomInstruments,
+                    QueryPlannerEvents,
+                ),
+                      fut| {
+                    let config = config_map_res.clone();
+                    async move {
+                        let span = Span::current();
+                        span.set_span_dyn_attributes(custom_attributes);
+                        let result: Result<query_planner::Response, CacheResolverError> = fut.await;
+                        match &result {
+                            Ok(resp) => {
+                                span.set_span_dyn_attributes(
+                                    config
+                                        .instrumentation
+                                        .spans
+                                        .query_planner
+                                        .attributes
+                                        .on_response(resp),
+                                );
+                                custom_instruments.on_response(resp);
+                                custom_events.on_response(resp);
+                            }
+                            Err(err) => {
+                                let err: BoxError = err.clone().into();
+                                span.set_span_dyn_attributes(
+                                    config
+                                        .instrumentation
+                                        .spans
+                                        .query_planner
+                                        .attributes
+                                        .on_error(&err, &ctx),
+                                );
+                                custom_instruments.on_error(&err, &ctx);
+                                custom_events.on_error(&err, &ctx);
+                            }
+                        }
+
+                        result
+                    }
+                },
+            )
+            .service(service)
+            .boxed_clone()
+    }
+
     pub(crate) fn activate(&self) {
         let mut activation = self.activation.lock();
         if activation.is_active {
@@ -893,11 +1149,17 @@ impl Telemetry {
         }
 
         activation.reload_metrics();
+        configure_exemplars(
+            &self.config.exporters.metrics.prometheus,
+            &self.config.exporters.metrics.common,
+        );
 
         let BuiltinInstruments {
             graphql_custom_instruments,
             router_custom_instruments,
             supergraph_custom_instruments,
+            query_planner_custom_instruments,
+            execution_custom_instruments,
             subgraph_custom_instruments,
             cache_custom_instruments,
         } = create_builtin_instruments(&self.config.instrumentation.instruments);
@@ -905,10 +1167,15 @@ impl Telemetry {
         *self.graphql_custom_instruments.write() = graphql_custom_instruments;
         *self.router_custom_instruments.write() = router_custom_instruments;
         *self.supergraph_custom_instruments.write() = supergraph_custom_instruments;
+        *self.query_planner_custom_instruments.write() = query_planner_custom_instruments;
+        *self.execution_custom_instruments.write() = execution_custom_instruments;
         *self.subgraph_custom_instruments.write() = subgraph_custom_instruments;
         *self.cache_custom_instruments.write() = cache_custom_instruments;
 
-        reload_fmt(create_fmt_layer(&self.config));
+        reload_fmt(create_fmt_layer(
+            &self.config,
+            activation.logger_provider.take(),
+        ));
         activation.is_active = true;
     }
 
@@ -991,6 +1258,7 @@ impl Telemetry {
         builder =
             setup_metrics_exporter(builder, &metrics_config.prometheus, metrics_common_config)?;
         builder = setup_metrics_exporter(builder, &metrics_config.otlp, metrics_common_config)?;
+        builder = setup_metrics_exporter(builder, &metrics_config.statsd, metrics_common_config)?;
         Ok(builder)
     }
 
@@ -1250,6 +1518,14 @@ impl Telemetry {
         now: Instant,
         result: &Result<Response, BoxError>,
     ) {
+        let duration = now.elapsed();
+        // Latency aware query planning weighs subgraphs by the durations recorded here
+        if result.is_ok() {
+            if let Some(subgraph_latencies) = SubgraphLatencies::from_context(context) {
+                subgraph_latencies.observe(&subgraph_attribute.value.as_str(), duration);
+            }
+        }
+
         let mut metric_attrs = context
             .extensions()
             .with_lock(|lock| lock.get::<SubgraphMetricsAttributes>().cloned())
@@ -1316,7 +1592,7 @@ impl Telemetry {
         f64_histogram!(
             "apollo_router_http_request_duration_seconds",
             "Duration of HTTP requests.",
-            now.elapsed().as_secs_f64(),
+            duration.as_secs_f64(),
             metric_attrs
         );
     }
@@ -1750,6 +2026,7 @@ impl Telemetry {
     fn plugin_metrics(config: &Arc<Conf>) {
         let metrics_prom_used = config.exporters.metrics.prometheus.enabled;
         let metrics_otlp_used = MetricsConfigurator::enabled(&config.exporters.metrics.otlp);
+        let metrics_statsd_used = config.exporters.metrics.statsd.enabled;
         let tracing_otlp_used = TracingConfigurator::enabled(&config.exporters.tracing.otlp);
         let tracing_datadog_used = config.exporters.tracing.datadog.enabled();
         let tracing_jaeger_used = config.exporters.tracing.jaeger.enabled();
@@ -1757,6 +2034,7 @@ impl Telemetry {
 
         if metrics_prom_used
             || metrics_otlp_used
+            || metrics_statsd_used
             || tracing_jaeger_used
             || tracing_otlp_used
             || tracing_zipkin_used
@@ -1766,6 +2044,7 @@ impl Telemetry {
                 monotonic_counter.apollo.router.operations.telemetry = 1u64,
                 telemetry.metrics.otlp = metrics_otlp_used.or_empty(),
                 telemetry.metrics.prometheus = metrics_prom_used.or_empty(),
+                telemetry.metrics.statsd = metrics_statsd_used.or_empty(),
                 telemetry.tracing.otlp = tracing_otlp_used.or_empty(),
                 telemetry.tracing.datadog = tracing_datadog_used.or_empty(),
                 telemetry.tracing.jaeger = tracing_jaeger_used.or_empty(),
@@ -1780,6 +2059,12 @@ impl Telemetry {
         }));
     }
 
+    fn checked_logger_shutdown(logger_provider: opentelemetry::sdk::logs::LoggerProvider) {
+        Self::checked_spawn_task(Box::new(move || {
+            drop(logger_provider);
+        }));
+    }
+
     fn checked_global_tracer_shutdown(global_tracer_provider: GlobalTracerProvider) {
         Self::checked_spawn_task(Box::new(move || {
             drop(global_tracer_provider);
@@ -3059,6 +3344,46 @@ mod tests {
         .await;
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn it_test_prometheus_openmetrics() {
+        async {
+            let plugin =
+                create_plugin_with_config(include_str!("testdata/prometheus.router.yaml")).await;
+            make_supergraph_request(plugin.as_ref()).await;
+            let web_endpoint = plugin
+                .web_endpoints()
+                .into_iter()
+                .next()
+                .unwrap()
+                .1
+                .into_iter()
+                .next()
+                .unwrap()
+                .into_router();
+
+            let http_req_prom = http::Request::get("http://localhost:9090/metrics")
+                .header(
+                    http::header::ACCEPT,
+                    "application/openmetrics-text; version=1.0.0,text/plain;q=0.5",
+                )
+                .body(Default::default())
+                .unwrap();
+            let mut resp = web_endpoint.oneshot(http_req_prom).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.headers().get(http::header::CONTENT_TYPE).unwrap(),
+                "application/openmetrics-text; version=1.0.0; charset=utf-8"
+            );
+            let body = get_body_bytes(resp.body_mut()).await.unwrap();
+            let body = String::from_utf8_lossy(&body);
+            assert!(body.ends_with("# EOF\n"));
+            assert!(body.contains("# TYPE apollo_router_http_request_duration_seconds histogram"));
+            assert!(body.contains("apollo_router_http_request_duration_seconds_bucket{"));
+        }
+        .with_metrics()
+        .await;
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn it_test_prometheus_metrics_custom_buckets() {
         async {
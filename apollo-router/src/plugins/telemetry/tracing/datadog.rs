@@ -40,6 +40,7 @@ use crate::plugins::telemetry::consts::SUPERGRAPH_SPAN_NAME;
 use crate::plugins::telemetry::endpoint::UriEndpoint;
 use crate::plugins::telemetry::tracing::datadog_exporter;
 use crate::plugins::telemetry::tracing::datadog_exporter::DatadogTraceState;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSamplingExt;
 use crate::plugins::telemetry::tracing::BatchProcessorConfig;
 use crate::plugins::telemetry::tracing::SpanProcessorExt;
 use crate::plugins::telemetry::tracing::TracingConfigurator;
@@ -220,7 +221,8 @@ impl TracingConfigurator for Config {
             )
             .with_batch_config(self.batch_processor.clone().into())
             .build()
-            .filtered(),
+            .filtered()
+            .tail_sampled(&trace.tail_sampling),
         ))
     }
 }
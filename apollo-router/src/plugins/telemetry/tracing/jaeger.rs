@@ -15,6 +15,7 @@ use crate::plugins::telemetry::config::TracingCommon;
 use crate::plugins::telemetry::config_new::spans::Spans;
 use crate::plugins::telemetry::endpoint::SocketEndpoint;
 use crate::plugins::telemetry::endpoint::UriEndpoint;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSamplingExt;
 use crate::plugins::telemetry::tracing::BatchProcessorConfig;
 use crate::plugins::telemetry::tracing::SpanProcessorExt;
 use crate::plugins::telemetry::tracing::TracingConfigurator;
@@ -108,7 +109,8 @@ impl TracingConfigurator for Config {
                     BatchSpanProcessor::builder(exporter, opentelemetry::runtime::Tokio)
                         .with_batch_config(batch_processor.clone().into())
                         .build()
-                        .filtered(),
+                        .filtered()
+                        .tail_sampled(&common.tail_sampling),
                 ))
             }
             Config::Collector {
@@ -139,7 +141,8 @@ impl TracingConfigurator for Config {
                 Ok(builder.with_span_processor(
                     BatchSpanProcessor::builder(exporter, runtime::Tokio)
                         .with_batch_config(batch_processor.clone().into())
-                        .build(),
+                        .build()
+                        .tail_sampled(&common.tail_sampling),
                 ))
             }
             _ => Ok(builder),
@@ -9,6 +9,7 @@ use tower::BoxError;
 use crate::plugins::telemetry::config::TracingCommon;
 use crate::plugins::telemetry::config_new::spans::Spans;
 use crate::plugins::telemetry::otlp::TelemetryDataKind;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSamplingExt;
 use crate::plugins::telemetry::tracing::SpanProcessorExt;
 use crate::plugins::telemetry::tracing::TracingConfigurator;
 
@@ -20,7 +21,7 @@ impl TracingConfigurator for super::super::otlp::Config {
     fn apply(
         &self,
         builder: Builder,
-        _common: &TracingCommon,
+        common: &TracingCommon,
         _spans_config: &Spans,
     ) -> Result<Builder, BoxError> {
         tracing::info!("Configuring Otlp tracing: {}", self.batch_processor);
@@ -33,7 +34,8 @@ impl TracingConfigurator for super::super::otlp::Config {
             )
             .with_batch_config(self.batch_processor.clone().into())
             .build()
-            .filtered(),
+            .filtered()
+            .tail_sampled(&common.tail_sampling),
         ))
     }
 }
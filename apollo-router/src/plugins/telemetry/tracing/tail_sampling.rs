@@ -0,0 +1,543 @@
+//! Tail based sampling
+//!
+//! The spans of each trace are buffered until the root span of the router request ends, then the
+//! whole trace is either sent to the exporter or dropped, depending on what happened during the
+//! request. The spans ending after the decision, like the parent `request` span, follow it.
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use opentelemetry::sdk::export::trace::SpanData;
+use opentelemetry::sdk::trace::Span;
+use opentelemetry::sdk::trace::SpanProcessor;
+use opentelemetry::trace::SpanId;
+use opentelemetry::trace::Status;
+use opentelemetry::trace::TraceId;
+use opentelemetry::trace::TraceResult;
+use opentelemetry::Context;
+use opentelemetry::Key;
+use opentelemetry::Value;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+
+use crate::plugins::telemetry::config_new::conditions::Condition;
+use crate::plugins::telemetry::config_new::selectors::RouterSelector;
+use crate::services::router;
+
+/// Marks the root span of a router request, where the sampling decision is made
+pub(crate) const TAIL_SAMPLING_ROOT: Key =
+    Key::from_static_str("apollo_private.tail_sampling.root");
+/// Set on the root span if the response contained GraphQL errors
+pub(crate) const TAIL_SAMPLING_GRAPHQL_ERROR: Key =
+    Key::from_static_str("apollo_private.tail_sampling.graphql_error");
+/// Set on the root span if the configured condition matched
+pub(crate) const TAIL_SAMPLING_KEEP: Key =
+    Key::from_static_str("apollo_private.tail_sampling.keep");
+
+/// Tail based sampling configuration
+#[derive(Clone, Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct TailSampling {
+    /// Buffer the spans of each trace and decide whether to export it at the end of the request
+    pub(crate) enabled: bool,
+    /// Maximum number of spans buffered for each exporter. When it is reached, the oldest traces are dropped
+    pub(crate) max_buffered_spans: usize,
+    /// Keep traces where a GraphQL error occurred or a span has an error status
+    pub(crate) keep_errors: bool,
+    /// Keep traces where the router request lasted longer than this
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "Option<String>", default)]
+    pub(crate) latency_threshold: Option<Duration>,
+    /// Keep traces where this condition on the router request and response is true
+    pub(crate) condition: Option<Condition<RouterSelector>>,
+    /// Ratio of the other traces to keep, between 0.0 and 1.0
+    pub(crate) ratio: f64,
+}
+
+impl Default for TailSampling {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_buffered_spans: 50_000,
+            keep_errors: true,
+            latency_threshold: None,
+            condition: None,
+            ratio: 0.0,
+        }
+    }
+}
+
+/// Tail sampling condition of a router request, evaluated in two stages: the request selectors
+/// are resolved when the request starts, the response selectors once it completes
+#[derive(Clone, Debug)]
+pub(crate) struct TailSamplingCondition {
+    condition: Condition<RouterSelector>,
+    /// set if the condition could be decided from the request
+    keep: Option<bool>,
+}
+
+impl TailSamplingCondition {
+    pub(crate) fn on_request(
+        condition: &Condition<RouterSelector>,
+        request: &router::Request,
+    ) -> Self {
+        let mut condition = condition.clone();
+        let keep = condition.evaluate_request(request);
+        Self { condition, keep }
+    }
+
+    /// Whether the trace must be kept. Returns `None` if the condition depends on the response
+    /// and the request failed
+    pub(crate) fn keep(&self, response: &Result<router::Response, BoxError>) -> Option<bool> {
+        match (self.keep, response) {
+            (Some(keep), _) => Some(keep),
+            (None, Ok(response)) => Some(self.condition.evaluate_response(response)),
+            (None, Err(_)) => None,
+        }
+    }
+}
+
+/// Number of recent decisions remembered, so that the spans ending after the root span of the
+/// router request follow the decision made for their trace
+const REMEMBERED_DECISIONS: usize = 10_000;
+
+impl TailSampling {
+    fn should_keep(&self, root: &SpanData, trace: &BufferedTrace) -> bool {
+        let is_true = |key: &Key| matches!(root.attributes.get(key), Some(Value::Bool(true)));
+
+        if self.keep_errors
+            && (trace.has_error
+                || matches!(root.status, Status::Error { .. })
+                || is_true(&TAIL_SAMPLING_GRAPHQL_ERROR))
+        {
+            return true;
+        }
+
+        if let Some(threshold) = self.latency_threshold {
+            if root
+                .end_time
+                .duration_since(root.start_time)
+                .is_ok_and(|latency| latency >= threshold)
+            {
+                return true;
+            }
+        }
+
+        if is_true(&TAIL_SAMPLING_KEEP) {
+            return true;
+        }
+
+        // same algorithm as the TraceIdRatioBased sampler, so that a trace gets the same decision
+        // for all exporters
+        let upper_bound = (self.ratio.clamp(0.0, 1.0) * (1u64 << 63) as f64) as u64;
+        let random = (u128::from_be_bytes(root.span_context.trace_id().to_bytes()) as u64) >> 1;
+        random < upper_bound
+    }
+}
+
+#[derive(Debug, Default)]
+struct BufferedTrace {
+    spans: Vec<SpanData>,
+    has_error: bool,
+}
+
+#[derive(Debug, Default)]
+struct Buffer {
+    traces: HashMap<TraceId, BufferedTrace>,
+    /// trace ids in order of arrival, used to drop the oldest traces first
+    order: VecDeque<TraceId>,
+    buffered_spans: usize,
+    /// whether recently decided traces were kept
+    decisions: HashMap<TraceId, bool>,
+    /// decided trace ids in order of decision, used to forget the oldest decisions first
+    decision_order: VecDeque<TraceId>,
+}
+
+impl Buffer {
+    fn push(&mut self, span: SpanData) {
+        let trace_id = span.span_context.trace_id();
+        let trace = self.traces.entry(trace_id).or_insert_with(|| {
+            self.order.push_back(trace_id);
+            BufferedTrace::default()
+        });
+        trace.has_error |= matches!(span.status, Status::Error { .. });
+        trace.spans.push(span);
+        self.buffered_spans += 1;
+    }
+
+    fn take(&mut self, trace_id: &TraceId) -> Option<BufferedTrace> {
+        let trace = self.traces.remove(trace_id)?;
+        self.buffered_spans -= trace.spans.len();
+
+        // the order queue is cleaned up lazily
+        if self.order.len() > 2 * self.traces.len() + 1024 {
+            let traces = &self.traces;
+            self.order.retain(|id| traces.contains_key(id));
+        }
+        Some(trace)
+    }
+
+    fn decide(&mut self, trace_id: TraceId, keep: bool) {
+        if self.decisions.insert(trace_id, keep).is_none() {
+            self.decision_order.push_back(trace_id);
+        }
+        while self.decision_order.len() > REMEMBERED_DECISIONS {
+            if let Some(oldest) = self.decision_order.pop_front() {
+                self.decisions.remove(&oldest);
+            }
+        }
+    }
+
+    fn evict_oldest(&mut self) -> usize {
+        while let Some(trace_id) = self.order.pop_front() {
+            if let Some(trace) = self.traces.remove(&trace_id) {
+                self.buffered_spans -= trace.spans.len();
+                return trace.spans.len();
+            }
+        }
+        0
+    }
+}
+
+/// Buffers the spans of each trace until the root span ends, and only forwards the traces
+/// selected by the tail sampling rules to the delegate
+#[derive(Debug)]
+pub(crate) struct TailSamplingSpanProcessor<T: SpanProcessor> {
+    delegate: T,
+    config: Option<TailSampling>,
+    buffer: Mutex<Buffer>,
+}
+
+impl<T: SpanProcessor> TailSamplingSpanProcessor<T> {
+    fn decide(&self, root: SpanData) {
+        let config = match &self.config {
+            Some(config) => config,
+            None => return self.delegate.on_end(root),
+        };
+
+        let trace_id = root.span_context.trace_id();
+        let mut buffer = self.buffer.lock();
+        let trace = buffer.take(&trace_id).unwrap_or_default();
+        let keep = config.should_keep(&root, &trace);
+        buffer.decide(trace_id, keep);
+        drop(buffer);
+
+        if keep {
+            for span in trace.spans {
+                self.delegate.on_end(span);
+            }
+            self.delegate.on_end(root);
+        }
+    }
+}
+
+impl<T: SpanProcessor> SpanProcessor for TailSamplingSpanProcessor<T> {
+    fn on_start(&self, span: &mut Span, cx: &Context) {
+        self.delegate.on_start(span, cx);
+    }
+
+    fn on_end(&self, span: SpanData) {
+        let max_buffered_spans = match &self.config {
+            Some(config) => config.max_buffered_spans,
+            None => return self.delegate.on_end(span),
+        };
+
+        if matches!(
+            span.attributes.get(&TAIL_SAMPLING_ROOT),
+            Some(Value::Bool(true))
+        ) {
+            return self.decide(span);
+        }
+
+        let mut buffer = self.buffer.lock();
+        if let Some(keep) = buffer.decisions.get(&span.span_context.trace_id()).copied() {
+            drop(buffer);
+            if keep {
+                self.delegate.on_end(span);
+            }
+            return;
+        }
+        if span.parent_span_id == SpanId::INVALID {
+            // a trace without a router request, like the spans of a schema reload, is decided on
+            // its own root span
+            drop(buffer);
+            return self.decide(span);
+        }
+        buffer.push(span);
+        let mut dropped = 0;
+        while buffer.buffered_spans > max_buffered_spans {
+            dropped += buffer.evict_oldest();
+        }
+        drop(buffer);
+
+        if dropped > 0 {
+            u64_counter!(
+                "apollo.router.telemetry.tail_sampling.dropped_spans",
+                "Number of spans dropped because the tail sampling buffer was full",
+                dropped as u64
+            );
+        }
+    }
+
+    fn force_flush(&self) -> TraceResult<()> {
+        self.delegate.force_flush()
+    }
+
+    fn shutdown(&mut self) -> TraceResult<()> {
+        // traces that did not complete cannot be sampled
+        *self.buffer.get_mut() = Buffer::default();
+        self.delegate.shutdown()
+    }
+}
+
+pub(crate) trait TailSamplingExt
+where
+    Self: Sized + SpanProcessor,
+{
+    fn tail_sampled(self, config: &TailSampling) -> TailSamplingSpanProcessor<Self>;
+}
+
+impl<T: SpanProcessor> TailSamplingExt for T
+where
+    Self: Sized,
+{
+    fn tail_sampled(self, config: &TailSampling) -> TailSamplingSpanProcessor<Self> {
+        TailSamplingSpanProcessor {
+            delegate: self,
+            config: config.enabled.then(|| config.clone()),
+            buffer: Default::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::sync::Arc;
+    use std::time::SystemTime;
+
+    use opentelemetry::sdk::trace::EvictedHashMap;
+    use opentelemetry::sdk::trace::EvictedQueue;
+    use opentelemetry::sdk::Resource;
+    use opentelemetry::trace::SpanContext;
+    use opentelemetry::trace::SpanKind;
+    use opentelemetry::trace::TraceFlags;
+    use opentelemetry::trace::TraceState;
+    use opentelemetry::KeyValue;
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<SpanData>>>);
+
+    impl SpanProcessor for Recorder {
+        fn on_start(&self, _span: &mut Span, _cx: &Context) {}
+
+        fn on_end(&self, span: SpanData) {
+            self.0.lock().push(span);
+        }
+
+        fn force_flush(&self) -> TraceResult<()> {
+            Ok(())
+        }
+
+        fn shutdown(&mut self) -> TraceResult<()> {
+            Ok(())
+        }
+    }
+
+    fn span(
+        trace_id: u128,
+        span_id: u64,
+        parent: u64,
+        duration: Duration,
+        attributes: Vec<KeyValue>,
+    ) -> SpanData {
+        let mut attrs = EvictedHashMap::new(128, attributes.len());
+        for attribute in attributes {
+            attrs.insert(attribute);
+        }
+        let start_time = SystemTime::now();
+        SpanData {
+            span_context: SpanContext::new(
+                TraceId::from_u128(trace_id),
+                SpanId::from_u64(span_id),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ),
+            parent_span_id: SpanId::from_u64(parent),
+            span_kind: SpanKind::Internal,
+            name: Cow::Borrowed("span"),
+            start_time,
+            end_time: start_time + duration,
+            attributes: attrs,
+            events: EvictedQueue::new(0),
+            links: EvictedQueue::new(0),
+            status: Status::Unset,
+            resource: Cow::Owned(Resource::empty()),
+            instrumentation_lib: Default::default(),
+        }
+    }
+
+    fn root(trace_id: u128, duration: Duration, attributes: Vec<KeyValue>) -> SpanData {
+        let mut attributes = attributes;
+        attributes.push(KeyValue::new(TAIL_SAMPLING_ROOT, true));
+        // the router span can have a remote parent
+        span(trace_id, 1, 1000, duration, attributes)
+    }
+
+    #[test]
+    fn keeps_errors_and_slow_traces() {
+        let recorder = Recorder::default();
+        let processor = recorder.clone().tail_sampled(&TailSampling {
+            enabled: true,
+            latency_threshold: Some(Duration::from_secs(1)),
+            ..Default::default()
+        });
+
+        // fast trace without errors is dropped
+        processor.on_end(span(1, 2, 1, Duration::from_millis(1), vec![]));
+        processor.on_end(root(1, Duration::from_millis(10), vec![]));
+        assert!(recorder.0.lock().is_empty());
+
+        // GraphQL errors
+        processor.on_end(span(2, 2, 1, Duration::from_millis(1), vec![]));
+        processor.on_end(root(
+            2,
+            Duration::from_millis(10),
+            vec![KeyValue::new(TAIL_SAMPLING_GRAPHQL_ERROR, true)],
+        ));
+        assert_eq!(recorder.0.lock().len(), 2);
+
+        // error status on a child span
+        let mut child = span(3, 2, 1, Duration::from_millis(1), vec![]);
+        child.status = Status::error("subgraph error");
+        processor.on_end(child);
+        processor.on_end(root(3, Duration::from_millis(10), vec![]));
+        assert_eq!(recorder.0.lock().len(), 4);
+
+        // slow request
+        processor.on_end(span(4, 2, 1, Duration::from_millis(1), vec![]));
+        processor.on_end(root(4, Duration::from_secs(2), vec![]));
+        assert_eq!(recorder.0.lock().len(), 6);
+
+        // condition matched
+        processor.on_end(root(
+            5,
+            Duration::from_millis(10),
+            vec![KeyValue::new(TAIL_SAMPLING_KEEP, true)],
+        ));
+        assert_eq!(recorder.0.lock().len(), 7);
+        assert_eq!(processor.buffer.lock().buffered_spans, 0);
+    }
+
+    #[test]
+    fn later_spans_follow_the_decision() {
+        let recorder = Recorder::default();
+        let processor = recorder.clone().tail_sampled(&TailSampling {
+            enabled: true,
+            ..Default::default()
+        });
+        let router = |trace_id, attributes: Vec<KeyValue>| {
+            let mut attributes = attributes;
+            attributes.push(KeyValue::new(TAIL_SAMPLING_ROOT, true));
+            span(trace_id, 2, 1, Duration::from_millis(10), attributes)
+        };
+
+        // request -> router -> subgraph, the request span ends last
+        processor.on_end(span(1, 3, 2, Duration::from_millis(1), vec![]));
+        processor.on_end(router(1, vec![KeyValue::new(TAIL_SAMPLING_KEEP, true)]));
+        assert_eq!(recorder.0.lock().len(), 2);
+        processor.on_end(span(1, 4, 2, Duration::from_millis(1), vec![]));
+        processor.on_end(span(1, 1, 0, Duration::from_millis(20), vec![]));
+        assert_eq!(
+            recorder
+                .0
+                .lock()
+                .iter()
+                .map(|span| span.span_context.span_id())
+                .collect::<Vec<_>>(),
+            [3, 2, 4, 1].map(SpanId::from_u64)
+        );
+
+        // dropped traces are not buffered again
+        processor.on_end(span(2, 3, 2, Duration::from_millis(1), vec![]));
+        processor.on_end(router(2, vec![]));
+        processor.on_end(span(2, 4, 2, Duration::from_millis(1), vec![]));
+        processor.on_end(span(2, 1, 0, Duration::from_millis(20), vec![]));
+        assert_eq!(recorder.0.lock().len(), 4);
+        assert_eq!(processor.buffer.lock().buffered_spans, 0);
+    }
+
+    #[test]
+    fn ratio() {
+        let recorder = Recorder::default();
+        let processor = recorder.clone().tail_sampled(&TailSampling {
+            enabled: true,
+            ratio: 1.0,
+            ..Default::default()
+        });
+        processor.on_end(span(1, 2, 1, Duration::from_millis(1), vec![]));
+        processor.on_end(root(1, Duration::from_millis(10), vec![]));
+        assert_eq!(recorder.0.lock().len(), 2);
+    }
+
+    #[test]
+    fn bounded_buffer() {
+        let recorder = Recorder::default();
+        let processor = recorder.clone().tail_sampled(&TailSampling {
+            enabled: true,
+            max_buffered_spans: 2,
+            ratio: 1.0,
+            ..Default::default()
+        });
+        processor.on_end(span(1, 2, 1, Duration::from_millis(1), vec![]));
+        processor.on_end(span(2, 2, 1, Duration::from_millis(1), vec![]));
+        processor.on_end(span(2, 3, 1, Duration::from_millis(1), vec![]));
+        assert_eq!(processor.buffer.lock().buffered_spans, 2);
+
+        // the first trace was evicted
+        processor.on_end(root(1, Duration::from_millis(10), vec![]));
+        assert_eq!(recorder.0.lock().len(), 1);
+        processor.on_end(root(2, Duration::from_millis(10), vec![]));
+        assert_eq!(recorder.0.lock().len(), 4);
+    }
+
+    #[test]
+    fn disabled() {
+        let recorder = Recorder::default();
+        let processor = recorder.clone().tail_sampled(&TailSampling::default());
+        processor.on_end(span(1, 2, 1, Duration::from_millis(1), vec![]));
+        assert_eq!(recorder.0.lock().len(), 1);
+    }
+
+    #[test]
+    fn condition_on_request_selectors() {
+        let condition: Condition<RouterSelector> =
+            serde_yaml::from_str("eq:\n  - request_header: x-debug\n  - \"true\"").unwrap();
+
+        // decided when the request starts, even if the request then fails
+        let request = router::Request::fake_builder()
+            .header("x-debug", "true")
+            .build()
+            .unwrap();
+        let tail_sampling = TailSamplingCondition::on_request(&condition, &request);
+        assert_eq!(tail_sampling.keep(&Err("error".into())), Some(true));
+        assert_eq!(
+            tail_sampling.keep(&Ok(router::Response::fake_builder().build().unwrap())),
+            Some(true)
+        );
+
+        let request = router::Request::fake_builder().build().unwrap();
+        let tail_sampling = TailSamplingCondition::on_request(&condition, &request);
+        assert_eq!(
+            tail_sampling.keep(&Ok(router::Response::fake_builder().build().unwrap())),
+            Some(false)
+        );
+        assert_eq!(tail_sampling.keep(&Err("error".into())), None);
+    }
+}
@@ -13,6 +13,7 @@ use crate::plugins::telemetry::config::GenericWith;
 use crate::plugins::telemetry::config::TracingCommon;
 use crate::plugins::telemetry::config_new::spans::Spans;
 use crate::plugins::telemetry::endpoint::UriEndpoint;
+use crate::plugins::telemetry::tracing::tail_sampling::TailSamplingExt;
 use crate::plugins::telemetry::tracing::BatchProcessorConfig;
 use crate::plugins::telemetry::tracing::SpanProcessorExt;
 use crate::plugins::telemetry::tracing::TracingConfigurator;
@@ -68,7 +69,8 @@ impl TracingConfigurator for Config {
             BatchSpanProcessor::builder(exporter, opentelemetry::runtime::Tokio)
                 .with_batch_config(self.batch_processor.clone().into())
                 .build()
-                .filtered(),
+                .filtered()
+                .tail_sampled(&trace.tail_sampling),
         ))
     }
 }
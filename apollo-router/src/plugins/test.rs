@@ -95,7 +95,8 @@ impl<T: Plugin> PluginTestHarness<T> {
             let schema = Schema::parse(schema, &config).unwrap();
             let sdl = schema.raw_sdl.clone();
             let supergraph = schema.supergraph_schema().clone();
-            let rust_planner = PlannerMode::maybe_rust(&schema, &config).unwrap();
+            let rust_planner =
+                PlannerMode::maybe_rust(&schema, &config, &Default::default()).unwrap();
             let planner = BridgeQueryPlanner::new(
                 schema.into(),
                 Arc::new(config),
@@ -0,0 +1,440 @@
+//! Adaptive concurrency limiting.
+//!
+//! The limit follows the gradient between the lowest observed latency and the latency of each
+//! request: it grows while latency stays close to the lowest one, and shrinks when requests start
+//! queueing in the router or in the subgraph. Requests over the limit are rejected right away
+//! instead of piling up.
+
+use std::error;
+use std::fmt;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+
+use crate::error::ConfigurationError;
+use crate::graphql;
+use crate::plugins::telemetry::config_new::Selector;
+
+/// Weight of each latency sample in the limit
+const SMOOTHING: f64 = 0.2;
+/// Decrease of the limit on errors and timeouts
+const BACKOFF_RATIO: f64 = 0.9;
+/// The lowest latency is forgotten after this many samples, to follow changes of the baseline
+const MIN_RTT_SAMPLES: usize = 1000;
+/// Share of the limit reserved for higher priority classes. The lowest priority class is rejected
+/// when the requests in flight enter this headroom
+const PRIORITY_HEADROOM: f64 = 0.2;
+
+/// Adaptive concurrency limit configuration
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AdaptiveConcurrencyConfig<S> {
+    /// Concurrency limit before any latency is observed
+    #[serde(default = "default_initial_limit")]
+    initial_limit: usize,
+    /// Lowest value of the concurrency limit
+    #[serde(default = "default_min_limit")]
+    min_limit: usize,
+    /// Highest value of the concurrency limit
+    #[serde(default = "default_max_limit")]
+    max_limit: usize,
+    /// How many times the lowest observed latency a request can take before the limit decreases
+    #[serde(default = "default_tolerance")]
+    tolerance: f64,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_retry_after"
+    )]
+    #[schemars(with = "String", default = "default_retry_after")]
+    /// Value of the `Retry-After` header of rejected requests
+    retry_after: Duration,
+    /// Rejects the requests of lower priority classes first, when getting close to the limit
+    priority: Option<PriorityConfig<S>>,
+}
+
+impl<S> AdaptiveConcurrencyConfig<S> {
+    pub(crate) fn validate(&self) -> Result<(), ConfigurationError> {
+        if self.min_limit == 0
+            || self.min_limit > self.initial_limit
+            || self.initial_limit > self.max_limit
+        {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "bad adaptive concurrency configuration for traffic_shaping plugin",
+                error: "limits must verify 0 < min_limit <= initial_limit <= max_limit".to_string(),
+            });
+        }
+        if self.tolerance < 1.0 {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "bad adaptive concurrency configuration for traffic_shaping plugin",
+                error: "tolerance must be greater than or equal to 1".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// How long clients should wait before retrying rejected requests
+    pub(crate) fn retry_after(&self) -> Duration {
+        self.retry_after
+    }
+}
+
+fn default_initial_limit() -> usize {
+    20
+}
+
+fn default_min_limit() -> usize {
+    1
+}
+
+fn default_max_limit() -> usize {
+    1000
+}
+
+fn default_tolerance() -> f64 {
+    2.0
+}
+
+fn default_retry_after() -> Duration {
+    Duration::from_secs(1)
+}
+
+/// Priority classes of requests
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PriorityConfig<S> {
+    /// Gives the priority class of a request
+    key: S,
+    /// Priority classes, from the highest priority. Requests of unlisted classes come last
+    classes: Vec<String>,
+}
+
+/// The overloaded error.
+#[derive(Debug, Default)]
+pub(crate) struct Overloaded;
+
+impl Overloaded {
+    /// Construct a new Overloaded error
+    pub(crate) fn new() -> Self {
+        Overloaded {}
+    }
+}
+
+impl fmt::Display for Overloaded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("your request has been rejected because the service is overloaded")
+    }
+}
+
+impl From<Overloaded> for graphql::Error {
+    fn from(_: Overloaded) -> Self {
+        graphql::Error::builder()
+            .message(String::from(
+                "Your request has been rejected because the service is overloaded",
+            ))
+            .extension_code("SERVICE_OVERLOADED")
+            .build()
+    }
+}
+
+impl error::Error for Overloaded {}
+
+#[derive(Debug)]
+struct LimitState {
+    limit: f64,
+    min_rtt: Option<Duration>,
+    samples: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct ConcurrencyLimiter {
+    state: Mutex<LimitState>,
+    in_flight: AtomicUsize,
+    min_limit: f64,
+    max_limit: f64,
+    tolerance: f64,
+}
+
+impl ConcurrencyLimiter {
+    fn new<S>(config: &AdaptiveConcurrencyConfig<S>) -> Self {
+        Self {
+            state: Mutex::new(LimitState {
+                limit: config.initial_limit as f64,
+                min_rtt: None,
+                samples: 0,
+            }),
+            in_flight: AtomicUsize::new(0),
+            min_limit: config.min_limit as f64,
+            max_limit: config.max_limit as f64,
+            tolerance: config.tolerance,
+        }
+    }
+
+    fn limit(&self) -> f64 {
+        self.state.lock().unwrap().limit
+    }
+
+    /// Reserves a slot for a request of the priority `level`, 0 being the highest of `levels`.
+    /// Any request is admitted below the limit, except in the headroom near the limit, where
+    /// lower priorities are rejected first.
+    fn try_acquire(self: &Arc<Self>, level: usize, levels: usize) -> Option<Permit> {
+        let limit = self.limit();
+        let reserved = if level == 0 {
+            0.0
+        } else {
+            (limit * PRIORITY_HEADROOM * level as f64 / (levels - 1) as f64).ceil()
+        };
+        let allowed = (limit.ceil() - reserved).max(1.0) as usize;
+        let in_flight = self
+            .in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |in_flight| {
+                (in_flight < allowed).then_some(in_flight + 1)
+            })
+            .ok()?;
+
+        Some(Permit {
+            limiter: self.clone(),
+            start: Instant::now(),
+            in_flight: in_flight + 1,
+        })
+    }
+
+    /// Updates the limit with the latency of a request, and the total number of requests that
+    /// were in flight with it
+    fn update(&self, rtt: Duration, in_flight: usize, is_error: bool) {
+        let mut state = self.state.lock().unwrap();
+        let limit = state.limit;
+        let new_limit = if is_error {
+            limit * BACKOFF_RATIO
+        } else {
+            state.samples += 1;
+            let min_rtt = match state.min_rtt {
+                Some(min_rtt) if state.samples < MIN_RTT_SAMPLES => min_rtt.min(rtt),
+                _ => {
+                    state.samples = 0;
+                    rtt
+                }
+            };
+            state.min_rtt = Some(min_rtt);
+
+            let gradient = if rtt.is_zero() {
+                1.0
+            } else {
+                (self.tolerance * min_rtt.as_secs_f64() / rtt.as_secs_f64()).clamp(0.5, 1.0)
+            };
+            // the square root leaves room for some queueing, so the limit can keep growing
+            let target = limit * gradient + limit.sqrt();
+            // the limit only grows when it is actually used
+            if target > limit && (in_flight as f64) < limit / 2.0 {
+                return;
+            }
+            limit * (1.0 - SMOOTHING) + target * SMOOTHING
+        };
+        state.limit = new_limit.clamp(self.min_limit, self.max_limit);
+    }
+}
+
+/// A slot in the concurrency limit, released when dropped
+struct Permit {
+    limiter: Arc<ConcurrencyLimiter>,
+    start: Instant,
+    in_flight: usize,
+}
+
+impl Permit {
+    fn complete(self, is_error: bool) {
+        // requests of all priority classes, admitted before or after this one, use the limit
+        let in_flight = self
+            .in_flight
+            .max(self.limiter.in_flight.load(Ordering::SeqCst));
+        self.limiter
+            .update(self.start.elapsed(), in_flight, is_error);
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AdaptiveConcurrencyLayer<S> {
+    limiter: Arc<ConcurrencyLimiter>,
+    priority: Option<Arc<PriorityConfig<S>>>,
+}
+
+impl<S> AdaptiveConcurrencyLayer<S> {
+    pub(crate) fn new(config: &AdaptiveConcurrencyConfig<S>) -> Self
+    where
+        S: Clone,
+    {
+        Self {
+            limiter: Arc::new(ConcurrencyLimiter::new(config)),
+            priority: config.priority.clone().map(Arc::new),
+        }
+    }
+}
+
+impl<S, Inner> Layer<Inner> for AdaptiveConcurrencyLayer<S> {
+    type Service = AdaptiveConcurrency<S, Inner>;
+
+    fn layer(&self, inner: Inner) -> Self::Service {
+        AdaptiveConcurrency {
+            limiter: self.limiter.clone(),
+            priority: self.priority.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct AdaptiveConcurrency<S, Inner> {
+    limiter: Arc<ConcurrencyLimiter>,
+    priority: Option<Arc<PriorityConfig<S>>>,
+    inner: Inner,
+}
+
+impl<S, Inner, Request> Service<Request> for AdaptiveConcurrency<S, Inner>
+where
+    S: Selector<Request = Request>,
+    Inner: Service<Request, Error = BoxError>,
+    Inner::Response: Send + 'static,
+    Inner::Future: Send + 'static,
+{
+    type Response = Inner::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Inner::Response, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let (level, levels) = match &self.priority {
+            Some(priority) => {
+                let level = priority
+                    .key
+                    .on_request(&request)
+                    .and_then(|class| {
+                        priority
+                            .classes
+                            .iter()
+                            .position(|c| c.as_str() == class.as_str())
+                    })
+                    .unwrap_or(priority.classes.len());
+                (level, priority.classes.len() + 1)
+            }
+            None => (0, 1),
+        };
+
+        let Some(permit) = self.limiter.try_acquire(level, levels) else {
+            tracing::trace!("concurrency limit exceeded; rejecting the request");
+            return async { Err(Overloaded::new().into()) }.boxed();
+        };
+        let future = self.inner.call(request);
+        async move {
+            let response = future.await;
+            permit.complete(response.is_err());
+            response
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugins::telemetry::config_new::selectors::SubgraphSelector;
+
+    fn config(yaml: &str) -> AdaptiveConcurrencyConfig<SubgraphSelector> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn limit_follows_latency() {
+        let limiter = ConcurrencyLimiter::new(&config("initial_limit: 10"));
+
+        // latency stays stable while the limit is used: the limit grows
+        for _ in 0..50 {
+            let limit = limiter.limit() as usize;
+            limiter.update(Duration::from_millis(10), limit, false);
+        }
+        let grown = limiter.limit();
+        assert!(grown > 20.0, "{grown}");
+
+        // the limit does not grow when it is not used
+        limiter.update(Duration::from_millis(10), 1, false);
+        assert_eq!(limiter.limit(), grown);
+
+        // requests queue up and get slower: the limit shrinks
+        for _ in 0..50 {
+            let limit = limiter.limit() as usize;
+            limiter.update(Duration::from_millis(100), limit, false);
+        }
+        assert!(limiter.limit() < 10.0);
+
+        for _ in 0..50 {
+            limiter.update(Duration::from_millis(10), 1, true);
+        }
+        assert_eq!(limiter.limit(), 1.0);
+    }
+
+    #[test]
+    fn lower_priorities_are_rejected_first() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(&config(
+            "{ initial_limit: 4, min_limit: 1, max_limit: 4 }",
+        )));
+
+        // the lowest priority is only rejected in the headroom near the limit
+        let low: Vec<_> = (0..3).map(|_| limiter.try_acquire(1, 2).unwrap()).collect();
+        assert!(limiter.try_acquire(1, 2).is_none());
+        let high = limiter.try_acquire(0, 2).unwrap();
+        assert!(limiter.try_acquire(0, 2).is_none());
+
+        drop(high);
+        drop(low);
+        assert_eq!(limiter.in_flight.load(Ordering::SeqCst), 0);
+        assert!(limiter.try_acquire(1, 2).is_some());
+    }
+
+    #[test]
+    fn low_priority_traffic_grows_the_limit() {
+        let limiter = Arc::new(ConcurrencyLimiter::new(&config(
+            "{ initial_limit: 10, max_limit: 100, priority: { key: { subgraph_name: true }, classes: [high, medium] } }",
+        )));
+
+        for _ in 0..20 {
+            // all requests have the lowest priority
+            let permits: Vec<_> = std::iter::from_fn(|| limiter.try_acquire(2, 3)).collect();
+            assert!(permits.len() as f64 >= limiter.limit() * (1.0 - PRIORITY_HEADROOM) - 1.0);
+            for permit in permits {
+                limiter.update(Duration::from_millis(10), permit.in_flight, false);
+            }
+        }
+        let limit = limiter.limit();
+        assert!(limit > 20.0, "{limit}");
+    }
+
+    #[test]
+    fn invalid_configuration() {
+        assert!(config("{}").validate().is_ok());
+        assert!(config("min_limit: 0").validate().is_err());
+        assert!(config("{ initial_limit: 10, max_limit: 5 }")
+            .validate()
+            .is_err());
+        assert!(config("tolerance: 0.5").validate().is_err());
+    }
+}
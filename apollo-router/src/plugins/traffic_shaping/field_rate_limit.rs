@@ -0,0 +1,556 @@
+//! Rate limits applied to specific fields, whatever the operation selecting them
+//!
+//! Limits are keyed by schema coordinate (`Type.field`). The fields selected by an operation are
+//! extracted from the executable document generated during query analysis, and each field is
+//! counted once per request. A field selected through an interface counts for the limits of the
+//! implementations of that field, and a field selected on an object type counts for the limits of
+//! the interfaces it implements.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use std::num::NonZeroUsize;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use apollo_compiler::executable::Selection;
+use apollo_compiler::executable::SelectionSet;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Schema;
+use http::StatusCode;
+use lru::LruCache;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::error::ConfigurationError;
+use crate::graphql;
+use crate::layers::ServiceBuilderExt;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::services::layers::query_analysis::ParsedDocument;
+use crate::services::supergraph;
+
+/// Maximum number of counters of a field limit. Above it, the least recently used counter is
+/// evicted
+const MAX_COUNTERS: usize = 10_000;
+
+/// Rate limit applied to a field
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct FieldRateLimitConf {
+    /// Number of requests selecting the field allowed
+    capacity: NonZeroU64,
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// Per interval
+    interval: Duration,
+    /// Separate counters by client
+    #[serde(default)]
+    by: RateLimitKey,
+}
+
+/// Selects the counter used for a request
+#[derive(PartialEq, Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum RateLimitKey {
+    /// One counter shared by all clients
+    #[default]
+    All,
+    /// One counter per client name
+    ClientName,
+    /// One counter per value of a client request header
+    Header(String),
+    /// One counter per value of a JWT claim
+    Claim(String),
+}
+
+/// Rate limits for all the configured fields
+#[derive(Debug, Clone)]
+pub(crate) struct FieldRateLimits {
+    limiters: Arc<HashMap<String, FieldRateLimiter>>,
+    // Coordinate of a selected field -> coordinates of the limits it counts for
+    triggers: Arc<HashMap<String, Vec<String>>>,
+}
+
+impl FieldRateLimits {
+    pub(crate) fn new(
+        config: &HashMap<String, FieldRateLimitConf>,
+        schema: &Valid<Schema>,
+    ) -> Result<Self, ConfigurationError> {
+        let implementers = schema.implementers_map();
+        let mut limiters = HashMap::new();
+        let mut triggers: HashMap<String, Vec<String>> = HashMap::new();
+        for (coordinate, conf) in config {
+            let (ty, field) = match coordinate.split_once('.') {
+                Some((ty, field)) if !ty.is_empty() && !field.is_empty() => (ty, field),
+                _ => {
+                    return Err(ConfigurationError::InvalidConfiguration {
+                        message: "bad configuration for traffic_shaping plugin",
+                        error: format!(
+                            "field rate limits must be keyed by a schema coordinate like 'Type.field', got '{coordinate}'"
+                        ),
+                    })
+                }
+            };
+            let interfaces = match schema.types.get(ty) {
+                Some(ExtendedType::Object(object)) if object.fields.contains_key(field) => {
+                    &object.implements_interfaces
+                }
+                Some(ExtendedType::Interface(interface))
+                    if interface.fields.contains_key(field) =>
+                {
+                    &interface.implements_interfaces
+                }
+                _ => {
+                    return Err(ConfigurationError::InvalidConfiguration {
+                        message: "bad configuration for traffic_shaping plugin",
+                        error: format!(
+                            "the rate limited field '{coordinate}' is not a field of an object or interface type of the supergraph schema"
+                        ),
+                    })
+                }
+            };
+            if conf.interval.is_zero() {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "bad configuration for traffic_shaping plugin",
+                    error: format!("the rate limit interval for field '{coordinate}' cannot be 0"),
+                });
+            }
+
+            // the field can be selected on the type itself, on the interfaces it implements, and
+            // on the implementations of an interface
+            let mut parents = vec![ty];
+            parents.extend(
+                interfaces
+                    .iter()
+                    .map(|interface| interface.name.as_str())
+                    .filter(|interface| {
+                        schema
+                            .get_interface(interface)
+                            .is_some_and(|interface| interface.fields.contains_key(field))
+                    }),
+            );
+            if let Some(implementers) = implementers.get(ty) {
+                parents.extend(implementers.objects.iter().map(|name| name.as_str()));
+                parents.extend(implementers.interfaces.iter().map(|name| name.as_str()));
+            }
+            for parent in parents {
+                triggers
+                    .entry(format!("{parent}.{field}"))
+                    .or_default()
+                    .push(coordinate.clone());
+            }
+            limiters.insert(coordinate.clone(), FieldRateLimiter::new(conf.clone()));
+        }
+
+        Ok(Self {
+            limiters: Arc::new(limiters),
+            triggers: Arc::new(triggers),
+        })
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.limiters.is_empty()
+    }
+
+    pub(crate) fn service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let limits = self.clone();
+        ServiceBuilder::new()
+            .checkpoint(move |request: supergraph::Request| {
+                let limited = limits.check(&request);
+                if limited.is_empty() {
+                    return Ok(ControlFlow::Continue(request));
+                }
+
+                let errors = limited
+                    .into_iter()
+                    .map(|coordinate| {
+                        graphql::Error::builder()
+                            .message(format!("Rate limit exceeded for field {coordinate}"))
+                            .extension_code("FIELD_RATE_LIMITED")
+                            .extension("field", coordinate)
+                            .build()
+                    })
+                    .collect::<Vec<_>>();
+
+                let response = supergraph::Response::error_builder()
+                    .errors(errors)
+                    .status_code(StatusCode::TOO_MANY_REQUESTS)
+                    .context(request.context)
+                    .build()?;
+                Ok(ControlFlow::Break(response))
+            })
+            .service(service)
+            .boxed()
+    }
+
+    /// Counts the request for each rate limited field selected by the operation, and returns
+    /// the fields whose limit was exceeded
+    fn check(&self, request: &supergraph::Request) -> Vec<String> {
+        let doc = request
+            .context
+            .extensions()
+            .with_lock(|lock| lock.get::<ParsedDocument>().cloned());
+        let doc = match doc {
+            Some(doc) => doc,
+            // the query analysis layer already returned an error
+            None => return Vec::new(),
+        };
+
+        let selected = selected_fields(
+            &doc.executable,
+            request.supergraph_request.body().operation_name.as_deref(),
+        );
+        self.acquire(&selected, |limiter| limiter.key(request), Instant::now())
+    }
+
+    /// Counts the request in the limits of the selected fields if none of them is exceeded,
+    /// otherwise returns the exceeded ones
+    fn acquire(
+        &self,
+        selected: &HashSet<String>,
+        key: impl Fn(&FieldRateLimiter) -> String,
+        now: Instant,
+    ) -> Vec<String> {
+        let mut coordinates: Vec<&String> = selected
+            .iter()
+            .filter_map(|coordinate| self.triggers.get(coordinate))
+            .flatten()
+            .collect();
+        // counters are always locked in the same order
+        coordinates.sort();
+        coordinates.dedup();
+
+        let mut locked: Vec<_> = coordinates
+            .into_iter()
+            .filter_map(|coordinate| {
+                let limiter = self.limiters.get(coordinate)?;
+                Some((coordinate, limiter, key(limiter), limiter.counters.lock()))
+            })
+            .collect();
+        let limited: Vec<String> = locked
+            .iter_mut()
+            .filter_map(|(coordinate, limiter, key, counters)| {
+                (!limiter.has_capacity(counters, key, now)).then(|| coordinate.to_string())
+            })
+            .collect();
+
+        if limited.is_empty() {
+            for (_, _, key, counters) in &mut locked {
+                FieldRateLimiter::count(counters, key);
+            }
+        }
+        for coordinate in &limited {
+            u64_counter!(
+                "apollo.router.operations.field_rate_limited",
+                "Number of requests rejected by field rate limits",
+                1u64,
+                "graphql.field.coordinate" = coordinate.clone()
+            );
+        }
+        limited
+    }
+}
+
+/// Returns the coordinates (`Type.field`) of all the fields selected by an operation
+pub(crate) fn selected_fields(
+    doc: &ExecutableDocument,
+    operation_name: Option<&str>,
+) -> HashSet<String> {
+    let mut fields = HashSet::new();
+    if let Ok(operation) = doc.operations.get(operation_name) {
+        let mut visited_fragments = HashSet::new();
+        collect_fields(
+            doc,
+            &operation.selection_set,
+            &mut visited_fragments,
+            &mut fields,
+        );
+    }
+    fields
+}
+
+fn collect_fields<'a>(
+    doc: &'a ExecutableDocument,
+    selection_set: &'a SelectionSet,
+    visited_fragments: &mut HashSet<&'a str>,
+    fields: &mut HashSet<String>,
+) {
+    for selection in &selection_set.selections {
+        match selection {
+            Selection::Field(field) => {
+                fields.insert(format!("{}.{}", selection_set.ty, field.name));
+                collect_fields(doc, &field.selection_set, visited_fragments, fields);
+            }
+            Selection::FragmentSpread(spread) => {
+                if visited_fragments.insert(spread.fragment_name.as_str()) {
+                    if let Some(fragment) = doc.fragments.get(&spread.fragment_name) {
+                        collect_fields(doc, &fragment.selection_set, visited_fragments, fields);
+                    }
+                }
+            }
+            Selection::InlineFragment(fragment) => {
+                collect_fields(doc, &fragment.selection_set, visited_fragments, fields);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct FieldRateLimiter {
+    conf: FieldRateLimitConf,
+    counters: Mutex<LruCache<String, Window>>,
+}
+
+/// Sliding window approximation: the count of the previous window is weighted by its overlap
+/// with a window ending now
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    previous: u64,
+    current: u64,
+}
+
+impl FieldRateLimiter {
+    fn new(conf: FieldRateLimitConf) -> Self {
+        Self {
+            conf,
+            counters: Mutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_COUNTERS).expect("MAX_COUNTERS is not 0"),
+            )),
+        }
+    }
+
+    fn key(&self, request: &supergraph::Request) -> String {
+        let key = match &self.conf.by {
+            RateLimitKey::All => None,
+            RateLimitKey::ClientName => {
+                request.context.get::<_, String>(CLIENT_NAME).ok().flatten()
+            }
+            RateLimitKey::Header(name) => request
+                .supergraph_request
+                .headers()
+                .get(name.as_str())
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string()),
+            RateLimitKey::Claim(claim) => request
+                .context
+                .get_json_value(APOLLO_AUTHENTICATION_JWT_CLAIMS)
+                .and_then(|claims| {
+                    claims.as_object().and_then(|claims| {
+                        claims
+                            .get(claim.as_str())
+                            .map(|value| match value.as_str() {
+                                Some(s) => s.to_string(),
+                                None => value.to_string(),
+                            })
+                    })
+                }),
+        };
+        // requests without a value for the key share the same counter
+        key.unwrap_or_default()
+    }
+
+    /// Whether the counter for `key` allows one more request, after moving its window to `now`
+    fn has_capacity(
+        &self,
+        counters: &mut LruCache<String, Window>,
+        key: &str,
+        now: Instant,
+    ) -> bool {
+        let interval = self.conf.interval;
+        let capacity = self.conf.capacity.get();
+
+        let window = counters.get_or_insert_mut(key.to_string(), || Window {
+            start: now,
+            previous: 0,
+            current: 0,
+        });
+
+        let elapsed = now.duration_since(window.start);
+        if elapsed >= interval {
+            window.previous = if elapsed < interval * 2 {
+                window.current
+            } else {
+                0
+            };
+            window.current = 0;
+            window.start =
+                now - Duration::from_nanos((elapsed.as_nanos() % interval.as_nanos()) as u64);
+        }
+
+        let overlap = 1.0 - now.duration_since(window.start).as_secs_f64() / interval.as_secs_f64();
+        let estimated = window.previous as f64 * overlap + window.current as f64;
+        estimated < capacity as f64
+    }
+
+    /// Counts a request for `key`, whose window was moved by `has_capacity`
+    fn count(counters: &mut LruCache<String, Window>, key: &str) {
+        if let Some(window) = counters.peek_mut(key) {
+            window.current += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+        type Query {
+            me: User
+            search(text: String): [Result]
+        }
+        interface Result { id: ID }
+        type User implements Result { id: ID name: String reports: [Report] }
+        type Report implements Result { id: ID exportReport: String }
+    "#;
+
+    #[test]
+    fn extract_selected_fields() {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        let doc = ExecutableDocument::parse_and_validate(
+            &schema,
+            r#"
+            query A { me { name ...Reports } }
+            query B { search(text: "a") { id ... on Report { exportReport } } }
+            fragment Reports on User { reports { exportReport } }
+            "#,
+            "query.graphql",
+        )
+        .unwrap();
+
+        let mut fields: Vec<_> = selected_fields(&doc, Some("A")).into_iter().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec![
+                "Query.me",
+                "Report.exportReport",
+                "User.name",
+                "User.reports"
+            ]
+        );
+
+        let mut fields: Vec<_> = selected_fields(&doc, Some("B")).into_iter().collect();
+        fields.sort();
+        assert_eq!(
+            fields,
+            vec!["Query.search", "Report.exportReport", "Result.id"]
+        );
+    }
+
+    fn limit(capacity: u64) -> FieldRateLimitConf {
+        FieldRateLimitConf {
+            capacity: NonZeroU64::new(capacity).unwrap(),
+            interval: Duration::from_secs(10),
+            by: RateLimitKey::All,
+        }
+    }
+
+    fn limits(config: &[(&str, u64)]) -> FieldRateLimits {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        let config = config
+            .iter()
+            .map(|(coordinate, capacity)| (coordinate.to_string(), limit(*capacity)))
+            .collect();
+        FieldRateLimits::new(&config, &schema).unwrap()
+    }
+
+    fn try_acquire(limiter: &FieldRateLimiter, key: &str, now: Instant) -> bool {
+        let mut counters = limiter.counters.lock();
+        let acquired = limiter.has_capacity(&mut counters, key, now);
+        if acquired {
+            FieldRateLimiter::count(&mut counters, key);
+        }
+        acquired
+    }
+
+    fn acquire(limits: &FieldRateLimits, selected: &[&str], now: Instant) -> Vec<String> {
+        let selected = selected.iter().map(|field| field.to_string()).collect();
+        limits.acquire(&selected, |_| String::new(), now)
+    }
+
+    #[test]
+    fn sliding_window() {
+        let limiter = FieldRateLimiter::new(FieldRateLimitConf {
+            capacity: NonZeroU64::new(2).unwrap(),
+            interval: Duration::from_secs(10),
+            by: RateLimitKey::ClientName,
+        });
+
+        let start = Instant::now();
+        assert!(try_acquire(&limiter, "a", start));
+        assert!(try_acquire(&limiter, "a", start));
+        assert!(!try_acquire(&limiter, "a", start));
+        // other clients have their own counter
+        assert!(try_acquire(&limiter, "b", start));
+
+        // the previous window still counts for most of the next one
+        assert!(try_acquire(&limiter, "a", start + Duration::from_secs(11)));
+        assert!(!try_acquire(&limiter, "a", start + Duration::from_secs(11)));
+        assert!(try_acquire(&limiter, "a", start + Duration::from_secs(16)));
+        assert!(try_acquire(&limiter, "a", start + Duration::from_secs(35)));
+    }
+
+    #[test]
+    fn bounded_counters() {
+        let limiter = FieldRateLimiter::new(limit(1));
+        let now = Instant::now();
+        for client in 0..MAX_COUNTERS + 10 {
+            assert!(try_acquire(&limiter, &client.to_string(), now));
+        }
+        assert_eq!(limiter.counters.lock().len(), MAX_COUNTERS);
+        // the least recently used counters were evicted
+        assert!(!try_acquire(&limiter, &(MAX_COUNTERS + 9).to_string(), now));
+        assert!(try_acquire(&limiter, "0", now));
+    }
+
+    #[test]
+    fn exceeded_limits_do_not_consume_other_limits() {
+        let limits = limits(&[("Query.me", 1), ("User.name", 2)]);
+        let now = Instant::now();
+        assert!(acquire(&limits, &["Query.me", "User.name"], now).is_empty());
+        assert_eq!(
+            acquire(&limits, &["Query.me", "User.name"], now),
+            vec!["Query.me"]
+        );
+        assert!(acquire(&limits, &["User.name"], now).is_empty());
+        assert_eq!(acquire(&limits, &["User.name"], now), vec!["User.name"]);
+    }
+
+    #[test]
+    fn abstract_parents() {
+        let limits = limits(&[("Report.id", 1), ("Result.id", 2)]);
+        let now = Instant::now();
+        // selected through the interface, the field counts for the limits of its implementations
+        assert!(acquire(&limits, &["Query.search", "Result.id"], now).is_empty());
+        assert_eq!(
+            acquire(&limits, &["Query.search", "Result.id"], now),
+            vec!["Report.id"]
+        );
+        // selected on an implementation, the field counts for the limit of the interface
+        assert!(acquire(&limits, &["User.id"], now).is_empty());
+        assert_eq!(acquire(&limits, &["User.id"], now), vec!["Result.id"]);
+    }
+
+    #[test]
+    fn invalid_coordinate() {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        for coordinate in ["search", "Query.unknown", "Unknown.id", "Result.name"] {
+            let config = [(coordinate.to_string(), limit(1))].into_iter().collect();
+            assert!(
+                FieldRateLimits::new(&config, &schema).is_err(),
+                "{coordinate}"
+            );
+        }
+    }
+}
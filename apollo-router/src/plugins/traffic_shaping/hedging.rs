@@ -0,0 +1,388 @@
+//! Request hedging.
+//!
+//! When a subgraph has not answered a query after a delay, a second identical request is sent,
+//! possibly to another endpoint, and the first response is used. The other request is cancelled.
+//! A budget caps the ratio of hedged requests, so that a slow subgraph does not get twice the
+//! load.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::retry::budget::Budget;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+use tower::ServiceExt;
+
+use super::retry::may_resend;
+use crate::error::ConfigurationError;
+use crate::query_planner::OperationKind;
+use crate::services::subgraph;
+
+/// Number of recent latencies the percentile is computed from
+const LATENCY_SAMPLES: usize = 500;
+/// Below this number of latencies, the fixed delay is used instead of the percentile
+const MIN_LATENCY_SAMPLES: usize = 20;
+/// The percentile is computed again after this many latencies
+const PERCENTILE_REFRESH: usize = 50;
+/// How long a request is counted in the hedging budget
+const BUDGET_TTL: Duration = Duration::from_secs(10);
+
+/// Request hedging configuration
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct HedgingConfig {
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_delay"
+    )]
+    #[schemars(with = "String", default = "default_delay")]
+    /// Sends a hedged request when the first one is not answered after this delay (default:
+    /// 100ms). With a `percentile`, this delay is used until enough latencies are observed
+    delay: Duration,
+    /// Sends a hedged request when the first one takes longer than this percentile of the recent
+    /// latencies of the subgraph, between 0 and 100
+    percentile: Option<f64>,
+    /// Ratio of requests that can be hedged, in addition to the hedged requests allowed by
+    /// `min_per_sec`. Must be between 0 and 1000 (default: 0.1)
+    #[serde(default = "default_hedge_percent")]
+    hedge_percent: f64,
+    /// Number of hedged requests per second allowed regardless of `hedge_percent` (default: 10)
+    #[serde(default = "default_min_per_sec")]
+    min_per_sec: u32,
+}
+
+impl HedgingConfig {
+    pub(crate) fn validate(&self) -> Result<(), ConfigurationError> {
+        if self
+            .percentile
+            .is_some_and(|percentile| !(percentile > 0.0 && percentile <= 100.0))
+        {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "bad hedging configuration for traffic_shaping plugin",
+                error: "percentile must be greater than 0 and less than or equal to 100"
+                    .to_string(),
+            });
+        }
+        if !(0.0..=1000.0).contains(&self.hedge_percent) {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "bad hedging configuration for traffic_shaping plugin",
+                error: "hedge_percent must be between 0 and 1000".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn default_delay() -> Duration {
+    Duration::from_millis(100)
+}
+
+fn default_hedge_percent() -> f64 {
+    0.1
+}
+
+fn default_min_per_sec() -> u32 {
+    10
+}
+
+/// Recent latencies of a subgraph
+#[derive(Default)]
+struct Latencies {
+    samples: VecDeque<Duration>,
+    since_refresh: usize,
+    percentile: Option<Duration>,
+}
+
+/// Decides when the requests to a subgraph are hedged
+struct Hedger {
+    subgraph_name: String,
+    delay: Duration,
+    percentile: Option<f64>,
+    budget: Budget,
+    latencies: Mutex<Latencies>,
+}
+
+impl Hedger {
+    fn new(subgraph_name: &str, config: &HedgingConfig) -> Self {
+        Self {
+            subgraph_name: subgraph_name.to_string(),
+            delay: config.delay,
+            percentile: config.percentile,
+            budget: Budget::new(BUDGET_TTL, config.min_per_sec, config.hedge_percent as f32),
+            latencies: Mutex::new(Latencies::default()),
+        }
+    }
+
+    /// How long to wait for the first response before hedging
+    fn delay(&self) -> Duration {
+        self.latencies
+            .lock()
+            .unwrap()
+            .percentile
+            .unwrap_or(self.delay)
+    }
+
+    fn record(&self, latency: Duration) {
+        let Some(percentile) = self.percentile else {
+            return;
+        };
+        let mut latencies = self.latencies.lock().unwrap();
+        if latencies.samples.len() == LATENCY_SAMPLES {
+            latencies.samples.pop_front();
+        }
+        latencies.samples.push_back(latency);
+        latencies.since_refresh += 1;
+        if latencies.samples.len() < MIN_LATENCY_SAMPLES
+            || (latencies.percentile.is_some() && latencies.since_refresh < PERCENTILE_REFRESH)
+        {
+            return;
+        }
+        let mut sorted: Vec<Duration> = latencies.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let index =
+            ((sorted.len() as f64 * percentile / 100.0).ceil() as usize).clamp(1, sorted.len()) - 1;
+        latencies.percentile = Some(sorted[index]);
+        latencies.since_refresh = 0;
+    }
+
+    fn count(&self, result: &'static str) {
+        u64_counter!(
+            "apollo.router.operations.subgraph.hedged_requests",
+            "Number of hedged subgraph requests",
+            1u64,
+            "subgraph.name" = self.subgraph_name.clone(),
+            "result" = result
+        );
+    }
+
+    async fn hedge<S>(
+        self: Arc<Self>,
+        first: S::Future,
+        service: S,
+        request: subgraph::Request,
+    ) -> Result<subgraph::Response, BoxError>
+    where
+        S: Service<subgraph::Request, Response = subgraph::Response, Error = BoxError>,
+    {
+        let start = Instant::now();
+        let mut first = std::pin::pin!(first);
+        tokio::select! {
+            response = &mut first => {
+                self.record(start.elapsed());
+                return response;
+            }
+            _ = tokio::time::sleep(self.delay()) => {}
+        }
+
+        if self.budget.withdraw().is_err() {
+            self.count("budget_exhausted");
+            let response = first.await;
+            self.record(start.elapsed());
+            return response;
+        }
+
+        // the other request is cancelled when its future is dropped, on return
+        let mut second = std::pin::pin!(service.oneshot(request));
+        let (response, hedge_won) = tokio::select! {
+            response = &mut first => match response {
+                // the hedged request may still succeed
+                Err(_) => (second.await, true),
+                Ok(_) => (response, false),
+            },
+            response = &mut second => match response {
+                Err(_) => (first.await, false),
+                Ok(_) => (response, true),
+            },
+        };
+        // when the hedged request wins, this is a lower bound of the latency of the first one
+        self.record(start.elapsed());
+        self.count(if hedge_won { "won" } else { "lost" });
+        response
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct HedgingLayer {
+    hedger: Arc<Hedger>,
+}
+
+impl HedgingLayer {
+    pub(crate) fn new(subgraph_name: &str, config: &HedgingConfig) -> Self {
+        Self {
+            hedger: Arc::new(Hedger::new(subgraph_name, config)),
+        }
+    }
+}
+
+impl<S> Layer<S> for HedgingLayer {
+    type Service = Hedging<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Hedging {
+            hedger: self.hedger.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct Hedging<S> {
+    hedger: Arc<Hedger>,
+    inner: S,
+}
+
+impl<S> Service<subgraph::Request> for Hedging<S>
+where
+    S: Service<subgraph::Request, Response = subgraph::Response, Error = BoxError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = subgraph::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<subgraph::Response, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: subgraph::Request) -> Self::Future {
+        // Only queries are hedged: like retries, hedging is not safe for mutations, and
+        // subscriptions are long lived
+        if !may_resend(&request, false) || request.operation_kind == OperationKind::Subscription {
+            return self.inner.call(request).boxed();
+        }
+        self.hedger.budget.deposit();
+
+        let hedged_request = request.clone();
+        // the hedged request goes through a clone of the service, which is made ready first
+        let service = self.inner.clone();
+        let first = self.inner.call(request);
+        self.hedger
+            .clone()
+            .hedge(first, service, hedged_request)
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use serde_json_bytes::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+
+    fn config(yaml: &str) -> HedgingConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    /// The first request is answered after `first_latency`, the next ones right away
+    fn subgraph(
+        first_latency: Duration,
+    ) -> (
+        Arc<AtomicUsize>,
+        impl Service<
+                subgraph::Request,
+                Response = subgraph::Response,
+                Error = BoxError,
+                Future = BoxFuture<'static, Result<subgraph::Response, BoxError>>,
+            > + Clone,
+    ) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let service = tower::service_fn({
+            let calls = calls.clone();
+            move |_request: subgraph::Request| {
+                let call = calls.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if call == 0 {
+                        tokio::time::sleep(first_latency).await;
+                    }
+                    Ok(subgraph::Response::fake_builder()
+                        .data(json!({ "call": call }))
+                        .build())
+                }
+                .boxed()
+            }
+        });
+        (calls, service)
+    }
+
+    fn request(operation_kind: OperationKind) -> subgraph::Request {
+        subgraph::Request::fake_builder()
+            .operation_kind(operation_kind)
+            .build()
+    }
+
+    #[tokio::test]
+    async fn hedges_slow_queries() {
+        async {
+            let layer = HedgingLayer::new("products", &config("delay: 10ms"));
+
+            let (calls, service) = subgraph(Duration::from_secs(5));
+            let response = layer
+                .layer(service)
+                .oneshot(request(OperationKind::Query))
+                .await
+                .unwrap();
+            assert_eq!(response.response.body().data, Some(json!({ "call": 1 })));
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+            assert_counter!(
+                "apollo.router.operations.subgraph.hedged_requests",
+                1,
+                "subgraph.name" = "products",
+                "result" = "won"
+            );
+
+            let (calls, service) = subgraph(Duration::ZERO);
+            let response = layer
+                .layer(service)
+                .oneshot(request(OperationKind::Query))
+                .await
+                .unwrap();
+            assert_eq!(response.response.body().data, Some(json!({ "call": 0 })));
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn does_not_hedge_mutations() {
+        let layer = HedgingLayer::new("products", &config("delay: 10ms"));
+        let (calls, service) = subgraph(Duration::from_millis(50));
+        let response = layer
+            .layer(service)
+            .oneshot(request(OperationKind::Mutation))
+            .await
+            .unwrap();
+        assert_eq!(response.response.body().data, Some(json!({ "call": 0 })));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn follows_the_latency_percentile() {
+        let hedger = Hedger::new("products", &config("percentile: 90"));
+        assert_eq!(hedger.delay(), Duration::from_millis(100));
+        for latency in 1..=20 {
+            hedger.record(Duration::from_millis(latency));
+        }
+        assert_eq!(hedger.delay(), Duration::from_millis(18));
+
+        assert!(config("percentile: 0").validate().is_err());
+        assert!(config("hedge_percent: -1").validate().is_err());
+    }
+}
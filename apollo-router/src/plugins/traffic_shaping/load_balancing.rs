@@ -0,0 +1,634 @@
+//! Client-side load balancing across several endpoints of a subgraph.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::sync::Weak;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use http::header::HOST;
+use http::HeaderValue;
+use http::Uri;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::error::ConfigurationError;
+use crate::plugins::telemetry::config_new::selectors::SubgraphSelector;
+use crate::plugins::telemetry::config_new::Selector;
+use crate::services::subgraph;
+
+/// Load balancing across several endpoints of a subgraph
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LoadBalancingConfig {
+    /// Subgraph endpoints, used instead of the routing URL from the supergraph schema
+    #[serde(default)]
+    endpoints: Vec<String>,
+    /// Discovers endpoints by resolving a DNS name
+    dns: Option<DnsDiscovery>,
+    /// How requests are spread across endpoints (default: round_robin)
+    #[serde(default)]
+    strategy: LoadBalancingStrategy,
+    /// Key of the request used by the `consistent_hash` strategy
+    hash_key: Option<SubgraphSelector>,
+    /// Temporarily stops sending requests to endpoints returning consecutive errors
+    outlier_detection: Option<OutlierDetection>,
+}
+
+/// DNS based endpoint discovery
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DnsDiscovery {
+    /// URL of the subgraph. Its host is resolved, and each address replaces it to make an endpoint.
+    /// Requests keep the host of the URL in their `Host` header. Only `http` URLs are supported,
+    /// since the TLS certificate of an endpoint cannot be verified against its address
+    url: String,
+    /// Resolves the SRV records of the host instead of its A and AAAA records. The port of each
+    /// SRV record replaces the port of the URL
+    #[serde(default)]
+    srv: bool,
+    #[serde(
+        deserialize_with = "humantime_serde::deserialize",
+        default = "default_refresh_interval"
+    )]
+    #[schemars(with = "String", default = "default_refresh_interval")]
+    /// How often the host is resolved again (default: 30s)
+    refresh_interval: Duration,
+}
+
+fn default_refresh_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+/// Load balancing strategy
+#[derive(PartialEq, Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum LoadBalancingStrategy {
+    /// Each endpoint in turn
+    #[default]
+    RoundRobin,
+    /// The endpoint with the fewest requests in flight
+    LeastOutstandingRequests,
+    /// Requests with the same `hash_key` go to the same endpoint
+    ConsistentHash,
+}
+
+/// Outlier detection configuration
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct OutlierDetection {
+    /// Number of consecutive errors after which an endpoint is ejected (default: 5)
+    consecutive_errors: u32,
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    /// How long an ejected endpoint receives no requests (default: 30s)
+    ejection_duration: Duration,
+    /// Maximum percentage of endpoints ejected at the same time (default: 50)
+    max_ejection_percent: u8,
+}
+
+impl Default for OutlierDetection {
+    fn default() -> Self {
+        Self {
+            consecutive_errors: 5,
+            ejection_duration: Duration::from_secs(30),
+            max_ejection_percent: 50,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    uri: Uri,
+    outstanding: AtomicUsize,
+    consecutive_errors: AtomicU32,
+    ejected_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    fn new(uri: Uri) -> Self {
+        Self {
+            uri,
+            outstanding: AtomicUsize::new(0),
+            consecutive_errors: AtomicU32::new(0),
+            ejected_until: Mutex::new(None),
+        }
+    }
+
+    fn is_ejected(&self, now: Instant) -> bool {
+        self.ejected_until
+            .lock()
+            .unwrap()
+            .is_some_and(|until| until > now)
+    }
+}
+
+/// Selects the endpoint of each request to a subgraph
+#[derive(Debug)]
+pub(crate) struct LoadBalancer {
+    subgraph_name: String,
+    endpoints: RwLock<Arc<Vec<Arc<Endpoint>>>>,
+    strategy: LoadBalancingStrategy,
+    hash_key: Option<SubgraphSelector>,
+    outlier_detection: Option<OutlierDetection>,
+    next: AtomicUsize,
+    /// `Host` header of the requests to discovered endpoints, whose URL has an address as host
+    host_header: Option<HeaderValue>,
+}
+
+impl LoadBalancer {
+    pub(crate) async fn new(
+        subgraph_name: &str,
+        config: &LoadBalancingConfig,
+    ) -> Result<Arc<Self>, ConfigurationError> {
+        let invalid = |error: String| ConfigurationError::InvalidConfiguration {
+            message: "bad load balancing configuration for traffic_shaping plugin",
+            error: format!("subgraph {subgraph_name}: {error}"),
+        };
+        if config.endpoints.is_empty() == config.dns.is_none() {
+            return Err(invalid(
+                "exactly one of `endpoints` and `dns` must be set".to_string(),
+            ));
+        }
+        if config.strategy == LoadBalancingStrategy::ConsistentHash && config.hash_key.is_none() {
+            return Err(invalid(
+                "the `consistent_hash` strategy requires a `hash_key`".to_string(),
+            ));
+        }
+        let endpoints = config
+            .endpoints
+            .iter()
+            .map(|url| {
+                Uri::from_str(url)
+                    .map(|uri| Arc::new(Endpoint::new(uri)))
+                    .map_err(|e| invalid(format!("invalid endpoint {url}: {e}")))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let template = config
+            .dns
+            .as_ref()
+            .map(|dns| {
+                let template = Uri::from_str(&dns.url)
+                    .map_err(|e| invalid(format!("invalid DNS discovery URL {}: {e}", dns.url)))?;
+                if template.scheme() != Some(&http::uri::Scheme::HTTP) {
+                    return Err(invalid(format!(
+                        "the DNS discovery URL {} must use the http scheme",
+                        dns.url
+                    )));
+                }
+                if dns.refresh_interval.is_zero() {
+                    return Err(invalid(
+                        "the DNS `refresh_interval` must be greater than 0".to_string(),
+                    ));
+                }
+                Ok(template)
+            })
+            .transpose()?;
+        let host_header = template
+            .as_ref()
+            .and_then(|template| template.authority())
+            .map(|authority| HeaderValue::from_str(authority.as_str()))
+            .transpose()
+            .map_err(|e| invalid(format!("invalid DNS discovery URL: {e}")))?;
+
+        let balancer = Arc::new(Self {
+            subgraph_name: subgraph_name.to_string(),
+            endpoints: RwLock::new(Arc::new(endpoints)),
+            strategy: config.strategy,
+            hash_key: config.hash_key.clone(),
+            outlier_detection: config.outlier_detection.clone(),
+            next: AtomicUsize::new(0),
+            host_header,
+        });
+
+        if let (Some(dns), Some(template)) = (&config.dns, template) {
+            let resolver = TokioAsyncResolver::tokio_from_system_conf()
+                .map_err(|e| invalid(format!("cannot create DNS resolver: {e}")))?;
+            // until the first resolution succeeds, requests use the routing URL from the schema
+            balancer.refresh(&resolver, &template, dns.srv).await;
+            tokio::spawn(refresh_periodically(
+                Arc::downgrade(&balancer),
+                resolver,
+                template,
+                dns.clone(),
+            ));
+        }
+
+        Ok(balancer)
+    }
+
+    async fn refresh(&self, resolver: &TokioAsyncResolver, template: &Uri, srv: bool) {
+        match resolve(resolver, template, srv).await {
+            Ok(uris) if uris.is_empty() => {
+                tracing::warn!(
+                    subgraph = %self.subgraph_name,
+                    "DNS discovery of {template} returned no addresses, keeping the current endpoints"
+                );
+            }
+            Ok(uris) => self.set_endpoints(uris),
+            Err(error) => {
+                tracing::warn!(
+                    subgraph = %self.subgraph_name,
+                    "DNS discovery of {template} failed, keeping the current endpoints: {error}"
+                );
+            }
+        }
+    }
+
+    /// Replaces the endpoints, keeping the state of the ones that are still present
+    fn set_endpoints(&self, uris: Vec<Uri>) {
+        let mut endpoints = self.endpoints.write().unwrap();
+        let updated = uris
+            .into_iter()
+            .map(|uri| {
+                endpoints
+                    .iter()
+                    .find(|endpoint| endpoint.uri == uri)
+                    .cloned()
+                    .unwrap_or_else(|| Arc::new(Endpoint::new(uri)))
+            })
+            .collect();
+        *endpoints = Arc::new(updated);
+    }
+
+    fn select(&self, request: &subgraph::Request) -> Option<Arc<Endpoint>> {
+        let endpoints = self.endpoints.read().unwrap().clone();
+        let now = Instant::now();
+        let mut available: Vec<&Arc<Endpoint>> = endpoints
+            .iter()
+            .filter(|endpoint| !endpoint.is_ejected(now))
+            .collect();
+        // if every endpoint is ejected, we'd rather try one of them than fail
+        if available.is_empty() {
+            available = endpoints.iter().collect();
+        }
+        if available.is_empty() {
+            return None;
+        }
+
+        let round_robin = |available: &[&Arc<Endpoint>]| {
+            available[self.next.fetch_add(1, Ordering::Relaxed) % available.len()].clone()
+        };
+        let endpoint = match self.strategy {
+            LoadBalancingStrategy::RoundRobin => round_robin(&available),
+            LoadBalancingStrategy::LeastOutstandingRequests => {
+                // start from a different endpoint each time to spread ties
+                let start = self.next.fetch_add(1, Ordering::Relaxed);
+                (0..available.len())
+                    .map(|i| available[(start + i) % available.len()])
+                    .min_by_key(|endpoint| endpoint.outstanding.load(Ordering::Relaxed))
+                    .cloned()?
+            }
+            LoadBalancingStrategy::ConsistentHash => {
+                match self
+                    .hash_key
+                    .as_ref()
+                    .and_then(|selector| selector.on_request(request))
+                {
+                    // rendezvous hashing: only the requests of an ejected endpoint move
+                    Some(key) => available
+                        .iter()
+                        .copied()
+                        .max_by_key(|endpoint| {
+                            let mut hasher = DefaultHasher::new();
+                            key.as_str().hash(&mut hasher);
+                            endpoint.uri.hash(&mut hasher);
+                            hasher.finish()
+                        })
+                        .cloned()?,
+                    None => round_robin(&available),
+                }
+            }
+        };
+        Some(endpoint)
+    }
+
+    fn record(&self, endpoint: &Endpoint, is_error: bool) {
+        let Some(outlier_detection) = &self.outlier_detection else {
+            return;
+        };
+        if !is_error {
+            endpoint.consecutive_errors.store(0, Ordering::Relaxed);
+            return;
+        }
+        let errors = endpoint.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1;
+        if errors < outlier_detection.consecutive_errors {
+            return;
+        }
+
+        let endpoints = self.endpoints.read().unwrap().clone();
+        let now = Instant::now();
+        let ejected = endpoints.iter().filter(|e| e.is_ejected(now)).count();
+        if (ejected + 1) * 100 > endpoints.len() * outlier_detection.max_ejection_percent as usize {
+            return;
+        }
+        endpoint.consecutive_errors.store(0, Ordering::Relaxed);
+        *endpoint.ejected_until.lock().unwrap() = Some(now + outlier_detection.ejection_duration);
+        tracing::warn!(
+            subgraph = %self.subgraph_name,
+            "ejecting endpoint {} after {errors} consecutive errors",
+            endpoint.uri
+        );
+    }
+}
+
+async fn refresh_periodically(
+    balancer: Weak<LoadBalancer>,
+    resolver: TokioAsyncResolver,
+    template: Uri,
+    dns: DnsDiscovery,
+) {
+    let start = tokio::time::Instant::now() + dns.refresh_interval;
+    let mut interval = tokio::time::interval_at(start, dns.refresh_interval);
+    loop {
+        interval.tick().await;
+        // the balancer is dropped with the traffic shaping plugin, on reloads
+        let Some(balancer) = balancer.upgrade() else {
+            break;
+        };
+        balancer.refresh(&resolver, &template, dns.srv).await;
+    }
+}
+
+async fn resolve(
+    resolver: &TokioAsyncResolver,
+    template: &Uri,
+    srv: bool,
+) -> Result<Vec<Uri>, BoxError> {
+    let host = template.host().ok_or("the URL has no host")?;
+    let mut addresses: Vec<(IpAddr, Option<u16>)> = Vec::new();
+    if srv {
+        for record in resolver.srv_lookup(host).await?.iter() {
+            let target = record.target().to_utf8();
+            // the other targets can still be used
+            match resolver.lookup_ip(target.as_str()).await {
+                Ok(ips) => {
+                    for ip in ips.iter() {
+                        addresses.push((ip, Some(record.port())));
+                    }
+                }
+                Err(error) => {
+                    tracing::warn!("cannot resolve the SRV target {target} of {host}: {error}");
+                }
+            }
+        }
+    } else {
+        for ip in resolver.lookup_ip(host).await?.iter() {
+            addresses.push((ip, template.port_u16()));
+        }
+    }
+
+    addresses
+        .into_iter()
+        .map(|(ip, port)| {
+            let host = match ip {
+                IpAddr::V4(ip) => ip.to_string(),
+                IpAddr::V6(ip) => format!("[{ip}]"),
+            };
+            let authority = match port {
+                Some(port) => format!("{host}:{port}"),
+                None => host,
+            };
+            let mut parts = template.clone().into_parts();
+            parts.authority = Some(authority.parse()?);
+            Ok(Uri::from_parts(parts)?)
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub(crate) struct LoadBalancingLayer {
+    balancer: Arc<LoadBalancer>,
+}
+
+impl LoadBalancingLayer {
+    pub(crate) fn new(balancer: Arc<LoadBalancer>) -> Self {
+        Self { balancer }
+    }
+}
+
+impl<S> Layer<S> for LoadBalancingLayer {
+    type Service = LoadBalancing<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadBalancing {
+            balancer: self.balancer.clone(),
+            inner,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct LoadBalancing<S> {
+    balancer: Arc<LoadBalancer>,
+    inner: S,
+}
+
+/// Keeps track of the requests in flight to an endpoint, even if they are cancelled
+struct Outstanding(Arc<Endpoint>);
+
+impl Outstanding {
+    fn new(endpoint: Arc<Endpoint>) -> Self {
+        endpoint.outstanding.fetch_add(1, Ordering::Relaxed);
+        Self(endpoint)
+    }
+}
+
+impl Drop for Outstanding {
+    fn drop(&mut self) {
+        self.0.outstanding.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<S> Service<subgraph::Request> for LoadBalancing<S>
+where
+    S: Service<subgraph::Request, Response = subgraph::Response, Error = BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = subgraph::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<subgraph::Response, BoxError>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: subgraph::Request) -> Self::Future {
+        let Some(endpoint) = self.balancer.select(&request) else {
+            return self.inner.call(request).boxed();
+        };
+        *request.subgraph_request.uri_mut() = endpoint.uri.clone();
+        if let Some(host_header) = &self.balancer.host_header {
+            request
+                .subgraph_request
+                .headers_mut()
+                .insert(HOST, host_header.clone());
+        }
+        let outstanding = Outstanding::new(endpoint);
+        let balancer = self.balancer.clone();
+        let future = self.inner.call(request);
+
+        async move {
+            let response = future.await;
+            let is_error = match &response {
+                Ok(response) => response.response.status().is_server_error(),
+                Err(_) => true,
+            };
+            balancer.record(&outstanding.0, is_error);
+            response
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::plugin::test::MockSubgraphService;
+
+    fn config(yaml: &str) -> LoadBalancingConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    async fn send(
+        balancer: &Arc<LoadBalancer>,
+        status: http::StatusCode,
+        header: Option<&str>,
+    ) -> Uri {
+        let mut mock = MockSubgraphService::new();
+        mock.expect_call().times(1).returning(move |request| {
+            let uri = request.subgraph_request.uri().to_string();
+            Ok(subgraph::Response::fake_builder()
+                .status_code(status)
+                .data(json!({ "uri": uri }))
+                .build())
+        });
+        let mut request = subgraph::Request::fake_builder().build();
+        if let Some(header) = header {
+            request
+                .subgraph_request
+                .headers_mut()
+                .insert("x-user", header.parse().unwrap());
+        }
+        let response = LoadBalancingLayer::new(balancer.clone())
+            .layer(mock)
+            .oneshot(request)
+            .await
+            .unwrap();
+        let uri = response.response.body().data.as_ref().unwrap()["uri"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        Uri::from_str(&uri).unwrap()
+    }
+
+    #[tokio::test]
+    async fn round_robin_and_outlier_ejection() {
+        let balancer = LoadBalancer::new(
+            "products",
+            &config(
+                r#"
+                endpoints: ["http://a:4001/graphql", "http://b:4001/graphql"]
+                outlier_detection:
+                  consecutive_errors: 2
+                "#,
+            ),
+        )
+        .await
+        .unwrap();
+
+        let mut hosts = Vec::new();
+        for _ in 0..4 {
+            let uri = send(&balancer, http::StatusCode::OK, None).await;
+            hosts.push(uri.host().unwrap().to_string());
+        }
+        assert_eq!(hosts, ["a", "b", "a", "b"]);
+
+        // two consecutive errors on `a` eject it
+        send(&balancer, http::StatusCode::BAD_GATEWAY, None).await;
+        send(&balancer, http::StatusCode::OK, None).await;
+        send(&balancer, http::StatusCode::BAD_GATEWAY, None).await;
+        for _ in 0..3 {
+            let uri = send(&balancer, http::StatusCode::OK, None).await;
+            assert_eq!(uri.host(), Some("b"));
+        }
+    }
+
+    #[tokio::test]
+    async fn consistent_hash() {
+        let balancer = LoadBalancer::new(
+            "products",
+            &config(
+                r#"
+                endpoints: ["http://a:4001/graphql", "http://b:4001/graphql", "http://c:4001/graphql"]
+                strategy: consistent_hash
+                hash_key:
+                  subgraph_request_header: x-user
+                "#,
+            ),
+        )
+        .await
+        .unwrap();
+
+        for user in ["1", "2", "3", "4"] {
+            let first = send(&balancer, http::StatusCode::OK, Some(user)).await;
+            for _ in 0..3 {
+                assert_eq!(
+                    send(&balancer, http::StatusCode::OK, Some(user)).await,
+                    first
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_configuration() {
+        assert!(
+            LoadBalancer::new("products", &config("strategy: round_robin"))
+                .await
+                .is_err()
+        );
+        assert!(LoadBalancer::new(
+            "products",
+            &config(
+                r#"
+                endpoints: ["http://a:4001/graphql"]
+                strategy: consistent_hash
+                "#,
+            ),
+        )
+        .await
+        .is_err());
+        assert!(LoadBalancer::new(
+            "products",
+            &config("dns: { url: https://products.internal/graphql }"),
+        )
+        .await
+        .is_err());
+        assert!(LoadBalancer::new(
+            "products",
+            &config("dns: { url: http://products.internal/graphql, refresh_interval: 0s }"),
+        )
+        .await
+        .is_err());
+    }
+}
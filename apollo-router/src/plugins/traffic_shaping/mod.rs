@@ -5,20 +5,30 @@
 //! * Timeout
 //! * Compression
 //! * Rate limiting
+//! * Field rate limiting
+//! * Load balancing
+//! * Adaptive concurrency limiting
+//! * Request hedging
 //!
+mod adaptive_concurrency;
 mod deduplication;
+pub(crate) mod field_rate_limit;
+mod hedging;
+mod load_balancing;
 pub(crate) mod rate;
 mod retry;
 pub(crate) mod timeout;
 
 use std::collections::HashMap;
 use std::num::NonZeroU64;
+use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
 
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use http::header::CONTENT_ENCODING;
+use http::header::RETRY_AFTER;
 use http::HeaderValue;
 use http::StatusCode;
 use schemars::JsonSchema;
@@ -29,7 +39,17 @@ use tower::Service;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 
+use self::adaptive_concurrency::AdaptiveConcurrencyConfig;
+use self::adaptive_concurrency::AdaptiveConcurrencyLayer;
+use self::adaptive_concurrency::Overloaded;
 use self::deduplication::QueryDeduplicationLayer;
+use self::field_rate_limit::FieldRateLimitConf;
+use self::field_rate_limit::FieldRateLimits;
+use self::hedging::HedgingConfig;
+use self::hedging::HedgingLayer;
+use self::load_balancing::LoadBalancer;
+use self::load_balancing::LoadBalancingConfig;
+use self::load_balancing::LoadBalancingLayer;
 use self::rate::RateLimitLayer;
 use self::rate::RateLimited;
 pub(crate) use self::retry::RetryPolicy;
@@ -40,6 +60,8 @@ use crate::graphql;
 use crate::layers::ServiceBuilderExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
+use crate::plugins::telemetry::config_new::selectors::SubgraphSelector;
+use crate::plugins::telemetry::config_new::selectors::SupergraphSelector;
 use crate::register_plugin;
 use crate::services::http::service::Compression;
 use crate::services::subgraph;
@@ -72,6 +94,10 @@ struct Shaping {
     experimental_retry: Option<RetryConfig>,
     /// Enable HTTP2 for subgraphs
     experimental_http2: Option<Http2Config>,
+    /// Adaptive concurrency limit, adjusted from the latency of the subgraph
+    experimental_adaptive_concurrency: Option<AdaptiveConcurrencyConfig<SubgraphSelector>>,
+    /// Sends a second request to slow subgraphs for queries, and uses the first response
+    experimental_hedging: Option<HedgingConfig>,
 }
 
 #[derive(PartialEq, Default, Debug, Clone, Deserialize, JsonSchema)]
@@ -109,6 +135,16 @@ impl Merge for Shaping {
                     .as_ref()
                     .or(fallback.experimental_http2.as_ref())
                     .cloned(),
+                experimental_adaptive_concurrency: self
+                    .experimental_adaptive_concurrency
+                    .as_ref()
+                    .or(fallback.experimental_adaptive_concurrency.as_ref())
+                    .cloned(),
+                experimental_hedging: self
+                    .experimental_hedging
+                    .as_ref()
+                    .or(fallback.experimental_hedging.as_ref())
+                    .cloned(),
             },
         }
     }
@@ -156,6 +192,9 @@ impl Merge for RetryConfig {
 struct SubgraphShaping {
     #[serde(flatten)]
     shaping: Shaping,
+    /// Load balancing across several endpoints of the subgraph
+    //  *experimental feature*: only available for specific subgraphs, not in `all`
+    experimental_load_balancing: Option<LoadBalancingConfig>,
 }
 
 impl Merge for SubgraphShaping {
@@ -164,6 +203,7 @@ impl Merge for SubgraphShaping {
             None => self.clone(),
             Some(fallback) => SubgraphShaping {
                 shaping: self.shaping.merge(Some(&fallback.shaping)),
+                experimental_load_balancing: self.experimental_load_balancing.clone(),
             },
         }
     }
@@ -178,6 +218,8 @@ struct RouterShaping {
     #[schemars(with = "String", default)]
     /// Enable timeout for incoming requests
     timeout: Option<Duration>,
+    /// Adaptive concurrency limit, adjusted from the latency of client requests
+    experimental_adaptive_concurrency: Option<AdaptiveConcurrencyConfig<SupergraphSelector>>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
@@ -192,6 +234,8 @@ pub(crate) struct Config {
     all: Option<SubgraphShaping>,
     /// Applied on specific subgraphs
     subgraphs: HashMap<String, SubgraphShaping>,
+    /// Rate limits applied to specific fields, keyed by schema coordinate (`Type.field`)
+    fields: HashMap<String, FieldRateLimitConf>,
     /// DEPRECATED, now always enabled: Enable variable deduplication optimization when sending requests to subgraphs (https://github.com/apollographql/router/issues/87)
     deduplicate_variables: Option<bool>,
 }
@@ -225,6 +269,12 @@ pub(crate) struct TrafficShaping {
     config: Config,
     rate_limit_router: Option<RateLimitLayer>,
     rate_limit_subgraphs: Mutex<HashMap<String, RateLimitLayer>>,
+    adaptive_concurrency_router: Option<AdaptiveConcurrencyLayer<SupergraphSelector>>,
+    adaptive_concurrency_subgraphs:
+        Mutex<HashMap<String, AdaptiveConcurrencyLayer<SubgraphSelector>>>,
+    hedging_subgraphs: Mutex<HashMap<String, HedgingLayer>>,
+    field_rate_limits: FieldRateLimits,
+    load_balancers: HashMap<String, Arc<LoadBalancer>>,
 }
 
 #[async_trait::async_trait]
@@ -255,14 +305,68 @@ impl Plugin for TrafficShaping {
             })
             .transpose()?;
 
+        let adaptive_concurrency_router = init
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.experimental_adaptive_concurrency.as_ref())
+            .map(|config| {
+                config
+                    .validate()
+                    .map(|()| AdaptiveConcurrencyLayer::new(config))
+            })
+            .transpose()?;
+        for subgraph in init.config.all.iter().chain(init.config.subgraphs.values()) {
+            if let Some(config) = &subgraph.shaping.experimental_adaptive_concurrency {
+                config.validate()?;
+            }
+            if let Some(config) = &subgraph.shaping.experimental_hedging {
+                config.validate()?;
+            }
+        }
+
+        let field_rate_limits = FieldRateLimits::new(&init.config.fields, &init.supergraph_schema)?;
+
+        if init
+            .config
+            .all
+            .as_ref()
+            .is_some_and(|all| all.experimental_load_balancing.is_some())
+        {
+            return Err(ConfigurationError::InvalidConfiguration {
+                message: "bad configuration for traffic_shaping plugin",
+                error: "load balancing can only be configured for specific subgraphs".to_string(),
+            }
+            .into());
+        }
+        let mut load_balancers = HashMap::new();
+        for (name, subgraph) in &init.config.subgraphs {
+            if let Some(load_balancing) = &subgraph.experimental_load_balancing {
+                load_balancers.insert(name.clone(), LoadBalancer::new(name, load_balancing).await?);
+            }
+        }
+
         {
             Ok(Self {
                 config: init.config,
                 rate_limit_router,
                 rate_limit_subgraphs: Mutex::new(HashMap::new()),
+                adaptive_concurrency_router,
+                adaptive_concurrency_subgraphs: Mutex::new(HashMap::new()),
+                hedging_subgraphs: Mutex::new(HashMap::new()),
+                field_rate_limits,
+                load_balancers,
             })
         }
     }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if self.field_rate_limits.is_empty() {
+            service
+        } else {
+            self.field_rate_limits.service(service)
+        }
+    }
 }
 
 pub(crate) type TrafficShapingSubgraphFuture<S> = Either<
@@ -302,11 +406,19 @@ impl TrafficShaping {
             + 'static,
         <S as Service<supergraph::Request>>::Future: std::marker::Send,
     {
+        let retry_after = self
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.experimental_adaptive_concurrency.as_ref())
+            .map(|config| config.retry_after())
+            .unwrap_or_default();
+
         ServiceBuilder::new()
             .map_future_with_request_data(
                 |req: &supergraph::Request| req.context.clone(),
                 move |ctx, future| {
-                    async {
+                    async move {
                         let response: Result<supergraph::Response, BoxError> = future.await;
                         match response {
                             Err(error) if error.is::<Elapsed>() => {
@@ -323,12 +435,24 @@ impl TrafficShaping {
                                     .context(ctx)
                                     .build()
                             }
+                            Err(error) if error.is::<Overloaded>() => {
+                                supergraph::Response::error_builder()
+                                    .status_code(StatusCode::SERVICE_UNAVAILABLE)
+                                    .header(
+                                        RETRY_AFTER,
+                                        retry_after.as_secs_f64().ceil().to_string(),
+                                    )
+                                    .error::<graphql::Error>(Overloaded::new().into())
+                                    .context(ctx)
+                                    .build()
+                            }
                             _ => response,
                         }
                     }
                     .boxed()
                 },
             )
+            .option_layer(self.adaptive_concurrency_router.clone())
             .layer(TimeoutLayer::new(
                 self.config
                     .router
@@ -393,6 +517,33 @@ impl TrafficShaping {
                 tower::retry::RetryLayer::new(retry_policy)
             });
 
+            let adaptive_concurrency = config
+                .shaping
+                .experimental_adaptive_concurrency
+                .as_ref()
+                .map(|config| {
+                    self.adaptive_concurrency_subgraphs
+                        .lock()
+                        .unwrap()
+                        .entry(name.to_string())
+                        .or_insert_with(|| AdaptiveConcurrencyLayer::new(config))
+                        .clone()
+                });
+
+            let hedging = config.shaping.experimental_hedging.as_ref().map(|config| {
+                self.hedging_subgraphs
+                    .lock()
+                    .unwrap()
+                    .entry(name.to_string())
+                    .or_insert_with(|| HedgingLayer::new(name, config))
+                    .clone()
+            });
+
+            let load_balancing = self
+                .load_balancers
+                .get(name)
+                .map(|balancer| LoadBalancingLayer::new(balancer.clone()));
+
             Either::A(ServiceBuilder::new()
 
                 .option_layer(config.shaping.deduplicate_query.unwrap_or_default().then(
@@ -418,18 +569,28 @@ impl TrafficShaping {
                                             .context(ctx)
                                             .build()
                                     }
+                                    Err(error) if error.is::<Overloaded>() => {
+                                        subgraph::Response::error_builder()
+                                            .status_code(StatusCode::SERVICE_UNAVAILABLE)
+                                            .error::<graphql::Error>(Overloaded::new().into())
+                                            .context(ctx)
+                                            .build()
+                                    }
                                     _ => response,
                                 }
                             }.boxed()
                         },
                     )
+                    .option_layer(adaptive_concurrency)
                     .layer(TimeoutLayer::new(
                         config.shaping
                         .timeout
                         .unwrap_or(DEFAULT_TIMEOUT),
                     ))
                     .option_layer(retry)
+                    .option_layer(hedging)
                     .option_layer(rate_limit)
+                    .option_layer(load_balancing)
                 .service(service)
                 .map_request(move |mut req: SubgraphRequest| {
                     if let Some(compression) = config.shaping.compression {
@@ -675,6 +836,108 @@ mod test {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn it_sheds_router_requests_over_the_concurrency_limit() {
+        let config = serde_yaml::from_str::<serde_json::Value>(
+            r#"
+        router:
+            experimental_adaptive_concurrency:
+                initial_limit: 1
+                max_limit: 1
+                retry_after: 5s
+        "#,
+        )
+        .unwrap();
+
+        let plugin = get_traffic_shaping_plugin(&config).await;
+        let service = plugin
+            .as_any()
+            .downcast_ref::<TrafficShaping>()
+            .unwrap()
+            .supergraph_service_internal(tower::service_fn(|_: SupergraphRequest| async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Ok(SupergraphResponse::fake_builder()
+                    .data(json!({ "test": 1234_u32 }))
+                    .build()
+                    .unwrap())
+            }));
+
+        let (first, second) = tokio::join!(
+            service
+                .clone()
+                .oneshot(SupergraphRequest::fake_builder().build().unwrap()),
+            service
+                .clone()
+                .oneshot(SupergraphRequest::fake_builder().build().unwrap()),
+        );
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_eq!(first.response.status(), StatusCode::OK);
+        assert_eq!(second.response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(second.response.headers().get(RETRY_AFTER).unwrap(), "5");
+    }
+
+    #[tokio::test]
+    async fn it_balances_subgraph_requests_across_endpoints() {
+        let config = serde_yaml::from_str::<serde_json::Value>(
+            r#"
+        subgraphs:
+            test:
+                experimental_load_balancing:
+                    endpoints:
+                        - http://products-1:4001/graphql
+                        - http://products-2:4001/graphql
+        "#,
+        )
+        .unwrap();
+
+        let plugin = get_traffic_shaping_plugin(&config).await;
+        let uris = Arc::new(Mutex::new(Vec::new()));
+        let seen = uris.clone();
+        let test_service =
+            MockSubgraph::new(HashMap::new()).map_request(move |req: SubgraphRequest| {
+                seen.lock()
+                    .unwrap()
+                    .push(req.subgraph_request.uri().to_string());
+                req
+            });
+        let service = plugin
+            .as_any()
+            .downcast_ref::<TrafficShaping>()
+            .unwrap()
+            .subgraph_service_internal("test", test_service);
+        for _ in 0..3 {
+            let _response = service
+                .clone()
+                .oneshot(SubgraphRequest::fake_builder().build())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            *uris.lock().unwrap(),
+            [
+                "http://products-1:4001/graphql",
+                "http://products-2:4001/graphql",
+                "http://products-1:4001/graphql"
+            ]
+        );
+
+        let config = serde_yaml::from_str::<Config>(
+            r#"
+        all:
+            experimental_load_balancing:
+                endpoints:
+                    - http://products-1:4001/graphql
+        "#,
+        )
+        .unwrap();
+        assert!(
+            TrafficShaping::new(PluginInit::fake_builder().config(config).build())
+                .await
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_merge_config() {
         let config = serde_yaml::from_str::<Config>(
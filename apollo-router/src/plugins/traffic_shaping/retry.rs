@@ -35,6 +35,12 @@ impl RetryPolicy {
     }
 }
 
+/// Returns `true` if the request may be sent again. Mutations may not be idempotent, so they are
+/// only sent again if `mutations` is set
+pub(crate) fn may_resend(req: &subgraph::Request, mutations: bool) -> bool {
+    req.operation_kind != OperationKind::Mutation || mutations
+}
+
 impl<Res, E> Policy<subgraph::Request, Res, E> for RetryPolicy {
     type Future = future::Ready<Self>;
 
@@ -47,7 +53,7 @@ impl<Res, E> Policy<subgraph::Request, Res, E> for RetryPolicy {
                 None
             }
             Err(_e) => {
-                if req.operation_kind == OperationKind::Mutation && !self.retry_mutations {
+                if !may_resend(req, self.retry_mutations) {
                     return None;
                 }
 
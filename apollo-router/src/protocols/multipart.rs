@@ -123,7 +123,10 @@ impl Stream for Multipart {
                                     response.errors.drain(..).collect()
                                 },
                                 payload: match response.data {
-                                    None | Some(Value::Null) if response.extensions.is_empty() => {
+                                    None | Some(Value::Null)
+                                        if response.extensions.is_empty()
+                                            && response.incremental.is_empty() =>
+                                    {
                                         None
                                     }
                                     _ => response.into(),
@@ -291,4 +294,55 @@ mod tests {
             }
         }
     }
+
+    #[tokio::test]
+    async fn test_subscription_event_with_deferred_payload() {
+        let responses = vec![
+            graphql::Response::builder()
+                .data(serde_json_bytes::json!({"user": {"id": "1"}}))
+                .has_next(true)
+                .subscribed(true)
+                .build(),
+            graphql::Response::builder()
+                .incremental(vec![graphql::IncrementalResponse::builder()
+                    .data(serde_json_bytes::json!({"name": "Ada"}))
+                    .path(crate::json_ext::Path::from("user"))
+                    .build()])
+                .has_next(false)
+                .subscribed(true)
+                .build(),
+            graphql::Response::builder().build(),
+        ];
+        let gql_responses = stream::iter(responses);
+
+        let mut protocol = Multipart::new(gql_responses, ProtocolMode::Subscription);
+        let heartbeat =
+            String::from("\r\n--graphql\r\ncontent-type: application/json\r\n\r\n{}\r\n--graphql");
+        let mut curr_index = 0;
+        while let Some(resp) = protocol.next().await {
+            let res = String::from_utf8(resp.unwrap().to_vec()).unwrap();
+            if res == heartbeat {
+                continue;
+            } else {
+                match curr_index {
+                    0 => {
+                        assert_eq!(res, "\r\n--graphql\r\ncontent-type: application/json\r\n\r\n{\"payload\":{\"data\":{\"user\":{\"id\":\"1\"}},\"hasNext\":true}}\r\n--graphql");
+                    }
+                    1 => {
+                        assert_eq!(
+                            res,
+                            "\r\ncontent-type: application/json\r\n\r\n{\"payload\":{\"hasNext\":false,\"incremental\":[{\"data\":{\"name\":\"Ada\"},\"path\":[\"user\"]}]}}\r\n--graphql"
+                        );
+                    }
+                    2 => {
+                        assert_eq!(res, "--\r\n");
+                    }
+                    _ => {
+                        panic!("should not happen, test failed");
+                    }
+                }
+                curr_index += 1;
+            }
+        }
+    }
 }
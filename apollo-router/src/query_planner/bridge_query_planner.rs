@@ -12,8 +12,10 @@ use apollo_compiler::validation::Valid;
 use apollo_compiler::Name;
 use apollo_federation::error::FederationError;
 use apollo_federation::error::SingleFederationError;
+use apollo_federation::query_plan::query_planner::LatencyWeights;
 use apollo_federation::query_plan::query_planner::QueryPlanOptions;
 use apollo_federation::query_plan::query_planner::QueryPlanner;
+use apollo_federation::query_plan::query_planner::SubgraphLatencyWeights;
 use futures::future::BoxFuture;
 use opentelemetry_api::metrics::MeterProvider as _;
 use opentelemetry_api::metrics::ObservableGauge;
@@ -31,6 +33,7 @@ use super::PlanNode;
 use super::QueryKey;
 use crate::apollo_studio_interop::generate_usage_reporting;
 use crate::cache::storage::CacheStorage;
+use crate::configuration::contracts::CONTRACT_VARIANT_CONTEXT_KEY;
 use crate::configuration::IntrospectionMode as IntrospectionConfig;
 use crate::configuration::QueryPlannerMode;
 use crate::error::PlanErrors;
@@ -46,7 +49,10 @@ use crate::json_ext::Path;
 use crate::metrics::meter_provider;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::authorization::CacheKeyMetadata;
+use crate::plugins::authorization::RejectedPaths;
 use crate::plugins::authorization::UnauthorizedPaths;
+use crate::plugins::authorization::UnauthorizedReason;
+use crate::plugins::expose_query_plan::EXPLAIN_QUERY_PLAN_CONTEXT_KEY;
 use crate::plugins::progressive_override::LABELS_TO_OVERRIDE_KEY;
 use crate::plugins::telemetry::config::ApolloSignatureNormalizationAlgorithm;
 use crate::plugins::telemetry::config::Conf as TelemetryConfig;
@@ -54,6 +60,7 @@ use crate::query_planner::convert::convert_root_query_plan_node;
 use crate::query_planner::dual_query_planner::BothModeComparisonJob;
 use crate::query_planner::fetch::QueryHash;
 use crate::query_planner::labeler::add_defer_labels;
+use crate::query_planner::subgraph_latency::LatencyWeightsSnapshot;
 use crate::services::layers::query_analysis::ParsedDocument;
 use crate::services::layers::query_analysis::ParsedDocumentInner;
 use crate::services::QueryPlannerContent;
@@ -158,25 +165,31 @@ impl PlannerMode {
     pub(crate) fn maybe_rust(
         schema: &Schema,
         configuration: &Configuration,
+        subgraph_latency_weights: &SubgraphLatencyWeights,
     ) -> Result<Option<Arc<QueryPlanner>>, ServiceBuildError> {
         match configuration.experimental_query_planner_mode {
             QueryPlannerMode::Legacy => Ok(None),
-            QueryPlannerMode::New | QueryPlannerMode::Both => {
-                Ok(Some(Self::rust(schema, configuration)?))
-            }
-            QueryPlannerMode::BothBestEffort => match Self::rust(schema, configuration) {
-                Ok(planner) => Ok(Some(planner)),
-                Err(error) => {
-                    tracing::info!("Falling back to the legacy query planner: {error}");
-                    Ok(None)
+            QueryPlannerMode::New | QueryPlannerMode::Both => Ok(Some(Self::rust(
+                schema,
+                configuration,
+                subgraph_latency_weights,
+            )?)),
+            QueryPlannerMode::BothBestEffort => {
+                match Self::rust(schema, configuration, subgraph_latency_weights) {
+                    Ok(planner) => Ok(Some(planner)),
+                    Err(error) => {
+                        tracing::info!("Falling back to the legacy query planner: {error}");
+                        Ok(None)
+                    }
                 }
-            },
+            }
         }
     }
 
     fn rust(
         schema: &Schema,
         configuration: &Configuration,
+        subgraph_latency_weights: &SubgraphLatencyWeights,
     ) -> Result<Arc<QueryPlanner>, ServiceBuildError> {
         let config = apollo_federation::query_plan::query_planner::QueryPlannerConfig {
             reuse_query_fragments: configuration
@@ -191,6 +204,11 @@ impl PlannerMode {
                 },
             type_conditioned_fetching: configuration.experimental_type_conditioned_fetching,
             debug: Default::default(),
+            subgraph_latency_weights: if configuration.latency_aware_planning() {
+                subgraph_latency_weights.clone()
+            } else {
+                Default::default()
+            },
         };
         let result = QueryPlanner::new(schema.federation_supergraph(), config);
 
@@ -257,12 +275,15 @@ impl PlannerMode {
         ))
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn plan(
         &self,
         doc: &ParsedDocument,
         filtered_query: String,
         operation: Option<String>,
         plan_options: PlanOptions,
+        explain: bool,
+        latency_weights: Option<LatencyWeights>,
         // Initialization code that needs mutable access to the plan,
         // before we potentially share it in Arc with a background thread
         // for "both" mode.
@@ -297,6 +318,8 @@ impl PlannerMode {
 
                     let query_plan_options = QueryPlanOptions {
                         override_conditions: plan_options.override_conditions,
+                        explain,
+                        subgraph_latency_weights: latency_weights,
                     };
 
                     let result = operation
@@ -344,6 +367,11 @@ impl PlannerMode {
                             .evaluated_plan_count
                             .clone()
                             .into_inner() as u64,
+                        explanation: plan
+                            .explanation
+                            .as_ref()
+                            .and_then(|explanation| serde_json_bytes::to_value(explanation).ok())
+                            .map(Arc::new),
                     },
                 })
             }
@@ -372,6 +400,8 @@ impl PlannerMode {
 
                 let query_plan_options = QueryPlanOptions {
                     override_conditions: plan_options.override_conditions,
+                    subgraph_latency_weights: latency_weights,
+                    ..Default::default()
                 };
                 BothModeComparisonJob {
                     rust_planner: rust.clone(),
@@ -524,6 +554,7 @@ impl BridgeQueryPlanner {
             filtered_query: None,
             unauthorized: UnauthorizedPaths {
                 paths: vec![],
+                reasons: vec![],
                 errors: AuthorizationPlugin::log_errors(&self.configuration),
             },
             subselections,
@@ -540,7 +571,10 @@ impl BridgeQueryPlanner {
     ) -> Result<QueryPlannerContent, QueryPlannerError> {
         match &self.introspection {
             IntrospectionMode::Disabled => return Ok(QueryPlannerContent::IntrospectionDisabled),
-            IntrospectionMode::Rust => {
+            // The JavaScript implementation only knows the full API schema, so contract variants
+            // always use the Rust implementation
+            IntrospectionMode::Js(_) | IntrospectionMode::Both(_) if key.contract.is_none() => {}
+            IntrospectionMode::Rust | IntrospectionMode::Js(_) | IntrospectionMode::Both(_) => {
                 let schema = self.schema.clone();
                 let response = Box::new(
                     tokio::task::spawn_blocking(move || {
@@ -551,7 +585,6 @@ impl BridgeQueryPlanner {
                 );
                 return Ok(QueryPlannerContent::Response { response });
             }
-            IntrospectionMode::Js(_) | IntrospectionMode::Both(_) => {}
         }
 
         if doc.executable.operations.len() > 1 {
@@ -622,7 +655,7 @@ impl BridgeQueryPlanner {
         key: &QueryKey,
         doc: &ParsedDocument,
     ) -> Result<graphql::Response, QueryPlannerError> {
-        let schema = schema.api_schema();
+        let schema = schema.contract_api_schema(key.contract.as_deref());
         let operation = doc.get_operation(key.operation_name.as_deref())?;
         let variable_values = Default::default();
         let variable_values =
@@ -654,6 +687,8 @@ impl BridgeQueryPlanner {
         key: CacheKeyMetadata,
         selections: Query,
         plan_options: PlanOptions,
+        explain: bool,
+        latency_weights: Option<LatencyWeights>,
         doc: &ParsedDocument,
         query_metrics: OperationLimits<u32>,
     ) -> Result<QueryPlannerContent, QueryPlannerError> {
@@ -664,6 +699,8 @@ impl BridgeQueryPlanner {
                 filtered_query.clone(),
                 operation.clone(),
                 plan_options,
+                explain,
+                latency_weights,
                 |root_node| {
                     root_node.init_parsed_operations_and_hash_subqueries(
                         &self.subgraph_schemas,
@@ -682,6 +719,7 @@ impl BridgeQueryPlanner {
                         query_plan: QueryPlan { node: Some(node) },
                         formatted_query_plan,
                         evaluated_plan_count,
+                        explanation,
                     },
                 mut usage_reporting,
             } => {
@@ -726,6 +764,8 @@ impl BridgeQueryPlanner {
                         query: Arc::new(selections),
                         query_metrics,
                         estimated_size: Default::default(),
+                        evaluated_plan_count,
+                        explanation,
                     }),
                 })
             }
@@ -815,6 +855,18 @@ impl Service<QueryPlannerRequest> for BridgeQueryPlanner {
                     .unwrap_or_default()
                     .unwrap_or_default(),
             };
+            let explain = context
+                .get(EXPLAIN_QUERY_PLAN_CONTEXT_KEY)
+                .unwrap_or_default()
+                .unwrap_or_default();
+            let contract = context
+                .get(CONTRACT_VARIANT_CONTEXT_KEY)
+                .unwrap_or_default();
+            // Plan with the weights the caching query planner used in its cache key
+            let latency_weights = context.extensions().with_lock(|lock| {
+                lock.get::<LatencyWeightsSnapshot>()
+                    .map(|snapshot| snapshot.weights.clone())
+            });
 
             let res = this
                 .get(
@@ -824,6 +876,9 @@ impl Service<QueryPlannerRequest> for BridgeQueryPlanner {
                         operation_name: operation_name.to_owned(),
                         metadata,
                         plan_options,
+                        explain,
+                        contract,
+                        latency_weights,
                     },
                     doc,
                 )
@@ -862,7 +917,7 @@ impl Service<QueryPlannerRequest> for BridgeQueryPlanner {
 }
 
 // Appease clippy::type_complexity
-pub(crate) type FilteredQuery = (Vec<Path>, ast::Document);
+pub(crate) type FilteredQuery = (Vec<Path>, Vec<UnauthorizedReason>, ast::Document);
 
 impl BridgeQueryPlanner {
     async fn get(
@@ -963,12 +1018,13 @@ impl BridgeQueryPlanner {
 
         let filter_res = if self.enable_authorization_directives {
             match AuthorizationPlugin::filter_query(&self.configuration, &key, &self.schema) {
-                Err(QueryPlannerError::Unauthorized(unauthorized_paths)) => {
+                Err(QueryPlannerError::Unauthorized(unauthorized_paths, unauthorized_reasons)) => {
                     let response = graphql::Response::builder()
                         .data(Object::new())
                         .errors(
                             unauthorized_paths
-                                .into_iter()
+                                .iter()
+                                .cloned()
                                 .map(|path| {
                                     graphql::Error::builder()
                                         .message("Unauthorized field or type")
@@ -979,8 +1035,12 @@ impl BridgeQueryPlanner {
                                 .collect(),
                         )
                         .build();
-                    return Ok(QueryPlannerContent::Response {
+                    return Ok(QueryPlannerContent::Unauthorized {
                         response: Box::new(response),
+                        rejected: RejectedPaths {
+                            paths: unauthorized_paths,
+                            reasons: unauthorized_reasons,
+                        },
                     });
                 }
                 other => other?,
@@ -989,7 +1049,7 @@ impl BridgeQueryPlanner {
             None
         };
 
-        if let Some((unauthorized_paths, new_doc)) = filter_res {
+        if let Some((unauthorized_paths, unauthorized_reasons, new_doc)) = filter_res {
             key.filtered_query = new_doc.to_string();
             let executable_document = new_doc
                 .to_executable_validate(self.schema.api_schema())
@@ -1007,6 +1067,7 @@ impl BridgeQueryPlanner {
                 hash: Arc::new(QueryHash(hash)),
             });
             selections.unauthorized.paths = unauthorized_paths;
+            selections.unauthorized.reasons = unauthorized_reasons;
         }
 
         if key.filtered_query != key.original_query {
@@ -1029,6 +1090,8 @@ impl BridgeQueryPlanner {
             key.metadata,
             selections,
             key.plan_options,
+            key.explain,
+            key.latency_weights,
             &doc,
             query_metrics,
         )
@@ -1044,6 +1107,9 @@ pub struct QueryPlanResult {
     pub(super) formatted_query_plan: Option<Arc<String>>,
     pub(super) query_plan: QueryPlan,
     pub(super) evaluated_plan_count: u64,
+    /// Only produced by the native query planner
+    #[serde(default)]
+    pub(super) explanation: Option<Arc<serde_json_bytes::Value>>,
 }
 
 impl QueryPlanResult {
@@ -1248,6 +1314,8 @@ mod tests {
                 CacheKeyMetadata::default(),
                 selections,
                 PlanOptions::default(),
+                false,
+                None,
                 &doc,
                 query_metrics
             )
@@ -1602,6 +1670,9 @@ mod tests {
                     operation_name: None,
                     metadata: CacheKeyMetadata::default(),
                     plan_options: PlanOptions::default(),
+                    explain: false,
+                    contract: None,
+                    latency_weights: None,
                 },
                 doc,
             )
@@ -1665,6 +1736,9 @@ mod tests {
                     operation_name,
                     metadata: CacheKeyMetadata::default(),
                     plan_options,
+                    explain: false,
+                    contract: None,
+                    latency_weights: None,
                 },
                 doc,
             )
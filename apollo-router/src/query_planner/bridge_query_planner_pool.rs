@@ -7,6 +7,7 @@ use std::sync::Mutex;
 use std::time::Instant;
 
 use apollo_compiler::validation::Valid;
+use apollo_federation::query_plan::query_planner::SubgraphLatencyWeights;
 use async_channel::bounded;
 use async_channel::Sender;
 use futures::future::BoxFuture;
@@ -50,6 +51,7 @@ pub(crate) struct BridgeQueryPlannerPool {
     v8_heap_total: Arc<AtomicU64>,
     v8_heap_total_gauge: Arc<Mutex<Option<ObservableGauge<u64>>>>,
     introspection_cache: CacheStorage<String, Response>,
+    subgraph_latency_weights: SubgraphLatencyWeights,
 }
 
 impl BridgeQueryPlannerPool {
@@ -59,7 +61,9 @@ impl BridgeQueryPlannerPool {
         configuration: Arc<Configuration>,
         size: NonZeroUsize,
     ) -> Result<Self, ServiceBuildError> {
-        let rust_planner = PlannerMode::maybe_rust(&schema, &configuration)?;
+        let subgraph_latency_weights = SubgraphLatencyWeights::default();
+        let rust_planner =
+            PlannerMode::maybe_rust(&schema, &configuration, &subgraph_latency_weights)?;
 
         let mut join_set = JoinSet::new();
 
@@ -159,6 +163,7 @@ impl BridgeQueryPlannerPool {
             v8_heap_total,
             v8_heap_total_gauge: Default::default(),
             introspection_cache,
+            subgraph_latency_weights,
         })
     }
 
@@ -209,6 +214,11 @@ impl BridgeQueryPlannerPool {
         self.schema.clone()
     }
 
+    /// The subgraph latency weights used by the query planners of the pool.
+    pub(crate) fn subgraph_latency_weights(&self) -> SubgraphLatencyWeights {
+        self.subgraph_latency_weights.clone()
+    }
+
     pub(crate) fn subgraph_schemas(
         &self,
     ) -> Arc<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>> {
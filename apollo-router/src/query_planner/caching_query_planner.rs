@@ -28,15 +28,21 @@ use crate::cache::estimate_size;
 use crate::cache::storage::InMemoryCache;
 use crate::cache::storage::ValueType;
 use crate::cache::DeduplicatingCache;
+use crate::configuration::contracts::CONTRACT_VARIANT_CONTEXT_KEY;
 use crate::configuration::PersistedQueriesPrewarmQueryPlanCache;
 use crate::error::CacheResolverError;
 use crate::error::QueryPlannerError;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::authorization::CacheKeyMetadata;
+use crate::plugins::expose_query_plan::EXPLAIN_QUERY_PLAN_CONTEXT_KEY;
 use crate::plugins::progressive_override::LABELS_TO_OVERRIDE_KEY;
 use crate::plugins::telemetry::utils::Timer;
 use crate::query_planner::fetch::SubgraphSchemas;
 use crate::query_planner::labeler::add_defer_labels;
+use crate::query_planner::plan_cache_snapshot::PlanCacheSnapshot;
+use crate::query_planner::plan_cache_snapshot::SnapshotTask;
+use crate::query_planner::subgraph_latency::LatencyWeightsSnapshot;
+use crate::query_planner::subgraph_latency::SubgraphLatencies;
 use crate::query_planner::BridgeQueryPlannerPool;
 use crate::query_planner::QueryPlanResult;
 use crate::services::layers::persisted_queries::PersistedQueryLayer;
@@ -67,6 +73,26 @@ pub(crate) enum ConfigMode {
     Js(Arc<QueryPlannerConfig>),
 }
 
+impl ConfigMode {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ConfigMode::Rust(_) => "new",
+            ConfigMode::Both(_) => "both",
+            ConfigMode::BothBestEffort(_) => "both_best_effort",
+            ConfigMode::Js(_) => "legacy",
+        }
+    }
+}
+
+/// Query planning details stored in the context extensions for telemetry
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct QueryPlanningInfo {
+    /// Whether the query plan was found in the query plan cache
+    pub(crate) cache_hit: bool,
+    /// The configured query planner mode
+    pub(crate) planner_mode: &'static str,
+}
+
 /// A query planner wrapper that caches results.
 ///
 /// The query planner performs LRU caching.
@@ -83,6 +109,9 @@ pub(crate) struct CachingQueryPlanner<T: Clone> {
     config_mode: ConfigMode,
     introspection: bool,
     legacy_introspection_caching: bool,
+    subgraph_latencies: Option<Arc<SubgraphLatencies>>,
+    disk_snapshot: Option<Arc<PlanCacheSnapshot>>,
+    _disk_snapshot_task: Option<Arc<SnapshotTask>>,
 }
 
 fn init_query_plan_from_redis(
@@ -115,6 +144,7 @@ where
         subgraph_schemas: Arc<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>>,
         configuration: &Configuration,
         plugins: Plugins,
+        subgraph_latencies: Option<Arc<SubgraphLatencies>>,
     ) -> Result<CachingQueryPlanner<T>, BoxError> {
         let cache = Arc::new(
             DeduplicatingCache::from_configuration(
@@ -141,6 +171,27 @@ where
                 ConfigMode::BothBestEffort(Arc::new(configuration.js_query_planner_config()))
             }
         };
+        let (disk_snapshot, disk_snapshot_task) = match &configuration
+            .supergraph
+            .query_planning
+            .cache
+            .experimental_disk
+        {
+            Some(disk_config) => {
+                let disk_snapshot = Arc::new(PlanCacheSnapshot::new(
+                    disk_config,
+                    configuration.hosted_supergraph.as_deref(),
+                    schema.schema_id.clone(),
+                    config_mode.clone(),
+                ));
+                let task = disk_snapshot
+                    .clone()
+                    .write_periodically(&cache.in_memory_cache(), disk_config.interval);
+                (Some(disk_snapshot), Some(Arc::new(task)))
+            }
+            None => (None, None),
+        };
+
         Ok(Self {
             cache,
             delegate,
@@ -154,13 +205,49 @@ where
                 .supergraph
                 .query_planning
                 .legacy_introspection_caching,
+            subgraph_latencies,
+            disk_snapshot,
+            _disk_snapshot_task: disk_snapshot_task,
         })
     }
 
+    fn latency_weights(&self) -> Option<LatencyWeightsSnapshot> {
+        self.subgraph_latencies
+            .as_ref()
+            .map(|latencies| latencies.snapshot())
+    }
+
     pub(crate) fn previous_cache(&self) -> InMemoryCachePlanner {
         self.cache.in_memory_cache()
     }
 
+    /// Writes the on-disk snapshot of the cache, if configured
+    pub(crate) async fn persist(&self) {
+        if let Some(disk_snapshot) = &self.disk_snapshot {
+            disk_snapshot.write(&self.cache.in_memory_cache()).await;
+        }
+    }
+
+    /// Fills the in memory cache from the on-disk snapshot, if configured
+    async fn load_disk_snapshot(&self) {
+        let Some(disk_snapshot) = &self.disk_snapshot else {
+            return;
+        };
+        let mut loaded = 0usize;
+        for (key, content) in disk_snapshot.load().await {
+            let mut entry = Ok(content);
+            if let Err(e) = init_query_plan_from_redis(&self.subgraph_schemas, &mut entry) {
+                tracing::warn!("ignoring query plan from the cache snapshot: {e}");
+                continue;
+            }
+            self.cache.insert_in_memory(key, entry).await;
+            loaded += 1;
+        }
+        if loaded > 0 {
+            tracing::info!("loaded {loaded} query plans from the query plan cache snapshot");
+        }
+    }
+
     pub(crate) async fn warm_up(
         &mut self,
         query_analysis: &QueryAnalysisLayer,
@@ -176,6 +263,11 @@ where
             );
         });
 
+        // on startup, the snapshot stands in for the cache of the previous router
+        if previous_cache.is_none() {
+            self.load_disk_snapshot().await;
+        }
+
         let mut service = ServiceBuilder::new().service(
             self.plugins
                 .iter()
@@ -193,6 +285,8 @@ where
 
                 cache
                     .iter()
+                    // Explained plans are only computed on demand
+                    .filter(|(key, _)| !key.explain)
                     .map(
                         |(
                             CachingQueryKey {
@@ -204,6 +298,9 @@ where
                                 config_mode: _,
                                 schema_id: _,
                                 introspection: _,
+                                latency_weights: _,
+                                explain: _,
+                                contract,
                             },
                             _,
                         )| WarmUpCachingQueryKey {
@@ -214,6 +311,7 @@ where
                             plan_options: plan_options.clone(),
                             config_mode: self.config_mode.clone(),
                             introspection: self.introspection,
+                            contract: contract.clone(),
                         },
                     )
                     .take(count)
@@ -260,6 +358,7 @@ where
                         plan_options: PlanOptions::default(),
                         config_mode: self.config_mode.clone(),
                         introspection: self.introspection,
+                        contract: None,
                     });
                 }
             }
@@ -277,11 +376,13 @@ where
             plan_options,
             config_mode: _,
             introspection: _,
+            contract,
         } in all_cache_keys
         {
             let context = Context::new();
+            let latency_weights = self.latency_weights();
             let (doc, _operation_def) = match query_analysis
-                .parse_document(&query, operation_name.as_deref())
+                .parse_document(&query, operation_name.as_deref(), contract.as_deref())
                 .await
             {
                 Ok(doc) => doc,
@@ -297,6 +398,11 @@ where
                 plan_options,
                 config_mode: self.config_mode.clone(),
                 introspection: self.introspection,
+                latency_weights: latency_weights
+                    .as_ref()
+                    .and_then(|snapshot| snapshot.cache_key.clone()),
+                explain: false,
+                contract: contract.clone(),
             };
 
             if experimental_reuse_query_plans {
@@ -325,7 +431,7 @@ where
                 .await;
             if entry.is_first() {
                 let (doc, _operation_def) = match query_analysis
-                    .parse_document(&query, operation_name.as_deref())
+                    .parse_document(&query, operation_name.as_deref(), contract.as_deref())
                     .await
                 {
                     Ok(doc) => doc,
@@ -345,8 +451,16 @@ where
 
                 context.extensions().with_lock(|mut lock| {
                     lock.insert::<ParsedDocument>(doc);
-                    lock.insert(caching_key.metadata)
+                    lock.insert(caching_key.metadata);
+                    if let Some(latency_weights) = latency_weights {
+                        lock.insert(latency_weights);
+                    }
                 });
+                if let Some(contract) = contract {
+                    context
+                        .insert(CONTRACT_VARIANT_CONTEXT_KEY, contract)
+                        .expect("cannot insert contract variant into context; this is a bug");
+                }
 
                 let request = QueryPlannerRequest {
                     query,
@@ -467,6 +581,15 @@ where
                 .unwrap_or_default()
                 .unwrap_or_default(),
         };
+        let explain = request
+            .context
+            .get(EXPLAIN_QUERY_PLAN_CONTEXT_KEY)
+            .unwrap_or_default()
+            .unwrap_or_default();
+        let contract = request
+            .context
+            .get(CONTRACT_VARIANT_CONTEXT_KEY)
+            .unwrap_or_default();
 
         let doc = match request
             .context
@@ -489,6 +612,8 @@ where
             .extensions()
             .with_lock(|lock| lock.get::<CacheKeyMetadata>().cloned())
             .unwrap_or_default();
+        // The same weights are used for the cache key and for planning
+        let latency_weights = self.latency_weights();
 
         let caching_key = CachingQueryKey {
             query: request.query.clone(),
@@ -499,6 +624,11 @@ where
             plan_options,
             config_mode: self.config_mode.clone(),
             introspection: self.introspection,
+            latency_weights: latency_weights
+                .as_ref()
+                .and_then(|snapshot| snapshot.cache_key.clone()),
+            explain,
+            contract,
         };
 
         let context = request.context.clone();
@@ -508,6 +638,12 @@ where
                 init_query_plan_from_redis(&self.subgraph_schemas, v)
             })
             .await;
+        context.extensions().with_lock(|mut lock| {
+            lock.insert(QueryPlanningInfo {
+                cache_hit: !entry.is_first(),
+                planner_mode: self.config_mode.as_str(),
+            })
+        });
         if entry.is_first() {
             let query_planner::CachingRequest {
                 mut query,
@@ -520,6 +656,12 @@ where
                 query = modified_query.to_string();
             }
 
+            if let Some(latency_weights) = latency_weights {
+                context
+                    .extensions()
+                    .with_lock(|mut lock| lock.insert(latency_weights));
+            }
+
             let request = QueryPlannerRequest::builder()
                 .query(query)
                 .and_operation_name(operation_name)
@@ -646,12 +788,18 @@ pub(crate) struct CachingQueryKey {
     pub(crate) plan_options: PlanOptions,
     pub(crate) config_mode: ConfigMode,
     pub(crate) introspection: bool,
+    /// Rounded subgraph latency weights used to plan the query, if any
+    pub(crate) latency_weights: Option<Arc<String>>,
+    /// Whether the plan explains how it was chosen
+    pub(crate) explain: bool,
+    /// Contract variant the query was validated against, if any
+    pub(crate) contract: Option<String>,
 }
 
 // Update this key every time the cache key or the query plan format has to change.
 // When changed it MUST BE CALLED OUT PROMINENTLY IN THE CHANGELOG.
-const CACHE_KEY_VERSION: usize = 0;
-const FEDERATION_VERSION: &str = std::env!("FEDERATION_VERSION");
+pub(super) const CACHE_KEY_VERSION: usize = 0;
+pub(super) const FEDERATION_VERSION: &str = std::env!("FEDERATION_VERSION");
 
 impl std::fmt::Display for CachingQueryKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -667,6 +815,16 @@ impl std::fmt::Display for CachingQueryKey {
             .update(serde_json::to_vec(&self.config_mode).expect("serialization should not fail"));
         hasher.update(&*self.schema_id);
         hasher.update([self.introspection as u8]);
+        if let Some(latency_weights) = &self.latency_weights {
+            hasher.update(latency_weights.as_bytes());
+        }
+        if self.explain {
+            hasher.update(b"explain");
+        }
+        if let Some(contract) = &self.contract {
+            hasher.update(b"contract:");
+            hasher.update(contract.as_bytes());
+        }
         let metadata = hex::encode(hasher.finalize());
 
         write!(
@@ -686,6 +844,9 @@ impl Hash for CachingQueryKey {
         self.plan_options.hash(state);
         self.config_mode.hash(state);
         self.introspection.hash(state);
+        self.latency_weights.hash(state);
+        self.explain.hash(state);
+        self.contract.hash(state);
     }
 }
 
@@ -698,13 +859,17 @@ pub(crate) struct WarmUpCachingQueryKey {
     pub(crate) plan_options: PlanOptions,
     pub(crate) config_mode: ConfigMode,
     pub(crate) introspection: bool,
+    pub(crate) contract: Option<String>,
 }
 
 impl ValueType for Result<QueryPlannerContent, Arc<QueryPlannerError>> {
     fn estimated_size(&self) -> Option<usize> {
         match self {
             Ok(QueryPlannerContent::Plan { plan }) => Some(plan.estimated_size()),
-            Ok(QueryPlannerContent::Response { response }) => Some(estimate_size(response)),
+            Ok(QueryPlannerContent::Response { response })
+            | Ok(QueryPlannerContent::Unauthorized { response, .. }) => {
+                Some(estimate_size(response))
+            }
             Ok(QueryPlannerContent::IntrospectionDisabled) => None,
             Err(e) => Some(estimate_size(e)),
         }
@@ -788,6 +953,7 @@ mod tests {
             Default::default(),
             &configuration,
             IndexMap::default(),
+            None,
         )
         .await
         .unwrap();
@@ -863,6 +1029,8 @@ mod tests {
                     query: Arc::new(Query::empty()),
                     query_metrics: Default::default(),
                     estimated_size: Default::default(),
+                    evaluated_plan_count: Default::default(),
+                    explanation: Default::default(),
                 };
                 let qp_content = QueryPlannerContent::Plan {
                     plan: Arc::new(query_plan),
@@ -895,6 +1063,7 @@ mod tests {
             Default::default(),
             &configuration,
             IndexMap::default(),
+            None,
         )
         .await
         .unwrap();
@@ -973,6 +1142,7 @@ mod tests {
             Default::default(),
             &configuration,
             IndexMap::default(),
+            None,
         )
         .await
         .unwrap();
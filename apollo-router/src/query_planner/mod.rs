@@ -20,9 +20,11 @@ mod execution;
 pub(crate) mod fetch;
 mod labeler;
 mod plan;
+mod plan_cache_snapshot;
 pub(crate) mod rewrites;
 mod selection;
 mod subgraph_context;
+pub(crate) mod subgraph_latency;
 pub(crate) mod subscription;
 
 pub(crate) const FETCH_SPAN_NAME: &str = "fetch";
@@ -3,6 +3,7 @@ use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use apollo_compiler::validation::Valid;
+use apollo_federation::query_plan::query_planner::LatencyWeights;
 use router_bridge::planner::PlanOptions;
 use router_bridge::planner::UsageReporting;
 use serde::Deserialize;
@@ -34,6 +35,12 @@ pub(crate) struct QueryKey {
     pub(crate) operation_name: Option<String>,
     pub(crate) metadata: CacheKeyMetadata,
     pub(crate) plan_options: PlanOptions,
+    /// Whether to explain how the query plan was chosen
+    pub(crate) explain: bool,
+    /// Contract variant whose API schema the query was validated against
+    pub(crate) contract: Option<String>,
+    /// Subgraph latency weights to plan with, if latency aware planning is enabled
+    pub(crate) latency_weights: Option<LatencyWeights>,
 }
 
 /// A plan for a given GraphQL query
@@ -49,6 +56,15 @@ pub struct QueryPlan {
     /// The estimated size in bytes of the query plan
     #[serde(default)]
     pub(crate) estimated_size: Arc<AtomicUsize>,
+
+    /// The number of plans the query planner evaluated before choosing this one
+    #[serde(default)]
+    pub(crate) evaluated_plan_count: u64,
+
+    /// How the query planner chose this plan, if it was requested and the native query planner
+    /// was used
+    #[serde(default)]
+    pub(crate) explanation: Option<Arc<serde_json_bytes::Value>>,
 }
 
 /// This default impl is useful for test users
@@ -72,6 +88,8 @@ impl QueryPlan {
             query: Arc::new(Query::empty()),
             query_metrics: Default::default(),
             estimated_size: Default::default(),
+            evaluated_plan_count: Default::default(),
+            explanation: Default::default(),
         }
     }
 }
@@ -338,6 +356,36 @@ impl PlanNode {
         }
     }
 
+    pub(crate) fn deferred_chunks(&self) -> usize {
+        match self {
+            PlanNode::Sequence { nodes } => nodes.iter().map(|n| n.deferred_chunks()).sum(),
+            PlanNode::Parallel { nodes } => nodes.iter().map(|n| n.deferred_chunks()).sum(),
+            PlanNode::Fetch(_) => 0,
+            PlanNode::Flatten(node) => node.node.deferred_chunks(),
+            // Each deferred node produces its own chunk in the response
+            PlanNode::Defer { primary, deferred } => {
+                primary.node.as_ref().map_or(0, |n| n.deferred_chunks())
+                    + deferred
+                        .iter()
+                        .map(|n| 1 + n.node.as_ref().map_or(0, |n| n.deferred_chunks()))
+                        .sum::<usize>()
+            }
+            PlanNode::Subscription { rest, .. } => rest.as_ref().map_or(0, |n| n.deferred_chunks()),
+            // Compute the highest possible value for condition nodes
+            PlanNode::Condition {
+                if_clause,
+                else_clause,
+                ..
+            } => std::cmp::max(
+                if_clause.as_ref().map(|n| n.deferred_chunks()).unwrap_or(0),
+                else_clause
+                    .as_ref()
+                    .map(|n| n.deferred_chunks())
+                    .unwrap_or(0),
+            ),
+        }
+    }
+
     pub(crate) fn init_parsed_operations(
         &mut self,
         subgraph_schemas: &SubgraphSchemas,
@@ -0,0 +1,372 @@
+//! On-disk snapshot of the query plan cache.
+//!
+//! The snapshot is a header line followed by the JSON list of cached plans. The header holds a
+//! key derived from the schema, the query planner configuration and the router version, and a
+//! checksum of the plans: a snapshot written for another schema or configuration, or corrupted, is
+//! ignored, and replaced on the next write.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Weak;
+
+use router_bridge::planner::PlanOptions;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tower::BoxError;
+
+use super::caching_query_planner::CACHE_KEY_VERSION;
+use super::caching_query_planner::FEDERATION_VERSION;
+use super::fetch::QueryHash;
+use super::CachingQueryKey;
+use super::ConfigMode;
+use super::InMemoryCachePlanner;
+use crate::configuration::QueryPlanDiskCache;
+use crate::plugins::authorization::CacheKeyMetadata;
+use crate::services::QueryPlannerContent;
+
+/// Update this version every time the snapshot format changes
+const SNAPSHOT_FORMAT_VERSION: usize = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    format: usize,
+    key: String,
+    checksum: String,
+}
+
+/// A cached plan, without the parts of its cache key given by the snapshot key
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    query: String,
+    operation: Option<String>,
+    hash: QueryHash,
+    metadata: CacheKeyMetadata,
+    plan_options: PlanOptions,
+    introspection: bool,
+    latency_weights: Option<Arc<String>>,
+    contract: Option<String>,
+    content: QueryPlannerContent,
+}
+
+/// Reads and writes the snapshot of the query plan cache of a supergraph
+pub(crate) struct PlanCacheSnapshot {
+    file: PathBuf,
+    key: String,
+    schema_id: Arc<String>,
+    config_mode: ConfigMode,
+}
+
+impl PlanCacheSnapshot {
+    pub(crate) fn new(
+        config: &QueryPlanDiskCache,
+        supergraph_name: Option<&str>,
+        schema_id: Arc<String>,
+        config_mode: ConfigMode,
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(CACHE_KEY_VERSION.to_string());
+        hasher.update(FEDERATION_VERSION);
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update(&*schema_id);
+        hasher.update(serde_json::to_vec(&config_mode).expect("serialization should not fail"));
+        // hosted supergraphs sharing the directory each have their own snapshot
+        let file = match supergraph_name {
+            Some(name) => format!("query_plans-{name}.snapshot"),
+            None => "query_plans.snapshot".to_string(),
+        };
+        Self {
+            file: config.path.join(file),
+            key: hex::encode(hasher.finalize()),
+            schema_id,
+            config_mode,
+        }
+    }
+
+    /// Reads the cached plans from the snapshot. Returns nothing if there is no snapshot, or if it
+    /// does not match the schema and the query planner configuration
+    pub(crate) async fn load(&self) -> Vec<(CachingQueryKey, QueryPlannerContent)> {
+        let contents = match tokio::fs::read(&self.file).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+            Err(e) => {
+                tracing::warn!(
+                    "cannot read the query plan cache snapshot {}: {e}",
+                    self.file.display()
+                );
+                return Vec::new();
+            }
+        };
+        let file = self.file.clone();
+        let key = self.key.clone();
+        let entries = tokio::task::spawn_blocking(move || decode(&contents, &key))
+            .await
+            .expect("snapshot decoding task panicked");
+        match entries {
+            Ok(Some(entries)) => entries
+                .into_iter()
+                .map(|entry| {
+                    (
+                        CachingQueryKey {
+                            query: entry.query,
+                            schema_id: self.schema_id.clone(),
+                            operation: entry.operation,
+                            hash: Arc::new(entry.hash),
+                            metadata: entry.metadata,
+                            plan_options: entry.plan_options,
+                            config_mode: self.config_mode.clone(),
+                            introspection: entry.introspection,
+                            latency_weights: entry.latency_weights,
+                            explain: false,
+                            contract: entry.contract,
+                        },
+                        entry.content,
+                    )
+                })
+                .collect(),
+            Ok(None) => {
+                tracing::info!(
+                    "the query plan cache snapshot {} was written for another schema, query planner configuration or router version, ignoring it",
+                    file.display()
+                );
+                Vec::new()
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "the query plan cache snapshot {} is invalid, ignoring it: {e}",
+                    file.display()
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Writes the plans of the in memory cache to the snapshot
+    pub(crate) async fn write(&self, cache: &InMemoryCachePlanner) {
+        // least recently used first, so that loading the snapshot keeps the order
+        let entries: Vec<Entry> = {
+            let cache = cache.lock().await;
+            cache
+                .iter()
+                .rev()
+                .filter(|(key, _)| !key.explain && key.schema_id == self.schema_id)
+                .filter_map(|(key, value)| {
+                    let content = value.as_ref().ok()?.clone();
+                    Some(Entry {
+                        query: key.query.clone(),
+                        operation: key.operation.clone(),
+                        hash: (*key.hash).clone(),
+                        metadata: key.metadata.clone(),
+                        plan_options: key.plan_options.clone(),
+                        introspection: key.introspection,
+                        latency_weights: key.latency_weights.clone(),
+                        contract: key.contract.clone(),
+                        content,
+                    })
+                })
+                .collect()
+        };
+        let count = entries.len();
+        let file = self.file.clone();
+        let key = self.key.clone();
+        let result = tokio::task::spawn_blocking(move || write_file(&file, &key, &entries))
+            .await
+            .expect("snapshot writing task panicked");
+        match result {
+            Ok(()) => tracing::debug!(
+                "wrote {count} query plans to the query plan cache snapshot {}",
+                self.file.display()
+            ),
+            Err(e) => tracing::warn!(
+                "cannot write the query plan cache snapshot {}: {e}",
+                self.file.display()
+            ),
+        }
+    }
+
+    /// Writes the snapshot at each interval, until the returned task is dropped
+    pub(crate) fn write_periodically(
+        self: Arc<Self>,
+        cache: &InMemoryCachePlanner,
+        interval: std::time::Duration,
+    ) -> SnapshotTask {
+        let cache: Weak<_> = Arc::downgrade(cache);
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let Some(cache) = cache.upgrade() else {
+                    break;
+                };
+                self.write(&cache).await;
+            }
+        });
+        SnapshotTask(handle)
+    }
+}
+
+/// Returns `None` if the snapshot was written with another key
+fn decode(contents: &[u8], key: &str) -> Result<Option<Vec<Entry>>, BoxError> {
+    let newline = contents
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or("missing header")?;
+    let (header, body) = contents.split_at(newline);
+    let header: Header = serde_json::from_slice(header)?;
+    if header.format != SNAPSHOT_FORMAT_VERSION || header.key != key {
+        return Ok(None);
+    }
+    let body = &body[1..];
+    if hex::encode(Sha256::digest(body)) != header.checksum {
+        return Err("checksum mismatch".into());
+    }
+    Ok(Some(serde_json::from_slice(body)?))
+}
+
+fn encode(key: &str, entries: &[Entry]) -> Result<Vec<u8>, BoxError> {
+    let body = serde_json::to_vec(entries)?;
+    let header = Header {
+        format: SNAPSHOT_FORMAT_VERSION,
+        key: key.to_string(),
+        checksum: hex::encode(Sha256::digest(&body)),
+    };
+    let mut contents = serde_json::to_vec(&header)?;
+    contents.push(b'\n');
+    contents.extend(body);
+    Ok(contents)
+}
+
+fn write_file(file: &std::path::Path, key: &str, entries: &[Entry]) -> Result<(), BoxError> {
+    let contents = encode(key, entries)?;
+    if let Some(directory) = file.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    // a snapshot is never read half written
+    let temporary = file.with_extension("tmp");
+    std::fs::write(&temporary, contents)?;
+    std::fs::rename(&temporary, file)?;
+    Ok(())
+}
+
+pub(crate) struct SnapshotTask(tokio::task::JoinHandle<()>);
+
+impl Drop for SnapshotTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use router_bridge::planner::QueryPlannerConfig;
+
+    use super::*;
+
+    fn plan_cache_snapshot(path: &std::path::Path, schema_id: &str) -> PlanCacheSnapshot {
+        PlanCacheSnapshot::new(
+            &QueryPlanDiskCache {
+                path: path.to_path_buf(),
+                interval: Duration::from_secs(300),
+            },
+            None,
+            Arc::new(schema_id.to_string()),
+            ConfigMode::Js(Arc::new(QueryPlannerConfig::default())),
+        )
+    }
+
+    fn entry(query: &str) -> Entry {
+        Entry {
+            query: query.to_string(),
+            operation: None,
+            hash: QueryHash(vec![1, 2, 3]),
+            metadata: CacheKeyMetadata::default(),
+            plan_options: PlanOptions::default(),
+            introspection: false,
+            latency_weights: None,
+            contract: None,
+            content: QueryPlannerContent::IntrospectionDisabled,
+        }
+    }
+
+    #[test]
+    fn decodes_snapshots_with_the_same_key() {
+        let contents = encode("key", &[entry("{ a }"), entry("{ b }")]).unwrap();
+        let entries = decode(&contents, "key").unwrap().unwrap();
+        assert_eq!(
+            entries.iter().map(|e| e.query.as_str()).collect::<Vec<_>>(),
+            ["{ a }", "{ b }"]
+        );
+
+        assert!(decode(&contents, "other key").unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_corrupted_snapshots() {
+        let mut contents = encode("key", &[entry("{ a }")]).unwrap();
+        let last = contents.len() - 3;
+        contents[last] = b'x';
+        assert!(decode(&contents, "key").is_err());
+
+        assert!(decode(b"not a snapshot", "key").is_err());
+        let truncated = encode("key", &[entry("{ a }")]).unwrap();
+        assert!(decode(&truncated[..truncated.len() - 1], "key").is_err());
+    }
+
+    #[tokio::test]
+    async fn loads_the_plans_of_its_own_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = plan_cache_snapshot(dir.path(), "schema");
+        let mut weighted = entry("{ b }");
+        weighted.operation = Some("B".to_string());
+        weighted.latency_weights = Some(Arc::new("accounts=2".to_string()));
+        write_file(&snapshot.file, &snapshot.key, &[entry("{ a }"), weighted]).unwrap();
+
+        let keys: Vec<_> = snapshot
+            .load()
+            .await
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys.iter()
+                .map(|key| key.query.as_str())
+                .collect::<Vec<_>>(),
+            ["{ a }", "{ b }"]
+        );
+        assert_eq!(keys[1].operation.as_deref(), Some("B"));
+        assert_eq!(
+            keys[1].latency_weights.as_deref().map(String::as_str),
+            Some("accounts=2")
+        );
+        assert!(keys
+            .iter()
+            .all(|key| key.schema_id.as_str() == "schema" && !key.explain));
+    }
+
+    #[tokio::test]
+    async fn ignores_stale_or_corrupted_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = plan_cache_snapshot(dir.path(), "schema");
+        // no snapshot yet
+        assert!(snapshot.load().await.is_empty());
+
+        // written for another schema
+        let stale = plan_cache_snapshot(dir.path(), "previous schema");
+        write_file(&stale.file, &stale.key, &[entry("{ a }")]).unwrap();
+        assert!(snapshot.load().await.is_empty());
+        assert_eq!(stale.load().await.len(), 1);
+
+        // checksum mismatch
+        let mut contents = encode(&snapshot.key, &[entry("{ a }")]).unwrap();
+        let last = contents.len() - 3;
+        contents[last] = b'x';
+        std::fs::write(&snapshot.file, contents).unwrap();
+        assert!(snapshot.load().await.is_empty());
+    }
+}
@@ -0,0 +1,247 @@
+//! Subgraph latency tracking for latency aware query planning.
+//!
+//! Each supergraph has its own latencies. The subgraph request durations recorded by the telemetry
+//! plugin are accumulated as they are observed, and periodically turned into per-subgraph weights
+//! relative to the average subgraph latency. Those weights are shared with the query planner, which
+//! uses them to weigh the cost of fetches.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use apollo_federation::query_plan::query_planner::LatencyWeights;
+use apollo_federation::query_plan::query_planner::SubgraphLatencyWeights;
+use itertools::Itertools;
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+
+use crate::configuration::LatencyAwarePlanning;
+use crate::Context;
+
+/// Smoothing factor of the moving average of each subgraph's latency
+const SMOOTHING: f64 = 0.3;
+
+/// Latencies observed since the last update
+#[derive(Default)]
+struct Window {
+    total_nanos: AtomicU64,
+    count: AtomicU64,
+}
+
+/// The subgraph latencies of a supergraph, shared by its query planners.
+pub(crate) struct SubgraphLatencies {
+    // One window per subgraph of the supergraph, so that observations do not take a lock
+    windows: HashMap<String, Window>,
+    // Moving average of each subgraph's latency, in seconds
+    averages: Mutex<HashMap<String, f64>>,
+    weights: SubgraphLatencyWeights,
+    // The current weights, along with their rendering for query plan cache keys
+    snapshot: RwLock<LatencyWeightsSnapshot>,
+}
+
+/// The weights used to plan a query, and the matching part of its query plan cache key.
+#[derive(Clone, Default)]
+pub(crate) struct LatencyWeightsSnapshot {
+    pub(crate) weights: LatencyWeights,
+    pub(crate) cache_key: Option<Arc<String>>,
+}
+
+impl SubgraphLatencies {
+    /// Tracks the latencies of the given subgraphs, and hands their weights to the query planner
+    /// through `weights`.
+    pub(crate) fn new(
+        subgraphs: impl IntoIterator<Item = String>,
+        weights: SubgraphLatencyWeights,
+    ) -> Self {
+        Self {
+            windows: subgraphs
+                .into_iter()
+                .map(|subgraph| (subgraph, Window::default()))
+                .collect(),
+            averages: Default::default(),
+            weights,
+            snapshot: Default::default(),
+        }
+    }
+
+    /// The latencies of the supergraph serving the request, if latency aware planning is enabled.
+    pub(crate) fn from_context(context: &Context) -> Option<Arc<Self>> {
+        context
+            .extensions()
+            .with_lock(|lock| lock.get::<Arc<Self>>().cloned())
+    }
+
+    /// Makes the latencies available to the subgraph requests of a supergraph request.
+    pub(crate) fn insert_into(self: &Arc<Self>, context: &Context) {
+        context
+            .extensions()
+            .with_lock(|mut lock| lock.insert(self.clone()));
+    }
+
+    /// The weights handed to the query planner.
+    pub(crate) fn weights(&self) -> &SubgraphLatencyWeights {
+        &self.weights
+    }
+
+    /// The current weights. The same snapshot must be used for the query plan cache key and for
+    /// planning, so that plans are cached under the weights they were computed with.
+    pub(crate) fn snapshot(&self) -> LatencyWeightsSnapshot {
+        self.snapshot.read().clone()
+    }
+
+    /// Records the duration of a subgraph request.
+    pub(crate) fn observe(&self, subgraph: &str, duration: Duration) {
+        if let Some(window) = self.windows.get(subgraph) {
+            window
+                .total_nanos
+                .fetch_add(duration.as_nanos() as u64, Ordering::Relaxed);
+            window.count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Computes the weights, then updates them periodically until the returned task is dropped.
+    pub(crate) fn start(self: &Arc<Self>, config: &LatencyAwarePlanning) -> LatencyWeightsTask {
+        self.update(&config.subgraphs);
+        let latencies = self.clone();
+        let config = config.clone();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.update_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            // The first tick completes immediately
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                latencies.update(&config.subgraphs);
+            }
+        });
+        LatencyWeightsTask(handle)
+    }
+
+    /// Folds the latencies observed since the last update into the moving averages, and recomputes
+    /// the weights. Configured weights take precedence over observed latencies.
+    pub(crate) fn update(&self, configured: &HashMap<String, f64>) {
+        let mut averages = self.averages.lock();
+        for (subgraph, window) in &self.windows {
+            let count = window.count.swap(0, Ordering::Relaxed);
+            let total_nanos = window.total_nanos.swap(0, Ordering::Relaxed);
+            if count == 0 {
+                continue;
+            }
+            let latency = Duration::from_nanos(total_nanos).as_secs_f64() / count as f64;
+            averages
+                .entry(subgraph.clone())
+                .and_modify(|average| *average += SMOOTHING * (latency - *average))
+                .or_insert(latency);
+        }
+
+        let mut weights = HashMap::new();
+        let observed: Vec<_> = averages
+            .iter()
+            .filter(|(subgraph, _)| !configured.contains_key(*subgraph))
+            .collect();
+        let mean =
+            observed.iter().map(|(_, latency)| **latency).sum::<f64>() / observed.len() as f64;
+        if observed.len() > 1 && mean > 0.0 {
+            for (subgraph, latency) in observed {
+                weights.insert(subgraph.clone(), latency / mean);
+            }
+        }
+        weights.extend(configured.iter().map(|(k, v)| (k.clone(), *v)));
+        drop(averages);
+        self.set_weights(weights);
+    }
+
+    fn set_weights(&self, weights: HashMap<String, f64>) {
+        let mut snapshot = self.snapshot.write();
+        self.weights.set(
+            weights
+                .into_iter()
+                .map(|(subgraph, weight)| (Arc::from(subgraph), weight)),
+        );
+        let weights = self.weights.snapshot();
+        let cache_key = (!weights.is_empty()).then(|| {
+            Arc::new(
+                weights
+                    .iter()
+                    .map(|(subgraph, weight)| format!("{subgraph}={weight}"))
+                    .join(","),
+            )
+        });
+        *snapshot = LatencyWeightsSnapshot { weights, cache_key };
+    }
+}
+
+/// Periodically updates the subgraph latency weights until dropped.
+pub(crate) struct LatencyWeightsTask(tokio::task::JoinHandle<()>);
+
+impl Drop for LatencyWeightsTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weights_from_observed_latencies() {
+        let latencies = SubgraphLatencies::new(
+            ["products", "reviews", "accounts"].map(String::from),
+            Default::default(),
+        );
+        latencies.observe("products", Duration::from_millis(10));
+        latencies.observe("products", Duration::from_millis(20));
+        latencies.observe("reviews", Duration::from_millis(120));
+        latencies.observe("accounts", Duration::from_millis(45));
+        latencies.update(&HashMap::from([("inventory".to_string(), 0.5)]));
+
+        // The mean observed latency is 60ms, and weights are rounded to powers of sqrt(2)
+        let weights = latencies.weights().snapshot();
+        assert_eq!(weights.get("products"), Some(&0.25));
+        assert_eq!(weights.get("reviews"), Some(&2.0));
+        assert!((weights["accounts"] - 0.5f64.sqrt()).abs() < 1e-9);
+        assert_eq!(weights.get("inventory"), Some(&0.5));
+
+        // Observations are folded into a moving average
+        latencies.observe("products", Duration::from_millis(15));
+        latencies.observe("reviews", Duration::from_millis(15));
+        latencies.observe("accounts", Duration::from_millis(15));
+        latencies.update(&HashMap::new());
+        let weights = latencies.weights().snapshot();
+        assert_eq!(weights.get("reviews"), Some(&2.0));
+        assert_eq!(weights.get("inventory"), None);
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let latencies = SubgraphLatencies::new([], Default::default());
+        assert!(latencies.snapshot().cache_key.is_none());
+        latencies.update(&HashMap::from([
+            ("reviews".to_string(), 2.0),
+            ("products".to_string(), 0.5),
+            ("accounts".to_string(), 1.1),
+        ]));
+        assert_eq!(
+            latencies
+                .snapshot()
+                .cache_key
+                .as_deref()
+                .map(String::as_str),
+            Some("products=0.5,reviews=2")
+        );
+    }
+
+    #[test]
+    fn test_single_subgraph_has_no_weight() {
+        let latencies = SubgraphLatencies::new(["products".to_string()], Default::default());
+        latencies.observe("products", Duration::from_millis(10));
+        // subgraphs that are not part of the supergraph are ignored
+        latencies.observe("reviews", Duration::from_millis(100));
+        latencies.update(&HashMap::new());
+        assert!(latencies.weights().is_empty());
+        assert!(latencies.snapshot().cache_key.is_none());
+    }
+}
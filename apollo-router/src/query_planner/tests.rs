@@ -88,6 +88,8 @@ async fn mock_subgraph_service_withf_panics_should_be_reported_as_service_closed
         }
         .into(),
         estimated_size: Default::default(),
+        evaluated_plan_count: Default::default(),
+        explanation: Default::default(),
     };
 
     let mut mock_products_service = plugin::test::MockSubgraphService::new();
@@ -144,6 +146,8 @@ async fn fetch_includes_operation_name() {
         query: Arc::new(Query::empty()),
         query_metrics: Default::default(),
         estimated_size: Default::default(),
+        evaluated_plan_count: Default::default(),
+        explanation: Default::default(),
     };
 
     let succeeded: Arc<AtomicBool> = Default::default();
@@ -205,6 +209,8 @@ async fn fetch_makes_post_requests() {
         query: Arc::new(Query::empty()),
         query_metrics: Default::default(),
         estimated_size: Default::default(),
+        evaluated_plan_count: Default::default(),
+        explanation: Default::default(),
     };
 
     let succeeded: Arc<AtomicBool> = Default::default();
@@ -334,6 +340,8 @@ async fn defer() {
             query: Arc::new(Query::empty()),
             query_metrics: Default::default(),
             estimated_size: Default::default(),
+            evaluated_plan_count: Default::default(),
+            explanation: Default::default(),
         };
 
     let mut mock_x_service = plugin::test::MockSubgraphService::new();
@@ -465,6 +473,8 @@ async fn defer_if_condition() {
         formatted_query_plan: None,
         query_metrics: Default::default(),
         estimated_size: Default::default(),
+        evaluated_plan_count: Default::default(),
+        explanation: Default::default(),
     };
 
     let mocked_accounts = MockSubgraph::builder()
@@ -621,6 +631,8 @@ async fn dependent_mutations() {
         query: Arc::new(Query::empty()),
         query_metrics: Default::default(),
         estimated_size: Default::default(),
+        evaluated_plan_count: Default::default(),
+        explanation: Default::default(),
     };
 
     let mut mock_a_service = plugin::test::MockSubgraphService::new();
@@ -1806,6 +1818,8 @@ fn broken_plan_does_not_panic() {
         query: Arc::new(Query::empty()),
         query_metrics: Default::default(),
         estimated_size: Default::default(),
+        evaluated_plan_count: Default::default(),
+        explanation: Default::default(),
     };
     let subgraph_schema = apollo_compiler::Schema::parse_and_validate(subgraph_schema, "").unwrap();
     let mut subgraph_schemas = HashMap::new();
@@ -19,6 +19,7 @@ use self::Event::NoMoreSchema;
 use self::Event::Reload;
 use self::Event::Shutdown;
 use self::Event::UpdateConfiguration;
+use self::Event::UpdateHostedSchema;
 use self::Event::UpdateLicense;
 use self::Event::UpdateSchema;
 use crate::uplink::license_enforcement::LicenseState;
@@ -38,6 +39,9 @@ pub(crate) enum Event {
     /// There are no more updates to the schema
     NoMoreSchema,
 
+    /// The schema of a hosted supergraph was updated.
+    UpdateHostedSchema(String, String),
+
     /// Update license {}
     UpdateLicense(LicenseState),
 
@@ -66,6 +70,9 @@ impl Debug for Event {
             NoMoreSchema => {
                 write!(f, "NoMoreSchema")
             }
+            UpdateHostedSchema(name, _) => {
+                write!(f, "UpdateHostedSchema({name}, <redacted>)")
+            }
             UpdateLicense(e) => {
                 write!(f, "UpdateLicense({e:?})")
             }
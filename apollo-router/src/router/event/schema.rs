@@ -10,6 +10,7 @@ use url::Url;
 
 use crate::router::Event;
 use crate::router::Event::NoMoreSchema;
+use crate::router::Event::UpdateHostedSchema;
 use crate::router::Event::UpdateSchema;
 use crate::uplink::schema_stream::SupergraphSdlQuery;
 use crate::uplink::stream_from_uplink;
@@ -176,6 +177,16 @@ impl SchemaSource {
         .chain(stream::iter(vec![NoMoreSchema]))
         .boxed()
     }
+
+    /// Convert this schema into a stream of updates of the hosted supergraph with this name.
+    pub(crate) fn into_hosted_stream(self, name: String) -> impl Stream<Item = Event> {
+        self.into_stream().filter_map(move |event| {
+            future::ready(match event {
+                UpdateSchema(schema) => Some(UpdateHostedSchema(name.clone(), schema)),
+                _ => None,
+            })
+        })
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
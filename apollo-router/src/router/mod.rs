@@ -4,6 +4,7 @@
 mod error;
 mod event;
 
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::Context;
@@ -94,6 +95,11 @@ impl RouterHttpServer {
     ///   Specifies where to find the router configuration.
     ///   If not provided, the default configuration as with an empty YAML file.
     ///
+    /// * `.hosted_schema(impl Into<String>, impl Into<`[`SchemaSource`]`>)`
+    ///   Optional.
+    ///   Specifies where to find the schema of a supergraph hosted alongside the main one,
+    ///   by the name it has in the `experimental_hosted_supergraphs` configuration.
+    ///
     /// * `.license(impl Into<`[`LicenseSource`]`>)`
     ///   Optional.
     ///   Specifies where to find the router license which controls if commercial features are enabled or not.
@@ -125,6 +131,7 @@ impl RouterHttpServer {
     #[builder(visibility = "pub", entry = "builder", exit = "start")]
     fn start(
         schema: SchemaSource,
+        hosted_schemas: HashMap<String, SchemaSource>,
         configuration: Option<ConfigurationSource>,
         license: Option<LicenseSource>,
         shutdown: Option<ShutdownSource>,
@@ -136,6 +143,7 @@ impl RouterHttpServer {
             shutdown.unwrap_or(ShutdownSource::CtrlC),
             configuration.unwrap_or_default(),
             schema,
+            hosted_schemas,
             uplink,
             license.unwrap_or_default(),
             shutdown_receiver,
@@ -229,13 +237,14 @@ fn generate_event_stream(
     shutdown: ShutdownSource,
     configuration: ConfigurationSource,
     schema: SchemaSource,
+    hosted_schemas: HashMap<String, SchemaSource>,
     uplink_config: Option<UplinkConfig>,
     license: LicenseSource,
     shutdown_receiver: oneshot::Receiver<()>,
 ) -> impl Stream<Item = Event> {
     let reload_source = ReloadSource::default();
 
-    let stream = stream::select_all(vec![
+    let mut streams = vec![
         shutdown.into_stream().boxed(),
         schema.into_stream().boxed(),
         license.into_stream().boxed(),
@@ -253,11 +262,18 @@ fn generate_event_stream(
             .into_stream()
             .map(|_| Event::Shutdown)
             .boxed(),
-    ])
-    .take_while(|msg| future::ready(!matches!(msg, Event::Shutdown)))
-    // Chain is required so that the final shutdown message is sent.
-    .chain(stream::iter(vec![Event::Shutdown]))
-    .boxed();
+    ];
+    streams.extend(
+        hosted_schemas
+            .into_iter()
+            .map(|(name, schema)| schema.into_hosted_stream(name).boxed()),
+    );
+
+    let stream = stream::select_all(streams)
+        .take_while(|msg| future::ready(!matches!(msg, Event::Shutdown)))
+        // Chain is required so that the final shutdown message is sent.
+        .chain(stream::iter(vec![Event::Shutdown]))
+        .boxed();
     stream
 }
 
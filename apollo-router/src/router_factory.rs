@@ -4,6 +4,7 @@ use std::sync::Arc;
 
 use apollo_compiler::validation::Valid;
 use axum::response::IntoResponse;
+use futures::future::BoxFuture;
 use http::StatusCode;
 use indexmap::IndexMap;
 use multimap::MultiMap;
@@ -123,6 +124,12 @@ pub(crate) trait RouterFactory:
     type Future: Send;
 
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint>;
+
+    /// Persists the state that should survive a restart, like the on-disk snapshot of the query
+    /// plan cache. Called on shutdown, once all connections are closed
+    fn persist(&self) -> BoxFuture<'static, ()> {
+        Box::pin(async {})
+    }
 }
 
 /// Factory for creating a RouterFactory
@@ -164,7 +171,11 @@ impl RouterSuperServiceFactory for YamlRouterFactory {
         let plugin_registry = &*crate::plugin::PLUGINS;
         let mut initial_telemetry_plugin = None;
 
-        if previous_router.is_none() && apollo_opentelemetry_initialized() {
+        // Hosted supergraphs share the telemetry of the main one
+        if previous_router.is_none()
+            && apollo_opentelemetry_initialized()
+            && configuration.hosted_supergraph.is_none()
+        {
             if let Some(factory) = plugin_registry
                 .iter()
                 .find(|factory| factory.name == "apollo.telemetry")
@@ -682,6 +693,7 @@ pub(crate) async fn create_plugins(
     add_optional_apollo_plugin!("preview_entity_cache");
     add_mandatory_apollo_plugin!("progressive_override");
     add_optional_apollo_plugin!("demand_control");
+    add_optional_apollo_plugin!("experimental_mock_subgraphs");
 
     // This relative ordering is documented in `docs/source/customizations/native.mdx`:
     add_optional_apollo_plugin!("rhai");
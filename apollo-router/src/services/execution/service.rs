@@ -33,6 +33,7 @@ use tracing_core::Level;
 
 use crate::apollo_studio_interop::extract_enums_from_response;
 use crate::apollo_studio_interop::ReferencedEnums;
+use crate::configuration::contracts::CONTRACT_VARIANT_CONTEXT_KEY;
 use crate::graphql::Error;
 use crate::graphql::IncrementalResponse;
 use crate::graphql::Response;
@@ -173,6 +174,15 @@ impl ExecutionService {
             };
             let stream = filter_stream(first, receiver, stream_mode);
             StreamWrapper(stream, tx_close_signal).boxed()
+        } else if has_initial_data && is_deferred {
+            // If it's a subscription event with deferred fragments, the initial payload of the event
+            // is followed by its deferred payloads
+            filter_stream(first, receiver, StreamMode::Defer)
+                .map(|mut response| {
+                    response.has_next.get_or_insert(true);
+                    response
+                })
+                .boxed()
         } else if has_initial_data {
             // If it's a subscription event
             once(ready(first)).boxed()
@@ -187,6 +197,7 @@ impl ExecutionService {
         }
 
         let schema = self.schema.clone();
+        let contract: Option<String> = context.get(CONTRACT_VARIANT_CONTEXT_KEY).ok().flatten();
         let mut nullified_paths: Vec<Path> = vec![];
 
         let metrics_ref_mode = match &self.apollo_telemetry_config {
@@ -244,6 +255,7 @@ impl ExecutionService {
                         &variables,
                         is_deferred,
                         &schema,
+                        contract.as_deref(),
                         &mut nullified_paths,
                         metrics_ref_mode,
                         &context,
@@ -263,6 +275,7 @@ impl ExecutionService {
         variables: &Object,
         is_deferred: bool,
         schema: &Arc<Schema>,
+        contract: Option<&str>,
         nullified_paths: &mut Vec<Path>,
         metrics_ref_mode: ApolloMetricsReferenceMode,
         context: &crate::Context,
@@ -296,6 +309,7 @@ impl ExecutionService {
 
         let has_next = response.has_next.unwrap_or(true);
         let variables_set = query.defer_variables_set(operation_name, variables);
+        let api_schema = schema.contract_api_schema(contract);
 
         tracing::debug_span!("format_response").in_scope(|| {
             let mut paths = Vec::new();
@@ -334,7 +348,7 @@ impl ExecutionService {
                     &mut response,
                     operation_name,
                     variables.clone(),
-                    schema.api_schema(),
+                    api_schema,
                     variables_set,
                 );
             }
@@ -345,7 +359,7 @@ impl ExecutionService {
                         &mut response,
                         operation_name,
                         variables.clone(),
-                        schema.api_schema(),
+                        api_schema,
                         variables_set,
                     )
                     ,
@@ -361,7 +375,7 @@ impl ExecutionService {
                 extract_enums_from_response(
                     query.clone(),
                     operation_name,
-                    schema.api_schema(),
+                    api_schema,
                     response_body,
                     &mut referenced_enums,
                 )
@@ -454,6 +468,7 @@ impl ExecutionService {
         let query = query.clone();
 
         let rewritten_label = rewrite_defer_label(&response);
+        let subscribed = response.subscribed;
         let incremental = sub_responses
             .into_iter()
             .filter_map(move |(path, data)| {
@@ -528,6 +543,7 @@ impl ExecutionService {
         Some(
             Response::builder()
                 .has_next(has_next)
+                .and_subscribed(subscribed)
                 .incremental(incremental)
                 .build(),
         )
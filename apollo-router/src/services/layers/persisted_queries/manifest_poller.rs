@@ -151,41 +151,46 @@ impl FreeformGraphQLSafelist {
     }
 
     fn normalize_body(&self, ast: Result<&ast::Document, &str>) -> String {
-        match ast {
-            Err(body_from_request) => {
-                // If we can't parse the operation (whether from the PQ list or the
-                // incoming request), then we can't normalize it. We keep it around
-                // unnormalized, so that it at least works as a byte-for-byte
-                // safelist entry.
-                body_from_request.to_string()
-            }
-            Ok(ast) => {
-                let mut operations = vec![];
-                let mut fragments = vec![];
-
-                for definition in &ast.definitions {
-                    match definition {
-                        ast::Definition::OperationDefinition(def) => operations.push(def.clone()),
-                        ast::Definition::FragmentDefinition(def) => fragments.push(def.clone()),
-                        _ => {}
-                    }
+        normalize_body(ast)
+    }
+}
+
+/// Normalizes an operation body, see [`FreeformGraphQLSafelist`] for details
+pub(crate) fn normalize_body(ast: Result<&ast::Document, &str>) -> String {
+    match ast {
+        Err(body_from_request) => {
+            // If we can't parse the operation (whether from the PQ list or the
+            // incoming request), then we can't normalize it. We keep it around
+            // unnormalized, so that it at least works as a byte-for-byte
+            // safelist entry.
+            body_from_request.to_string()
+        }
+        Ok(ast) => {
+            let mut operations = vec![];
+            let mut fragments = vec![];
+
+            for definition in &ast.definitions {
+                match definition {
+                    ast::Definition::OperationDefinition(def) => operations.push(def.clone()),
+                    ast::Definition::FragmentDefinition(def) => fragments.push(def.clone()),
+                    _ => {}
                 }
+            }
 
-                let mut new_document = ast::Document::new();
+            let mut new_document = ast::Document::new();
 
-                // First include operation definitions, sorted by name.
-                operations.sort_by_key(|x| x.name.clone());
-                new_document
-                    .definitions
-                    .extend(operations.into_iter().map(Into::into));
+            // First include operation definitions, sorted by name.
+            operations.sort_by_key(|x| x.name.clone());
+            new_document
+                .definitions
+                .extend(operations.into_iter().map(Into::into));
 
-                // Next include fragment definitions, sorted by name.
-                fragments.sort_by_key(|x| x.name.clone());
-                new_document
-                    .definitions
-                    .extend(fragments.into_iter().map(Into::into));
-                new_document.to_string()
-            }
+            // Next include fragment definitions, sorted by name.
+            fragments.sort_by_key(|x| x.name.clone());
+            new_document
+                .definitions
+                .extend(fragments.into_iter().map(Into::into));
+            new_document.to_string()
         }
     }
 }
@@ -0,0 +1,462 @@
+//! Records the operations sent by clients into a persisted query manifest ("learning" mode).
+//!
+//! The recorded manifest uses the `apollo-persisted-query-manifest` format, so it can be loaded
+//! with `experimental_local_manifests` once it has been reviewed, and merged with the manifests
+//! recorded by other router instances with the `router persisted-queries merge` command.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use tower::BoxError;
+
+use crate::configuration::PersistedQueriesLearning;
+
+const MANIFEST_FORMAT: &str = "apollo-persisted-query-manifest";
+
+/// A persisted query manifest with the metadata collected in learning mode
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub(crate) struct LearnedManifest {
+    pub(crate) format: String,
+    pub(crate) version: u64,
+    pub(crate) operations: Vec<LearnedOperation>,
+}
+
+/// An operation observed in client traffic
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LearnedOperation {
+    /// SHA-256 hash of the normalized body
+    pub(crate) id: String,
+    /// normalized body
+    pub(crate) body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    /// clients that sent this operation
+    #[serde(default)]
+    pub(crate) clients: Vec<LearnedClient>,
+    /// first time this operation was seen, in seconds since the UNIX epoch
+    pub(crate) first_seen: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct LearnedClient {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) version: Option<String>,
+}
+
+impl LearnedManifest {
+    fn new(operations: Vec<LearnedOperation>) -> Self {
+        Self {
+            format: MANIFEST_FORMAT.to_string(),
+            version: 1,
+            operations,
+        }
+    }
+
+    pub(crate) fn from_file(path: &Path) -> Result<Self, BoxError> {
+        let content = std::fs::read_to_string(path).map_err(|e| -> BoxError {
+            format!(
+                "could not read persisted query manifest {}: {}",
+                path.display(),
+                e
+            )
+            .into()
+        })?;
+        let manifest: LearnedManifest =
+            serde_json::from_str(&content).map_err(|e| -> BoxError {
+                format!(
+                    "could not parse persisted query manifest {}: {}",
+                    path.display(),
+                    e
+                )
+                .into()
+            })?;
+
+        if manifest.format != MANIFEST_FORMAT {
+            return Err(format!("manifest format is not '{MANIFEST_FORMAT}'").into());
+        }
+        if manifest.version != 1 {
+            return Err("persisted query manifest version is not 1".into());
+        }
+
+        Ok(manifest)
+    }
+
+    /// Writes the manifest to a temporary file then moves it to its final location, so that
+    /// readers never see a partially written file
+    pub(crate) fn write_to_file(&self, path: &Path) -> Result<(), BoxError> {
+        let mut tmp_path = path.to_path_buf();
+        tmp_path.set_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Merges manifests, deduplicating operations by ID
+    ///
+    /// Client lists are merged and the earliest first seen time is kept. Operations are sorted
+    /// by ID to produce stable output.
+    pub(crate) fn merge(manifests: impl IntoIterator<Item = LearnedManifest>) -> Self {
+        let mut operations: BTreeMap<String, LearnedOperation> = BTreeMap::new();
+
+        for operation in manifests.into_iter().flat_map(|m| m.operations) {
+            match operations.get_mut(&operation.id) {
+                None => {
+                    operations.insert(operation.id.clone(), operation);
+                }
+                Some(existing) => existing.merge(operation),
+            }
+        }
+
+        Self::new(
+            operations
+                .into_values()
+                .map(|mut operation| {
+                    operation.clients.sort();
+                    operation
+                })
+                .collect(),
+        )
+    }
+}
+
+impl LearnedOperation {
+    fn merge(&mut self, other: LearnedOperation) {
+        self.first_seen = self.first_seen.min(other.first_seen);
+        if self.name.is_none() {
+            self.name = other.name;
+        }
+        for client in other.clients {
+            if !self.clients.contains(&client) {
+                self.clients.push(client);
+            }
+        }
+    }
+}
+
+/// Collects the distinct operations sent by clients, and periodically writes them to a manifest file
+#[derive(Debug)]
+pub(crate) struct PersistedQueryManifestRecorder {
+    path: PathBuf,
+    max_operations: usize,
+    recorded: Arc<Mutex<Recorded>>,
+    _drop_signal: mpsc::Sender<()>,
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    operations: HashMap<String, LearnedOperation>,
+    /// whether operations were recorded since the last flush
+    dirty: bool,
+}
+
+impl PersistedQueryManifestRecorder {
+    pub(crate) fn new(config: &PersistedQueriesLearning) -> Result<Self, BoxError> {
+        let path = config
+            .path
+            .clone()
+            .ok_or("persisted query learning mode requires a manifest path")?;
+
+        // keep the operations recorded by a previous run
+        let mut operations = HashMap::new();
+        if path.exists() {
+            for operation in LearnedManifest::from_file(&path)?.operations {
+                operations.insert(operation.id.clone(), operation);
+            }
+        }
+
+        let recorded = Arc::new(Mutex::new(Recorded {
+            operations,
+            dirty: false,
+        }));
+        let (_drop_signal, mut drop_receiver) = mpsc::channel::<()>(1);
+
+        let flush_recorded = recorded.clone();
+        let flush_path = path.clone();
+        let mut interval = tokio::time::interval(config.flush_interval);
+        tokio::task::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        flush(&flush_recorded, &flush_path).await;
+                    }
+                    _ = drop_receiver.recv() => {
+                        flush(&flush_recorded, &flush_path).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            max_operations: config.max_operations,
+            recorded,
+            _drop_signal,
+        })
+    }
+
+    /// Records an operation, identified by its normalized body
+    pub(crate) fn record(
+        &self,
+        normalized_body: &str,
+        operation_name: Option<&str>,
+        client: LearnedClient,
+    ) {
+        let id = hex::encode(Sha256::digest(normalized_body.as_bytes()));
+
+        let mut recorded = self.recorded.lock();
+        let recorded = &mut *recorded;
+        match recorded.operations.get_mut(&id) {
+            Some(operation) => {
+                if !operation.clients.contains(&client) {
+                    operation.clients.push(client);
+                    recorded.dirty = true;
+                }
+            }
+            None => {
+                if recorded.operations.len() >= self.max_operations {
+                    tracing::warn!(
+                        path = %self.path.display(),
+                        "persisted query manifest recorder reached its maximum number of operations"
+                    );
+                    return;
+                }
+                recorded.dirty = true;
+                recorded.operations.insert(
+                    id.clone(),
+                    LearnedOperation {
+                        id,
+                        body: normalized_body.to_string(),
+                        name: operation_name.map(|name| name.to_string()),
+                        clients: vec![client],
+                        first_seen: SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .expect("we should not run before EPOCH")
+                            .as_secs(),
+                    },
+                );
+            }
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn manifest(&self) -> LearnedManifest {
+        LearnedManifest::merge([LearnedManifest::new(
+            self.recorded.lock().operations.values().cloned().collect(),
+        )])
+    }
+}
+
+/// Serializes the flushes of all the recorders. During a reload, the recorders of the previous and
+/// of the new configuration write to the same file
+static FLUSH_LOCK: Mutex<()> = parking_lot::const_mutex(());
+
+/// Writes the recorded operations if some were recorded since the last flush. The file is read
+/// and written on a blocking thread
+async fn flush(recorded: &Mutex<Recorded>, path: &Path) {
+    let operations: Vec<LearnedOperation> = {
+        let mut recorded = recorded.lock();
+        if !recorded.dirty {
+            return;
+        }
+        recorded.dirty = false;
+        recorded.operations.values().cloned().collect()
+    };
+
+    let path = path.to_path_buf();
+    let written = tokio::task::spawn_blocking(move || write_merged(operations, &path))
+        .await
+        .unwrap_or(false);
+    if !written {
+        // try again on the next flush
+        recorded.lock().dirty = true;
+    }
+}
+
+/// Merges the recorded operations with the manifest currently on disk, then writes it, so that
+/// operations flushed by another recorder are not lost
+fn write_merged(operations: Vec<LearnedOperation>, path: &Path) -> bool {
+    let recorded = LearnedManifest::new(operations);
+
+    let _guard = FLUSH_LOCK.lock();
+    let on_disk = if path.exists() {
+        match LearnedManifest::from_file(path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                // do not overwrite a manifest we cannot read
+                tracing::error!(
+                    path = %path.display(),
+                    error = %e,
+                    "could not merge the recorded persisted query manifest with the existing one"
+                );
+                return false;
+            }
+        }
+    } else {
+        LearnedManifest::default()
+    };
+    let manifest = LearnedManifest::merge([on_disk, recorded]);
+
+    match manifest.write_to_file(path) {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::error!(
+                path = %path.display(),
+                error = %e,
+                "could not write the recorded persisted query manifest"
+            );
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operation(id: &str, client: &str, first_seen: u64) -> LearnedOperation {
+        LearnedOperation {
+            id: id.to_string(),
+            body: format!("query {{ {id} }}"),
+            name: None,
+            clients: vec![LearnedClient {
+                name: Some(client.to_string()),
+                version: Some("1.0".to_string()),
+            }],
+            first_seen,
+        }
+    }
+
+    #[test]
+    fn merge_deduplicates_operations() {
+        let merged = LearnedManifest::merge([
+            LearnedManifest::new(vec![operation("b", "web", 20), operation("a", "web", 10)]),
+            LearnedManifest::new(vec![operation("b", "ios", 5), operation("b", "web", 30)]),
+        ]);
+
+        assert_eq!(merged.format, MANIFEST_FORMAT);
+        assert_eq!(merged.operations.len(), 2);
+        assert_eq!(merged.operations[0].id, "a");
+        assert_eq!(merged.operations[1].id, "b");
+        assert_eq!(merged.operations[1].first_seen, 5);
+        assert_eq!(
+            merged.operations[1]
+                .clients
+                .iter()
+                .map(|c| c.name.as_deref().unwrap())
+                .collect::<Vec<_>>(),
+            vec!["ios", "web"]
+        );
+    }
+
+    #[tokio::test]
+    async fn records_and_writes_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let recorder = PersistedQueryManifestRecorder::new(&PersistedQueriesLearning {
+            enabled: true,
+            path: Some(path.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let client = |name: &str| LearnedClient {
+            name: Some(name.to_string()),
+            version: None,
+        };
+        recorder.record("{a}", None, client("web"));
+        recorder.record("{a}", None, client("web"));
+        recorder.record("{a}", None, client("ios"));
+        recorder.record("query Q{b}", Some("Q"), client("web"));
+
+        let manifest = recorder.manifest();
+        assert_eq!(manifest.operations.len(), 2);
+        let a = manifest
+            .operations
+            .iter()
+            .find(|operation| operation.body == "{a}")
+            .unwrap();
+        assert_eq!(a.clients.len(), 2);
+
+        drop(recorder);
+        // the manifest is written by the flush task when the recorder is dropped
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert_eq!(LearnedManifest::from_file(&path).unwrap(), manifest);
+    }
+
+    #[tokio::test]
+    async fn recorders_sharing_a_file_keep_each_other_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let config = PersistedQueriesLearning {
+            enabled: true,
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        let client = LearnedClient {
+            name: Some("web".to_string()),
+            version: None,
+        };
+
+        // during a reload, the previous and the new recorders are alive at the same time
+        let previous = PersistedQueryManifestRecorder::new(&config).unwrap();
+        let new = PersistedQueryManifestRecorder::new(&config).unwrap();
+        previous.record("{a}", None, client.clone());
+        new.record("{b}", None, client.clone());
+
+        drop(new);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        drop(previous);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let manifest = LearnedManifest::from_file(&path).unwrap();
+        assert_eq!(manifest.operations.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn flush_skips_unchanged_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("manifest.json");
+        let recorder = PersistedQueryManifestRecorder::new(&PersistedQueriesLearning {
+            enabled: true,
+            path: Some(path.clone()),
+            ..Default::default()
+        })
+        .unwrap();
+        let client = LearnedClient {
+            name: Some("web".to_string()),
+            version: None,
+        };
+
+        recorder.record("{a}", None, client.clone());
+        flush(&recorder.recorded, &path).await;
+        assert!(path.exists());
+
+        // nothing new was recorded, the manifest is not written again
+        std::fs::remove_file(&path).unwrap();
+        recorder.record("{a}", None, client.clone());
+        flush(&recorder.recorded, &path).await;
+        assert!(!path.exists());
+
+        recorder.record("{b}", None, client);
+        flush(&recorder.recorded, &path).await;
+        assert_eq!(
+            LearnedManifest::from_file(&path).unwrap().operations.len(),
+            2
+        );
+    }
+}
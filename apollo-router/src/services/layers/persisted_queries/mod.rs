@@ -1,5 +1,6 @@
 mod id_extractor;
 mod manifest_poller;
+mod manifest_recorder;
 
 #[cfg(test)]
 use std::sync::Arc;
@@ -9,11 +10,17 @@ use http::HeaderValue;
 use http::StatusCode;
 use id_extractor::PersistedQueryIdExtractor;
 pub(crate) use manifest_poller::PersistedQueryManifestPoller;
+use manifest_recorder::LearnedClient;
+pub(crate) use manifest_recorder::LearnedManifest;
+use manifest_recorder::PersistedQueryManifestRecorder;
 use tower::BoxError;
 
+use self::manifest_poller::normalize_body;
 use self::manifest_poller::FreeformGraphQLAction;
 use super::query_analysis::ParsedDocument;
 use crate::graphql::Error as GraphQLError;
+use crate::plugins::telemetry::CLIENT_NAME;
+use crate::plugins::telemetry::CLIENT_VERSION;
 use crate::services::SupergraphRequest;
 use crate::services::SupergraphResponse;
 use crate::Configuration;
@@ -27,6 +34,8 @@ pub(crate) struct PersistedQueryLayer {
     /// Manages polling uplink for persisted queries and caches the current
     /// value of the manifest and projected safelist. None if the layer is disabled.
     pub(crate) manifest_poller: Option<PersistedQueryManifestPoller>,
+    /// Records the operations sent by clients into a manifest file. None if learning mode is disabled.
+    manifest_recorder: Option<PersistedQueryManifestRecorder>,
     introspection_enabled: bool,
 }
 
@@ -34,16 +43,30 @@ impl PersistedQueryLayer {
     /// Create a new [`PersistedQueryLayer`] from CLI options, YAML configuration,
     /// and optionally, an existing persisted query manifest poller.
     pub(crate) async fn new(configuration: &Configuration) -> Result<Self, BoxError> {
+        let manifest_recorder = if configuration
+            .persisted_queries
+            .experimental_learning
+            .enabled
+        {
+            Some(PersistedQueryManifestRecorder::new(
+                &configuration.persisted_queries.experimental_learning,
+            )?)
+        } else {
+            None
+        };
+
         if configuration.persisted_queries.enabled {
             Ok(Self {
                 manifest_poller: Some(
                     PersistedQueryManifestPoller::new(configuration.clone()).await?,
                 ),
+                manifest_recorder,
                 introspection_enabled: configuration.supergraph.introspection,
             })
         } else {
             Ok(Self {
                 manifest_poller: None,
+                manifest_recorder,
                 introspection_enabled: configuration.supergraph.introspection,
             })
         }
@@ -148,6 +171,10 @@ impl PersistedQueryLayer {
         &self,
         request: SupergraphRequest,
     ) -> Result<SupergraphRequest, SupergraphResponse> {
+        if let Some(manifest_recorder) = &self.manifest_recorder {
+            record_operation(manifest_recorder, &request);
+        }
+
         let manifest_poller = match &self.manifest_poller {
             // PQ feature entirely disabled; just pass through.
             None => return Ok(request),
@@ -247,6 +274,33 @@ impl PersistedQueryLayer {
     }
 }
 
+/// Records a freeform operation in learning mode. Operations resolved from the manifest by ID
+/// are already registered, so they are not recorded.
+fn record_operation(
+    manifest_recorder: &PersistedQueryManifestRecorder,
+    request: &SupergraphRequest,
+) {
+    let doc = request.context.extensions().with_lock(|lock| {
+        if lock.get::<UsedQueryIdFromManifest>().is_some() {
+            None
+        } else {
+            lock.get::<ParsedDocument>().cloned()
+        }
+    });
+
+    if let Some(doc) = doc {
+        let client = LearnedClient {
+            name: request.context.get(CLIENT_NAME).ok().flatten(),
+            version: request.context.get(CLIENT_VERSION).ok().flatten(),
+        };
+        manifest_recorder.record(
+            &normalize_body(Ok(&doc.ast)),
+            request.supergraph_request.body().operation_name.as_deref(),
+            client,
+        );
+    }
+}
+
 fn log_unknown_operation(operation_body: &str) {
     tracing::warn!(message = "unknown operation", operation_body);
 }
@@ -378,12 +432,14 @@ mod tests {
     use super::*;
     use crate::configuration::Apq;
     use crate::configuration::PersistedQueries;
+    use crate::configuration::PersistedQueriesLearning;
     use crate::configuration::PersistedQueriesSafelist;
     use crate::configuration::Supergraph;
     use crate::services::layers::persisted_queries::manifest_poller::FreeformGraphQLBehavior;
     use crate::services::layers::query_analysis::QueryAnalysisLayer;
     use crate::spec::Schema;
     use crate::test_harness::mocks::persisted_queries::*;
+    use crate::Context;
 
     #[tokio::test(flavor = "multi_thread")]
     async fn disabled_pq_layer_has_no_poller() {
@@ -763,6 +819,99 @@ mod tests {
         ).await;
     }
 
+    async fn run_all_layers(
+        pq_layer: &PersistedQueryLayer,
+        query_analysis_layer: &QueryAnalysisLayer,
+        request: SupergraphRequest,
+    ) {
+        let request = pq_layer
+            .supergraph_request(request)
+            .ok()
+            .expect("pq layer returned error response instead of returning a request");
+        let request = query_analysis_layer
+            .supergraph_request(request)
+            .await
+            .ok()
+            .expect("QA layer returned error response instead of returning a request");
+        pq_layer
+            .supergraph_request_with_analyzed_query(request)
+            .await
+            .ok()
+            .expect("pq layer second hook returned error response instead of returning a request");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn learning_mode_records_freeform_operations() {
+        let manifest = HashMap::from([("me-id".to_string(), "query Me { me { id } }".to_string())]);
+        let (_mock_guard, uplink_config) = mock_pq_uplink(&manifest).await;
+        let dir = tempfile::tempdir().unwrap();
+
+        let config = Configuration::fake_builder()
+            .persisted_query(
+                PersistedQueries::builder()
+                    .enabled(true)
+                    .experimental_learning(PersistedQueriesLearning {
+                        enabled: true,
+                        path: Some(dir.path().join("manifest.json")),
+                        ..Default::default()
+                    })
+                    .build(),
+            )
+            .uplink(uplink_config)
+            .apq(Apq::fake_builder().enabled(false).build())
+            .build()
+            .unwrap();
+        let pq_layer = PersistedQueryLayer::new(&config).await.unwrap();
+        let schema = Arc::new(
+            Schema::parse(
+                include_str!("../../../testdata/supergraph.graphql"),
+                &Default::default(),
+            )
+            .unwrap(),
+        );
+        let query_analysis_layer = QueryAnalysisLayer::new(schema, Arc::new(config)).await;
+
+        let context = Context::new();
+        context.insert(CLIENT_NAME, "web".to_string()).unwrap();
+        context.insert(CLIENT_VERSION, "1.0".to_string()).unwrap();
+        run_all_layers(
+            &pq_layer,
+            &query_analysis_layer,
+            SupergraphRequest::fake_builder()
+                .query("query SomeQuery { me { name } }")
+                .operation_name("SomeQuery")
+                .context(context)
+                .build()
+                .unwrap(),
+        )
+        .await;
+
+        // the operation resolved from the manifest by ID is already registered
+        run_all_layers(
+            &pq_layer,
+            &query_analysis_layer,
+            SupergraphRequest::fake_builder()
+                .extension(
+                    "persistedQuery",
+                    json!({"version": 1, "sha256Hash": "me-id"}),
+                )
+                .build()
+                .unwrap(),
+        )
+        .await;
+
+        let recorded = pq_layer.manifest_recorder.as_ref().unwrap().manifest();
+        assert_eq!(recorded.operations.len(), 1);
+        assert_eq!(recorded.operations[0].name.as_deref(), Some("SomeQuery"));
+        assert_eq!(
+            recorded.operations[0].clients,
+            vec![LearnedClient {
+                name: Some("web".to_string()),
+                version: Some("1.0".to_string()),
+            }]
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn pq_layer_rejects_invalid_ids_with_safelisting_enabled() {
         let (_id, _body, manifest) = fake_manifest();
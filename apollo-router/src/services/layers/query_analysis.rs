@@ -17,11 +17,13 @@ use tokio::task;
 
 use crate::apollo_studio_interop::generate_extended_references;
 use crate::apollo_studio_interop::ExtendedReferenceStats;
+use crate::configuration::contracts::CONTRACT_VARIANT_CONTEXT_KEY;
 use crate::context::OPERATION_KIND;
 use crate::context::OPERATION_NAME;
 use crate::graphql::Error;
 use crate::graphql::ErrorExtension;
 use crate::graphql::IntoGraphQLErrors;
+use crate::introspection;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::telemetry::config::ApolloMetricsReferenceMode;
 use crate::plugins::telemetry::config::Conf as TelemetryConfig;
@@ -51,6 +53,7 @@ pub(crate) struct QueryAnalysisLayer {
 struct QueryAnalysisKey {
     query: String,
     operation_name: Option<String>,
+    contract: Option<String>,
 }
 
 impl QueryAnalysisLayer {
@@ -79,9 +82,11 @@ impl QueryAnalysisLayer {
         &self,
         query: &str,
         operation_name: Option<&str>,
+        contract: Option<&str>,
     ) -> Result<(ParsedDocument, Node<Operation>), SpecError> {
         let query = query.to_string();
         let operation_name = operation_name.map(|o| o.to_string());
+        let contract = contract.map(|c| c.to_string());
         let schema = self.schema.clone();
         let conf = self.configuration.clone();
 
@@ -91,10 +96,11 @@ impl QueryAnalysisLayer {
 
         task::spawn_blocking(move || {
             span.in_scope(|| {
-                let doc = Query::parse_document(
+                let doc = Query::parse_contract_document(
                     &query,
                     operation_name.as_deref(),
                     schema.as_ref(),
+                    contract.as_deref(),
                     conf.as_ref(),
                 )?;
                 let operation = doc.get_operation(operation_name.as_deref())?.clone();
@@ -139,6 +145,17 @@ impl QueryAnalysisLayer {
             .query
             .clone()
             .expect("query presence was already checked");
+        let contract = self
+            .configuration
+            .supergraph
+            .experimental_contracts
+            .select(&request.supergraph_request, &request.context);
+        if let Some(contract) = &contract {
+            request
+                .context
+                .insert(CONTRACT_VARIANT_CONTEXT_KEY, contract.clone())
+                .expect("cannot insert contract variant into context; this is a bug");
+        }
         let entry = self
             .cache
             .lock()
@@ -146,16 +163,21 @@ impl QueryAnalysisLayer {
             .get(&QueryAnalysisKey {
                 query: query.clone(),
                 operation_name: op_name.clone(),
+                contract: contract.clone(),
             })
             .cloned();
 
         let res = match entry {
-            None => match self.parse_document(&query, op_name.as_deref()).await {
+            None => match self
+                .parse_document(&query, op_name.as_deref(), contract.as_deref())
+                .await
+            {
                 Err(errors) => {
                     (*self.cache.lock().await).put(
                         QueryAnalysisKey {
                             query,
                             operation_name: op_name.clone(),
+                            contract: contract.clone(),
                         },
                         Err(errors.clone()),
                     );
@@ -185,6 +207,7 @@ impl QueryAnalysisLayer {
                         QueryAnalysisKey {
                             query,
                             operation_name: op_name.clone(),
+                            contract: contract.clone(),
                         },
                         Ok((context.clone(), doc.clone())),
                     );
@@ -197,6 +220,14 @@ impl QueryAnalysisLayer {
 
         match res {
             Ok((context, doc)) => {
+                if let Err(errors) = self.check_introspection(&request, &doc, op_name.as_deref()) {
+                    return Err(SupergraphResponse::builder()
+                        .errors(errors)
+                        .status_code(StatusCode::BAD_REQUEST)
+                        .context(request.context)
+                        .build()
+                        .expect("response is valid"));
+                }
                 request.context.extend(&context);
 
                 let extended_ref_stats = if matches!(
@@ -206,7 +237,7 @@ impl QueryAnalysisLayer {
                     Some(generate_extended_references(
                         doc.executable.clone(),
                         op_name,
-                        self.schema.api_schema(),
+                        self.schema.contract_api_schema(contract.as_deref()),
                         &request.supergraph_request.body().variables.clone(),
                     ))
                 } else {
@@ -248,6 +279,58 @@ impl QueryAnalysisLayer {
             }
         }
     }
+
+    /// Checks schema introspection queries against the introspection access control and limits
+    fn check_introspection(
+        &self,
+        request: &SupergraphRequest,
+        doc: &ParsedDocument,
+        operation_name: Option<&str>,
+    ) -> Result<(), Vec<Error>> {
+        let control = &self
+            .configuration
+            .supergraph
+            .experimental_introspection_control;
+        // When introspection is disabled, the query planner rejects introspection queries
+        if !self.configuration.supergraph.introspection
+            || (control.allow.is_empty() && !control.has_limits())
+        {
+            return Ok(());
+        }
+        let Ok(operation) = doc.get_operation(operation_name) else {
+            return Ok(());
+        };
+        if !introspection::is_schema_introspection(&doc.executable, operation) {
+            return Ok(());
+        }
+
+        let (code, message) = if !control.allows(&request.supergraph_request, &request.context) {
+            (
+                "INTROSPECTION_NOT_ALLOWED",
+                "introspection is not allowed for this request".to_string(),
+            )
+        } else if let Err(message) =
+            introspection::check_limits(control, &doc.executable, operation)
+        {
+            tracing::debug!("introspection query exceeded limits: {message}");
+            (
+                "INTROSPECTION_LIMITS_EXCEEDED",
+                format!("introspection query exceeded limits: {message}"),
+            )
+        } else {
+            return Ok(());
+        };
+        u64_counter!(
+            "apollo.router.operations.introspection.rejected",
+            "Number of introspection queries rejected by the introspection access control and limits",
+            1u64,
+            "graphql.error.extensions.code" = code
+        );
+        Err(vec![Error::builder()
+            .message(message)
+            .extension_code(code)
+            .build()])
+    }
 }
 
 pub(crate) type ParsedDocument = Arc<ParsedDocumentInner>;
@@ -298,3 +381,97 @@ impl PartialEq for ParsedDocumentInner {
 }
 
 impl Eq for ParsedDocumentInner {}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::configuration::Supergraph;
+    use crate::metrics::FutureMetricsExt;
+
+    const INTROSPECTION_QUERY: &str = "{ __schema { types { name } } }";
+
+    async fn layer(control: serde_json::Value) -> QueryAnalysisLayer {
+        let configuration = Configuration::fake_builder()
+            .supergraph(
+                Supergraph::fake_builder()
+                    .introspection(true)
+                    .experimental_introspection_control(serde_json::from_value(control).unwrap())
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let schema = Schema::parse(
+            include_str!("../../testdata/supergraph.graphql"),
+            &configuration,
+        )
+        .unwrap();
+        QueryAnalysisLayer::new(Arc::new(schema), Arc::new(configuration)).await
+    }
+
+    /// The error code of the response, if the request was rejected
+    async fn rejection(layer: &QueryAnalysisLayer, query: &str, header: &str) -> Option<String> {
+        let request = SupergraphRequest::fake_builder()
+            .query(query)
+            .header("x-introspection", header)
+            .build()
+            .unwrap();
+        let mut response = layer.supergraph_request(request).await.err()?;
+        assert_eq!(response.response.status(), StatusCode::BAD_REQUEST);
+        let response = response.next_response().await.unwrap();
+        response.errors[0]
+            .extensions
+            .get("code")
+            .and_then(|code| code.as_str())
+            .map(|code| code.to_string())
+    }
+
+    #[tokio::test]
+    async fn introspection_not_allowed() {
+        async {
+            let layer = layer(json!({
+                "allow": [{ "header": { "name": "x-introspection", "value": "allowed" } }]
+            }))
+            .await;
+
+            assert_eq!(
+                rejection(&layer, INTROSPECTION_QUERY, "denied").await,
+                Some("INTROSPECTION_NOT_ALLOWED".to_string())
+            );
+            assert_counter!(
+                "apollo.router.operations.introspection.rejected",
+                1,
+                "graphql.error.extensions.code" = "INTROSPECTION_NOT_ALLOWED"
+            );
+            assert_eq!(
+                rejection(&layer, INTROSPECTION_QUERY, "allowed").await,
+                None
+            );
+            // other operations are not restricted
+            assert_eq!(rejection(&layer, "{ me { id } }", "denied").await, None);
+        }
+        .with_metrics()
+        .await;
+    }
+
+    #[tokio::test]
+    async fn introspection_limits_exceeded() {
+        async {
+            let layer = layer(json!({ "max_depth": 3 })).await;
+
+            assert_eq!(
+                rejection(&layer, "{ __schema { types { fields { name } } } }", "").await,
+                Some("INTROSPECTION_LIMITS_EXCEEDED".to_string())
+            );
+            assert_counter!(
+                "apollo.router.operations.introspection.rejected",
+                1,
+                "graphql.error.extensions.code" = "INTROSPECTION_LIMITS_EXCEEDED"
+            );
+            assert_eq!(rejection(&layer, INTROSPECTION_QUERY, "").await, None);
+        }
+        .with_metrics()
+        .await;
+    }
+}
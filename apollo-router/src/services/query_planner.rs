@@ -8,8 +8,10 @@ use serde::Deserialize;
 use serde::Serialize;
 use static_assertions::assert_impl_all;
 
+use crate::error::CacheResolverError;
 use crate::error::QueryPlannerError;
 use crate::graphql;
+use crate::plugins::authorization::RejectedPaths;
 use crate::query_planner::QueryPlan;
 use crate::Context;
 
@@ -78,8 +80,17 @@ pub(crate) struct Response {
 /// Query, QueryPlan and Introspection data.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) enum QueryPlannerContent {
-    Plan { plan: Arc<QueryPlan> },
-    Response { response: Box<graphql::Response> },
+    Plan {
+        plan: Arc<QueryPlan>,
+    },
+    Response {
+        response: Box<graphql::Response>,
+    },
+    /// the query was rejected by the authorization directives
+    Unauthorized {
+        response: Box<graphql::Response>,
+        rejected: RejectedPaths,
+    },
     IntrospectionDisabled,
 }
 
@@ -103,6 +114,8 @@ impl Response {
 }
 
 pub(crate) type BoxService = tower::util::BoxService<Request, Response, QueryPlannerError>;
+pub(crate) type BoxCloneCachingService =
+    tower::util::BoxCloneService<CachingRequest, Response, CacheResolverError>;
 #[allow(dead_code)]
 pub(crate) type BoxCloneService =
     tower::util::BoxCloneService<Request, Response, QueryPlannerError>;
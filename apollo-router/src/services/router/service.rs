@@ -855,6 +855,11 @@ impl RouterFactory for RouterCreator {
             .for_each(|p| mm.extend(p.web_endpoints()));
         mm
     }
+
+    fn persist(&self) -> BoxFuture<'static, ()> {
+        let supergraph_creator = self.supergraph_creator.clone();
+        Box::pin(async move { supergraph_creator.persist_query_planner_cache().await })
+    }
 }
 
 impl RouterCreator {
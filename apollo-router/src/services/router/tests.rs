@@ -571,3 +571,74 @@ async fn escaped_quotes_in_string_literal() {
     // The string literal made it through unchanged:
     assert!(subgraph_query.contains(r#"reviewsForAuthor(authorID:"\"1\"")"#));
 }
+
+#[tokio::test]
+async fn it_serves_contract_variants() {
+    const TAG_DIRECTIVE: &str = "directive @tag(name: String!) repeatable on FIELD_DEFINITION \
+        | OBJECT | INTERFACE | UNION | ARGUMENT_DEFINITION | SCALAR | ENUM | ENUM_VALUE \
+        | INPUT_OBJECT | INPUT_FIELD_DEFINITION";
+    let schema = include_str!("../../../testing_schema.graphql").replace(
+        "inStock: Boolean @join__field(graph: INVENTORY)",
+        r#"inStock: Boolean @join__field(graph: INVENTORY) @tag(name: "internal")"#,
+    ) + TAG_DIRECTIVE;
+    let config = serde_json::json!({
+        "supergraph": {
+            "introspection": true,
+            "experimental_contracts": {
+                "variants": [{
+                    "name": "public",
+                    "exclude": ["internal"],
+                    "selectors": [{ "header": { "name": "x-contract", "value": "public" } }]
+                }]
+            }
+        }
+    });
+    let router = crate::TestHarness::builder()
+        .schema(&schema)
+        .configuration_json(config)
+        .unwrap()
+        .build_router()
+        .await
+        .unwrap();
+
+    let query = |query: &str, contract: Option<&str>| {
+        let mut builder = supergraph::Request::fake_builder()
+            .query(query)
+            .header(CONTENT_TYPE, APPLICATION_JSON.essence_str());
+        if let Some(contract) = contract {
+            builder = builder.header("x-contract", contract);
+        }
+        let request = builder.build().unwrap().try_into().unwrap();
+        let router = router.clone();
+        async move {
+            let response = router.oneshot(request).await.unwrap().response;
+            let status = response.status();
+            let body: serde_json::Value =
+                serde_json::from_slice(&get_body_bytes(response.into_body()).await.unwrap())
+                    .unwrap();
+            (status, body)
+        }
+    };
+
+    let introspection = r#"{ __type(name: "Product") { fields { name } } }"#;
+    let has_in_stock = |body: &serde_json::Value| {
+        body["data"]["__type"]["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|field| field["name"] == "inStock")
+    };
+    let (_, body) = query(introspection, None).await;
+    assert!(has_in_stock(&body));
+    let (_, body) = query(introspection, Some("public")).await;
+    assert!(!has_in_stock(&body));
+
+    let (status, _) = query("{ topProducts { inStock } }", None).await;
+    assert_eq!(status, http::StatusCode::OK);
+    let (status, body) = query("{ topProducts { inStock } }", Some("public")).await;
+    assert_eq!(status, http::StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["errors"][0]["extensions"]["code"],
+        "GRAPHQL_VALIDATION_FAILED"
+    );
+}
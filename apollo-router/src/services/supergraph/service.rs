@@ -12,6 +12,7 @@ use http::StatusCode;
 use indexmap::IndexMap;
 use opentelemetry::Key;
 use opentelemetry::KeyValue;
+use parking_lot::Mutex;
 use router_bridge::planner::Planner;
 use router_bridge::planner::UsageReporting;
 use tokio::sync::mpsc;
@@ -44,6 +45,8 @@ use crate::plugins::telemetry::Telemetry;
 use crate::plugins::telemetry::LOGGING_DISPLAY_BODY;
 use crate::plugins::traffic_shaping::TrafficShaping;
 use crate::plugins::traffic_shaping::APOLLO_TRAFFIC_SHAPING;
+use crate::query_planner::subgraph_latency::LatencyWeightsTask;
+use crate::query_planner::subgraph_latency::SubgraphLatencies;
 use crate::query_planner::subscription::SubscriptionHandle;
 use crate::query_planner::subscription::OPENED_SUBSCRIPTIONS;
 use crate::query_planner::subscription::SUBSCRIPTION_EVENT_SPAN_NAME;
@@ -87,7 +90,7 @@ pub(crate) type Plugins = IndexMap<String, Box<dyn DynPlugin>>;
 #[derive(Clone)]
 pub(crate) struct SupergraphService {
     execution_service_factory: ExecutionServiceFactory,
-    query_planner_service: CachingQueryPlanner<BridgeQueryPlannerPool>,
+    query_planner_service: query_planner::BoxCloneCachingService,
     schema: Arc<Schema>,
     notify: Notify<String, graphql::Response>,
 }
@@ -96,7 +99,7 @@ pub(crate) struct SupergraphService {
 impl SupergraphService {
     #[builder]
     pub(crate) fn new(
-        query_planner_service: CachingQueryPlanner<BridgeQueryPlannerPool>,
+        query_planner_service: query_planner::BoxCloneCachingService,
         execution_service_factory: ExecutionServiceFactory,
         schema: Arc<Schema>,
         notify: Notify<String, graphql::Response>,
@@ -161,7 +164,7 @@ impl Service<SupergraphRequest> for SupergraphService {
 }
 
 async fn service_call(
-    planning: CachingQueryPlanner<BridgeQueryPlannerPool>,
+    planning: query_planner::BoxCloneCachingService,
     execution_service_factory: ExecutionServiceFactory,
     schema: Arc<Schema>,
     req: SupergraphRequest,
@@ -213,6 +216,14 @@ async fn service_call(
         Some(QueryPlannerContent::Response { response }) => Ok(
             SupergraphResponse::new_from_graphql_response(*response, context),
         ),
+        Some(QueryPlannerContent::Unauthorized { response, rejected }) => {
+            context
+                .extensions()
+                .with_lock(|mut lock| lock.insert(rejected));
+            Ok(SupergraphResponse::new_from_graphql_response(
+                *response, context,
+            ))
+        }
         Some(QueryPlannerContent::IntrospectionDisabled) => {
             let mut response = SupergraphResponse::new_from_graphql_response(
                 graphql::Response::builder()
@@ -456,6 +467,8 @@ async fn subscription_task(
                 query: query_plan.query.clone(),
                 query_metrics: query_plan.query_metrics,
                 estimated_size: Default::default(),
+                evaluated_plan_count: query_plan.evaluated_plan_count,
+                explanation: query_plan.explanation.clone(),
             })
         }),
         _ => {
@@ -629,9 +642,8 @@ async fn dispatch_event(
                 .await;
 
             let execution_service = execution_service_factory.create();
-            let execution_response = execution_service.oneshot(execution_request).await;
-            let next_response = match execution_response {
-                Ok(mut execution_response) => execution_response.next_response().await,
+            let mut execution_response = match execution_service.oneshot(execution_request).await {
+                Ok(execution_response) => execution_response,
                 Err(err) => {
                     tracing::error!("cannot execute the subscription event: {err:?}");
                     let _ = sender
@@ -650,16 +662,25 @@ async fn dispatch_event(
                 }
             };
 
-            if let Some(mut next_response) = next_response {
+            let mut res = Ok(());
+            if let Some(mut next_response) = execution_response.next_response().await {
                 next_response.created_at = val.created_at;
                 next_response.subscribed = val.subscribed;
                 val.errors.append(&mut next_response.errors);
                 next_response.errors = val.errors;
 
-                sender.send(next_response).await
-            } else {
-                Ok(())
+                res = sender.send(next_response).await;
             }
+
+            // Deferred payloads of the event, if any
+            while res.is_ok() {
+                let Some(mut next_response) = execution_response.next_response().await else {
+                    break;
+                };
+                next_response.subscribed = val.subscribed;
+                res = sender.send(next_response).await;
+            }
+            res
         }
         None => sender.send(val).await,
     };
@@ -671,8 +692,23 @@ async fn dispatch_event(
     res
 }
 
+/// Wraps the query planner with the telemetry `query_planner` instrumentation stage
+fn instrument_query_planner(
+    planning: CachingQueryPlanner<BridgeQueryPlannerPool>,
+    plugins: &Plugins,
+) -> query_planner::BoxCloneCachingService {
+    match plugins
+        .iter()
+        .find(|i| i.0.as_str() == "apollo.telemetry")
+        .and_then(|plugin| (*plugin.1).as_any().downcast_ref::<Telemetry>())
+    {
+        Some(telemetry) => telemetry.query_planner_service(planning.boxed_clone()),
+        None => planning.boxed_clone(),
+    }
+}
+
 async fn plan_query(
-    mut planning: CachingQueryPlanner<BridgeQueryPlannerPool>,
+    planning: query_planner::BoxCloneCachingService,
     operation_name: Option<String>,
     context: Context,
     schema: Arc<Schema>,
@@ -701,7 +737,7 @@ async fn plan_query(
     }
 
     let qpr = planning
-        .call(
+        .oneshot(
             query_planner::CachingRequest::builder()
                 .query(query_str)
                 .and_operation_name(operation_name)
@@ -799,20 +835,44 @@ impl PluggableSupergraphServiceBuilder {
         let schema = self.planner.schema();
         let subgraph_schemas = self.planner.subgraph_schemas();
 
+        let latency_aware_planning = &configuration
+            .supergraph
+            .query_planning
+            .experimental_latency_aware_planning;
+        if latency_aware_planning.enabled && !configuration.latency_aware_planning() {
+            tracing::warn!(
+                "latency aware query planning requires `experimental_query_planner_mode: new`, it will be ignored"
+            );
+        }
+        // Each supergraph tracks the latencies of its own subgraphs
+        let subgraph_latencies = configuration.latency_aware_planning().then(|| {
+            Arc::new(SubgraphLatencies::new(
+                schema.subgraphs().map(|(name, _)| name.clone()),
+                self.planner.subgraph_latency_weights(),
+            ))
+        });
+        let latency_weights_task = subgraph_latencies
+            .as_ref()
+            .map(|latencies| Arc::new(latencies.start(latency_aware_planning)));
+
         let query_planner_service = CachingQueryPlanner::new(
             self.planner,
             schema.clone(),
             subgraph_schemas,
             &configuration,
             IndexMap::default(),
+            subgraph_latencies.clone(),
         )
         .await?;
 
         // Activate the telemetry plugin.
         // We must NOT fail to go live with the new router from this point as the telemetry plugin activate interacts with globals.
-        for (_, plugin) in self.plugins.iter() {
-            if let Some(telemetry) = plugin.as_any().downcast_ref::<Telemetry>() {
-                telemetry.activate();
+        // Hosted supergraphs share the telemetry of the main one, so they do not activate their own.
+        if configuration.hosted_supergraph.is_none() {
+            for (_, plugin) in self.plugins.iter() {
+                if let Some(telemetry) = plugin.as_any().downcast_ref::<Telemetry>() {
+                    telemetry.activate();
+                }
             }
         }
 
@@ -820,6 +880,13 @@ impl PluggableSupergraphServiceBuilder {
         // For now just shoe-horn something in, but if we ever reintroduce the query planner hook in plugins and activate then this can be made clean.
         query_planner_service.activate();
 
+        // The instrumentation reads the telemetry instruments, so it is built after activation
+        // and then shared by every request
+        let instrumented_query_planner_service = Arc::new(Mutex::new(instrument_query_planner(
+            query_planner_service.clone(),
+            &self.plugins,
+        )));
+
         let subgraph_service_factory = Arc::new(SubgraphServiceFactory::new(
             self.subgraph_services
                 .into_iter()
@@ -830,10 +897,13 @@ impl PluggableSupergraphServiceBuilder {
 
         Ok(SupergraphCreator {
             query_planner_service,
+            instrumented_query_planner_service,
             subgraph_service_factory,
             schema,
             plugins: self.plugins,
             config: configuration,
+            subgraph_latencies,
+            _latency_weights_task: latency_weights_task,
         })
     }
 }
@@ -842,10 +912,14 @@ impl PluggableSupergraphServiceBuilder {
 #[derive(Clone)]
 pub(crate) struct SupergraphCreator {
     query_planner_service: CachingQueryPlanner<BridgeQueryPlannerPool>,
+    // `BoxCloneService` is not `Sync`, the mutex is only held to clone it for each supergraph service
+    instrumented_query_planner_service: Arc<Mutex<query_planner::BoxCloneCachingService>>,
     subgraph_service_factory: Arc<SubgraphServiceFactory>,
     schema: Arc<Schema>,
     config: Arc<Configuration>,
     plugins: Arc<Plugins>,
+    subgraph_latencies: Option<Arc<SubgraphLatencies>>,
+    _latency_weights_task: Option<Arc<LatencyWeightsTask>>,
 }
 
 pub(crate) trait HasPlugins {
@@ -895,7 +969,7 @@ impl SupergraphCreator {
         Future = BoxFuture<'static, supergraph::ServiceResult>,
     > + Send {
         let supergraph_service = SupergraphService::builder()
-            .query_planner_service(self.query_planner_service.clone())
+            .query_planner_service(self.instrumented_query_planner_service.lock().clone())
             .execution_service_factory(ExecutionServiceFactory {
                 schema: self.schema.clone(),
                 subgraph_schemas: self.query_planner_service.subgraph_schemas(),
@@ -916,7 +990,14 @@ impl SupergraphCreator {
         let supergraph_service = AllowOnlyHttpPostMutationsLayer::default()
             .layer(shaping.supergraph_service_internal(supergraph_service));
 
+        let subgraph_latencies = self.subgraph_latencies.clone();
         ServiceBuilder::new()
+            .map_request(move |request: supergraph::Request| {
+                if let Some(subgraph_latencies) = &subgraph_latencies {
+                    subgraph_latencies.insert_into(&request.context);
+                }
+                request
+            })
             .layer(content_negotiation::SupergraphLayer::default())
             .service(
                 self.plugins
@@ -932,6 +1013,10 @@ impl SupergraphCreator {
         self.query_planner_service.previous_cache()
     }
 
+    pub(crate) async fn persist_query_planner_cache(&self) {
+        self.query_planner_service.persist().await
+    }
+
     pub(crate) fn js_planners(&self) -> Vec<Arc<Planner<QueryPlanResult>>> {
         self.query_planner_service.js_planners()
     }
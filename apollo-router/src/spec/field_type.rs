@@ -131,8 +131,11 @@ fn validate_input_value(
         .get(type_name)
         .ok_or(InvalidValue)?;
     match (type_def, value) {
-        // Custom scalar: accept any JSON value
-        (schema::ExtendedType::Scalar(_), _) => Ok(()),
+        // Custom scalar: accept any JSON value, unless its format is known
+        (schema::ExtendedType::Scalar(_), _) => match schema.api_schema().scalars.get(type_name) {
+            Some(format) => from_bool(format.is_valid(value)),
+            None => Ok(()),
+        },
 
         (schema::ExtendedType::Enum(def), Value::String(s)) => {
             from_bool(def.values.contains_key(s.as_str()))
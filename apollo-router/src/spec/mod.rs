@@ -6,6 +6,7 @@ mod field_type;
 mod fragments;
 pub(crate) mod operation_limits;
 pub(crate) mod query;
+mod scalars;
 mod schema;
 mod selection;
 
@@ -14,6 +15,8 @@ pub(crate) use field_type::*;
 pub(crate) use fragments::*;
 pub(crate) use query::Query;
 pub(crate) use query::TYPENAME;
+pub(crate) use scalars::ScalarFormat;
+pub(crate) use scalars::ScalarRegistry;
 pub(crate) use schema::Schema;
 pub(crate) use selection::*;
 use serde::Deserialize;
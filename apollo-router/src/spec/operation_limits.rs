@@ -117,7 +117,15 @@ pub(crate) fn check(
     Ok(())
 }
 
-enum Computation<T> {
+/// Measures the given selection set against each limit
+pub(crate) fn measure(
+    document: &ExecutableDocument,
+    selection_set: &executable::SelectionSet,
+) -> OperationLimits<u32> {
+    count(document, &mut HashMap::new(), selection_set)
+}
+
+pub(crate) enum Computation<T> {
     InProgress,
     Done(T),
 }
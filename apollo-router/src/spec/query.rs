@@ -21,6 +21,7 @@ use self::change::QueryHashVisitor;
 use self::subselections::BooleanValues;
 use self::subselections::SubSelectionKey;
 use self::subselections::SubSelectionValue;
+use crate::configuration::InvalidScalarResponse;
 use crate::error::FetchError;
 use crate::graphql::Error;
 use crate::graphql::Request;
@@ -273,6 +274,17 @@ impl Query {
         operation_name: Option<&str>,
         schema: &Schema,
         configuration: &Configuration,
+    ) -> Result<ParsedDocument, SpecError> {
+        Self::parse_contract_document(query, operation_name, schema, None, configuration)
+    }
+
+    /// Parses a query and validates it against the API schema of a contract variant
+    pub(crate) fn parse_contract_document(
+        query: &str,
+        operation_name: Option<&str>,
+        schema: &Schema,
+        contract: Option<&str>,
+        configuration: &Configuration,
     ) -> Result<ParsedDocument, SpecError> {
         let parser = &mut apollo_compiler::parser::Parser::new()
             .recursion_limit(configuration.limits.parser_max_recursion)
@@ -284,7 +296,7 @@ impl Query {
             }
         };
 
-        let api_schema = schema.api_schema();
+        let api_schema = schema.contract_api_schema(contract);
         let executable_document = match ast.to_executable_validate(api_schema) {
             Ok(doc) => doc,
             Err(errors) => {
@@ -516,10 +528,40 @@ impl Query {
             }
             executable::Type::Named(type_name) => {
                 // we cannot know about the expected format of custom scalars
-                // so we must pass them directly to the client
+                // so we must pass them directly to the client, unless their
+                // format was declared
                 match parameters.schema.types.get(type_name) {
                     Some(ExtendedType::Scalar(_)) => {
-                        *output = input.clone();
+                        let invalid_format = parameters
+                            .schema
+                            .scalars
+                            .get(type_name)
+                            .filter(|format| !input.is_null() && !format.is_valid(input));
+                        match invalid_format {
+                            Some(format) => {
+                                let message = format!(
+                                    "Invalid value for custom scalar {type_name}: expected {format}"
+                                );
+                                match parameters.schema.scalars.invalid_response() {
+                                    InvalidScalarResponse::Log => {
+                                        tracing::warn!(
+                                            path = %Path::from_response_slice(path),
+                                            "{message}"
+                                        );
+                                        *output = input.clone();
+                                    }
+                                    InvalidScalarResponse::Nullify => {
+                                        parameters.errors.push(Error {
+                                            message,
+                                            path: Some(Path::from_response_slice(path)),
+                                            ..Error::default()
+                                        });
+                                        *output = Value::Null;
+                                    }
+                                }
+                            }
+                            None => *output = input.clone(),
+                        }
                         return Ok(());
                     }
                     Some(ExtendedType::Enum(enum_type)) => {
@@ -5863,3 +5863,92 @@ fn filtered_defer_fragment() {
 
     assert_json_snapshot!(response);
 }
+
+#[test]
+fn custom_scalars_with_known_format() {
+    let schema = with_supergraph_boilerplate(
+        r#"
+        type Query {
+            events(after: DateTime): [Event]
+        }
+
+        type Event {
+            id: ID!
+            at: DateTime
+            owner: EmailAddress
+        }
+
+        scalar DateTime @specifiedBy(url: "https://scalars.graphql.org/andimarek/date-time")
+        scalar EmailAddress
+        "#,
+        "Query",
+    );
+    let config = Configuration::fake_builder()
+        .supergraph(
+            crate::configuration::Supergraph::fake_builder()
+                .custom_scalars(crate::configuration::CustomScalars {
+                    enabled: true,
+                    invalid_response: InvalidScalarResponse::Nullify,
+                    formats: [("EmailAddress".to_string(), crate::spec::ScalarFormat::Email)]
+                        .into_iter()
+                        .collect(),
+                })
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let schema = Schema::parse(&schema, &config).expect("could not parse schema");
+
+    let query_str = "query($after: DateTime) { events(after: $after) { id at owner } }";
+    let query = Query::parse(query_str, None, &schema, &config).expect("could not parse query");
+
+    let validate = |variables: Value| {
+        let request = Request::builder()
+            .query(query_str)
+            .variables(variables.as_object().unwrap().clone())
+            .build();
+        query.validate_variables(&request, &schema)
+    };
+    assert!(validate(json!({ "after": "2024-07-01T12:00:00Z" })).is_ok());
+    assert!(validate(json!({ "after": null })).is_ok());
+    assert!(validate(json!({ "after": "yesterday" })).is_err());
+
+    let mut response = Response::builder()
+        .data(json! {{
+            "events": [
+                { "id": "1", "at": "2024-07-01T12:00:00Z", "owner": "a@example.com" },
+                { "id": "2", "at": "tomorrow", "owner": "not an email" },
+            ]
+        }})
+        .build();
+    query.format_response(
+        &mut response,
+        None,
+        Object::new(),
+        schema.api_schema(),
+        BooleanValues { bits: 0 },
+    );
+
+    assert_eq_and_ordered_json!(
+        response.data.unwrap(),
+        json! {{
+            "events": [
+                { "id": "1", "at": "2024-07-01T12:00:00Z", "owner": "a@example.com" },
+                { "id": "2", "at": null, "owner": null },
+            ]
+        }}
+    );
+    assert_eq_and_ordered_json!(
+        response.extensions.get("valueCompletion").unwrap().clone(),
+        json! {[
+            {
+                "message": "Invalid value for custom scalar DateTime: expected DateTime",
+                "path": ["events", 1, "at"]
+            },
+            {
+                "message": "Invalid value for custom scalar EmailAddress: expected Email",
+                "path": ["events", 1, "owner"]
+            }
+        ]}
+    );
+}
@@ -0,0 +1,298 @@
+//! Validation of custom scalars with a known format.
+//!
+//! GraphQL gives no way to know what a custom scalar looks like, so by default its values are
+//! passed through untouched. When `supergraph.custom_scalars` is enabled, scalars that declare
+//! a well known `@specifiedBy` URL, or that are mapped to a format by type name in the
+//! configuration, are checked in input variables and in subgraph responses. Each scalar is checked
+//! by a [`ScalarValidator`] registered by type name in the [`ScalarRegistry`], the built-in
+//! [`ScalarFormat`]s being one kind of validator.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::Name;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+
+use crate::configuration::CustomScalars;
+use crate::configuration::InvalidScalarResponse;
+use crate::json_ext::Value;
+
+const SPECIFIED_BY_DIRECTIVE_NAME: &str = "specifiedBy";
+const SPECIFIED_BY_URL_ARGUMENT: &str = "url";
+
+// https://html.spec.whatwg.org/multipage/input.html#valid-e-mail-address
+#[allow(clippy::expect_used)]
+static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^[a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?)*$",
+    )
+    .expect("this regex to check email addresses is valid")
+});
+
+/// Checks the values of a custom scalar. Its `Display` implementation names the expected format
+/// in error messages
+pub(crate) trait ScalarValidator: fmt::Debug + fmt::Display + Send + Sync {
+    /// Checks that a non null value is valid
+    fn is_valid(&self, value: &Value) -> bool;
+}
+
+/// Format of a custom scalar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ScalarFormat {
+    /// RFC 3339 date and time with an offset, like `2024-07-01T12:00:00Z`
+    DateTime,
+    /// RFC 3339 full date, like `2024-07-01`
+    Date,
+    /// Hyphenated RFC 4122 UUID
+    Uuid,
+    /// Email address, as accepted by HTML email inputs
+    Email,
+    /// Absolute URL
+    Url,
+    /// Integer of arbitrary size, as a JSON integer or a string of digits
+    BigInt,
+    /// Any JSON value
+    Json,
+}
+
+impl ScalarFormat {
+    /// Finds the format matching a `@specifiedBy` URL
+    pub(crate) fn from_specified_by(url: &str) -> Option<Self> {
+        match url.trim_end_matches('/') {
+            "https://scalars.graphql.org/andimarek/date-time"
+            | "https://tools.ietf.org/html/rfc3339"
+            | "https://datatracker.ietf.org/doc/html/rfc3339" => Some(ScalarFormat::DateTime),
+            "https://scalars.graphql.org/andimarek/local-date" => Some(ScalarFormat::Date),
+            "https://tools.ietf.org/html/rfc4122"
+            | "https://datatracker.ietf.org/doc/html/rfc4122"
+            | "https://datatracker.ietf.org/doc/html/rfc9562" => Some(ScalarFormat::Uuid),
+            "https://html.spec.whatwg.org/multipage/input.html#valid-e-mail-address" => {
+                Some(ScalarFormat::Email)
+            }
+            "https://url.spec.whatwg.org"
+            | "https://tools.ietf.org/html/rfc3986"
+            | "https://datatracker.ietf.org/doc/html/rfc3986" => Some(ScalarFormat::Url),
+            "https://www.rfc-editor.org/rfc/rfc8259"
+            | "https://tools.ietf.org/html/rfc8259"
+            | "https://datatracker.ietf.org/doc/html/rfc8259"
+            | "http://www.ecma-international.org/publications/files/ECMA-ST/ECMA-404.pdf" => {
+                Some(ScalarFormat::Json)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl ScalarValidator for ScalarFormat {
+    fn is_valid(&self, value: &Value) -> bool {
+        match self {
+            ScalarFormat::DateTime => value
+                .as_str()
+                .is_some_and(|s| time::OffsetDateTime::parse(s, &Rfc3339).is_ok()),
+            ScalarFormat::Date => value.as_str().is_some_and(is_valid_date),
+            ScalarFormat::Uuid => value
+                .as_str()
+                .is_some_and(|s| s.len() == 36 && uuid::Uuid::parse_str(s).is_ok()),
+            ScalarFormat::Email => value.as_str().is_some_and(|s| EMAIL_REGEX.is_match(s)),
+            ScalarFormat::Url => value.as_str().is_some_and(|s| url::Url::parse(s).is_ok()),
+            ScalarFormat::BigInt => match value {
+                Value::Number(n) => n.is_i64() || n.is_u64(),
+                Value::String(s) => {
+                    let digits = s.as_str().strip_prefix('-').unwrap_or(s.as_str());
+                    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+                }
+                _ => false,
+            },
+            ScalarFormat::Json => true,
+        }
+    }
+}
+
+impl fmt::Display for ScalarFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ScalarFormat::DateTime => "DateTime",
+            ScalarFormat::Date => "Date",
+            ScalarFormat::Uuid => "UUID",
+            ScalarFormat::Email => "Email",
+            ScalarFormat::Url => "URL",
+            ScalarFormat::BigInt => "BigInt",
+            ScalarFormat::Json => "JSON",
+        })
+    }
+}
+
+fn is_valid_date(s: &str) -> bool {
+    let mut parts = s.splitn(3, '-');
+    let (Some(year), Some(month), Some(day)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse::<i32>(), month.parse::<u8>(), day.parse())
+    else {
+        return false;
+    };
+    time::Month::try_from(month)
+        .and_then(|month| time::Date::from_calendar_date(year, month, day))
+        .is_ok()
+}
+
+/// Validators of the custom scalars of a schema, by type name
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScalarRegistry {
+    validators: HashMap<Name, Arc<dyn ScalarValidator>>,
+    invalid_response: InvalidScalarResponse,
+}
+
+impl ScalarRegistry {
+    pub(crate) fn new(schema: &apollo_compiler::Schema, config: &CustomScalars) -> Self {
+        if !config.enabled {
+            return Self::default();
+        }
+
+        let mut registry = Self {
+            validators: HashMap::new(),
+            invalid_response: config.invalid_response,
+        };
+        let formats = schema.types.iter().filter_map(|(name, ty)| {
+            let ExtendedType::Scalar(scalar) = ty else {
+                return None;
+            };
+            if scalar.is_built_in() {
+                return None;
+            }
+            let format = config.formats.get(name.as_str()).copied().or_else(|| {
+                scalar
+                    .directives
+                    .get(SPECIFIED_BY_DIRECTIVE_NAME)?
+                    .specified_argument_by_name(SPECIFIED_BY_URL_ARGUMENT)?
+                    .as_str()
+                    .and_then(ScalarFormat::from_specified_by)
+            })?;
+            Some((name.clone(), format))
+        });
+        for (name, format) in formats {
+            registry.register(name, Arc::new(format));
+        }
+        registry
+    }
+
+    /// Checks the values of the custom scalar `type_name` with `validator`, replacing any
+    /// validator previously registered for it
+    pub(crate) fn register(&mut self, type_name: Name, validator: Arc<dyn ScalarValidator>) {
+        self.validators.insert(type_name, validator);
+    }
+
+    /// Returns the validator of a custom scalar, if its format is known
+    pub(crate) fn get(&self, type_name: &str) -> Option<&dyn ScalarValidator> {
+        self.validators.get(type_name).map(|validator| &**validator)
+    }
+
+    /// What to do with subgraph response values that do not match their format
+    pub(crate) fn invalid_response(&self) -> InvalidScalarResponse {
+        self.invalid_response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    #[test]
+    fn validates_formats() {
+        assert!(ScalarFormat::DateTime.is_valid(&json!("2024-07-01T12:00:00Z")));
+        assert!(ScalarFormat::DateTime.is_valid(&json!("2024-07-01T12:00:00.123+02:00")));
+        assert!(!ScalarFormat::DateTime.is_valid(&json!("2024-07-01")));
+        assert!(!ScalarFormat::DateTime.is_valid(&json!(1719835200)));
+
+        assert!(ScalarFormat::Date.is_valid(&json!("2024-02-29")));
+        assert!(!ScalarFormat::Date.is_valid(&json!("2023-02-29")));
+        assert!(!ScalarFormat::Date.is_valid(&json!("2024-7-1")));
+
+        assert!(ScalarFormat::Uuid.is_valid(&json!("67e55044-10b1-426f-9247-bb680e5fe0c8")));
+        assert!(!ScalarFormat::Uuid.is_valid(&json!("67e5504410b1426f9247bb680e5fe0c8")));
+
+        assert!(ScalarFormat::Email.is_valid(&json!("user@example.com")));
+        assert!(!ScalarFormat::Email.is_valid(&json!("user.example.com")));
+
+        assert!(ScalarFormat::Url.is_valid(&json!("https://example.com/a?b=c")));
+        assert!(!ScalarFormat::Url.is_valid(&json!("/a/b")));
+
+        assert!(ScalarFormat::BigInt.is_valid(&json!(42)));
+        assert!(ScalarFormat::BigInt.is_valid(&json!("-123456789012345678901234567890")));
+        assert!(!ScalarFormat::BigInt.is_valid(&json!("12.5")));
+        assert!(!ScalarFormat::BigInt.is_valid(&json!(12.5)));
+
+        assert!(ScalarFormat::Json.is_valid(&json!({"a": [1, "b"]})));
+    }
+
+    #[test]
+    fn registry_from_specified_by_and_configuration() {
+        let schema = apollo_compiler::Schema::parse_and_validate(
+            r#"
+            type Query { a: DateTime b: Id c: Other }
+            scalar DateTime @specifiedBy(url: "https://scalars.graphql.org/andimarek/date-time")
+            scalar Id
+            scalar Other @specifiedBy(url: "https://example.com/other")
+            "#,
+            "schema.graphql",
+        )
+        .unwrap();
+
+        let format = |registry: &ScalarRegistry, type_name: &str| {
+            registry
+                .get(type_name)
+                .map(|validator| validator.to_string())
+        };
+
+        let disabled = ScalarRegistry::new(&schema, &CustomScalars::default());
+        assert_eq!(format(&disabled, "DateTime"), None);
+
+        let config = CustomScalars {
+            enabled: true,
+            formats: [("Id".to_string(), ScalarFormat::Uuid)]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let mut registry = ScalarRegistry::new(&schema, &config);
+        assert_eq!(format(&registry, "DateTime").as_deref(), Some("DateTime"));
+        assert_eq!(format(&registry, "Id").as_deref(), Some("UUID"));
+        assert_eq!(format(&registry, "Other"), None);
+        assert_eq!(format(&registry, "String"), None);
+
+        // other validators can be registered by type name
+        #[derive(Debug)]
+        struct Even;
+
+        impl fmt::Display for Even {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("even number")
+            }
+        }
+
+        impl ScalarValidator for Even {
+            fn is_valid(&self, value: &Value) -> bool {
+                value.as_i64().is_some_and(|n| n % 2 == 0)
+            }
+        }
+
+        registry.register(Name::new("Other").unwrap(), Arc::new(Even));
+        let other = registry.get("Other").unwrap();
+        assert_eq!(other.to_string(), "even number");
+        assert!(other.is_valid(&json!(4)));
+        assert!(!other.is_valid(&json!(3)));
+    }
+}
@@ -10,6 +10,7 @@ use apollo_compiler::validation::Valid;
 use apollo_compiler::Name;
 use apollo_federation::schema::ValidFederationSchema;
 use apollo_federation::ApiSchemaOptions;
+use apollo_federation::ContractFilter;
 use apollo_federation::Supergraph;
 use http::Uri;
 use semver::Version;
@@ -20,6 +21,7 @@ use sha2::Sha256;
 use crate::error::ParseErrors;
 use crate::error::SchemaError;
 use crate::query_planner::OperationKind;
+use crate::spec::ScalarRegistry;
 use crate::Configuration;
 
 /// A GraphQL schema.
@@ -29,12 +31,18 @@ pub(crate) struct Schema {
     subgraphs: HashMap<String, Uri>,
     pub(crate) implementers_map: apollo_compiler::collections::HashMap<Name, Implementers>,
     api_schema: ApiSchema,
+    /// API schemas of the contract variants, by name
+    contracts: HashMap<String, ApiSchema>,
     pub(crate) schema_id: Arc<String>,
 }
 
 /// Wrapper type to distinguish from `Schema::definitions` for the supergraph schema
 #[derive(Debug)]
-pub(crate) struct ApiSchema(pub(crate) ValidFederationSchema);
+pub(crate) struct ApiSchema {
+    schema: ValidFederationSchema,
+    /// Formats of the custom scalars that can be validated
+    pub(crate) scalars: ScalarRegistry,
+}
 
 impl Schema {
     pub(crate) fn parse(raw_sdl: &str, config: &Configuration) -> Result<Self, SchemaError> {
@@ -108,27 +116,56 @@ impl Schema {
         );
 
         let implementers_map = definitions.implementers_map();
+        let scalars = ScalarRegistry::new(&definitions, &config.supergraph.custom_scalars);
         let supergraph = Supergraph::from_schema(definitions)?;
 
         let schema_id = Arc::new(Schema::schema_id(&raw_sdl));
 
+        let api_schema_options = ApiSchemaOptions {
+            include_defer: config.supergraph.defer_support,
+            ..Default::default()
+        };
         let api_schema = supergraph
-            .to_api_schema(ApiSchemaOptions {
-                include_defer: config.supergraph.defer_support,
-                ..Default::default()
-            })
+            .to_api_schema(api_schema_options.clone())
             .map_err(|e| {
                 SchemaError::Api(format!(
                     "The supergraph schema failed to produce a valid API schema: {e}"
                 ))
             })?;
 
+        let mut contracts = HashMap::new();
+        for variant in &config.supergraph.experimental_contracts.variants {
+            let filter = ContractFilter {
+                include: variant.include.clone(),
+                exclude: variant.exclude.clone(),
+            };
+            let schema = supergraph
+                .to_contract_api_schema(&filter, api_schema_options.clone())
+                .map_err(|e| {
+                    SchemaError::Api(format!(
+                        "The supergraph schema failed to produce a valid API schema for contract variant '{}': {e}",
+                        variant.name
+                    ))
+                })?;
+            contracts.insert(
+                variant.name.clone(),
+                ApiSchema {
+                    schema,
+                    scalars: scalars.clone(),
+                },
+            );
+        }
+
         Ok(Schema {
             raw_sdl,
             supergraph,
             subgraphs,
             implementers_map,
-            api_schema: ApiSchema(api_schema),
+            api_schema: ApiSchema {
+                schema: api_schema,
+                scalars,
+            },
+            contracts,
             schema_id,
         })
     }
@@ -208,6 +245,13 @@ impl Schema {
         &self.api_schema
     }
 
+    /// Returns the API schema of a contract variant, or the full API schema if there is none
+    pub(crate) fn contract_api_schema(&self, contract: Option<&str>) -> &ApiSchema {
+        contract
+            .and_then(|name| self.contracts.get(name))
+            .unwrap_or(&self.api_schema)
+    }
+
     pub(crate) fn root_operation_name(&self, kind: OperationKind) -> &str {
         if let Some(name) = self.supergraph_schema().root_operation(kind.into()) {
             name.as_str()
@@ -336,6 +380,7 @@ impl std::fmt::Debug for Schema {
             subgraphs,
             implementers_map,
             api_schema: _, // skip
+            contracts: _,  // skip
             schema_id: _,
         } = self;
         f.debug_struct("Schema")
@@ -350,7 +395,7 @@ impl std::ops::Deref for ApiSchema {
     type Target = Valid<apollo_compiler::Schema>;
 
     fn deref(&self) -> &Self::Target {
-        self.0.schema()
+        self.schema.schema()
     }
 }
 
@@ -1,8 +1,10 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::sync::Arc;
 
 use futures::prelude::*;
+use multimap::MultiMap;
 use tokio::sync::mpsc;
 #[cfg(test)]
 use tokio::sync::Notify;
@@ -25,14 +27,17 @@ use super::router::ApolloRouterError::NoConfiguration;
 use super::router::ApolloRouterError::NoSchema;
 use super::router::ApolloRouterError::{self};
 use super::router::Event::UpdateConfiguration;
+use super::router::Event::UpdateHostedSchema;
 use super::router::Event::UpdateSchema;
 use super::router::Event::{self};
 use crate::configuration::metrics::Metrics;
 use crate::configuration::Configuration;
 use crate::configuration::Discussed;
 use crate::configuration::ListenAddr;
+use crate::hosted_supergraphs::HostedSupergraphsRouterFactory;
 use crate::plugins::telemetry::reload::apollo_opentelemetry_initialized;
 use crate::router::Event::UpdateLicense;
+use crate::router_factory::Endpoint;
 use crate::router_factory::RouterFactory;
 use crate::router_factory::RouterSuperServiceFactory;
 use crate::spec::Schema;
@@ -49,12 +54,20 @@ pub(crate) struct ListenAddresses {
     pub(crate) extra_listen_addresses: Vec<ListenAddr>,
 }
 
+/// The router serving a hosted supergraph
+struct HostedRouter<RF> {
+    schema: Arc<String>,
+    router_service_factory: RF,
+    uses_restricted_features: bool,
+}
+
 /// This state maintains private information that is not exposed to the user via state listener.
 #[allow(clippy::large_enum_variant)]
 enum State<FA: RouterSuperServiceFactory> {
     Startup {
         configuration: Option<Arc<Configuration>>,
         schema: Option<Arc<String>>,
+        hosted_schemas: HashMap<String, Arc<String>>,
         license: Option<LicenseState>,
         listen_addresses_guard: OwnedRwLockWriteGuard<ListenAddresses>,
     },
@@ -62,9 +75,12 @@ enum State<FA: RouterSuperServiceFactory> {
         configuration: Arc<Configuration>,
         _metrics: Option<Metrics>,
         schema: Arc<String>,
+        hosted_schemas: HashMap<String, Arc<String>>,
         license: LicenseState,
         server_handle: Option<HttpServerHandle>,
         router_service_factory: FA::RouterFactory,
+        uses_restricted_features: bool,
+        hosted_routers: HashMap<String, HostedRouter<FA::RouterFactory>>,
         all_connections_stopped_signals: Vec<mpsc::Receiver<()>>,
     },
     Stopped,
@@ -121,6 +137,7 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         new_schema: Option<Arc<String>>,
         new_configuration: Option<Arc<Configuration>>,
         new_license: Option<LicenseState>,
+        new_hosted_schema: Option<(String, Arc<String>)>,
     ) -> Self
     where
         S: HttpServerFactory,
@@ -129,6 +146,7 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         match &mut self {
             Startup {
                 schema,
+                hosted_schemas,
                 configuration,
                 license,
                 listen_addresses_guard,
@@ -136,6 +154,9 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                 *schema = new_schema.or_else(|| schema.take());
                 *configuration = new_configuration.or_else(|| configuration.take());
                 *license = new_license.or_else(|| license.take());
+                if let Some((name, new_hosted_schema)) = new_hosted_schema {
+                    hosted_schemas.insert(name, new_hosted_schema);
+                }
 
                 if let (Some(schema), Some(configuration), Some(license)) =
                     (schema, configuration, license)
@@ -145,8 +166,11 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                             state_machine,
                             &mut None,
                             None,
+                            &HashMap::new(),
+                            None,
                             configuration.clone(),
                             schema.clone(),
+                            hosted_schemas.clone(),
                             *license,
                             listen_addresses_guard,
                             vec![],
@@ -161,10 +185,13 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             }
             Running {
                 schema,
+                hosted_schemas,
                 configuration,
                 license,
                 server_handle,
                 router_service_factory,
+                uses_restricted_features,
+                hosted_routers,
                 all_connections_stopped_signals,
                 ..
             } => {
@@ -199,17 +226,43 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                         license_reload = true;
                     }
                 }
+                let mut hosted_schema_reload = None;
+                if let Some((name, new_hosted_schema)) = new_hosted_schema {
+                    if hosted_schemas.get(&name) != Some(&new_hosted_schema) {
+                        // Schemas of supergraphs missing from the configuration are kept for when they are added
+                        if configuration
+                            .experimental_hosted_supergraphs
+                            .iter()
+                            .any(|hosted| hosted.name == name)
+                        {
+                            hosted_schema_reload = Some(name.clone());
+                        }
+                        hosted_schemas.insert(name, new_hosted_schema);
+                    }
+                }
 
                 // Let users know we are about to process a state reload event
                 tracing::info!(
                     new_schema = schema_reload,
+                    new_hosted_schema = hosted_schema_reload.as_deref(),
                     new_license = license_reload,
                     new_configuration = configuration_reload,
                     event = STATE_CHANGE,
                     "processing event"
                 );
 
-                let need_reload = schema_reload || license_reload || configuration_reload;
+                let need_reload = schema_reload
+                    || license_reload
+                    || configuration_reload
+                    || hosted_schema_reload.is_some();
+                // A hosted supergraph reloads on its own when nothing else changed
+                let hosted_reload = if schema_reload || license_reload || configuration_reload {
+                    None
+                } else {
+                    hosted_schema_reload
+                        .as_deref()
+                        .map(|name| (name, *uses_restricted_features))
+                };
 
                 if need_reload {
                     // We update the running config. This is OK even in the case that the router could not reload as we always want to retain the latest information for when we try to reload next.
@@ -220,8 +273,11 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                         state_machine,
                         server_handle,
                         Some(router_service_factory),
+                        hosted_routers,
+                        hosted_reload,
                         configuration.clone(),
                         schema.clone(),
+                        hosted_schemas.clone(),
                         *license,
                         &mut guard,
                         signals,
@@ -280,6 +336,8 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             Running {
                 server_handle: Some(server_handle),
                 mut all_connections_stopped_signals,
+                router_service_factory,
+                hosted_routers,
                 ..
             } => {
                 // We want to set the ready state to false before we start shutting down the server.
@@ -296,6 +354,15 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                 // We ignore the results of recv()
                 let _: Vec<_> = futs.collect().await;
                 tracing::info!("all connections shut down");
+                let persisted: futures::stream::FuturesUnordered<_> =
+                    std::iter::once(router_service_factory.persist())
+                        .chain(
+                            hosted_routers
+                                .values()
+                                .map(|router| router.router_service_factory.persist()),
+                        )
+                        .collect();
+                let _: Vec<_> = persisted.collect().await;
                 state
             }
             _ => Stopped,
@@ -307,8 +374,11 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         state_machine: &mut StateMachine<S, FA>,
         server_handle: &mut Option<HttpServerHandle>,
         previous_router_service_factory: Option<&FA::RouterFactory>,
+        previous_hosted_routers: &HashMap<String, HostedRouter<FA::RouterFactory>>,
+        hosted_reload: Option<(&str, bool)>,
         configuration: Arc<Configuration>,
         sdl: Arc<String>,
+        hosted_schemas: HashMap<String, Arc<String>>,
         license: LicenseState,
         listen_addresses_guard: &mut OwnedRwLockWriteGuard<ListenAddresses>,
         mut all_connections_stopped_signals: Vec<mpsc::Receiver<()>>,
@@ -317,57 +387,109 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         S: HttpServerFactory,
         FA: RouterSuperServiceFactory,
     {
-        let schema = Arc::new(
-            Schema::parse_arc(sdl.clone(), &configuration)
-                .map_err(|e| ServiceCreationError(e.to_string().into()))?,
-        );
-        // Check the license
-        let report = LicenseEnforcementReport::build(&configuration, &schema);
-
-        match license {
-            LicenseState::Licensed => {
-                tracing::debug!("A valid Apollo license has been detected.");
-            }
-            LicenseState::LicensedWarn if report.uses_restricted_features() => {
-                tracing::error!("License has expired. The Router will soon stop serving requests. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS that provides an active license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
-            }
-            LicenseState::LicensedHalt if report.uses_restricted_features() => {
-                tracing::error!("License has expired. The Router will no longer serve requests. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS that provides an active license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
-            }
-            LicenseState::Unlicensed if report.uses_restricted_features() => {
-                // This is OSS, so fail to reload or start.
-                if std::env::var("APOLLO_KEY").is_ok() && std::env::var("APOLLO_GRAPH_REF").is_ok()
-                {
-                    tracing::error!("License not found. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS that provides a license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
-                } else {
-                    tracing::error!("Not connected to GraphOS. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS (using APOLLO_KEY and APOLLO_GRAPH_REF) that provides a license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
+        // When only a hosted supergraph changed, the other routers are kept as they are
+        let (router_service_factory, uses_restricted_features) =
+            match (hosted_reload, previous_router_service_factory) {
+                (Some((_, uses_restricted_features)), Some(previous)) => {
+                    (previous.clone(), uses_restricted_features)
+                }
+                _ => {
+                    Self::create_router(
+                        state_machine,
+                        configuration.clone(),
+                        sdl.clone(),
+                        license,
+                        previous_router_service_factory,
+                    )
+                    .await?
                 }
+            };
 
-                return Err(ApolloRouterError::LicenseViolation);
+        let mut hosted_routers = HashMap::new();
+        for hosted in &configuration.experimental_hosted_supergraphs {
+            let Some(hosted_sdl) = hosted_schemas.get(&hosted.name) else {
+                tracing::warn!(
+                    "hosted supergraph '{}' has no schema yet, it will be served once its schema is available",
+                    hosted.name
+                );
+                continue;
+            };
+            let previous = previous_hosted_routers.get(&hosted.name);
+            if let (Some((name, _)), Some(previous)) = (hosted_reload, previous) {
+                if name != hosted.name {
+                    hosted_routers.insert(
+                        hosted.name.clone(),
+                        HostedRouter {
+                            schema: previous.schema.clone(),
+                            router_service_factory: previous.router_service_factory.clone(),
+                            uses_restricted_features: previous.uses_restricted_features,
+                        },
+                    );
+                    continue;
+                }
             }
-            _ => {
-                tracing::debug!("A valid Apollo license was not detected. However, no restricted features are in use.");
+
+            // A hosted supergraph failing to build does not prevent the others from being served
+            let created = match hosted.configuration(&configuration) {
+                Ok(hosted_configuration) => {
+                    Self::create_router(
+                        state_machine,
+                        Arc::new(hosted_configuration),
+                        hosted_sdl.clone(),
+                        license,
+                        previous.map(|previous| &previous.router_service_factory),
+                    )
+                    .await
+                }
+                Err(e) => Err(ServiceCreationError(e.to_string().into())),
+            };
+            match created {
+                Ok((hosted_router_service_factory, hosted_uses_restricted_features)) => {
+                    hosted_routers.insert(
+                        hosted.name.clone(),
+                        HostedRouter {
+                            schema: hosted_sdl.clone(),
+                            router_service_factory: hosted_router_service_factory,
+                            uses_restricted_features: hosted_uses_restricted_features,
+                        },
+                    );
+                }
+                Err(e) => match previous {
+                    Some(previous) => {
+                        tracing::error!(
+                            "cannot reload hosted supergraph '{}', it keeps its previous router: {e}",
+                            hosted.name
+                        );
+                        hosted_routers.insert(
+                            hosted.name.clone(),
+                            HostedRouter {
+                                schema: previous.schema.clone(),
+                                router_service_factory: previous.router_service_factory.clone(),
+                                uses_restricted_features: previous.uses_restricted_features,
+                            },
+                        );
+                    }
+                    None => {
+                        tracing::error!(
+                            "cannot create hosted supergraph '{}', it will not be served: {e}",
+                            hosted.name
+                        );
+                    }
+                },
             }
         }
 
         // If there are no restricted featured in use then the effective license is Licensed as we don't need warn or halt behavior.
-        let effective_license = if !report.uses_restricted_features() {
+        let effective_license = if !uses_restricted_features
+            && !hosted_routers
+                .values()
+                .any(|hosted| hosted.uses_restricted_features)
+        {
             LicenseState::Licensed
         } else {
             license
         };
 
-        let router_service_factory = state_machine
-            .router_configurator
-            .create(
-                state_machine.is_telemetry_disabled,
-                configuration.clone(),
-                schema,
-                previous_router_service_factory,
-                None,
-            )
-            .await
-            .map_err(ServiceCreationError)?;
         // used to track if there are still in flight connections when shutting down
         let (all_connections_stopped_sender, all_connections_stopped_signal) =
             mpsc::channel::<()>(1);
@@ -375,32 +497,37 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
         let web_endpoints = router_service_factory.web_endpoints();
 
         // The point of no return. We take the previous server handle.
-        let server_handle = match server_handle.take() {
-            None => {
-                state_machine
-                    .http_server_factory
-                    .create(
-                        router_service_factory.clone(),
-                        configuration.clone(),
-                        Default::default(),
-                        Default::default(),
-                        web_endpoints,
-                        effective_license,
-                        all_connections_stopped_sender,
-                    )
-                    .await?
-            }
-            Some(server_handle) => {
-                server_handle
-                    .restart(
-                        &state_machine.http_server_factory,
-                        router_service_factory.clone(),
-                        configuration.clone(),
-                        web_endpoints,
-                        effective_license,
-                    )
-                    .await?
-            }
+        let server_handle = if hosted_routers.is_empty() {
+            serve(
+                &state_machine.http_server_factory,
+                server_handle.take(),
+                router_service_factory.clone(),
+                configuration.clone(),
+                web_endpoints,
+                effective_license,
+                all_connections_stopped_sender,
+            )
+            .await?
+        } else {
+            let hosted = configuration
+                .experimental_hosted_supergraphs
+                .iter()
+                .filter_map(|hosted| {
+                    hosted_routers
+                        .get(&hosted.name)
+                        .map(|router| (hosted.clone(), router.router_service_factory.clone()))
+                })
+                .collect();
+            serve(
+                &state_machine.http_server_factory,
+                server_handle.take(),
+                HostedSupergraphsRouterFactory::new(router_service_factory.clone(), hosted),
+                configuration.clone(),
+                web_endpoints,
+                effective_license,
+                all_connections_stopped_sender,
+            )
+            .await?
         };
 
         listen_addresses_guard.extra_listen_addresses = server_handle.listen_addresses().to_vec();
@@ -423,12 +550,117 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
             configuration,
             _metrics: metrics,
             schema: sdl,
+            hosted_schemas,
             license,
             server_handle: Some(server_handle),
             router_service_factory,
+            uses_restricted_features,
+            hosted_routers,
             all_connections_stopped_signals,
         })
     }
+
+    /// Creates the router of a supergraph, if the license allows the features it uses.
+    /// Also returns whether restricted features are in use.
+    async fn create_router<S>(
+        state_machine: &mut StateMachine<S, FA>,
+        configuration: Arc<Configuration>,
+        sdl: Arc<String>,
+        license: LicenseState,
+        previous_router_service_factory: Option<&FA::RouterFactory>,
+    ) -> Result<(FA::RouterFactory, bool), ApolloRouterError>
+    where
+        S: HttpServerFactory,
+        FA: RouterSuperServiceFactory,
+    {
+        let schema = Arc::new(
+            Schema::parse_arc(sdl, &configuration)
+                .map_err(|e| ServiceCreationError(e.to_string().into()))?,
+        );
+        // Check the license
+        let report = LicenseEnforcementReport::build(&configuration, &schema);
+
+        match license {
+            LicenseState::Licensed => {
+                tracing::debug!("A valid Apollo license has been detected.");
+            }
+            LicenseState::LicensedWarn if report.uses_restricted_features() => {
+                tracing::error!("License has expired. The Router will soon stop serving requests. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS that provides an active license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
+            }
+            LicenseState::LicensedHalt if report.uses_restricted_features() => {
+                tracing::error!("License has expired. The Router will no longer serve requests. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS that provides an active license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
+            }
+            LicenseState::Unlicensed if report.uses_restricted_features() => {
+                // This is OSS, so fail to reload or start.
+                if std::env::var("APOLLO_KEY").is_ok() && std::env::var("APOLLO_GRAPH_REF").is_ok()
+                {
+                    tracing::error!("License not found. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS that provides a license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
+                } else {
+                    tracing::error!("Not connected to GraphOS. In order to enable these features for a self-hosted instance of Apollo Router, the Router must be connected to a graph in GraphOS (using APOLLO_KEY and APOLLO_GRAPH_REF) that provides a license for the following features:\n\n{}\n\nSee {LICENSE_EXPIRED_URL} for more information.", report);
+                }
+
+                return Err(ApolloRouterError::LicenseViolation);
+            }
+            _ => {
+                tracing::debug!("A valid Apollo license was not detected. However, no restricted features are in use.");
+            }
+        }
+
+        let router_service_factory = state_machine
+            .router_configurator
+            .create(
+                state_machine.is_telemetry_disabled,
+                configuration,
+                schema,
+                previous_router_service_factory,
+                None,
+            )
+            .await
+            .map_err(ServiceCreationError)?;
+        Ok((router_service_factory, report.uses_restricted_features()))
+    }
+}
+
+/// Starts the HTTP server, or restarts it if it is already running
+async fn serve<S, RF>(
+    http_server_factory: &S,
+    server_handle: Option<HttpServerHandle>,
+    router_service_factory: RF,
+    configuration: Arc<Configuration>,
+    web_endpoints: MultiMap<ListenAddr, Endpoint>,
+    license: LicenseState,
+    all_connections_stopped_sender: mpsc::Sender<()>,
+) -> Result<HttpServerHandle, ApolloRouterError>
+where
+    S: HttpServerFactory,
+    RF: RouterFactory,
+{
+    match server_handle {
+        None => {
+            http_server_factory
+                .create(
+                    router_service_factory,
+                    configuration,
+                    Default::default(),
+                    Default::default(),
+                    web_endpoints,
+                    license,
+                    all_connections_stopped_sender,
+                )
+                .await
+        }
+        Some(server_handle) => {
+            server_handle
+                .restart(
+                    http_server_factory,
+                    router_service_factory,
+                    configuration,
+                    web_endpoints,
+                    license,
+                )
+                .await
+        }
+    }
 }
 
 /// A state machine that responds to events to control the lifecycle of the server.
@@ -514,6 +746,7 @@ where
         let mut state: State<FA> = Startup {
             configuration: None,
             schema: None,
+            hosted_schemas: HashMap::new(),
             license: None,
             listen_addresses_guard: self
                 .listen_addresses_guard
@@ -533,22 +766,27 @@ where
             state = match event {
                 UpdateConfiguration(configuration) => {
                     state
-                        .update_inputs(&mut self, None, Some(Arc::new(configuration)), None)
+                        .update_inputs(&mut self, None, Some(Arc::new(configuration)), None, None)
                         .await
                 }
                 NoMoreConfiguration => state.no_more_configuration().await,
                 UpdateSchema(schema) => {
                     state
-                        .update_inputs(&mut self, Some(Arc::new(schema)), None, None)
+                        .update_inputs(&mut self, Some(Arc::new(schema)), None, None, None)
                         .await
                 }
                 NoMoreSchema => state.no_more_schema().await,
+                UpdateHostedSchema(name, schema) => {
+                    state
+                        .update_inputs(&mut self, None, None, None, Some((name, Arc::new(schema))))
+                        .await
+                }
                 UpdateLicense(license) => {
                     state
-                        .update_inputs(&mut self, None, None, Some(license))
+                        .update_inputs(&mut self, None, None, Some(license), None)
                         .await
                 }
-                Reload => state.update_inputs(&mut self, None, None, None).await,
+                Reload => state.update_inputs(&mut self, None, None, None, None).await,
                 NoMoreLicense => state.no_more_license().await,
                 Shutdown => state.shutdown(&self.http_server_factory).await,
             };
@@ -1107,6 +1345,123 @@ mod tests {
         assert_eq!(shutdown_receivers.0.lock().unwrap().len(), 2);
     }
 
+    fn hosted_products_configuration() -> Configuration {
+        let hosted = json!({ "name": "products", "path": "/products" });
+        let mut configuration = Configuration::builder()
+            .experimental_hosted_supergraph(serde_json::from_value(hosted.clone()).unwrap())
+            .build()
+            .unwrap();
+        configuration.validated_yaml = Some(json!({ "experimental_hosted_supergraphs": [hosted] }));
+        configuration
+    }
+
+    #[test(tokio::test)]
+    async fn hosted_supergraph_failure_does_not_prevent_serving() {
+        fn mock_router() -> MockMyRouterFactory {
+            let mut router = MockMyRouterFactory::new();
+            router.expect_clone().returning(mock_router);
+            router.expect_web_endpoints().returning(MultiMap::new);
+            router
+        }
+
+        let mut router_factory = MockMyRouterConfigurator::new();
+        router_factory
+            .expect_create()
+            .times(1)
+            .withf(|_, configuration, _, _, _| configuration.hosted_supergraph.is_none())
+            .returning(|_, _, _, _, _| Ok(mock_router()));
+        router_factory
+            .expect_create()
+            .times(1)
+            .withf(|_, configuration, _, _, _| {
+                configuration.hosted_supergraph.as_deref() == Some("products")
+            })
+            .returning(|_, _, _, _, _| Err("invalid products schema".into()));
+
+        let (server_factory, shutdown_receivers) = create_mock_server_factory(1, 1, 1, 1, 1);
+        let minimal_schema = include_str!("testdata/minimal_supergraph.graphql");
+
+        // the main supergraph is served without the hosted one
+        assert_matches!(
+            execute(
+                server_factory,
+                router_factory,
+                stream::iter(vec![
+                    UpdateConfiguration(hosted_products_configuration()),
+                    UpdateHostedSchema("products".to_string(), minimal_schema.to_owned()),
+                    UpdateSchema(example_schema()),
+                    Shutdown
+                ]),
+            )
+            .await,
+            Ok(())
+        );
+        assert_eq!(shutdown_receivers.0.lock().unwrap().len(), 1);
+    }
+
+    #[test(tokio::test)]
+    async fn hosted_supergraph_reloads_on_its_own() {
+        fn mock_router() -> MockMyRouterFactory {
+            let mut router = MockMyRouterFactory::new();
+            router.expect_clone().returning(mock_router);
+            router.expect_web_endpoints().returning(MultiMap::new);
+            router
+        }
+
+        let mut seq = Sequence::new();
+        let mut router_factory = MockMyRouterConfigurator::new();
+        router_factory
+            .expect_create()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|_, configuration, _, previous_router_service_factory, _| {
+                configuration.hosted_supergraph.is_none()
+                    && previous_router_service_factory.is_none()
+            })
+            .returning(|_, _, _, _, _| Ok(mock_router()));
+        router_factory
+            .expect_create()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|_, configuration, _, previous_router_service_factory, _| {
+                configuration.hosted_supergraph.as_deref() == Some("products")
+                    && previous_router_service_factory.is_none()
+            })
+            .returning(|_, _, _, _, _| Ok(mock_router()));
+        // Only the hosted supergraph is rebuilt when its schema changes
+        router_factory
+            .expect_create()
+            .times(1)
+            .in_sequence(&mut seq)
+            .withf(|_, configuration, _, previous_router_service_factory, _| {
+                configuration.hosted_supergraph.as_deref() == Some("products")
+                    && previous_router_service_factory.is_some()
+            })
+            .returning(|_, _, _, _, _| Ok(mock_router()));
+
+        let (server_factory, shutdown_receivers) = create_mock_server_factory(2, 1, 1, 1, 1);
+        let minimal_schema = include_str!("testdata/minimal_supergraph.graphql");
+        assert_matches!(
+            execute(
+                server_factory,
+                router_factory,
+                stream::iter(vec![
+                    UpdateConfiguration(hosted_products_configuration()),
+                    UpdateSchema(example_schema()),
+                    UpdateHostedSchema("products".to_string(), minimal_schema.to_owned()),
+                    // Not configured, so ignored
+                    UpdateHostedSchema("reviews".to_string(), minimal_schema.to_owned()),
+                    UpdateLicense(LicenseState::default()),
+                    UpdateHostedSchema("products".to_string(), example_schema()),
+                    Shutdown
+                ]),
+            )
+            .await,
+            Ok(())
+        );
+        assert_eq!(shutdown_receivers.0.lock().unwrap().len(), 2);
+    }
+
     mock! {
         #[derive(Debug)]
         MyRouterConfigurator {}
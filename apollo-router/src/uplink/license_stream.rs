@@ -488,6 +488,7 @@ mod test {
         NoMoreConfiguration,
         UpdateSchema,
         NoMoreSchema,
+        UpdateHostedSchema,
         UpdateLicense,
         HaltLicense,
         WarnLicense,
@@ -503,6 +504,7 @@ mod test {
                 Event::NoMoreConfiguration => SimpleEvent::NoMoreConfiguration,
                 Event::UpdateSchema(_) => SimpleEvent::UpdateSchema,
                 Event::NoMoreSchema => SimpleEvent::NoMoreSchema,
+                Event::UpdateHostedSchema(_, _) => SimpleEvent::UpdateHostedSchema,
                 Event::UpdateLicense(LicenseState::LicensedHalt) => SimpleEvent::HaltLicense,
                 Event::UpdateLicense(LicenseState::LicensedWarn) => SimpleEvent::WarnLicense,
                 Event::UpdateLicense(_) => SimpleEvent::UpdateLicense,
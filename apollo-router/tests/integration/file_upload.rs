@@ -9,6 +9,7 @@ use tower::BoxError;
 
 const FILE_CONFIG: &str = include_str!("../fixtures/file_upload/default.router.yaml");
 const FILE_CONFIG_LARGE_LIMITS: &str = include_str!("../fixtures/file_upload/large.router.yaml");
+const FILE_CONFIG_BUFFER: &str = include_str!("../fixtures/file_upload/buffer.router.yaml");
 
 /// Create a valid handler for the [helper::FileUploadTestServer].
 macro_rules! make_handler {
@@ -892,6 +893,138 @@ async fn it_fails_incompatible_query_order() -> Result<(), BoxError> {
         .await
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn it_uploads_misordered_files_in_buffer_mode() -> Result<(), BoxError> {
+    use reqwest::multipart::Form;
+    use reqwest::multipart::Part;
+
+    // Construct a manual multipart request with files out of order
+    let request = Form::new()
+        .part(
+            "operations",
+            Part::text(
+                serde_json::json!({
+                    "query": "mutation ($file0: Upload, $file1: UploadClone) {
+                        file0: singleUpload(file: $file0) { filename body }
+                        file1: singleUploadClone(file: $file1) { filename body }
+                    }",
+                    "variables": {
+                        "file0": null,
+                        "file1": null,
+                    },
+                })
+                .to_string(),
+            ),
+        )
+        .part(
+            "map",
+            Part::text(
+                serde_json::json!({
+                    "0": ["variables.file0"],
+                    "1": ["variables.file1"],
+                })
+                .to_string(),
+            ),
+        )
+        .part("1", Part::text("file1 contents").file_name("file1"))
+        .part("0", Part::text("file0").file_name("file0"));
+
+    // Run the test
+    helper::FileUploadTestServer::builder()
+        .config(FILE_CONFIG_BUFFER)
+        .handler(make_handler!(
+            "/s1" => helper::echo_single_file,
+            "/s2" => helper::echo_single_file
+        ))
+        .request(request)
+        .subgraph_mapping("uploads", "/s1")
+        .subgraph_mapping("uploads_clone", "/s2")
+        .build()
+        .run_test(|response| {
+            insta::assert_json_snapshot!(response, @r###"
+            {
+              "data": {
+                "file0": {
+                  "filename": "file0",
+                  "body": "file0"
+                },
+                "file1": {
+                  "filename": "file1",
+                  "body": "file1 contents"
+                }
+              }
+            }
+            "###);
+        })
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn it_uploads_in_query_order_in_buffer_mode() -> Result<(), BoxError> {
+    use reqwest::multipart::Form;
+    use reqwest::multipart::Part;
+
+    // The second file is needed first, which can't be streamed
+    let request = Form::new()
+        .part(
+            "operations",
+            Part::text(
+                serde_json::json!({
+                    "query": "mutation SomeMutation($file0: UploadClone, $file1: Upload) {
+                        file1: singleUpload(file: $file1) { filename body }
+                        file0: singleUploadClone(file: $file0) { filename body }
+                    }",
+                    "variables": {
+                        "file0": null,
+                        "file1": null,
+                    },
+                })
+                .to_string(),
+            ),
+        )
+        .part(
+            "map",
+            Part::text(
+                serde_json::json!({
+                    "0": ["variables.file0"],
+                    "1": ["variables.file1"],
+                })
+                .to_string(),
+            ),
+        )
+        .part("0", Part::text("file0 contents").file_name("file0"))
+        .part("1", Part::text("file1").file_name("file1"));
+
+    // Run the test
+    helper::FileUploadTestServer::builder()
+        .config(FILE_CONFIG_BUFFER)
+        .handler(make_handler!(
+            "/s1" => helper::echo_single_file,
+            "/s2" => helper::echo_single_file
+        ))
+        .request(request)
+        .subgraph_mapping("uploads", "/s1")
+        .subgraph_mapping("uploads_clone", "/s2")
+        .build()
+        .run_test(|response| {
+            insta::assert_json_snapshot!(response, @r###"
+            {
+              "data": {
+                "file1": {
+                  "filename": "file1",
+                  "body": "file1"
+                },
+                "file0": {
+                  "filename": "file0",
+                  "body": "file0 contents"
+                }
+              }
+            }
+            "###);
+        })
+        .await
+}
+
 mod helper {
     use std::collections::BTreeMap;
     use std::collections::HashMap;